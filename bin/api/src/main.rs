@@ -31,6 +31,10 @@ async fn main() -> Result<()> {
             "/backtests/:backtest_id",
             get(routes::list_backtest_pairs::handler),
         )
+        .route(
+            "/backtests/:backtest_id/metrics",
+            get(routes::get_backtest_metrics::handler),
+        )
         .route(
             "/backtests/:backtest_id/pairs/:pair_address",
             get(routes::get_backtest_pair::handler),
@@ -9,6 +9,7 @@ pub enum AppError {
     EyreError(eyre::Report),
     R2D2Error(r2d2::Error),
     RusqliteError(rusqlite::Error),
+    NotFound(String),
     Error(String),
 }
 
@@ -59,6 +60,7 @@ impl IntoResponse for AppError {
                     "Something went wrong".to_owned(),
                 )
             }
+            AppError::NotFound(message) => (StatusCode::NOT_FOUND, message),
             AppError::Error(message) => {
                 tracing::error!("app error: {}", message);
                 (
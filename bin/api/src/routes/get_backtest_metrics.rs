@@ -0,0 +1,56 @@
+use crate::primitives::{AppError, AppJson, AppState};
+use pochtecatl_db::BacktestMetricsModel;
+
+use axum::extract::{Path, State};
+use serde::Serialize;
+
+// Wei amounts are decimal strings to avoid losing precision in JSON numbers
+#[derive(Serialize)]
+pub struct Response {
+    backtest_id: i64,
+    realized_pnl: String,
+    trade_count: u64,
+    win_rate: f64,
+    average_win: String,
+    average_loss: String,
+    profit_factor: Option<f64>,
+    max_drawdown: String,
+    sharpe_ratio: Option<f64>,
+    sortino_ratio: Option<f64>,
+    exposure_seconds: u64,
+}
+
+impl From<BacktestMetricsModel> for Response {
+    fn from(metrics: BacktestMetricsModel) -> Self {
+        Self {
+            backtest_id: metrics.backtest_id,
+            realized_pnl: metrics.realized_pnl,
+            trade_count: metrics.trade_count.into(),
+            win_rate: metrics.win_rate,
+            average_win: metrics.average_win,
+            average_loss: metrics.average_loss,
+            profit_factor: metrics.profit_factor,
+            max_drawdown: metrics.max_drawdown,
+            sharpe_ratio: metrics.sharpe_ratio,
+            sortino_ratio: metrics.sortino_ratio,
+            exposure_seconds: metrics.exposure_seconds.into(),
+        }
+    }
+}
+
+pub async fn handler(
+    Path(backtest_id): Path<i64>,
+    State(app_state): State<AppState>,
+) -> eyre::Result<AppJson<Response>, AppError> {
+    let metrics = {
+        let mut db_conn = app_state.db().get()?;
+        let tx = db_conn.transaction()?;
+        let metrics = BacktestMetricsModel::query_by_backtest_id(&tx, backtest_id)?;
+        tx.rollback()?;
+        metrics
+    };
+
+    metrics
+        .map(|metrics| AppJson(Response::from(metrics)))
+        .ok_or_else(|| AppError::NotFound(format!("No metrics for backtest {}", backtest_id)))
+}
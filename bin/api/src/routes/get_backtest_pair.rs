@@ -1,18 +1,19 @@
 use crate::primitives::{AppError, AppJson, AppState};
 
-use pochtecatl_db::{BacktestClosedTradeModel, BlockModel};
+use pochtecatl_db::{BacktestClosedTradeModel, BlockModel, TimePriceBarModel};
 use pochtecatl_primitives::{
-    u32f96_from_u256_frac, Block, IndicatorsConfig, Resolution, ResolutionTimestamp, TimePriceBar,
-    TimePriceBars, TradeMetadata, TradeRequestOp,
+    u32f96_from_u256_frac, Block, FinalizedTimePriceBar, IndicatorsConfig, Resolution,
+    ResolutionTimestamp, TimePriceBar, TimePriceBars, TradeMetadata, TradeRequestOp,
 };
 
-use alloy::primitives::{uint, Address, TxHash, U256};
+use alloy::primitives::{uint, Address, BlockNumber, TxHash, U256};
 use axum::extract::{Path, Query, State};
 use eyre::Result;
 use fixed::traits::LossyInto;
 use lazy_static::lazy_static;
 use rusqlite::Transaction;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use tracing::error;
 
 lazy_static! {
@@ -167,19 +168,29 @@ fn get_trades(
     })
 }
 
-fn get_price_ticks(
+// Rebuilds the time price bars for the pair from the indexed blocks, for
+// ranges indexed without persisting time price bars (e.g. sweeps).
+fn rebuild_price_ticks(
     tx: &Transaction,
     pair_address: Address,
     start_at: u64,
     end_at: u64,
     resolution: Resolution,
-) -> eyre::Result<Vec<PriceTickResponse>, AppError> {
-    let blocks = BlockModel::query_by_timestamp_range(&tx, start_at, end_at)?;
+) -> Result<Vec<PriceTickResponse>> {
+    let blocks = BlockModel::query_by_timestamp_range(&tx, start_at, end_at)?
+        .into_iter()
+        .map(Block::from)
+        .collect::<Vec<Block>>();
+
+    // Every indexed block's timestamp, to pad the blocks without pair ticks
+    let block_timestamps = blocks
+        .iter()
+        .map(|block| (block.block_number, block.block_timestamp))
+        .collect::<BTreeMap<BlockNumber, u64>>();
+
     let pair_time_price_bars = blocks
         .into_iter()
         .filter_map(|block| {
-            let block = Block::from(block);
-
             block.pair_ticks.get(&pair_address).map(|pair_block_tick| {
                 (
                     block.block_number,
@@ -192,15 +203,18 @@ fn get_price_ticks(
             TimePriceBars::new(None, resolution, Some(IndicatorsConfig::All)),
             |mut acc, (block_number, block_timestamp, tick)| {
                 let _ = acc
-                    .insert_data(
-                        block_number,
-                        tick,
-                        block_timestamp,
-                        Some(
-                            ResolutionTimestamp::from_timestamp(block_timestamp, &resolution)
-                                .previous(&resolution),
-                        ),
-                    )
+                    .pad_to(block_number, &block_timestamps)
+                    .and_then(|_| {
+                        acc.insert_data(
+                            block_number,
+                            tick,
+                            block_timestamp,
+                            Some(
+                                ResolutionTimestamp::from_timestamp(block_timestamp, &resolution)
+                                    .previous(&resolution),
+                            ),
+                        )
+                    })
                     .inspect_err(|e| error!("Failed to insert data: {}", e));
                 acc
             },
@@ -213,6 +227,42 @@ fn get_price_ticks(
         .collect())
 }
 
+fn get_price_ticks(
+    tx: &Transaction,
+    pair_address: Address,
+    start_at: u64,
+    end_at: u64,
+    resolution: Resolution,
+) -> eyre::Result<Vec<PriceTickResponse>, AppError> {
+    let time_price_bar_models = TimePriceBarModel::query_by_pair_timestamp_range(
+        &tx,
+        pair_address,
+        resolution.as_str(),
+        ResolutionTimestamp::from_timestamp(start_at, &resolution).0,
+        end_at,
+    )?;
+
+    // Fall back to the indexed blocks when no bars were persisted for the range
+    if time_price_bar_models.is_empty() {
+        return rebuild_price_ticks(tx, pair_address, start_at, end_at, resolution)
+            .map_err(Into::into);
+    }
+
+    time_price_bar_models
+        .into_iter()
+        .map(|time_price_bar_model| {
+            let ts = ResolutionTimestamp(time_price_bar_model.timestamp.0);
+            FinalizedTimePriceBar::try_from(time_price_bar_model).map(|finalized_time_price_bar| {
+                PriceTickResponse::from_time_price_bar(
+                    ts,
+                    &TimePriceBar::Finalized(finalized_time_price_bar),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+        .map_err(Into::into)
+}
+
 #[derive(Deserialize)]
 pub struct Params {
     start_at: u64,
@@ -231,7 +281,14 @@ pub async fn handler(
 ) -> eyre::Result<AppJson<Response>, AppError> {
     let mut db_conn = app_state.db().get()?;
     let tx = db_conn.transaction()?;
-    let trade_ticks = get_trades(&tx, backtest_id, pair_address, start_at, end_at, &resolution)?;
+    let trade_ticks = get_trades(
+        &tx,
+        backtest_id,
+        pair_address,
+        start_at,
+        end_at,
+        &resolution,
+    )?;
     let price_ticks = get_price_ticks(&tx, pair_address, start_at, end_at, resolution)?;
     tx.finish()?;
 
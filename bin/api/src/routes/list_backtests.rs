@@ -10,6 +10,7 @@ pub struct BacktestItem {
     created_at: u64,
     start_block_number: u64,
     end_block_number: u64,
+    strategy_name: Option<String>,
 }
 
 impl From<BacktestModel> for BacktestItem {
@@ -19,6 +20,7 @@ impl From<BacktestModel> for BacktestItem {
             created_at: backtest.created_at.into(),
             start_block_number: backtest.start_block_number.into(),
             end_block_number: backtest.end_block_number.into(),
+            strategy_name: backtest.strategy_name,
         }
     }
 }
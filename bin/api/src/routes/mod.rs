@@ -1,3 +1,4 @@
 pub mod list_backtests;
 pub mod list_backtest_pairs;
 pub mod get_backtest_pair;
+pub mod get_backtest_metrics;
@@ -0,0 +1,413 @@
+use crate::trade_controller::closed_trade_pnl;
+
+use pochtecatl_db::BacktestMetricsModel;
+use pochtecatl_primitives::TradeMetadata;
+
+use alloy::primitives::{I256, U256};
+use eyre::{eyre, Report};
+use std::{cmp::Ordering, str::FromStr};
+
+// The equity curve is sampled once a day for the returns the ratios are
+// computed over, and the ratios annualized by the days in a year
+const RETURN_PERIOD_SECONDS: u64 = 86_400;
+const RETURN_PERIODS_PER_YEAR: f64 = 365.0;
+
+fn to_f64<N: ToString>(value: N) -> f64 {
+    value.to_string().parse().unwrap_or(0.0)
+}
+
+// What the metrics need to know about a closed position
+#[derive(Debug, Clone)]
+pub struct ClosedTradeSummary {
+    pub open_timestamp: u64,
+    pub close_timestamp: u64,
+    pub pnl: I256,
+}
+
+impl ClosedTradeSummary {
+    pub fn new(open_trade: &TradeMetadata, close_trade: &TradeMetadata) -> Self {
+        Self {
+            open_timestamp: *open_trade.block_timestamp(),
+            close_timestamp: *close_trade.block_timestamp(),
+            pnl: closed_trade_pnl(open_trade, close_trade),
+        }
+    }
+}
+
+// Performance of a backtest's closed trades. Pnl amounts are in wei net of
+// gas, and the equity curve is the starting cash plus the cumulative pnl
+// ordered by close time.
+#[derive(Debug, Clone, Default)]
+pub struct BacktestMetrics {
+    pub realized_pnl: I256,
+    pub trade_count: usize,
+    pub winning_trade_count: usize,
+    pub average_win: I256,
+    // Negative, or zero if there were no losing trades
+    pub average_loss: I256,
+    // Gross profit over gross loss, None if there were no losing trades
+    pub profit_factor: Option<f64>,
+    // Largest decline of the equity curve from a previous peak
+    pub max_drawdown: I256,
+    // Annualized ratios of the daily returns of the equity curve, sampled at
+    // the end of each UTC day from the day of the first open to the day of
+    // the last close and scaled by the square root of 365. None with fewer
+    // than two days or no variation in returns.
+    pub sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    // Time with at least one position open
+    pub exposure_seconds: u64,
+}
+
+impl BacktestMetrics {
+    pub fn from_closed_trades(
+        closed_trades: &[(TradeMetadata, TradeMetadata)],
+        starting_cash: U256,
+    ) -> Self {
+        Self::from_summaries(
+            closed_trades
+                .iter()
+                .map(|(open_trade, close_trade)| ClosedTradeSummary::new(open_trade, close_trade))
+                .collect(),
+            starting_cash,
+        )
+    }
+
+    pub fn from_summaries(mut summaries: Vec<ClosedTradeSummary>, starting_cash: U256) -> Self {
+        summaries.sort_by_key(|summary| summary.close_timestamp);
+
+        let (wins, losses): (Vec<I256>, Vec<I256>) = summaries
+            .iter()
+            .map(|summary| summary.pnl)
+            .filter(|pnl| !pnl.is_zero())
+            .partition(|pnl| pnl.is_positive());
+        let gross_profit = wins
+            .iter()
+            .fold(I256::ZERO, |acc, pnl| acc.saturating_add(*pnl));
+        let gross_loss = losses
+            .iter()
+            .fold(I256::ZERO, |acc, pnl| acc.saturating_add(*pnl));
+        let average = |total: I256, count: usize| {
+            if count == 0 {
+                I256::ZERO
+            } else {
+                total / I256::try_from(count).unwrap_or(I256::MAX)
+            }
+        };
+
+        let (realized_pnl, max_drawdown, _) = summaries.iter().fold(
+            (I256::ZERO, I256::ZERO, I256::ZERO),
+            |(equity, max_drawdown, peak), summary| {
+                let equity = equity.saturating_add(summary.pnl);
+                let peak = peak.max(equity);
+                (equity, max_drawdown.max(peak - equity), peak)
+            },
+        );
+
+        let returns = daily_returns(&summaries, starting_cash);
+
+        Self {
+            realized_pnl,
+            trade_count: summaries.len(),
+            winning_trade_count: wins.len(),
+            average_win: average(gross_profit, wins.len()),
+            average_loss: average(gross_loss, losses.len()),
+            profit_factor: if gross_loss.is_zero() {
+                None
+            } else {
+                Some(to_f64(gross_profit) / to_f64(gross_loss.unsigned_abs()))
+            },
+            max_drawdown,
+            sharpe_ratio: sharpe_ratio(&returns),
+            sortino_ratio: sortino_ratio(&returns),
+            exposure_seconds: exposure_seconds(&summaries),
+        }
+    }
+
+    // Share of closed trades with a positive pnl, zero if there were none
+    pub fn win_rate(&self) -> f64 {
+        if self.trade_count == 0 {
+            0.0
+        } else {
+            self.winning_trade_count as f64 / self.trade_count as f64
+        }
+    }
+
+    pub fn to_model(&self, backtest_id: i64) -> BacktestMetricsModel {
+        BacktestMetricsModel {
+            backtest_id,
+            realized_pnl: self.realized_pnl.to_string(),
+            trade_count: (self.trade_count as u64).into(),
+            win_rate: self.win_rate(),
+            average_win: self.average_win.to_string(),
+            average_loss: self.average_loss.to_string(),
+            profit_factor: self.profit_factor,
+            max_drawdown: self.max_drawdown.to_string(),
+            sharpe_ratio: self.sharpe_ratio,
+            sortino_ratio: self.sortino_ratio,
+            exposure_seconds: self.exposure_seconds.into(),
+        }
+    }
+
+    // Orders by the given metric, greater is better. Undefined ratios rank last.
+    pub fn compare(&self, other: &Self, metric: &RankMetric) -> Ordering {
+        let ratio = |ratio: Option<f64>| ratio.unwrap_or(f64::NEG_INFINITY);
+        match metric {
+            RankMetric::RealizedPnl => self.realized_pnl.cmp(&other.realized_pnl),
+            RankMetric::WinRate => self.win_rate().total_cmp(&other.win_rate()),
+            RankMetric::TradeCount => self.trade_count.cmp(&other.trade_count),
+            RankMetric::ProfitFactor => {
+                ratio(self.profit_factor).total_cmp(&ratio(other.profit_factor))
+            }
+            RankMetric::MaxDrawdown => other.max_drawdown.cmp(&self.max_drawdown),
+            RankMetric::SharpeRatio => {
+                ratio(self.sharpe_ratio).total_cmp(&ratio(other.sharpe_ratio))
+            }
+            RankMetric::SortinoRatio => {
+                ratio(self.sortino_ratio).total_cmp(&ratio(other.sortino_ratio))
+            }
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Returns of the equity curve over each day spanned by the trades, summaries
+// in close order. Days without a close have a return of zero.
+fn daily_returns(summaries: &[ClosedTradeSummary], starting_cash: U256) -> Vec<f64> {
+    let (first_open_timestamp, last_close_timestamp) = match (
+        summaries.iter().map(|summary| summary.open_timestamp).min(),
+        summaries.last(),
+    ) {
+        (Some(first_open_timestamp), Some(last)) => (first_open_timestamp, last.close_timestamp),
+        _ => return Vec::new(),
+    };
+
+    let mut equity = to_f64(starting_cash);
+    let mut closes = summaries.iter().peekable();
+    let mut returns = Vec::new();
+    for day in
+        first_open_timestamp / RETURN_PERIOD_SECONDS..=last_close_timestamp / RETURN_PERIOD_SECONDS
+    {
+        let day_start_equity = equity;
+        while let Some(summary) =
+            closes.next_if(|summary| summary.close_timestamp / RETURN_PERIOD_SECONDS <= day)
+        {
+            equity += to_f64(summary.pnl);
+        }
+
+        // Returns are undefined once the book is wiped out
+        if day_start_equity <= 0.0 {
+            break;
+        }
+        returns.push(equity / day_start_equity - 1.0);
+    }
+
+    returns
+}
+
+// Mean return over the sample standard deviation of returns, annualized
+fn sharpe_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let mean = mean(returns);
+    let variance = returns
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (returns.len() - 1) as f64;
+
+    Some(mean / variance.sqrt() * RETURN_PERIODS_PER_YEAR.sqrt()).filter(|ratio| ratio.is_finite())
+}
+
+// Mean return over the downside deviation, which only penalizes losses,
+// annualized
+fn sortino_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let downside_variance = returns
+        .iter()
+        .map(|value| value.min(0.0).powi(2))
+        .sum::<f64>()
+        / returns.len() as f64;
+
+    Some(mean(returns) / downside_variance.sqrt() * RETURN_PERIODS_PER_YEAR.sqrt())
+        .filter(|ratio| ratio.is_finite())
+}
+
+// Length of the union of the holding periods, so that positions held
+// concurrently are not counted twice.
+fn exposure_seconds(summaries: &[ClosedTradeSummary]) -> u64 {
+    let mut periods = summaries
+        .iter()
+        .map(|summary| (summary.open_timestamp, summary.close_timestamp))
+        .collect::<Vec<_>>();
+    periods.sort();
+
+    let mut exposure_seconds = 0;
+    let mut current: Option<(u64, u64)> = None;
+    for (start, end) in periods.into_iter() {
+        current = match current {
+            Some((current_start, current_end)) if start <= current_end => {
+                Some((current_start, current_end.max(end)))
+            }
+            Some((current_start, current_end)) => {
+                exposure_seconds += current_end - current_start;
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+
+    exposure_seconds
+        + current
+            .map(|(start, end)| end.saturating_sub(start))
+            .unwrap_or(0)
+}
+
+// The metric parameter sweep results are ranked by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankMetric {
+    RealizedPnl,
+    WinRate,
+    TradeCount,
+    ProfitFactor,
+    // Ranked ascending, the smallest drawdown is best
+    MaxDrawdown,
+    SharpeRatio,
+    SortinoRatio,
+}
+
+// Parses "realized_pnl", "win_rate", "trade_count", "profit_factor",
+// "max_drawdown", "sharpe_ratio" or "sortino_ratio"
+impl FromStr for RankMetric {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "realized_pnl" => Ok(Self::RealizedPnl),
+            "win_rate" => Ok(Self::WinRate),
+            "trade_count" => Ok(Self::TradeCount),
+            "profit_factor" => Ok(Self::ProfitFactor),
+            "max_drawdown" => Ok(Self::MaxDrawdown),
+            "sharpe_ratio" => Ok(Self::SharpeRatio),
+            "sortino_ratio" => Ok(Self::SortinoRatio),
+            _ => Err(eyre!("Failed to parse rank metric: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BacktestMetrics, ClosedTradeSummary, RankMetric, RETURN_PERIOD_SECONDS};
+
+    use alloy::primitives::{I256, U256};
+    use std::cmp::Ordering;
+
+    fn pnl(value: i64) -> I256 {
+        I256::try_from(value).unwrap()
+    }
+
+    fn trade(open_timestamp: u64, close_timestamp: u64, value: i64) -> ClosedTradeSummary {
+        ClosedTradeSummary {
+            open_timestamp,
+            close_timestamp,
+            pnl: pnl(value),
+        }
+    }
+
+    const STARTING_CASH: u64 = 1000;
+
+    #[test]
+    fn test_from_summaries() {
+        let metrics = BacktestMetrics::from_summaries(
+            vec![
+                trade(0, 10, 100),
+                trade(10, 20, -40),
+                trade(30, 40, 20),
+                trade(35, 50, -80),
+            ],
+            U256::from(STARTING_CASH),
+        );
+
+        assert_eq!(metrics.realized_pnl, pnl(0));
+        assert_eq!(metrics.trade_count, 4);
+        assert_eq!(metrics.win_rate(), 0.5);
+        assert_eq!(metrics.average_win, pnl(60));
+        assert_eq!(metrics.average_loss, pnl(-60));
+        assert_eq!(metrics.profit_factor, Some(1.0));
+        // peak of 100 after the first trade, trough of 0 after the last
+        assert_eq!(metrics.max_drawdown, pnl(100));
+        // 0 to 20 and 30 to 50
+        assert_eq!(metrics.exposure_seconds, 40);
+        // a single day of returns leaves the ratios undefined
+        assert_eq!(metrics.sharpe_ratio, None);
+        assert_eq!(metrics.sortino_ratio, None);
+    }
+
+    #[test]
+    fn test_ratios() {
+        let day = |day: u64| day * RETURN_PERIOD_SECONDS + 100;
+        let metrics = BacktestMetrics::from_summaries(
+            vec![
+                trade(day(0), day(0) + 10, 100),
+                trade(day(1), day(1) + 10, 300),
+                trade(day(3), day(3) + 10, -100),
+            ],
+            U256::from(STARTING_CASH),
+        );
+
+        // equity of 1100, 1400, 1400 and 1300 at the end of each day
+        let returns = [0.1, 3.0 / 11.0, 0.0, -1.0 / 14.0];
+        let mean = returns.iter().sum::<f64>() / 4.0;
+        let std_dev = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 3.0).sqrt();
+        let downside_deviation = ((1.0f64 / 14.0).powi(2) / 4.0).sqrt();
+
+        let sharpe_ratio = metrics.sharpe_ratio.unwrap();
+        assert!((sharpe_ratio - mean / std_dev * 365f64.sqrt()).abs() < 1e-9);
+        let sortino_ratio = metrics.sortino_ratio.unwrap();
+        assert!((sortino_ratio - mean / downside_deviation * 365f64.sqrt()).abs() < 1e-9);
+        assert_eq!(metrics.max_drawdown, pnl(100));
+
+        // a single trade or no losses leave ratios undefined
+        let metrics =
+            BacktestMetrics::from_summaries(vec![trade(0, 10, 100)], U256::from(STARTING_CASH));
+        assert_eq!(metrics.sharpe_ratio, None);
+        assert_eq!(metrics.profit_factor, None);
+        assert_eq!(metrics.max_drawdown, I256::ZERO);
+
+        let empty = BacktestMetrics::from_summaries(vec![], U256::from(STARTING_CASH));
+        assert_eq!(empty.realized_pnl, I256::ZERO);
+        assert_eq!(empty.win_rate(), 0.0);
+        assert_eq!(empty.exposure_seconds, 0);
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = BacktestMetrics::from_summaries(
+            vec![trade(0, 10, 100), trade(10, 20, -40)],
+            U256::from(STARTING_CASH),
+        );
+        let b = BacktestMetrics::from_summaries(
+            vec![trade(0, 10, 10), trade(10, 20, 10), trade(20, 30, -1)],
+            U256::from(STARTING_CASH),
+        );
+
+        assert_eq!(a.compare(&b, &RankMetric::RealizedPnl), Ordering::Greater);
+        assert_eq!(a.compare(&b, &RankMetric::WinRate), Ordering::Less);
+        assert_eq!(a.compare(&b, &RankMetric::TradeCount), Ordering::Less);
+        assert_eq!(a.compare(&b, &RankMetric::MaxDrawdown), Ordering::Less);
+        assert_eq!(a.compare(&b, &RankMetric::ProfitFactor), Ordering::Less);
+        assert_eq!(
+            "win_rate".parse::<RankMetric>().unwrap(),
+            RankMetric::WinRate
+        );
+        assert!("calmar_ratio".parse::<RankMetric>().is_err());
+    }
+}
@@ -0,0 +1,11 @@
+pub use metrics::{BacktestMetrics, RankMetric};
+pub use paper_trading::PaperTradingRecorder;
+pub use persist::{insert_backtest, update_backtest};
+pub use sweep::{Sweep, SweepMode};
+pub use walk_forward::{warmup_blocks, WalkForward};
+
+mod metrics;
+mod paper_trading;
+mod persist;
+mod sweep;
+mod walk_forward;
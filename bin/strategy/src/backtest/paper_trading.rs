@@ -0,0 +1,103 @@
+use super::{insert_backtest, update_backtest};
+use crate::strategies::StrategyExecutor;
+
+use alloy::{
+    network::Ethereum, primitives::BlockNumber, providers::Provider, transports::Transport,
+};
+
+use eyre::Result;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+// Persists each paper trading strategy as a backtest from the start block to
+// the last block it has seen, so that it can be inspected and ranked like one
+// while the run grows.
+pub struct PaperTradingRecorder<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    db_pool: Arc<Pool<SqliteConnectionManager>>,
+    start_block_number: BlockNumber,
+    // Backtest id of each strategy executor
+    strategy_executors: Vec<(i64, Arc<StrategyExecutor<T, P>>)>,
+}
+
+impl<T, P> PaperTradingRecorder<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    pub fn new(
+        db_pool: Arc<Pool<SqliteConnectionManager>>,
+        start_block_number: BlockNumber,
+        strategy_executors: &[Arc<StrategyExecutor<T, P>>],
+    ) -> Result<Self> {
+        let strategy_executors = {
+            let mut conn = db_pool.get()?;
+            let tx = conn.transaction()?;
+            let strategy_executors = strategy_executors
+                .iter()
+                .map(|strategy_executor| {
+                    insert_backtest(
+                        &tx,
+                        start_block_number,
+                        start_block_number,
+                        strategy_executor,
+                    )
+                    .map(|(backtest_id, _)| (backtest_id, Arc::clone(strategy_executor)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            tx.commit()?;
+
+            strategy_executors
+        };
+
+        Ok(Self {
+            db_pool,
+            start_block_number,
+            strategy_executors,
+        })
+    }
+
+    pub fn persist(&self) -> Result<()> {
+        let mut conn = self.db_pool.get()?;
+        let tx = conn.transaction()?;
+
+        for (backtest_id, strategy_executor) in self.strategy_executors.iter() {
+            let end_block_number = strategy_executor
+                .last_block()
+                .map(|(block_number, _)| block_number)
+                .unwrap_or(self.start_block_number);
+            let metrics = update_backtest(&tx, *backtest_id, end_block_number, strategy_executor)?;
+            info!(
+                backtest_id,
+                end_block_number,
+                realized_pnl = metrics.realized_pnl.to_string(),
+                trade_count = metrics.trade_count,
+                "persisted paper trading"
+            );
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        T: Send + Sync,
+        P: Send + Sync,
+    {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+
+                if let Err(err) = self.persist() {
+                    error!("Failed to persist paper trading: {:?}", err);
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,93 @@
+use super::BacktestMetrics;
+use crate::{config, strategies::StrategyExecutor};
+
+use pochtecatl_db::{
+    BacktestClosedTradeModel, BacktestMetricsModel, BacktestModel, BacktestOpenTradeModel,
+    BacktestRiskRejectionModel, NewBacktestModel, NewBacktestOpenTradeModel,
+};
+
+use alloy::{
+    network::Ethereum, primitives::BlockNumber, providers::Provider, transports::Transport,
+};
+use eyre::Result;
+
+// Persists a completed backtest of the strategy executor along with its
+// strategy, closed trades, marked open trades, risk rejections and metrics.
+pub fn insert_backtest<T, P>(
+    tx: &rusqlite::Transaction,
+    start_block_number: BlockNumber,
+    end_block_number: BlockNumber,
+    strategy_executor: &StrategyExecutor<T, P>,
+) -> Result<(i64, BacktestMetrics)>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let backtest_id = NewBacktestModel::new(start_block_number, end_block_number).insert(tx)?;
+    strategy_executor.insert_backtest_strategy(tx, backtest_id)?;
+    let metrics = insert_backtest_results(tx, backtest_id, strategy_executor)?;
+
+    Ok((backtest_id, metrics))
+}
+
+// Persists a backtest again up to the end block, replacing its results. Lets
+// a paper trading run be inspected like a backtest as it grows.
+pub fn update_backtest<T, P>(
+    tx: &rusqlite::Transaction,
+    backtest_id: i64,
+    end_block_number: BlockNumber,
+    strategy_executor: &StrategyExecutor<T, P>,
+) -> Result<BacktestMetrics>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    BacktestModel::update_end_block_number(tx, backtest_id, end_block_number)?;
+    BacktestClosedTradeModel::delete_by_backtest_id(tx, backtest_id)?;
+    BacktestOpenTradeModel::delete_by_backtest_id(tx, backtest_id)?;
+    BacktestRiskRejectionModel::delete_by_backtest_id(tx, backtest_id)?;
+    BacktestMetricsModel::delete_by_backtest_id(tx, backtest_id)?;
+
+    insert_backtest_results(tx, backtest_id, strategy_executor)
+}
+
+fn insert_backtest_results<T, P>(
+    tx: &rusqlite::Transaction,
+    backtest_id: i64,
+    strategy_executor: &StrategyExecutor<T, P>,
+) -> Result<BacktestMetrics>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let trade_controller = strategy_executor.trade_controller();
+    trade_controller.insert_backtest_closed_trades(tx, backtest_id)?;
+    trade_controller.insert_backtest_risk_rejections(tx, backtest_id)?;
+
+    for marked_position in strategy_executor.open_positions().iter() {
+        NewBacktestOpenTradeModel::new(
+            backtest_id,
+            *marked_position
+                .open_trade_metadata
+                .indexed_trade()
+                .pair_address(),
+            serde_json::to_value(&marked_position.open_trade_metadata)?,
+            marked_position.price.to_string(),
+            marked_position.market_value().to_string(),
+            marked_position.unrealized_pnl().to_string(),
+            (
+                marked_position.block_number,
+                marked_position.block_timestamp,
+            ),
+        )
+        .insert(tx)?;
+    }
+
+    let metrics = BacktestMetrics::from_closed_trades(
+        &trade_controller.closed_trades(),
+        *config::BACKTEST_STARTING_CASH,
+    );
+    metrics.to_model(backtest_id).insert(tx)?;
+
+    Ok(metrics)
+}
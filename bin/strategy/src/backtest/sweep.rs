@@ -0,0 +1,249 @@
+use super::{insert_backtest, walk_forward::warmup_blocks, BacktestMetrics, RankMetric};
+use crate::{
+    indexer::{BlockRangeIndexer, Indexer},
+    strategies::{
+        new_parameterized_strategy, strategy_parameter_space, ParameterSet, Strategy,
+        StrategyExecutor,
+    },
+};
+
+use pochtecatl_primitives::RpcProvider;
+
+use alloy::{
+    network::Ethereum, primitives::BlockNumber, providers::Provider, transports::Transport,
+};
+
+use eyre::{eyre, Report, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::{str::FromStr, sync::Arc};
+use tracing::{info, instrument};
+
+// Backtests the strategy executors side by side in a single indexing pass
+// over the range, persisting each as a backtest. Returns the backtest ids and
+// metrics in the order of the executors. The warmup blocks preceding the
+// range are indexed without being traded. Time price bars are neither
+// rehydrated nor persisted, so every pass starts from the same cold bars.
+pub(super) async fn run_backtest_pass<T, P>(
+    rpc_provider: &Arc<RpcProvider<T, P>>,
+    db_pool: &Arc<Pool<SqliteConnectionManager>>,
+    start_block_number: BlockNumber,
+    end_block_number: BlockNumber,
+    warmup_blocks: u64,
+    strategy_executors: &[Arc<StrategyExecutor<T, P>>],
+) -> Result<Vec<(i64, BacktestMetrics)>>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let mut indexer = BlockRangeIndexer::new(
+        Arc::clone(rpc_provider),
+        Arc::clone(db_pool),
+        start_block_number,
+        end_block_number,
+        true,
+    )
+    .without_persisted_time_price_bars()
+    .with_warmup_blocks(warmup_blocks);
+    indexer.exec(strategy_executors.to_vec()).await?;
+
+    for strategy_executor in strategy_executors.iter() {
+        strategy_executor.settle().await?;
+    }
+
+    let mut conn = db_pool.get()?;
+    let tx = conn.transaction()?;
+    let backtests = strategy_executors
+        .iter()
+        .map(|strategy_executor| {
+            insert_backtest(&tx, start_block_number, end_block_number, strategy_executor)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    tx.commit()?;
+
+    Ok(backtests)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepMode {
+    // Every combination in the parameter space
+    Grid,
+    // Distinct combinations sampled from the parameter space
+    Random(usize),
+}
+
+// Parses "grid" or "random:<count>"
+impl FromStr for SweepMode {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            None if s.trim() == "grid" => Ok(SweepMode::Grid),
+            Some(("random", count)) => count
+                .parse()
+                .map(SweepMode::Random)
+                .map_err(|err| eyre!("Failed to parse sweep mode {}: {:?}", s, err)),
+            _ => Err(eyre!("Failed to parse sweep mode: {}", s)),
+        }
+    }
+}
+
+// A parameter set and the executor backtesting it
+type SweepRun<T, P> = (ParameterSet, Arc<StrategyExecutor<T, P>>);
+
+pub struct SweepResult {
+    pub backtest_id: i64,
+    pub parameters: ParameterSet,
+    pub metrics: BacktestMetrics,
+}
+
+// Backtests a strategy once per parameter set over the same block range, all
+// in a single indexing pass. The time price bars are indexed with the default
+// indicators and strategies with other periods replay theirs from the bars.
+pub struct Sweep {
+    strategy_name: String,
+    mode: SweepMode,
+    seed: u64,
+    rank_by: RankMetric,
+}
+
+impl Sweep {
+    pub fn new(strategy_name: &str, mode: SweepMode) -> Self {
+        Self {
+            strategy_name: strategy_name.to_string(),
+            mode,
+            seed: 0,
+            rank_by: RankMetric::RealizedPnl,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_rank_by(mut self, rank_by: RankMetric) -> Self {
+        self.rank_by = rank_by;
+        self
+    }
+
+    pub fn strategy_name(&self) -> &str {
+        &self.strategy_name
+    }
+
+    pub fn parameter_sets(&self) -> Result<Vec<ParameterSet>> {
+        let parameter_space = strategy_parameter_space(&self.strategy_name)?;
+        info!(
+            strategy = self.strategy_name,
+            grid_size = parameter_space.grid_size(),
+            "sweeping parameter space"
+        );
+
+        Ok(match self.mode {
+            SweepMode::Grid => parameter_space.grid(),
+            SweepMode::Random(count) => parameter_space.sample(count, self.seed),
+        })
+    }
+
+    // Runs and persists a backtest per parameter set, returning the results
+    // ranked best first.
+    #[instrument(skip_all)]
+    pub async fn run<T, P, F>(
+        &self,
+        rpc_provider: Arc<RpcProvider<T, P>>,
+        db_pool: Arc<Pool<SqliteConnectionManager>>,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+        make_strategy_executor: F,
+    ) -> Result<Vec<SweepResult>>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+        F: Fn(Box<dyn Strategy>) -> Arc<StrategyExecutor<T, P>>,
+    {
+        let mut runs: Vec<SweepRun<T, P>> = Vec::new();
+        let mut pass_warmup_blocks = 0;
+        for parameters in self.parameter_sets()? {
+            let strategy = new_parameterized_strategy(&self.strategy_name, &parameters)?;
+            pass_warmup_blocks = pass_warmup_blocks.max(warmup_blocks(
+                &strategy.indicators_config(),
+                start_block_number,
+            ));
+            runs.push((parameters, make_strategy_executor(strategy)));
+        }
+
+        info!(run_count = runs.len(), "starting sweep pass");
+        let backtests = run_backtest_pass(
+            &rpc_provider,
+            &db_pool,
+            start_block_number,
+            end_block_number,
+            pass_warmup_blocks,
+            &runs
+                .iter()
+                .map(|(_, strategy_executor)| Arc::clone(strategy_executor))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+        let mut results = runs
+            .into_iter()
+            .zip(backtests.into_iter())
+            .map(|((parameters, _), (backtest_id, metrics))| SweepResult {
+                backtest_id,
+                parameters,
+                metrics,
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.metrics.compare(&a.metrics, &self.rank_by));
+        for (rank, result) in results.iter().enumerate() {
+            info!(
+                rank = rank + 1,
+                backtest_id = result.backtest_id,
+                parameters = result.parameters.to_json().to_string(),
+                realized_pnl = result.metrics.realized_pnl.to_string(),
+                trade_count = result.metrics.trade_count,
+                win_rate = result.metrics.win_rate(),
+                "sweep result"
+            );
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sweep, SweepMode};
+
+    #[test]
+    fn test_parse_sweep_mode() {
+        assert_eq!("grid".parse::<SweepMode>().unwrap(), SweepMode::Grid);
+        assert_eq!(
+            "random:25".parse::<SweepMode>().unwrap(),
+            SweepMode::Random(25)
+        );
+        assert!("random".parse::<SweepMode>().is_err());
+        assert!("random:many".parse::<SweepMode>().is_err());
+    }
+
+    #[test]
+    fn test_parameter_sets() {
+        let grid = Sweep::new("momentum", SweepMode::Grid)
+            .parameter_sets()
+            .unwrap();
+        assert_eq!(grid.len(), 36);
+
+        let sample = Sweep::new("momentum", SweepMode::Random(5))
+            .with_seed(3)
+            .parameter_sets()
+            .unwrap();
+        assert_eq!(sample.len(), 5);
+        assert!(sample.iter().all(|parameters| grid.contains(parameters)));
+
+        assert!(Sweep::new("unknown", SweepMode::Grid)
+            .parameter_sets()
+            .is_err());
+    }
+}
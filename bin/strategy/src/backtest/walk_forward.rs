@@ -0,0 +1,307 @@
+use super::{sweep::run_backtest_pass, Sweep};
+use crate::{
+    strategies::{new_parameterized_strategy, Strategy, StrategyExecutor},
+    trade_controller::closed_trade_pnl,
+};
+
+use pochtecatl_db::{NewWalkForwardModel, WalkForwardWindowModel};
+use pochtecatl_primitives::{constants, IndicatorsConfig, Resolution, RpcProvider};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{BlockNumber, I256},
+    providers::Provider,
+    transports::Transport,
+};
+
+use eyre::{eyre, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::sync::Arc;
+use tracing::{info, instrument};
+
+// An in sample range to optimize over and the out of sample range that
+// immediately follows it, both inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkForwardWindow {
+    pub in_sample: (BlockNumber, BlockNumber),
+    pub out_of_sample: (BlockNumber, BlockNumber),
+}
+
+// Rolls fixed size windows across the range, stepping by the out of sample
+// size so the out of sample ranges tile the remainder of the range. The
+// final out of sample range is truncated to the end of the range.
+pub fn walk_forward_windows(
+    start_block_number: BlockNumber,
+    end_block_number: BlockNumber,
+    in_sample_blocks: u64,
+    out_of_sample_blocks: u64,
+) -> Vec<WalkForwardWindow> {
+    let mut windows = Vec::new();
+    if in_sample_blocks == 0 || out_of_sample_blocks == 0 {
+        return windows;
+    }
+
+    let mut window_start = start_block_number;
+    while window_start + in_sample_blocks <= end_block_number {
+        let out_of_sample_start = window_start + in_sample_blocks;
+        windows.push(WalkForwardWindow {
+            in_sample: (window_start, out_of_sample_start - 1),
+            out_of_sample: (
+                out_of_sample_start,
+                (out_of_sample_start + out_of_sample_blocks - 1).min(end_block_number),
+            ),
+        });
+        window_start += out_of_sample_blocks;
+    }
+
+    windows
+}
+
+// Blocks replayed ahead of a backtested range, enough to fill the indicator
+// periods so the range is not scored on cold indicators. Capped at the blocks
+// available, e.g. the tail of the in sample window ahead of the out of sample
+// window.
+pub fn warmup_blocks(indicators_config: &IndicatorsConfig, max_blocks: u64) -> u64 {
+    let period = indicators_config
+        .bb_period()
+        .max(indicators_config.ema_period());
+
+    ((period + 1) * Resolution::FiveMinutes.offset() / constants::AVERAGE_BLOCK_TIME_SECONDS)
+        .min(max_blocks)
+}
+
+// Joins the closed trade pnls of consecutive out of sample windows into a
+// single cumulative equity curve of (block timestamp, pnl).
+pub fn stitch_equity_curve(window_trade_pnls: Vec<Vec<(u64, I256)>>) -> Vec<(u64, I256)> {
+    let mut equity = I256::ZERO;
+    window_trade_pnls
+        .into_iter()
+        .flat_map(|mut trade_pnls| {
+            trade_pnls.sort_by_key(|(block_timestamp, _)| *block_timestamp);
+            trade_pnls
+        })
+        .map(|(block_timestamp, pnl)| {
+            equity = equity.saturating_add(pnl);
+            (block_timestamp, equity)
+        })
+        .collect()
+}
+
+// Optimizes the strategy with a sweep over each in sample window, then
+// backtests the best parameters on the following out of sample window.
+pub struct WalkForward {
+    sweep: Sweep,
+    in_sample_blocks: u64,
+    out_of_sample_blocks: u64,
+}
+
+impl WalkForward {
+    pub fn new(sweep: Sweep, in_sample_blocks: u64, out_of_sample_blocks: u64) -> Self {
+        Self {
+            sweep,
+            in_sample_blocks,
+            out_of_sample_blocks,
+        }
+    }
+
+    // Persists the walk forward with its windows and returns its id
+    #[instrument(skip_all)]
+    pub async fn run<T, P, F>(
+        &self,
+        rpc_provider: Arc<RpcProvider<T, P>>,
+        db_pool: Arc<Pool<SqliteConnectionManager>>,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+        make_strategy_executor: F,
+    ) -> Result<i64>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+        F: Fn(Box<dyn Strategy>) -> Arc<StrategyExecutor<T, P>>,
+    {
+        let windows = walk_forward_windows(
+            start_block_number,
+            end_block_number,
+            self.in_sample_blocks,
+            self.out_of_sample_blocks,
+        );
+        if windows.is_empty() {
+            return Err(eyre!(
+                "Block range {} to {} is too short for a walk forward window",
+                start_block_number,
+                end_block_number
+            ));
+        }
+
+        let mut window_models = Vec::new();
+        let mut window_trade_pnls = Vec::new();
+        for (window_index, window) in windows.into_iter().enumerate() {
+            let in_sample_result = self
+                .sweep
+                .run(
+                    Arc::clone(&rpc_provider),
+                    Arc::clone(&db_pool),
+                    window.in_sample.0,
+                    window.in_sample.1,
+                    &make_strategy_executor,
+                )
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| eyre!("Sweep produced no results"))?;
+
+            let strategy = new_parameterized_strategy(
+                self.sweep.strategy_name(),
+                &in_sample_result.parameters,
+            )?;
+            let indicators_config = strategy.indicators_config();
+            let strategy_executor = make_strategy_executor(strategy);
+            let (out_of_sample_backtest_id, out_of_sample_metrics) = run_backtest_pass(
+                &rpc_provider,
+                &db_pool,
+                window.out_of_sample.0,
+                window.out_of_sample.1,
+                warmup_blocks(&indicators_config, self.in_sample_blocks),
+                &[Arc::clone(&strategy_executor)],
+            )
+            .await?
+            .swap_remove(0);
+
+            info!(
+                window_index,
+                in_sample_backtest_id = in_sample_result.backtest_id,
+                out_of_sample_backtest_id,
+                out_of_sample_realized_pnl = out_of_sample_metrics.realized_pnl.to_string(),
+                parameters = in_sample_result.parameters.to_json().to_string(),
+                "completed walk forward window"
+            );
+
+            window_trade_pnls.push(
+                strategy_executor
+                    .trade_controller()
+                    .closed_trades()
+                    .iter()
+                    .map(|(open_trade, close_trade)| {
+                        (
+                            *close_trade.block_timestamp(),
+                            closed_trade_pnl(open_trade, close_trade),
+                        )
+                    })
+                    .collect(),
+            );
+            window_models.push((
+                window_index as i64,
+                window,
+                in_sample_result.parameters.to_json(),
+                in_sample_result.backtest_id,
+                out_of_sample_backtest_id,
+            ));
+        }
+
+        let equity_curve = stitch_equity_curve(window_trade_pnls)
+            .into_iter()
+            .map(|(block_timestamp, pnl)| {
+                serde_json::json!({ "block_timestamp": block_timestamp, "pnl": pnl.to_string() })
+            })
+            .collect::<Vec<_>>();
+
+        let mut conn = db_pool.get()?;
+        let tx = conn.transaction()?;
+        let walk_forward_id = NewWalkForwardModel::new(
+            self.sweep.strategy_name().to_string(),
+            start_block_number,
+            end_block_number,
+            serde_json::Value::Array(equity_curve),
+        )
+        .insert(&tx)?;
+        for (window_index, window, parameters, in_sample_backtest_id, out_of_sample_backtest_id) in
+            window_models.into_iter()
+        {
+            WalkForwardWindowModel::new(
+                walk_forward_id,
+                window_index,
+                window.in_sample,
+                window.out_of_sample,
+                parameters,
+                in_sample_backtest_id,
+                out_of_sample_backtest_id,
+            )
+            .insert(&tx)?;
+        }
+        tx.commit()?;
+
+        Ok(walk_forward_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stitch_equity_curve, walk_forward_windows, warmup_blocks, WalkForwardWindow};
+
+    use pochtecatl_primitives::IndicatorsConfig;
+
+    use alloy::primitives::I256;
+    use fixed::types::U32F96;
+
+    fn pnl(value: i64) -> I256 {
+        I256::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_walk_forward_windows() {
+        let windows = walk_forward_windows(100, 349, 100, 50);
+
+        assert_eq!(
+            windows,
+            vec![
+                WalkForwardWindow {
+                    in_sample: (100, 199),
+                    out_of_sample: (200, 249),
+                },
+                WalkForwardWindow {
+                    in_sample: (150, 249),
+                    out_of_sample: (250, 299),
+                },
+                WalkForwardWindow {
+                    in_sample: (200, 299),
+                    out_of_sample: (300, 349),
+                },
+            ]
+        );
+
+        // the last out of sample window is truncated
+        let windows = walk_forward_windows(100, 330, 100, 50);
+        assert_eq!(windows.last().unwrap().out_of_sample, (300, 330));
+
+        assert!(walk_forward_windows(100, 199, 100, 50).is_empty());
+        assert!(walk_forward_windows(100, 1000, 100, 0).is_empty());
+    }
+
+    #[test]
+    fn test_warmup_blocks() {
+        let indicators_config = IndicatorsConfig::Custom {
+            bb_period: 20,
+            bb_std_dev: U32F96::from_num(2),
+            ema_period: 9,
+        };
+
+        // 21 five minute bars of two second blocks
+        assert_eq!(warmup_blocks(&indicators_config, 10_000), 3_150);
+        assert_eq!(warmup_blocks(&indicators_config, 1_000), 1_000);
+    }
+
+    #[test]
+    fn test_stitch_equity_curve() {
+        let equity_curve = stitch_equity_curve(vec![
+            vec![(20, pnl(-5)), (10, pnl(10))],
+            vec![],
+            vec![(30, pnl(7))],
+        ]);
+
+        assert_eq!(
+            equity_curve,
+            vec![(10, pnl(10)), (20, pnl(5)), (30, pnl(12))]
+        );
+    }
+}
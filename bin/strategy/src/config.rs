@@ -1,10 +1,18 @@
-use pochtecatl_primitives::BlockId;
+use crate::{
+    backtest::{RankMetric, SweepMode},
+    strategies::{ExitRules, SizingPolicy, SlippagePolicy},
+    trade_controller::{RiskLimits, SubmissionBackend},
+};
 
-use alloy::primitives::{Address, FixedBytes};
+use pochtecatl_primitives::{
+    constants, BarSampling, BlockId, RpcPolicy, RpcPolicyLayer, SignerSource,
+};
+
+use alloy::primitives::{Address, FixedBytes, U256};
 
 use eyre::Context;
 use lazy_static::lazy_static;
-use std::{env, ffi::OsStr, sync::Once};
+use std::{env, ffi::OsStr, sync::Once, time::Duration};
 use tracing_subscriber::fmt::format::FmtSpan;
 
 static DOTENV_INIT: Once = Once::new();
@@ -23,6 +31,113 @@ fn get_env_var<K: AsRef<OsStr>>(k: K) -> Result<String, env::VarError> {
     env::var(k)
 }
 
+// The first configured of a remote signing service, an encrypted keystore, a
+// mnemonic or a raw private key signs our txs
+fn get_signer_source() -> SignerSource {
+    if let Ok(url) = get_env_var("WALLET_REMOTE_SIGNER_URL") {
+        return SignerSource::Remote(
+            url.parse()
+                .expect("Failed to parse WALLET_REMOTE_SIGNER_URL"),
+        );
+    }
+
+    #[cfg(feature = "keystore")]
+    if let Ok(path) = get_env_var("WALLET_KEYSTORE_PATH") {
+        // Read from the password file if set, prompting for it otherwise
+        let password = match get_env_var("WALLET_KEYSTORE_PASSWORD_FILE") {
+            Ok(password_path) => std::fs::read_to_string(password_path)
+                .expect("Failed to read WALLET_KEYSTORE_PASSWORD_FILE")
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+            Err(_) => rpassword::prompt_password(format!("Password for keystore {}: ", path))
+                .expect("Failed to read keystore password"),
+        };
+
+        return SignerSource::Keystore {
+            path: path.into(),
+            password,
+        };
+    }
+
+    #[cfg(feature = "mnemonic")]
+    if let Ok(mnemonic_path) = get_env_var("WALLET_MNEMONIC_PATH") {
+        return SignerSource::Mnemonic {
+            phrase: std::fs::read_to_string(mnemonic_path)
+                .expect("Failed to read WALLET_MNEMONIC_PATH")
+                .trim()
+                .to_string(),
+            derivation_path: get_env_var("WALLET_DERIVATION_PATH")
+                .unwrap_or_else(|_| pochtecatl_primitives::DEFAULT_DERIVATION_PATH.to_string()),
+        };
+    }
+
+    get_env_var("WALLET_PRIVATE_KEY")
+        .wrap_err("Failed to read WALLET_PRIVATE_KEY from env")
+        .and_then(|key| hex::decode(&key).wrap_err("Failed to decode WALLET_PRIVATE_KEY"))
+        .and_then(|key| {
+            FixedBytes::try_from(key.as_slice()).wrap_err("Failed to create FixedBytes")
+        })
+        .map(SignerSource::PrivateKey)
+        .unwrap()
+}
+
+// Retries, rate limits and circuit breaking applied to every rpc request.
+// RPC_METHOD_POLICIES overrides the retries and compute units of individual
+// methods as comma separated "<method>=<max_retries>:<compute_units>".
+fn get_rpc_policy_layer() -> RpcPolicyLayer {
+    let parse_u64 = |key: &str, default: u64| {
+        get_env_var(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", key))
+            })
+            .unwrap_or(default)
+    };
+
+    let mut layer = RpcPolicyLayer::new()
+        .with_max_retries(parse_u64("RPC_MAX_RETRIES", 5) as u32)
+        .with_backoff(
+            Duration::from_millis(parse_u64("RPC_INITIAL_BACKOFF_MS", 200)),
+            Duration::from_millis(parse_u64("RPC_MAX_BACKOFF_MS", 10_000)),
+        )
+        .with_circuit_breaker(
+            parse_u64("RPC_CIRCUIT_BREAKER_THRESHOLD", 20) as u32,
+            Duration::from_secs(parse_u64("RPC_CIRCUIT_BREAKER_COOLDOWN_SECONDS", 30)),
+        );
+
+    if let Ok(limit) = get_env_var("RPC_MAX_REQUESTS_PER_SECOND") {
+        layer = layer.with_max_requests_per_second(
+            limit
+                .parse()
+                .expect("Failed to parse RPC_MAX_REQUESTS_PER_SECOND"),
+        );
+    }
+    if let Ok(limit) = get_env_var("RPC_MAX_COMPUTE_UNITS_PER_SECOND") {
+        layer = layer.with_max_compute_units_per_second(
+            limit
+                .parse()
+                .expect("Failed to parse RPC_MAX_COMPUTE_UNITS_PER_SECOND"),
+        );
+    }
+    if let Ok(policies) = get_env_var("RPC_METHOD_POLICIES") {
+        for policy in policies.split(',') {
+            let (method, policy) = policy
+                .trim()
+                .split_once('=')
+                .expect("Failed to parse RPC_METHOD_POLICIES");
+            layer = layer.with_method_policy(
+                method,
+                policy
+                    .parse::<RpcPolicy>()
+                    .expect("Failed to parse RPC_METHOD_POLICIES"),
+            );
+        }
+    }
+
+    layer
+}
+
 lazy_static! {
     pub static ref RUST_LOG: String =
         get_env_var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
@@ -48,6 +163,30 @@ lazy_static! {
         .wrap_err("Failed to read RPC_URL from env")
         .and_then(|u| url::Url::parse(u.as_str()).wrap_err("Failed to parse RPC_URL"))
         .unwrap();
+    // Comma separated endpoints requests fail over to from RPC_URL
+    pub static ref RPC_FALLBACK_URLS: Vec<url::Url> = get_env_var("RPC_FALLBACK_URLS")
+        .map(|urls| {
+            urls.split(',')
+                .map(|url| {
+                    url::Url::parse(url.trim()).expect("Failed to parse RPC_FALLBACK_URLS")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Blocks an endpoint may trail the best head by before it is tried last
+    pub static ref RPC_MAX_HEAD_LAG_BLOCKS: u64 = get_env_var("RPC_MAX_HEAD_LAG_BLOCKS")
+        .map(|blocks| blocks.parse().expect("Failed to parse RPC_MAX_HEAD_LAG_BLOCKS"))
+        .unwrap_or(2);
+    // Seconds between polling the head of every endpoint
+    pub static ref RPC_HEALTH_CHECK_INTERVAL_SECONDS: u64 =
+        get_env_var("RPC_HEALTH_CHECK_INTERVAL_SECONDS")
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("Failed to parse RPC_HEALTH_CHECK_INTERVAL_SECONDS")
+            })
+            .unwrap_or(10);
+    pub static ref RPC_POLICY_LAYER: RpcPolicyLayer = get_rpc_policy_layer();
     pub static ref DB_PATH: String = get_env_var("DB_PATH")
         .wrap_err("Failed to read DB_PATH from env")
         .unwrap();
@@ -55,20 +194,231 @@ lazy_static! {
         .wrap_err("Failed to read WETH_ADDRESS from env")
         .and_then(|a| a.parse().wrap_err("Failed to parse WETH_ADDRESS"))
         .unwrap();
-    pub static ref EXECUTOR_ADDRESS: Address = get_env_var("EXECUTOR_ADDRESS")
-        .wrap_err("Failed to read EXECUTOR_ADDRESS from env")
-        .and_then(|a| a.parse().wrap_err("Failed to parse EXECUTOR_ADDRESS"))
-        .unwrap();
-    pub static ref WALLET_PRIVATE_KEY: FixedBytes<32> =
-        get_env_var("WALLET_PRIVATE_KEY")
-            .wrap_err("Failed to read WALLET_PRIVATE_KEY from env")
-            .and_then(|key| hex::decode(&key).wrap_err("Failed to decode WALLET_PRIVATE_KEY"))
-            .and_then(
-                |key| FixedBytes::try_from(key.as_slice()).wrap_err("Failed to create FixedBytes")
-            )
-            .unwrap();
+    // Trades swap directly against pairs through our executor contract if
+    // set, or through the dex routers otherwise
+    pub static ref EXECUTOR_ADDRESS: Option<Address> = get_env_var("EXECUTOR_ADDRESS")
+        .ok()
+        .map(|a| a.parse().expect("Failed to parse EXECUTOR_ADDRESS"));
+    pub static ref SIGNER_SOURCE: SignerSource = get_signer_source();
+    // Comma separated activity samplings to maintain alongside time price bars,
+    // e.g. "trades:50,imbalance:5000000000000000000"
+    pub static ref BAR_SAMPLINGS: Vec<BarSampling> = get_env_var("BAR_SAMPLINGS")
+        .map(|samplings| {
+            samplings
+                .split(',')
+                .filter(|sampling| !sampling.trim().is_empty())
+                .map(|sampling| sampling.parse().unwrap())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Comma separated strategies to run side by side over the same blocks,
+    // each with its own trade book and persisted backtest.
+    pub static ref STRATEGIES: Vec<String> = get_env_var("STRATEGIES")
+        .map(|strategies| {
+            strategies
+                .split(',')
+                .map(|strategy| strategy.trim().to_string())
+                .filter(|strategy| !strategy.is_empty())
+                .collect()
+        })
+        .unwrap_or_else(|_| vec!["momentum".to_string()]);
+    // Max weth in wei each strategy may have deployed at once, uncapped if unset
+    pub static ref STRATEGY_CAPITAL_ALLOCATION: Option<U256> =
+        get_env_var("STRATEGY_CAPITAL_ALLOCATION")
+            .ok()
+            .map(|allocation| {
+                allocation
+                    .parse()
+                    .expect("Failed to parse STRATEGY_CAPITAL_ALLOCATION")
+            });
+    // How opens are sized when the signal does not specify a size, e.g.
+    // "fixed:1000000000000000000", "fraction:500", "atr:100:2.5" or "kelly:1000"
+    pub static ref SIZING_POLICY: SizingPolicy = get_env_var("SIZING_POLICY")
+        .map(|policy| policy.parse().expect("Failed to parse SIZING_POLICY"))
+        .unwrap_or_default();
+    // Shortfall from the quote each trade tolerates before reverting, e.g.
+    // "static:50", "atr:1.5:10:300" or "adaptive:50:250". Exact by default.
+    pub static ref SLIPPAGE_POLICY: SlippagePolicy = get_env_var("SLIPPAGE_POLICY")
+        .map(|policy| policy.parse().expect("Failed to parse SLIPPAGE_POLICY"))
+        .unwrap_or_default();
+    // Seconds past its block that a trade remains valid, two blocks if unset
+    pub static ref TRADE_DEADLINE_SECONDS: u64 = get_env_var("TRADE_DEADLINE_SECONDS")
+        .map(|seconds| seconds.parse().expect("Failed to parse TRADE_DEADLINE_SECONDS"))
+        .unwrap_or(constants::AVERAGE_BLOCK_TIME_SECONDS * 2);
+    // Blocks of fee history the priority fee is drawn from, and the
+    // percentile of priority fees paid in each
+    pub static ref GAS_FEE_HISTORY_BLOCKS: u64 = get_env_var("GAS_FEE_HISTORY_BLOCKS")
+        .map(|blocks| blocks.parse().expect("Failed to parse GAS_FEE_HISTORY_BLOCKS"))
+        .unwrap_or(10);
+    pub static ref GAS_PRIORITY_FEE_PERCENTILE: f64 = get_env_var("GAS_PRIORITY_FEE_PERCENTILE")
+        .map(|percentile| {
+            percentile
+                .parse()
+                .expect("Failed to parse GAS_PRIORITY_FEE_PERCENTILE")
+        })
+        .unwrap_or(50.0);
+    // Max fee per gas in wei a trade will pay, uncapped if unset
+    pub static ref GAS_MAX_FEE_PER_GAS: Option<U256> = get_env_var("GAS_MAX_FEE_PER_GAS")
+        .ok()
+        .map(|wei| wei.parse().expect("Failed to parse GAS_MAX_FEE_PER_GAS"));
+    // Seconds a trade tx has to confirm before it is replaced with higher
+    // fees, and replacements sent before it is cancelled instead
+    pub static ref TX_CONFIRMATION_TIMEOUT_SECONDS: u64 =
+        get_env_var("TX_CONFIRMATION_TIMEOUT_SECONDS")
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("Failed to parse TX_CONFIRMATION_TIMEOUT_SECONDS")
+            })
+            .unwrap_or(10);
+    pub static ref TX_MAX_REPLACEMENTS: u32 = get_env_var("TX_MAX_REPLACEMENTS")
+        .map(|replacements| replacements.parse().expect("Failed to parse TX_MAX_REPLACEMENTS"))
+        .unwrap_or(2);
+    // Cancellations sent after the replacements before the nonce is left to
+    // wallet reconciliation
+    pub static ref TX_MAX_CANCELLATIONS: u32 = get_env_var("TX_MAX_CANCELLATIONS")
+        .map(|cancellations| {
+            cancellations
+                .parse()
+                .expect("Failed to parse TX_MAX_CANCELLATIONS")
+        })
+        .unwrap_or(3);
+    // Fee increase of each replacement, nodes require at least 10%
+    pub static ref TX_FEE_BUMP_BPS: u64 = get_env_var("TX_FEE_BUMP_BPS")
+        .map(|bps| bps.parse().expect("Failed to parse TX_FEE_BUMP_BPS"))
+        .unwrap_or(1250);
+    // Where live trades are sent, one of "public", "private:<rpc url>" or
+    // "bundle:<relay url>"
+    pub static ref SUBMISSION_BACKEND: SubmissionBackend = get_env_var("SUBMISSION_BACKEND")
+        .map(|backend| backend.parse().expect("Failed to parse SUBMISSION_BACKEND"))
+        .unwrap_or_default();
+    // Seconds between checks of the signer's balances against the books of
+    // live strategies, and the difference from a position's amount tolerated
+    pub static ref WALLET_RECONCILE_INTERVAL_SECONDS: u64 =
+        get_env_var("WALLET_RECONCILE_INTERVAL_SECONDS")
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("Failed to parse WALLET_RECONCILE_INTERVAL_SECONDS")
+            })
+            .unwrap_or(60);
+    pub static ref WALLET_MISMATCH_TOLERANCE_BPS: u64 =
+        get_env_var("WALLET_MISMATCH_TOLERANCE_BPS")
+            .map(|bps| bps.parse().expect("Failed to parse WALLET_MISMATCH_TOLERANCE_BPS"))
+            .unwrap_or(100);
+    // Orphaned holdings are sold back to weth at this slippage if set, and
+    // only flagged otherwise
+    pub static ref WALLET_LIQUIDATION_SLIPPAGE_BPS: Option<u64> =
+        get_env_var("WALLET_LIQUIDATION_SLIPPAGE_BPS").ok().map(|bps| {
+            bps.parse()
+                .expect("Failed to parse WALLET_LIQUIDATION_SLIPPAGE_BPS")
+        });
+    // Weth in wei each strategy starts a backtest or paper trading with. Live
+    // trading sizes against the signer's balance instead.
+    pub static ref BACKTEST_STARTING_CASH: U256 = get_env_var("BACKTEST_STARTING_CASH")
+        .map(|cash| cash.parse().expect("Failed to parse BACKTEST_STARTING_CASH"))
+        .unwrap_or(U256::from(10_000_000_000_000_000_000u128));
+    // Protective exits applied to every open position, thresholds in basis
+    // points of the entry price. Unset rules are disabled.
+    pub static ref EXIT_RULES: ExitRules = ExitRules {
+        stop_loss_bps: get_env_var("EXIT_STOP_LOSS_BPS")
+            .ok()
+            .map(|bps| bps.parse().expect("Failed to parse EXIT_STOP_LOSS_BPS")),
+        take_profit_bps: get_env_var("EXIT_TAKE_PROFIT_BPS")
+            .ok()
+            .map(|bps| bps.parse().expect("Failed to parse EXIT_TAKE_PROFIT_BPS")),
+        trailing_stop_bps: get_env_var("EXIT_TRAILING_STOP_BPS")
+            .ok()
+            .map(|bps| bps.parse().expect("Failed to parse EXIT_TRAILING_STOP_BPS")),
+        max_hold_seconds: get_env_var("EXIT_MAX_HOLD_SECONDS")
+            .ok()
+            .map(|seconds| seconds.parse().expect("Failed to parse EXIT_MAX_HOLD_SECONDS")),
+        breakeven_trigger_bps: get_env_var("EXIT_BREAKEVEN_TRIGGER_BPS")
+            .ok()
+            .map(|bps| bps.parse().expect("Failed to parse EXIT_BREAKEVEN_TRIGGER_BPS")),
+    };
+    // Portfolio limits each trade controller checks before opening a position,
+    // notional amounts in wei. Unset limits are disabled.
+    pub static ref RISK_LIMITS: RiskLimits = RiskLimits {
+        max_open_positions: get_env_var("RISK_MAX_OPEN_POSITIONS")
+            .ok()
+            .map(|count| count.parse().expect("Failed to parse RISK_MAX_OPEN_POSITIONS")),
+        max_token_notional: get_env_var("RISK_MAX_TOKEN_NOTIONAL")
+            .ok()
+            .map(|wei| wei.parse().expect("Failed to parse RISK_MAX_TOKEN_NOTIONAL")),
+        max_total_notional: get_env_var("RISK_MAX_TOTAL_NOTIONAL")
+            .ok()
+            .map(|wei| wei.parse().expect("Failed to parse RISK_MAX_TOTAL_NOTIONAL")),
+        max_block_notional: get_env_var("RISK_MAX_BLOCK_NOTIONAL")
+            .ok()
+            .map(|wei| wei.parse().expect("Failed to parse RISK_MAX_BLOCK_NOTIONAL")),
+        daily_loss_limit: get_env_var("RISK_DAILY_LOSS_LIMIT")
+            .ok()
+            .map(|wei| wei.parse().expect("Failed to parse RISK_DAILY_LOSS_LIMIT")),
+        stop_out_cooldown_seconds: get_env_var("RISK_STOP_OUT_COOLDOWN_SECONDS")
+            .ok()
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("Failed to parse RISK_STOP_OUT_COOLDOWN_SECONDS")
+            }),
+    };
+    // If set, backtests SWEEP_STRATEGY over its parameter space rather than
+    // running STRATEGIES, e.g. "grid" or "random:20"
+    pub static ref SWEEP: Option<SweepMode> = get_env_var("SWEEP")
+        .ok()
+        .map(|mode| mode.parse().expect("Failed to parse SWEEP"));
+    pub static ref SWEEP_STRATEGY: String =
+        get_env_var("SWEEP_STRATEGY").unwrap_or_else(|_| "momentum".to_string());
+    pub static ref SWEEP_SEED: u64 = get_env_var("SWEEP_SEED")
+        .map(|seed| seed.parse().expect("Failed to parse SWEEP_SEED"))
+        .unwrap_or(0);
+    pub static ref SWEEP_RANK_BY: RankMetric = get_env_var("SWEEP_RANK_BY")
+        .map(|metric| metric.parse().expect("Failed to parse SWEEP_RANK_BY"))
+        .unwrap_or(RankMetric::RealizedPnl);
+    // If both are set, walk forward optimizes SWEEP_STRATEGY with the SWEEP
+    // mode (grid if unset) over rolling in sample windows of this many blocks,
+    // each validated on the following out of sample window.
+    pub static ref WALK_FORWARD_IN_SAMPLE_BLOCKS: Option<u64> =
+        get_env_var("WALK_FORWARD_IN_SAMPLE_BLOCKS")
+            .ok()
+            .map(|blocks| {
+                blocks
+                    .parse()
+                    .expect("Failed to parse WALK_FORWARD_IN_SAMPLE_BLOCKS")
+            });
+    pub static ref WALK_FORWARD_OUT_OF_SAMPLE_BLOCKS: Option<u64> =
+        get_env_var("WALK_FORWARD_OUT_OF_SAMPLE_BLOCKS")
+            .ok()
+            .map(|blocks| {
+                blocks
+                    .parse()
+                    .expect("Failed to parse WALK_FORWARD_OUT_OF_SAMPLE_BLOCKS")
+            });
+    // If set, backtests simulate closing positions still open at the end
+    // block so that their pnl is realized rather than marked to market
+    pub static ref BACKTEST_FORCE_CLOSE_AT_END: bool = get_env_var("BACKTEST_FORCE_CLOSE_AT_END")
+        .unwrap_or_else(|_| "false".to_string())
+        .parse()
+        .expect("Failed to parse BACKTEST_FORCE_CLOSE_AT_END");
     pub static ref IS_BACKTEST: bool = match (END_BLOCK_ID.deref(), START_BLOCK_ID.deref()) {
         (BlockId::Latest, BlockId::Latest) => false,
         _ => true,
     };
+    // Follows the chain from the latest block like live trading, but fills
+    // trades by simulation rather than sending them
+    pub static ref IS_PAPER_TRADING: bool = !*IS_BACKTEST
+        && get_env_var("PAPER_TRADING")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .expect("Failed to parse PAPER_TRADING");
+    // Seconds between persisting paper trading results as a growing backtest
+    pub static ref PAPER_TRADING_PERSIST_INTERVAL_SECONDS: u64 =
+        get_env_var("PAPER_TRADING_PERSIST_INTERVAL_SECONDS")
+            .map(|seconds| {
+                seconds
+                    .parse()
+                    .expect("Failed to parse PAPER_TRADING_PERSIST_INTERVAL_SECONDS")
+            })
+            .unwrap_or(60);
 }
@@ -1,5 +1,5 @@
-use pochtecatl_db::BlockModel;
-use pochtecatl_primitives::{constants, Block, BlockBuilder, IndexedTrade, RpcProvider};
+use pochtecatl_db::{BlockChunkModel, BlockModel};
+use pochtecatl_primitives::{Block, BlockBuilder, IndexedTrade, RpcProvider};
 
 use alloy::{
     network::Ethereum,
@@ -13,7 +13,7 @@ use eyre::{eyre, Result};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use std::collections::BTreeMap;
-use tracing::{debug, error};
+use tracing::debug;
 
 pub enum BlockChunkSource {
     Rpc,
@@ -51,7 +51,24 @@ impl BlockChunk {
                     range_end_block_number = end_block_number,
                     "Fetched block chunk from rpc"
                 );
-                BlockChunk::fetch_from_rpc(rpc_provider, start_block_number, end_block_number).await
+                let block_chunk =
+                    BlockChunk::fetch_from_rpc(rpc_provider, start_block_number, end_block_number)
+                        .await;
+
+                // Untrust whatever was previously indexed for the range
+                if let Err(err) = &block_chunk {
+                    let mut conn = db_provider.get()?;
+                    let tx = conn.transaction()?;
+                    BlockChunkModel::incomplete(
+                        start_block_number,
+                        end_block_number,
+                        format!("{:?}", err),
+                    )
+                    .upsert(&tx)?;
+                    tx.commit()?;
+                }
+
+                block_chunk
             }
         }
     }
@@ -65,30 +82,18 @@ impl BlockChunk {
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
     {
-        let filter = Filter::new()
-            .from_block(start_block_number)
-            .to_block(end_block_number)
-            .event_signature(IndexedTrade::event_signature_hashes());
+        let filter = Filter::new().event_signature(IndexedTrade::event_signature_hashes());
 
-        let (logs, start_block_header) = tokio::join!(
-            rpc_provider.get_logs(&filter),
+        let (logs, block_timestamps) = tokio::join!(
+            rpc_provider.get_logs_in_range(&filter, start_block_number, end_block_number),
             rpc_provider
                 .block_provider()
-                .get_block_header(start_block_number)
+                .get_block_timestamps(start_block_number, end_block_number)
         );
 
-        let mut logs_by_block_number: BTreeMap<BlockNumber, Vec<RpcLog>> = logs
-            .unwrap_or_else(|err| {
-                error!(
-                    range_start_block_number = start_block_number,
-                    range_end_block_number = end_block_number,
-                    "get_logs failed: {:?}",
-                    err
-                );
-                Vec::new()
-            })
-            .into_iter()
-            .fold(BTreeMap::new(), |mut acc, log| {
+        // A range whose logs can't be fetched must not be indexed as empty
+        let mut logs_by_block_number: BTreeMap<BlockNumber, Vec<RpcLog>> =
+            logs?.into_iter().fold(BTreeMap::new(), |mut acc, log| {
                 acc.entry(log.block_number.unwrap())
                     .or_insert_with(Vec::new)
                     .push(log);
@@ -96,26 +101,15 @@ impl BlockChunk {
                 acc
             });
 
-        let start_block_timestamp = start_block_header.and_then(|header| {
-            header
-                .map(|h| h.timestamp.to::<u64>())
-                .ok_or_else(|| eyre!("Expected block {} but found None", start_block_number))
-        })?;
-
         // Parse the blocks
         let block_builders = (start_block_number..=end_block_number)
-            .map(|block_number| {
+            .zip(block_timestamps?)
+            .map(|(block_number, block_timestamp)| {
                 let block_logs = logs_by_block_number
                     .remove(&block_number)
                     .unwrap_or_default();
 
-                // Estimate the block timestamp from the start block timestamp and average
-                // block time. This avoids an rpc call for each block to lookup the header
-                // or split the multicall.
-                let estimated_block_timestamp = start_block_timestamp
-                    + (block_number - start_block_number) * constants::AVERAGE_BLOCK_TIME_SECONDS;
-
-                BlockBuilder::new(block_number, estimated_block_timestamp, &block_logs)
+                BlockBuilder::new(block_number, block_timestamp, &block_logs)
             })
             .collect();
 
@@ -125,6 +119,24 @@ impl BlockChunk {
         })
     }
 
+    // Persists the blocks along with a complete chunk, so later runs trust
+    // them rather than fetching the range again
+    pub fn persist(&self, db_pool: &Pool<SqliteConnectionManager>) -> Result<()> {
+        let (Some(first_block), Some(last_block)) = (self.data.first(), self.data.last()) else {
+            return Ok(());
+        };
+
+        let mut conn = db_pool.get()?;
+        let tx = conn.transaction()?;
+        for block in self.data.iter() {
+            BlockModel::from(block).upsert(&tx)?;
+        }
+        BlockChunkModel::complete(first_block.block_number, last_block.block_number).upsert(&tx)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
     fn fetch_from_db(
         db_pool: &Pool<SqliteConnectionManager>,
         start_block_number: BlockNumber,
@@ -132,6 +144,10 @@ impl BlockChunk {
     ) -> Result<Self> {
         let mut conn = db_pool.get()?;
         let tx = conn.transaction()?;
+        if !BlockChunkModel::is_range_complete(&tx, start_block_number, end_block_number)? {
+            return Err(eyre!("Blocks not indexed from a complete chunk."));
+        }
+
         let blocks = BlockModel::query_by_number_range(&tx, start_block_number, end_block_number)?;
         if blocks.len() == (end_block_number - start_block_number + 1) as usize {
             Ok(BlockChunk {
@@ -151,19 +167,21 @@ impl BlockChunk {
 mod tests {
     use super::{BlockChunk, BlockChunkSource};
     use crate::config;
-    use pochtecatl_db::{connect as connect_db, BlockModel};
-    use pochtecatl_primitives::new_http_signer_provider;
+    use pochtecatl_db::{connect as connect_db, BlockChunkModel, BlockModel};
+    use pochtecatl_primitives::{new_http_signer_provider, SignerSource};
 
     use eyre::Result;
-    use std::sync::Arc;
     use hex_literal::hex;
+    use std::sync::Arc;
 
     #[tokio::test]
     async fn test_fetch_block_chunk_rpc() -> Result<()> {
         let rpc_provider = Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -212,7 +230,9 @@ mod tests {
         let rpc_provider = Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -235,6 +255,8 @@ mod tests {
                 }
                 .insert(&tx)?;
             }
+            BlockChunkModel::complete(start_block_number as u64, end_block_number as u64)
+                .upsert(&tx)?;
 
             tx.commit()?;
         }
@@ -1,7 +1,6 @@
-use crate::strategies::StrategyExecutor;
+use crate::{config, strategies::StrategyExecutor};
 
-use pochtecatl_db::BlockModel;
-use pochtecatl_primitives::{Block, Resolution, RpcProvider};
+use pochtecatl_primitives::{Block, BlockMessage, Resolution, RpcProvider};
 
 use super::{
     super::{time_price_bar_store::TimePriceBarStore, Indexer},
@@ -35,6 +34,11 @@ where
     rpc_provider: Arc<RpcProvider<T, P>>,
     start_block_number: BlockNumber,
     end_block_number: BlockNumber,
+    // Blocks preceding the range indexed only to warm up the time price bars
+    warmup_blocks: u64,
+    is_backtest: bool,
+    // Whether time price bars are rehydrated from and persisted to the db
+    persist_time_price_bars: bool,
     time_price_bar_store: Arc<TimePriceBarStore>,
 }
 
@@ -50,18 +54,51 @@ where
         end_block_number: B,
         is_backtest: bool,
     ) -> BlockRangeIndexer<T, P> {
+        let time_price_bar_store = Arc::new(
+            TimePriceBarStore::new(Resolution::FiveMinutes, 60, is_backtest)
+                .with_bar_samplings(config::BAR_SAMPLINGS.clone())
+                .with_db_pool(Arc::clone(&db_pool)),
+        );
+
         BlockRangeIndexer {
             rpc_provider,
+            time_price_bar_store,
             db_pool,
             start_block_number: start_block_number.into(),
             end_block_number: end_block_number.into(),
-            time_price_bar_store: Arc::new(TimePriceBarStore::new(
-                Resolution::FiveMinutes,
-                60,
-                is_backtest,
-            )),
+            warmup_blocks: 0,
+            is_backtest,
+            persist_time_price_bars: true,
+        }
+    }
+
+    // Neither rehydrates time price bars from nor persists them to the db, so
+    // runs that are compared against each other all start from the same cold
+    // bars.
+    pub fn without_persisted_time_price_bars(mut self) -> Self {
+        self.persist_time_price_bars = false;
+        self.time_price_bar_store = Arc::new(self.new_time_price_bar_store());
+        self
+    }
+
+    fn new_time_price_bar_store(&self) -> TimePriceBarStore {
+        let time_price_bar_store =
+            TimePriceBarStore::new(Resolution::FiveMinutes, 60, self.is_backtest)
+                .with_bar_samplings(config::BAR_SAMPLINGS.clone());
+
+        if self.persist_time_price_bars {
+            time_price_bar_store.with_db_pool(Arc::clone(&self.db_pool))
+        } else {
+            time_price_bar_store
         }
     }
+
+    // Indexes the given number of blocks preceding the range into the time
+    // price bars without evaluating the strategies against them.
+    pub fn with_warmup_blocks(mut self, warmup_blocks: u64) -> Self {
+        self.warmup_blocks = warmup_blocks;
+        self
+    }
 }
 
 const BLOCK_PARSER_CHUNK_SIZE: u64 = 100;
@@ -125,10 +162,10 @@ where
     Ok(())
 }
 
-async fn execute_strategy_for_block<T, P>(
+pub async fn execute_strategy_for_block<T, P>(
     parsed_block: Block,
     rpc_provider: Arc<RpcProvider<T, P>>,
-    strategy_executor: Arc<StrategyExecutor<T, P>>,
+    strategy_executors: &[Arc<StrategyExecutor<T, P>>],
     time_price_bar_store: Arc<TimePriceBarStore>,
 ) -> Result<()>
 where
@@ -139,9 +176,25 @@ where
         .insert_block(Arc::clone(&rpc_provider), &parsed_block)
         .await?;
 
-    strategy_executor
-        .on_indexed_block_message(parsed_block.into(), &time_price_bar_store)
-        .await?;
+    // Strategies share the indexed block but not their trade books, so they
+    // can be evaluated concurrently.
+    let block_message = BlockMessage::from(parsed_block);
+    let mut strategy_tasks = JoinSet::new();
+    for strategy_executor in strategy_executors.iter() {
+        let strategy_executor = Arc::clone(strategy_executor);
+        let time_price_bar_store = Arc::clone(&time_price_bar_store);
+        let block_message = block_message.clone();
+
+        strategy_tasks.spawn(async move {
+            strategy_executor
+                .on_indexed_block_message(block_message, &time_price_bar_store)
+                .await
+        });
+    }
+
+    while let Some(result) = strategy_tasks.join_next().await {
+        result??;
+    }
 
     Ok(())
 }
@@ -152,27 +205,30 @@ async fn handle_block_chunk_task<T, P>(
     db_provider: &Pool<SqliteConnectionManager>,
     rpc_provider: Arc<RpcProvider<T, P>>,
     time_price_bar_store: Arc<TimePriceBarStore>,
-    strategy_executor: Arc<StrategyExecutor<T, P>>,
+    strategy_executors: Vec<Arc<StrategyExecutor<T, P>>>,
     start_block_number: BlockNumber,
+    strategy_start_block_number: BlockNumber,
 ) -> Result<()>
 where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + 'static,
 {
+    // Warmup blocks are only inserted into the time price bars
+    let block_strategy_executors = |block: &Block| {
+        if block.block_number < strategy_start_block_number {
+            &[][..]
+        } else {
+            strategy_executors.as_slice()
+        }
+    };
+
     let mut next_block_number = start_block_number;
     let mut chunk_buffer: BTreeMap<BlockNumber, BlockChunk> = BTreeMap::new();
 
     while let Some(parsed_block_chunk) = parsed_block_chunk_receiver.recv().await {
         // Write the chunk to DB if required.
         if matches!(parsed_block_chunk.source, BlockChunkSource::Rpc) {
-            let mut conn = db_provider.get()?;
-            let tx = conn.transaction()?;
-
-            for block in parsed_block_chunk.data.iter() {
-                BlockModel::from(block).insert(&tx)?;
-            }
-
-            tx.commit()?;
+            parsed_block_chunk.persist(db_provider)?;
         }
 
         // If the chunk is the next chunk we're looking for, execute the strategy for
@@ -186,10 +242,11 @@ where
             {
                 // process the chunk
                 for block in parsed_block_chunk.data {
+                    let strategy_executors = block_strategy_executors(&block);
                     execute_strategy_for_block(
                         block,
                         Arc::clone(&rpc_provider),
-                        Arc::clone(&strategy_executor),
+                        strategy_executors,
                         Arc::clone(&time_price_bar_store),
                     )
                     .await?
@@ -216,10 +273,11 @@ where
                         .map(|b| b.block_number)
                         .unwrap_or(block_number);
                     for block in parsed_block_chunk.data {
+                        let strategy_executors = block_strategy_executors(&block);
                         execute_strategy_for_block(
                             block,
                             Arc::clone(&rpc_provider),
-                            Arc::clone(&strategy_executor),
+                            strategy_executors,
                             Arc::clone(&time_price_bar_store),
                         )
                         .await?
@@ -250,14 +308,29 @@ where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + 'static,
 {
-    async fn exec(&mut self, strategy_executor: StrategyExecutor<T, P>) -> Result<()> {
+    async fn exec(&mut self, strategy_executors: Vec<Arc<StrategyExecutor<T, P>>>) -> Result<()> {
+        let index_start_block_number = self.start_block_number.saturating_sub(self.warmup_blocks);
+
+        // Restore the finalized time price bars preceding the range so indicators
+        // are warm from the first block. Backtests warm up from blocks instead so
+        // that reruns of the same range do not depend on what was persisted since.
+        if !self.is_backtest {
+            let start_block_header = self
+                .rpc_provider
+                .block_provider()
+                .get_block_header(index_start_block_number)
+                .await?
+                .ok_or_else(|| eyre!("Missing header for block {}", index_start_block_number))?;
+            self.time_price_bar_store
+                .rehydrate(start_block_header.timestamp.to::<u64>())?;
+        }
+
         let (block_chunk_sender, block_chunk_receiver) = channel(PARSED_BLOCK_CHANNEL_CAPACITY);
 
         let strategy_executor_join_handle = {
             let rpc_provider = Arc::clone(&self.rpc_provider);
             let time_price_bar_store = Arc::clone(&self.time_price_bar_store);
-            let start_block_number = self.start_block_number;
-            let strategy_executor = Arc::new(strategy_executor);
+            let strategy_start_block_number = self.start_block_number;
             let db_pool = Arc::clone(&self.db_pool);
 
             tokio::spawn(async move {
@@ -266,15 +339,15 @@ where
                     &db_pool,
                     rpc_provider,
                     time_price_bar_store,
-                    strategy_executor,
-                    start_block_number,
+                    strategy_executors,
+                    index_start_block_number,
+                    strategy_start_block_number,
                 )
                 .await
             })
         };
         let parser_join_handle = {
             let rpc_provider = Arc::clone(&self.rpc_provider);
-            let start_block_number = self.start_block_number;
             let end_block_number = self.end_block_number;
             let db_pool = Arc::clone(&self.db_pool);
 
@@ -282,7 +355,7 @@ where
                 fetch_block_chunks_task(
                     rpc_provider,
                     db_pool,
-                    start_block_number,
+                    index_start_block_number,
                     end_block_number,
                     block_chunk_sender,
                 )
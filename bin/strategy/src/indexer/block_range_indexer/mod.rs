@@ -1,5 +1,5 @@
 pub use block_chunk::{BlockChunk, BlockChunkSource};
-pub use block_range_indexer::BlockRangeIndexer;
+pub use block_range_indexer::{execute_strategy_for_block, BlockRangeIndexer};
 
 mod block_chunk;
 mod block_range_indexer;
@@ -5,13 +5,16 @@ use crate::config;
 
 use alloy::{network::Ethereum, providers::Provider, transports::Transport};
 use eyre::Result;
+use std::sync::Arc;
 
 pub trait Indexer<T, P>
 where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + 'static,
 {
-    async fn exec(&mut self, strategy_executor: StrategyExecutor<T, P>) -> Result<()>;
+    // Each strategy executor is evaluated against every indexed block with its
+    // own isolated trade book.
+    async fn exec(&mut self, strategy_executors: Vec<Arc<StrategyExecutor<T, P>>>) -> Result<()>;
 }
 
 #[cfg(test)]
@@ -20,6 +23,7 @@ mod tests {
 
     use pochtecatl_primitives::{
         new_http_signer_provider, BlockBuilder, BlockMessage, DexPair, IndexedTrade, Pair,
+        SignerSource,
     };
 
     use alloy::{primitives::address, rpc::types::eth::Filter};
@@ -32,7 +36,9 @@ mod tests {
         let rpc_provider = Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
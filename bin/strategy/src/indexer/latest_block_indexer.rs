@@ -0,0 +1,149 @@
+use crate::{config, strategies::StrategyExecutor};
+
+use pochtecatl_primitives::{constants, Resolution, RpcProvider};
+
+use super::{
+    block_range_indexer::execute_strategy_for_block, BlockChunk, Indexer, TimePriceBarStore,
+};
+
+use alloy::{
+    network::Ethereum, primitives::BlockNumber, providers::Provider, transports::Transport,
+};
+
+use eyre::{eyre, Result};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::{cmp::min, sync::Arc, time::Duration};
+use tokio::time::sleep;
+use tracing::{debug, error, instrument};
+
+// Max blocks fetched at once when catching up to the chain head
+const BLOCK_CHUNK_SIZE: u64 = 100;
+
+// Follows the chain head from the start block, executing the strategies
+// against each block as it is mined. Runs until an error.
+pub struct LatestBlockIndexer<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    db_pool: Arc<Pool<SqliteConnectionManager>>,
+    rpc_provider: Arc<RpcProvider<T, P>>,
+    start_block_number: BlockNumber,
+    time_price_bar_store: Arc<TimePriceBarStore>,
+}
+
+impl<T, P> LatestBlockIndexer<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    pub fn new<B: Into<BlockNumber>>(
+        rpc_provider: Arc<RpcProvider<T, P>>,
+        db_pool: Arc<Pool<SqliteConnectionManager>>,
+        start_block_number: B,
+    ) -> LatestBlockIndexer<T, P> {
+        LatestBlockIndexer {
+            rpc_provider,
+            time_price_bar_store: Arc::new(
+                TimePriceBarStore::new(Resolution::FiveMinutes, 60, false)
+                    .with_bar_samplings(config::BAR_SAMPLINGS.clone())
+                    .with_db_pool(Arc::clone(&db_pool)),
+            ),
+            db_pool,
+            start_block_number: start_block_number.into(),
+        }
+    }
+
+    async fn latest_block_number(&self) -> Result<BlockNumber> {
+        self.rpc_provider
+            .block_provider()
+            .get_latest_block_header()
+            .await?
+            .number
+            .map(|number| number.to::<u64>())
+            .ok_or_else(|| eyre!("Latest block header has no number"))
+    }
+
+    // Fetches the blocks in the range, persisting them for later backtests
+    #[instrument(skip(self))]
+    async fn fetch_block_chunk(
+        &self,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+    ) -> Result<BlockChunk> {
+        let block_chunk =
+            BlockChunk::fetch_from_rpc(&self.rpc_provider, start_block_number, end_block_number)
+                .await?;
+
+        block_chunk.persist(&self.db_pool)?;
+
+        Ok(block_chunk)
+    }
+}
+
+impl<T, P> Indexer<T, P> for LatestBlockIndexer<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    async fn exec(&mut self, strategy_executors: Vec<Arc<StrategyExecutor<T, P>>>) -> Result<()> {
+        // Restore the finalized time price bars preceding the start block so
+        // indicators are warm from the first block.
+        {
+            let start_block_header = self
+                .rpc_provider
+                .block_provider()
+                .get_block_header(self.start_block_number)
+                .await?
+                .ok_or_else(|| eyre!("Missing header for block {}", self.start_block_number))?;
+            self.time_price_bar_store
+                .rehydrate(start_block_header.timestamp.to::<u64>())?;
+        }
+
+        let mut next_block_number = self.start_block_number;
+        loop {
+            // Failed rpc calls are retried on the next block rather than
+            // ending the run
+            let block_chunk = match self.latest_block_number().await {
+                Ok(latest_block_number) if latest_block_number >= next_block_number => {
+                    let end_block_number = min(
+                        latest_block_number,
+                        next_block_number + BLOCK_CHUNK_SIZE - 1,
+                    );
+                    self.fetch_block_chunk(next_block_number, end_block_number)
+                        .await
+                        .map_err(|err| {
+                            error!(
+                                start_block_number = next_block_number,
+                                end_block_number, "Failed to fetch latest blocks: {:?}", err
+                            )
+                        })
+                        .ok()
+                }
+                Ok(_) => None,
+                Err(err) => {
+                    error!("Failed to get latest block number: {:?}", err);
+                    None
+                }
+            };
+
+            match block_chunk {
+                Some(block_chunk) => {
+                    for block in block_chunk.data {
+                        next_block_number = block.block_number + 1;
+                        execute_strategy_for_block(
+                            block,
+                            Arc::clone(&self.rpc_provider),
+                            &strategy_executors,
+                            Arc::clone(&self.time_price_bar_store),
+                        )
+                        .await?
+                    }
+                    debug!(next_block_number, "Processed latest blocks");
+                }
+                None => sleep(Duration::from_secs(constants::AVERAGE_BLOCK_TIME_SECONDS)).await,
+            }
+        }
+    }
+}
@@ -2,8 +2,11 @@ pub use block_range_indexer::{BlockChunk, BlockRangeIndexer};
 
 pub use indexer::Indexer;
 
+pub use latest_block_indexer::LatestBlockIndexer;
+
 pub use time_price_bar_store::TimePriceBarStore;
 
 mod block_range_indexer;
 mod indexer;
+mod latest_block_indexer;
 mod time_price_bar_store;
@@ -1,5 +1,7 @@
+use pochtecatl_db::TimePriceBarModel;
 use pochtecatl_primitives::{
-    constants, Block, IndicatorsConfig, Resolution, ResolutionTimestamp, RpcProvider, TimePriceBars,
+    constants, BarSampling, Block, FinalizedTimePriceBar, IndicatorsConfig, PriceBars, Resolution,
+    ResolutionTimestamp, RpcProvider, SampledPriceBars, TimePriceBars,
 };
 
 use alloy::{
@@ -11,7 +13,12 @@ use alloy::{
 
 use eyre::{Context, Result};
 use fnv::FnvHashMap;
-use std::sync::{Arc, RwLock};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
 use tracing::{debug, instrument, warn};
 
 pub struct TimePriceBarStore {
@@ -20,6 +27,18 @@ pub struct TimePriceBarStore {
     retention_count: u64,
     is_backtest: bool,
 
+    // Activity sampled bars maintained alongside the time price bars, one
+    // collection per configured sampling in the same order.
+    bar_samplings: Vec<BarSampling>,
+    sampled_price_bars: RwLock<FnvHashMap<Address, Vec<SampledPriceBars>>>,
+
+    // If set, time price bars are persisted as they finalize
+    db_pool: Option<Arc<Pool<SqliteConnectionManager>>>,
+
+    // Timestamps of the blocks within the retention window, used to pad the
+    // time price bars of pairs without trades in some blocks
+    block_timestamps: RwLock<BTreeMap<BlockNumber, u64>>,
+
     last_inserted_block_number: RwLock<Option<BlockNumber>>,
     last_pruned_at_block_number: RwLock<Option<BlockNumber>>,
 }
@@ -31,15 +50,101 @@ impl TimePriceBarStore {
             time_price_bars: RwLock::new(FnvHashMap::default()),
             retention_count,
             is_backtest,
+            bar_samplings: Vec::new(),
+            sampled_price_bars: RwLock::new(FnvHashMap::default()),
+            db_pool: None,
+            block_timestamps: RwLock::new(BTreeMap::new()),
             last_inserted_block_number: RwLock::new(None),
             last_pruned_at_block_number: RwLock::new(None),
         }
     }
 
+    pub fn with_bar_samplings(mut self, bar_samplings: Vec<BarSampling>) -> Self {
+        self.bar_samplings = bar_samplings;
+        self
+    }
+
+    pub fn with_db_pool(mut self, db_pool: Arc<Pool<SqliteConnectionManager>>) -> Self {
+        self.db_pool = Some(db_pool);
+        self
+    }
+
+    fn new_time_price_bars(&self) -> TimePriceBars {
+        TimePriceBars::new(
+            Some(self.retention_count),
+            self.resolution,
+            Some(IndicatorsConfig::All),
+        )
+    }
+
+    // Restores the finalized time price bars within the retention window
+    // preceding the given timestamp, so indicators are warm when indexing resumes.
+    #[instrument(skip(self))]
+    pub fn rehydrate(&self, before_timestamp: u64) -> Result<()> {
+        let db_pool = match self.db_pool.as_ref() {
+            Some(db_pool) => db_pool,
+            None => return Ok(()),
+        };
+
+        let end_timestamp = ResolutionTimestamp::from_timestamp(before_timestamp, &self.resolution)
+            .previous(&self.resolution);
+        let start_timestamp = ResolutionTimestamp(
+            end_timestamp
+                .0
+                .saturating_sub((self.retention_count - 1) * self.resolution.offset()),
+        );
+
+        let time_price_bar_models = {
+            let mut conn = db_pool.get()?;
+            let tx = conn.transaction()?;
+            let models = TimePriceBarModel::query_by_timestamp_range(
+                &tx,
+                self.resolution.as_str(),
+                start_timestamp.0,
+                end_timestamp.0,
+            )?;
+            tx.finish()?;
+            models
+        };
+
+        let mut time_price_bars = self.time_price_bars.write().unwrap();
+        let rehydrated_count = time_price_bar_models.len();
+        for time_price_bar_model in time_price_bar_models.into_iter() {
+            let pair_address: Address = time_price_bar_model.pair_address.0.into();
+            let timestamp = ResolutionTimestamp(time_price_bar_model.timestamp.0);
+
+            time_price_bars
+                .entry(pair_address)
+                .or_insert_with(|| self.new_time_price_bars())
+                .insert_finalized(
+                    timestamp,
+                    FinalizedTimePriceBar::try_from(time_price_bar_model)?,
+                )
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to rehydrate time price bar for pair {}",
+                        pair_address
+                    )
+                })?;
+        }
+
+        debug!(
+            rehydrated_count,
+            pair_count = time_price_bars.len(),
+            "rehydrated time price bars"
+        );
+
+        Ok(())
+    }
+
     pub fn time_price_bars(&self) -> &RwLock<FnvHashMap<Address, TimePriceBars>> {
         &self.time_price_bars
     }
 
+    pub fn sampled_price_bars(&self) -> &RwLock<FnvHashMap<Address, Vec<SampledPriceBars>>> {
+        &self.sampled_price_bars
+    }
+
     pub fn resolution(&self) -> &Resolution {
         &self.resolution
     }
@@ -83,9 +188,48 @@ impl TimePriceBarStore {
                 })
         };
 
+        // Fetch the timestamps of any blocks the time price bars must be padded
+        // with that are not already known
+        let pad_from_block_number = {
+            let time_price_bars = self.time_price_bars.read().unwrap();
+            block
+                .pair_ticks
+                .keys()
+                .filter_map(|pair_address| {
+                    time_price_bars
+                        .get(pair_address)
+                        .and_then(|pair_time_price_bars| pair_time_price_bars.last_block_number())
+                })
+                .filter(|last_block_number| last_block_number + 1 < block.block_number)
+                .map(|last_block_number| last_block_number + 1)
+                .min()
+        };
+        if let Some(pad_from_block_number) = pad_from_block_number {
+            let fetch_from_block_number = {
+                let block_timestamps = self.block_timestamps.read().unwrap();
+                (pad_from_block_number..block.block_number)
+                    .find(|block_number| !block_timestamps.contains_key(block_number))
+            };
+
+            if let Some(fetch_from_block_number) = fetch_from_block_number {
+                let fetched_block_timestamps = rpc_provider
+                    .block_provider()
+                    .get_block_timestamps(fetch_from_block_number, block.block_number - 1)
+                    .await
+                    .wrap_err("Failed to get timestamps of padded blocks")?;
+                self.block_timestamps.write().unwrap().extend(
+                    (fetch_from_block_number..block.block_number).zip(fetched_block_timestamps),
+                );
+            }
+        }
+
+        let mut newly_finalized_time_price_bars = Vec::new();
+
         // Insert the new block price bars
         {
             let mut time_price_bars = self.time_price_bars.write().unwrap();
+            let mut sampled_price_bars = self.sampled_price_bars.write().unwrap();
+            let mut block_timestamps = self.block_timestamps.write().unwrap();
 
             // If this block is behind the last inserted block number, this is a reorg
             // and we need to prune existing data
@@ -109,6 +253,25 @@ impl TimePriceBarStore {
                             time_price_bars.remove(pair_address);
                         }
 
+                        let mut pair_addresses_to_remove = Vec::new();
+                        for (pair_address, pair_sampled_price_bars) in sampled_price_bars.iter_mut()
+                        {
+                            for price_bars in pair_sampled_price_bars.iter_mut() {
+                                price_bars.prune_to_reorged_block_number(block.block_number)?;
+                            }
+
+                            if pair_sampled_price_bars
+                                .iter()
+                                .all(|price_bars| price_bars.is_empty())
+                            {
+                                pair_addresses_to_remove.push(pair_address.clone());
+                            }
+                        }
+
+                        for pair_address in pair_addresses_to_remove.iter() {
+                            sampled_price_bars.remove(pair_address);
+                        }
+
                         debug!(
                             block_number = block.block_number,
                             "pruned time price bars due to reorg"
@@ -118,19 +281,22 @@ impl TimePriceBarStore {
                 }
             }
 
+            // Record this block's timestamp, replacing any reorged blocks
+            block_timestamps.split_off(&block.block_number);
+            block_timestamps.insert(block.block_number, block.block_timestamp);
+
             // Insert new BlockPriceBar items into time_price_bars
             for (pair_address, pair) in block.pair_ticks.iter() {
-                let time_price_bars =
-                    time_price_bars
-                        .entry(pair_address.clone())
-                        .or_insert_with(|| {
-                            TimePriceBars::new(
-                                Some(self.retention_count),
-                                self.resolution,
-                                Some(IndicatorsConfig::All),
-                            )
-                        });
+                let time_price_bars = time_price_bars
+                    .entry(pair_address.clone())
+                    .or_insert_with(|| self.new_time_price_bars());
+                let previous_finalized_timestamp = *time_price_bars.last_finalized_timestamp();
 
+                time_price_bars
+                    .pad_to(block.block_number, &block_timestamps)
+                    .wrap_err_with(|| {
+                        format!("Failed to pad time price bars for pair {}", pair_address)
+                    })?;
                 time_price_bars
                     .insert_data(
                         block.block_number,
@@ -143,7 +309,61 @@ impl TimePriceBarStore {
                             "Failed to insert new block price bar for pair {}",
                             pair_address
                         )
-                    })?
+                    })?;
+
+                if self.db_pool.is_some() {
+                    if let Some(last_finalized_timestamp) =
+                        time_price_bars.last_finalized_timestamp()
+                    {
+                        let start_timestamp = previous_finalized_timestamp
+                            .map(|ts| ts.next(&self.resolution))
+                            .unwrap_or_else(ResolutionTimestamp::zero);
+                        newly_finalized_time_price_bars.extend(
+                            time_price_bars
+                                .finalized_range(&start_timestamp, last_finalized_timestamp)
+                                .into_iter()
+                                .map(|(timestamp, finalized_time_price_bar)| {
+                                    finalized_time_price_bar.to_model(
+                                        *pair_address,
+                                        &self.resolution,
+                                        timestamp,
+                                    )
+                                }),
+                        );
+                    }
+                }
+
+                if !self.bar_samplings.is_empty() {
+                    let pair_sampled_price_bars = sampled_price_bars
+                        .entry(pair_address.clone())
+                        .or_insert_with(|| {
+                            self.bar_samplings
+                                .iter()
+                                .map(|bar_sampling| {
+                                    SampledPriceBars::new(
+                                        Some(self.retention_count),
+                                        bar_sampling.clone(),
+                                    )
+                                })
+                                .collect()
+                        });
+
+                    for price_bars in pair_sampled_price_bars.iter_mut() {
+                        price_bars
+                            .insert_pair_block_tick(
+                                block.block_number,
+                                pair,
+                                block.block_timestamp,
+                                finalized_timestamp,
+                            )
+                            .wrap_err_with(|| {
+                                format!(
+                                    "Failed to insert new sampled price bar for pair {}",
+                                    pair_address
+                                )
+                            })?;
+                    }
+                }
             }
 
             // Prune any stale time price bars
@@ -175,8 +395,17 @@ impl TimePriceBarStore {
                                 "pruning time price bar"
                             );
                             time_price_bars.remove(&pair_address);
+                            sampled_price_bars.remove(&pair_address);
                         }
 
+                        // Prune block timestamps outside the retention window
+                        let retained_from_timestamp = block
+                            .block_timestamp
+                            .saturating_sub(self.retention_count * self.resolution.offset());
+                        block_timestamps.retain(|_, block_timestamp| {
+                            *block_timestamp >= retained_from_timestamp
+                        });
+
                         last_pruned_at_block_number.replace(block.block_number);
                     }
                     None => {
@@ -188,6 +417,19 @@ impl TimePriceBarStore {
             }
         }
 
+        // Persist any time price bars finalized by this block
+        if let (Some(db_pool), false) = (
+            self.db_pool.as_ref(),
+            newly_finalized_time_price_bars.is_empty(),
+        ) {
+            let mut conn = db_pool.get()?;
+            let tx = conn.transaction()?;
+            for time_price_bar_model in newly_finalized_time_price_bars.into_iter() {
+                time_price_bar_model.upsert(&tx)?;
+            }
+            tx.commit()?;
+        }
+
         // Update the last inserted block number
         {
             let mut last_inserted_block_number = self.last_inserted_block_number.write().unwrap();
@@ -205,7 +447,7 @@ mod tests {
 
     use pochtecatl_primitives::{
         new_http_signer_provider, Block, BlockBuilder, IndexedTrade, Resolution,
-        ResolutionTimestamp, RpcProvider, TTLCache,
+        ResolutionTimestamp, RpcProvider, SignerSource, TTLCache,
     };
 
     use alloy::{
@@ -263,7 +505,9 @@ mod tests {
         let rpc_provider = Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -321,8 +565,10 @@ mod tests {
             let inner = Arc::new(
                 new_http_signer_provider(
                     url::Url::parse(config::RPC_URL.as_str())?,
-                    &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
-                        .into(),
+                    &SignerSource::PrivateKey(
+                        hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                            .into(),
+                    ),
                     None,
                     true,
                 )
@@ -340,8 +586,10 @@ mod tests {
             Arc::new(
                 new_http_signer_provider(
                     url::Url::parse(config::RPC_URL.as_str())?,
-                    &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
-                        .into(),
+                    &SignerSource::PrivateKey(
+                        hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                            .into(),
+                    ),
                     Some(TTLCache::new(mock_finalized_header, None)),
                     true,
                 )
@@ -404,8 +652,10 @@ mod tests {
             let inner = Arc::new(
                 new_http_signer_provider(
                     url::Url::parse(config::RPC_URL.as_str())?,
-                    &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
-                        .into(),
+                    &SignerSource::PrivateKey(
+                        hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                            .into(),
+                    ),
                     None,
                     false,
                 )
@@ -423,8 +673,10 @@ mod tests {
             Arc::new(
                 new_http_signer_provider(
                     url::Url::parse(config::RPC_URL.as_str())?,
-                    &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
-                        .into(),
+                    &SignerSource::PrivateKey(
+                        hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                            .into(),
+                    ),
                     Some(TTLCache::new(mock_finalized_header, None)),
                     false,
                 )
@@ -1,31 +1,46 @@
+mod backtest;
 mod config;
 mod indexer;
 mod strategies;
 mod trade_controller;
 
-use pochtecatl_db::{connect, NewBacktestModel};
-use pochtecatl_primitives::{new_http_signer_provider, BlockId, RpcProvider};
+use pochtecatl_db::connect;
+use pochtecatl_primitives::{
+    new_failover_signer_provider, BlockId, FailoverTransport, IndicatorsConfig, RpcProvider,
+};
 
-use indexer::{BlockRangeIndexer, Indexer};
+use backtest::{
+    insert_backtest, warmup_blocks, PaperTradingRecorder, Sweep, SweepMode, WalkForward,
+};
+use indexer::{BlockRangeIndexer, Indexer, LatestBlockIndexer};
 
-use strategies::{MomentumStrategy, StrategyExecutor};
+use strategies::{new_strategy, Strategy, StrategyExecutor};
 use tracing_subscriber::EnvFilter;
-use trade_controller::TradeController;
+use trade_controller::{
+    GasPolicy, Ledger, TradeController, TradeJournal, TransactionManager, WalletReconciler,
+};
 
-use alloy::{network::Ethereum, providers::Provider, transports::Transport};
+use alloy::{network::Ethereum, primitives::I256, providers::Provider, transports::Transport};
 
-use eyre::{eyre, Result, WrapErr};
+use eyre::{eyre, Result};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tracing::{info, instrument};
 
-fn make_indexer<T, P>(
+// Indexes the configured block range, or follows the chain from the latest
+// block if live or paper trading
+async fn exec_indexer<T, P>(
     rpc_provider: Arc<RpcProvider<T, P>>,
     db_pool: Arc<Pool<SqliteConnectionManager>>,
     start_block_id: &BlockId,
     end_block_id: &BlockId,
-) -> Result<impl Indexer<T, P>>
+    strategy_executors: Vec<Arc<StrategyExecutor<T, P>>>,
+) -> Result<()>
 where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + 'static,
@@ -33,13 +48,20 @@ where
     match (start_block_id, end_block_id) {
         (BlockId::BlockNumber(start), BlockId::BlockNumber(end)) => {
             if start < end {
-                Ok(BlockRangeIndexer::new(
+                BlockRangeIndexer::new(
                     rpc_provider,
                     db_pool,
                     start.clone(),
                     end.clone(),
                     *config::IS_BACKTEST,
-                ))
+                )
+                .with_warmup_blocks(if *config::IS_BACKTEST {
+                    warmup_blocks(&IndicatorsConfig::All, *start)
+                } else {
+                    0
+                })
+                .exec(strategy_executors)
+                .await
             } else {
                 Err(eyre!(
                     "Failed to create BlockRangeIndexer due to invalid block numbers: start {}, end {}",
@@ -48,11 +70,93 @@ where
                 ))
             }
         }
-        (BlockId::Latest, BlockId::Latest) => unimplemented!(),
+        (BlockId::Latest, BlockId::Latest) => {
+            let start_block_number = rpc_provider
+                .block_provider()
+                .get_latest_block_header()
+                .await?
+                .number
+                .map(|number| number.to::<u64>())
+                .ok_or_else(|| eyre!("Latest block header has no number"))?;
+
+            if *config::IS_PAPER_TRADING {
+                PaperTradingRecorder::new(
+                    Arc::clone(&db_pool),
+                    start_block_number,
+                    &strategy_executors,
+                )?
+                .spawn(Duration::from_secs(
+                    *config::PAPER_TRADING_PERSIST_INTERVAL_SECONDS,
+                ));
+            }
+
+            LatestBlockIndexer::new(rpc_provider, db_pool, start_block_number)
+                .exec(strategy_executors)
+                .await
+        }
         _ => Err(eyre!("Failed to create Indexer")),
     }
 }
 
+fn make_gas_policy() -> GasPolicy {
+    let gas_policy = GasPolicy::new()
+        .with_fee_history_blocks(*config::GAS_FEE_HISTORY_BLOCKS)
+        .with_priority_fee_percentile(*config::GAS_PRIORITY_FEE_PERCENTILE);
+
+    match *config::GAS_MAX_FEE_PER_GAS {
+        Some(max_fee_per_gas) => gas_policy.with_max_fee_per_gas(max_fee_per_gas),
+        None => gas_policy,
+    }
+}
+
+// Each strategy trades against its own trade controller, isolating books.
+// Live strategies share the ledger of the signer's cash.
+fn make_strategy_executor<T, P>(
+    rpc_provider: &Arc<RpcProvider<T, P>>,
+    db_pool: &Arc<Pool<SqliteConnectionManager>>,
+    transaction_manager: &Arc<TransactionManager>,
+    live_ledger: &Arc<Mutex<Ledger>>,
+    strategy: Box<dyn Strategy>,
+) -> Arc<StrategyExecutor<T, P>>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let trade_controller = TradeController::new(Arc::clone(rpc_provider))
+        .with_risk_limits(config::RISK_LIMITS.clone())
+        .with_gas_policy(make_gas_policy())
+        .with_transaction_manager(Arc::clone(transaction_manager));
+    let trade_controller = if *config::IS_BACKTEST || *config::IS_PAPER_TRADING {
+        trade_controller.with_ledger(Ledger::new(*config::BACKTEST_STARTING_CASH))
+    } else {
+        // Synced from the signer's balance on each block, with positions
+        // journaled so that they survive a restart
+        trade_controller
+            .with_shared_ledger(Arc::clone(live_ledger))
+            .with_journal(TradeJournal::new(
+                Arc::clone(db_pool),
+                strategy.name().to_string(),
+            ))
+    };
+
+    let strategy_executor = StrategyExecutor::new(Arc::new(trade_controller), strategy)
+        .with_exit_rules(config::EXIT_RULES.clone())
+        .with_sizing_policy(config::SIZING_POLICY.clone())
+        .with_slippage_policy(config::SLIPPAGE_POLICY.clone())
+        .with_deadline_seconds(*config::TRADE_DEADLINE_SECONDS)
+        .with_force_close_at_end(*config::BACKTEST_FORCE_CLOSE_AT_END);
+
+    let strategy_executor = match *config::EXECUTOR_ADDRESS {
+        Some(executor_address) => strategy_executor.with_executor_address(executor_address),
+        None => strategy_executor,
+    };
+
+    Arc::new(match *config::STRATEGY_CAPITAL_ALLOCATION {
+        Some(capital_allocation) => strategy_executor.with_capital_allocation(capital_allocation),
+        None => strategy_executor,
+    })
+}
+
 #[tokio::main]
 #[instrument]
 async fn main() -> Result<()> {
@@ -74,38 +178,175 @@ async fn main() -> Result<()> {
         "start"
     );
 
+    let rpc_transport = FailoverTransport::new(
+        std::iter::once(config::RPC_URL.clone()).chain(config::RPC_FALLBACK_URLS.iter().cloned()),
+    )?
+    .with_max_head_lag(*config::RPC_MAX_HEAD_LAG_BLOCKS);
+    rpc_transport.spawn_health_check(Duration::from_secs(
+        *config::RPC_HEALTH_CHECK_INTERVAL_SECONDS,
+    ));
     let rpc_provider = Arc::new(
-        new_http_signer_provider(
-            config::RPC_URL.clone(),
-            &config::WALLET_PRIVATE_KEY,
+        new_failover_signer_provider(
+            rpc_transport,
+            config::RPC_POLICY_LAYER.clone(),
+            &config::SIGNER_SOURCE,
             None,
             *config::IS_BACKTEST,
         )
         .await?,
     );
-    let trade_controller = Arc::new(TradeController::new(Arc::clone(&rpc_provider)));
     let db_pool = Arc::new(connect(&config::DB_PATH)?);
+    // Shared by every strategy as they all send from the same signer
+    let transaction_manager = Arc::new(
+        TransactionManager::new()
+            .with_confirmation_timeout(Duration::from_secs(
+                *config::TX_CONFIRMATION_TIMEOUT_SECONDS,
+            ))
+            .with_max_replacements(*config::TX_MAX_REPLACEMENTS)
+            .with_max_cancellations(*config::TX_MAX_CANCELLATIONS)
+            .with_fee_bump_bps(*config::TX_FEE_BUMP_BPS)
+            .with_submission_backend(config::SUBMISSION_BACKEND.clone()),
+    );
+    // Shared by every live strategy as they all spend the signer's balance
+    let live_ledger = Arc::new(Mutex::new(Ledger::default()));
+
+    // A walk forward persists its own windows and out of sample backtests
+    if let (Some(in_sample_blocks), Some(out_of_sample_blocks)) = (
+        *config::WALK_FORWARD_IN_SAMPLE_BLOCKS,
+        *config::WALK_FORWARD_OUT_OF_SAMPLE_BLOCKS,
+    ) {
+        return match (*config::START_BLOCK_ID, *config::END_BLOCK_ID) {
+            (BlockId::BlockNumber(start_block_number), BlockId::BlockNumber(end_block_number)) => {
+                let sweep = Sweep::new(
+                    &config::SWEEP_STRATEGY,
+                    config::SWEEP.unwrap_or(SweepMode::Grid),
+                )
+                .with_seed(*config::SWEEP_SEED)
+                .with_rank_by(*config::SWEEP_RANK_BY);
 
-    let mut indexer = make_indexer(
+                let walk_forward_id =
+                    WalkForward::new(sweep, in_sample_blocks, out_of_sample_blocks)
+                        .run(
+                            Arc::clone(&rpc_provider),
+                            Arc::clone(&db_pool),
+                            start_block_number,
+                            end_block_number,
+                            |strategy| {
+                                make_strategy_executor(
+                                    &rpc_provider,
+                                    &db_pool,
+                                    &transaction_manager,
+                                    &live_ledger,
+                                    strategy,
+                                )
+                            },
+                        )
+                        .await?;
+                info!(walk_forward_id, "walk forward complete");
+
+                Ok(())
+            }
+            _ => Err(eyre!("WALK_FORWARD requires a block number range")),
+        };
+    }
+
+    // A sweep persists and ranks its own backtests
+    if let Some(sweep_mode) = *config::SWEEP {
+        return match (*config::START_BLOCK_ID, *config::END_BLOCK_ID) {
+            (BlockId::BlockNumber(start_block_number), BlockId::BlockNumber(end_block_number)) => {
+                let results = Sweep::new(&config::SWEEP_STRATEGY, sweep_mode)
+                    .with_seed(*config::SWEEP_SEED)
+                    .with_rank_by(*config::SWEEP_RANK_BY)
+                    .run(
+                        Arc::clone(&rpc_provider),
+                        Arc::clone(&db_pool),
+                        start_block_number,
+                        end_block_number,
+                        |strategy| {
+                            make_strategy_executor(
+                                &rpc_provider,
+                                &db_pool,
+                                &transaction_manager,
+                                &live_ledger,
+                                strategy,
+                            )
+                        },
+                    )
+                    .await?;
+
+                if let Some(best) = results.first() {
+                    info!(
+                        backtest_id = best.backtest_id,
+                        parameters = best.parameters.to_json().to_string(),
+                        "sweep complete"
+                    );
+                }
+
+                Ok(())
+            }
+            _ => Err(eyre!("SWEEP requires a block number range")),
+        };
+    }
+
+    let strategy_executors = config::STRATEGIES
+        .iter()
+        .map(|name| {
+            Ok(make_strategy_executor(
+                &rpc_provider,
+                &db_pool,
+                &transaction_manager,
+                &live_ledger,
+                new_strategy(name)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Pick up positions left by a previous live run before any signals
+    for strategy_executor in strategy_executors.iter() {
+        strategy_executor.restore_positions().await?;
+    }
+
+    // Check the signer's balances against the books of every strategy
+    if !*config::IS_BACKTEST && !*config::IS_PAPER_TRADING {
+        let wallet_reconciler = WalletReconciler::new(
+            Arc::clone(&rpc_provider),
+            strategy_executors
+                .iter()
+                .map(|strategy_executor| strategy_executor.trade_controller().trades().clone())
+                .collect(),
+        )
+        .with_mismatch_tolerance_bps(*config::WALLET_MISMATCH_TOLERANCE_BPS);
+
+        match *config::WALLET_LIQUIDATION_SLIPPAGE_BPS {
+            Some(liquidation_slippage_bps) => wallet_reconciler.with_liquidator(
+                Arc::new(
+                    TradeController::new(Arc::clone(&rpc_provider))
+                        .with_gas_policy(make_gas_policy())
+                        .with_transaction_manager(Arc::clone(&transaction_manager)),
+                ),
+                liquidation_slippage_bps,
+            ),
+            None => wallet_reconciler,
+        }
+        .spawn(Duration::from_secs(
+            *config::WALLET_RECONCILE_INTERVAL_SECONDS,
+        ));
+    }
+
+    // Execute the indexer with the strategy executors
+    exec_indexer(
         Arc::clone(&rpc_provider),
         Arc::clone(&db_pool),
         &config::START_BLOCK_ID,
         &config::END_BLOCK_ID,
-    )?;
-
-    // Execute the indexer with the strategy executor
-    indexer
-        .exec(StrategyExecutor::new(
-            Arc::clone(&trade_controller),
-            Box::new(MomentumStrategy::new()),
-        ))
-        .await?;
+        strategy_executors.clone(),
+    )
+    .await?;
 
     // wait for pending positions to settle
-    trade_controller
-        .pending_handle()
-        .await
-        .with_context(|| "pending_handle failed")?;
+    for strategy_executor in strategy_executors.iter() {
+        strategy_executor.settle().await?;
+    }
 
     // If backtesting, persist the trades for later inspection
     match (*config::START_BLOCK_ID, *config::END_BLOCK_ID) {
@@ -113,9 +354,31 @@ async fn main() -> Result<()> {
             let mut conn = db_pool.get()?;
             let tx = conn.transaction()?;
 
-            let backtest_id =
-                NewBacktestModel::new(start_block_number, end_block_number).insert(&tx)?;
-            trade_controller.insert_backtest_closed_trades(&tx, backtest_id)?;
+            for strategy_executor in strategy_executors.iter() {
+                let (backtest_id, metrics) =
+                    insert_backtest(&tx, start_block_number, end_block_number, strategy_executor)?;
+                let open_positions = strategy_executor.open_positions();
+                let unrealized_pnl = open_positions
+                    .iter()
+                    .fold(I256::ZERO, |acc, marked_position| {
+                        acc + marked_position.unrealized_pnl()
+                    });
+                info!(
+                    backtest_id,
+                    realized_pnl = metrics.realized_pnl.to_string(),
+                    trade_count = metrics.trade_count,
+                    open_position_count = open_positions.len(),
+                    unrealized_pnl = unrealized_pnl.to_string(),
+                    risk_rejection_count =
+                        strategy_executor.trade_controller().risk_rejections().len(),
+                    cash = strategy_executor
+                        .trade_controller()
+                        .cash()
+                        .unwrap_or_default()
+                        .to_string(),
+                    "backtest complete"
+                );
+            }
 
             tx.commit()?;
         }
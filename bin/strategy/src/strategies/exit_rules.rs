@@ -0,0 +1,233 @@
+use super::SignalReason;
+
+use pochtecatl_primitives::constants;
+
+use fixed::types::U32F96;
+
+// Protective exits evaluated by the executor on every indexed block for each
+// open position, independently of the strategy. Thresholds are in basis
+// points relative to the entry price, None disables the rule.
+#[derive(Debug, Clone, Default)]
+pub struct ExitRules {
+    pub stop_loss_bps: Option<u32>,
+    pub take_profit_bps: Option<u32>,
+    // Distance below the highest price seen since entry
+    pub trailing_stop_bps: Option<u32>,
+    pub max_hold_seconds: Option<u64>,
+    // Once price has risen this far above entry, exit if it returns to entry
+    pub breakeven_trigger_bps: Option<u32>,
+}
+
+// Per position state carried between evaluations.
+#[derive(Debug, Clone, Default)]
+pub struct ExitState {
+    high_water_mark: Option<U32F96>,
+    breakeven_armed: bool,
+}
+
+fn below(price: U32F96, bps: u32) -> U32F96 {
+    price.saturating_sub(price / constants::BP_FACTOR.to::<u128>() * u128::from(bps))
+}
+
+fn above(price: U32F96, bps: u32) -> U32F96 {
+    price.saturating_add(price / constants::BP_FACTOR.to::<u128>() * u128::from(bps))
+}
+
+impl ExitRules {
+    pub fn is_empty(&self) -> bool {
+        self.stop_loss_bps.is_none()
+            && self.take_profit_bps.is_none()
+            && self.trailing_stop_bps.is_none()
+            && self.max_hold_seconds.is_none()
+            && self.breakeven_trigger_bps.is_none()
+    }
+
+    // Returns the reason to exit if any rule has triggered at the given price.
+    pub fn evaluate(
+        &self,
+        state: &mut ExitState,
+        entry_price: U32F96,
+        opened_timestamp: u64,
+        price: U32F96,
+        timestamp: u64,
+    ) -> Option<SignalReason> {
+        let high_water_mark = state
+            .high_water_mark
+            .map_or(price.max(entry_price), |high_water_mark| {
+                high_water_mark.max(price)
+            });
+        state.high_water_mark = Some(high_water_mark);
+
+        if let Some(stop_price) = self.stop_loss_bps.map(|bps| below(entry_price, bps)) {
+            if price <= stop_price {
+                return Some(SignalReason::StopLoss { price, stop_price });
+            }
+        }
+
+        if let Some(trigger_bps) = self.breakeven_trigger_bps {
+            if high_water_mark >= above(entry_price, trigger_bps) {
+                state.breakeven_armed = true;
+            }
+            if state.breakeven_armed && price <= entry_price {
+                return Some(SignalReason::BreakevenStop { price, entry_price });
+            }
+        }
+
+        if let Some(stop_price) = self
+            .trailing_stop_bps
+            .map(|bps| below(high_water_mark, bps))
+        {
+            if price <= stop_price {
+                return Some(SignalReason::TrailingStop {
+                    price,
+                    high_water_mark,
+                    stop_price,
+                });
+            }
+        }
+
+        if let Some(target_price) = self.take_profit_bps.map(|bps| above(entry_price, bps)) {
+            if price >= target_price {
+                return Some(SignalReason::TakeProfit {
+                    price,
+                    target_price,
+                });
+            }
+        }
+
+        let held_seconds = timestamp.saturating_sub(opened_timestamp);
+        if self
+            .max_hold_seconds
+            .is_some_and(|max_hold_seconds| held_seconds >= max_hold_seconds)
+        {
+            return Some(SignalReason::MaxHoldTime { held_seconds });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ExitRules, ExitState};
+    use crate::strategies::SignalReason;
+
+    use fixed::types::U32F96;
+
+    fn price(v: f64) -> U32F96 {
+        U32F96::from_num(v)
+    }
+
+    #[test]
+    fn test_no_rules() {
+        let rules = ExitRules::default();
+        let mut state = ExitState::default();
+
+        assert!(rules.is_empty());
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(0.01), 1_000_000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stop_loss_and_take_profit() {
+        let rules = ExitRules {
+            stop_loss_bps: Some(1_000),
+            take_profit_bps: Some(2_000),
+            ..Default::default()
+        };
+        let mut state = ExitState::default();
+
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(0.95), 2),
+            None
+        );
+        assert!(matches!(
+            rules.evaluate(&mut state, price(1.0), 0, price(0.89), 4),
+            Some(SignalReason::StopLoss { .. })
+        ));
+
+        let mut state = ExitState::default();
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.1), 2),
+            None
+        );
+        assert!(matches!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.25), 4),
+            Some(SignalReason::TakeProfit { .. })
+        ));
+    }
+
+    #[test]
+    fn test_trailing_stop() {
+        let rules = ExitRules {
+            trailing_stop_bps: Some(1_000),
+            ..Default::default()
+        };
+        let mut state = ExitState::default();
+
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.5), 2),
+            None
+        );
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(2.0), 4),
+            None
+        );
+        // 10% below the high water mark of 2.0
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.85), 6),
+            None
+        );
+        assert!(matches!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.75), 8),
+            Some(SignalReason::TrailingStop { .. })
+        ));
+    }
+
+    #[test]
+    fn test_breakeven_stop() {
+        let rules = ExitRules {
+            breakeven_trigger_bps: Some(500),
+            ..Default::default()
+        };
+        let mut state = ExitState::default();
+
+        // not armed until price has risen 5% above entry
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.02), 2),
+            None
+        );
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(0.99), 4),
+            None
+        );
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.06), 6),
+            None
+        );
+        assert!(matches!(
+            rules.evaluate(&mut state, price(1.0), 0, price(1.0), 8),
+            Some(SignalReason::BreakevenStop { .. })
+        ));
+    }
+
+    #[test]
+    fn test_max_hold_time() {
+        let rules = ExitRules {
+            max_hold_seconds: Some(600),
+            ..Default::default()
+        };
+        let mut state = ExitState::default();
+
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 100, price(1.0), 698),
+            None
+        );
+        assert_eq!(
+            rules.evaluate(&mut state, price(1.0), 100, price(1.0), 700),
+            Some(SignalReason::MaxHoldTime { held_seconds: 600 })
+        );
+    }
+}
@@ -1,10 +1,22 @@
 pub use strategy_executor::StrategyExecutor;
+pub use exit_rules::{ExitRules, ExitState};
 pub use momentum_strategy::MomentumStrategy;
-pub use strategy::Strategy;
+pub use parameter_space::{ParameterSet, ParameterSpace};
+pub use signal::{Signal, SignalReason, SignalSide, TradeSignal};
+pub use sizing_policy::SizingPolicy;
+pub use slippage_policy::SlippagePolicy;
+pub use strategy::{new_parameterized_strategy, new_strategy, strategy_parameter_space, Strategy};
+pub use strategy_context::{PairState, Position, StrategyContext};
 
 // traits
 mod strategy;
 
+mod exit_rules;
+mod parameter_space;
+mod signal;
+mod sizing_policy;
+mod slippage_policy;
+mod strategy_context;
 mod strategy_executor;
 
 mod momentum_strategy;
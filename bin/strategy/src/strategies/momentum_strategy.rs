@@ -1,98 +1,320 @@
-use super::Strategy;
-use pochtecatl_primitives::{Indicators, ResolutionTimestamp, TimePriceBars};
+use super::{
+    PairState, ParameterSet, ParameterSpace, Position, Signal, SignalReason, SignalSide, Strategy,
+    StrategyContext, TradeSignal,
+};
+use pochtecatl_primitives::{Indicators, IndicatorsConfig, ResolutionTimestamp};
 
 use eyre::{eyre, Result};
-use tracing::debug;
+use fixed::types::U32F96;
 
-pub struct MomentumStrategy {}
+pub struct MomentumStrategy {
+    indicators_config: IndicatorsConfig,
+}
+
+// Per pair state kept between blocks
+#[derive(Default)]
+struct MomentumState {
+    // The bar the last exit was signaled in, entries are held until the next
+    // bar so a close hovering around the ema does not churn in and out
+    exited_at: Option<ResolutionTimestamp>,
+}
 
 impl MomentumStrategy {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            indicators_config: IndicatorsConfig::All,
+        }
+    }
+
+    pub fn parameter_space() -> ParameterSpace {
+        ParameterSpace::new()
+            .with_parameter("ema_period", vec![5.0, 9.0, 13.0, 21.0])
+            .with_parameter("bb_period", vec![10.0, 20.0, 30.0])
+            .with_parameter("bb_std_dev", vec![1.5, 2.0, 2.5])
+    }
+
+    // Parameters missing from the set keep their default values
+    pub fn from_parameters(parameters: &ParameterSet) -> Result<Self> {
+        let defaults = IndicatorsConfig::All;
+        let bb_std_dev = parameters
+            .get("bb_std_dev")
+            .unwrap_or_else(|| defaults.bb_std_dev().to_num());
+        if !bb_std_dev.is_finite() || bb_std_dev <= 0.0 {
+            return Err(eyre!(
+                "Invalid value {} for parameter bb_std_dev",
+                bb_std_dev
+            ));
+        }
+
+        let indicators_config = IndicatorsConfig::Custom {
+            bb_period: parameters
+                .get_period("bb_period")?
+                .unwrap_or(defaults.bb_period()),
+            bb_std_dev: U32F96::from_num(bb_std_dev),
+            ema_period: parameters
+                .get_period("ema_period")?
+                .unwrap_or(defaults.ema_period()),
+        };
+
+        // Default periods read the indicators maintained on the time price bars
+        // rather than replaying them
+        let is_default = indicators_config.bb_period() == defaults.bb_period()
+            && indicators_config.bb_std_dev() == defaults.bb_std_dev()
+            && indicators_config.ema_period() == defaults.ema_period();
+
+        Ok(Self {
+            indicators_config: if is_default {
+                defaults
+            } else {
+                indicators_config
+            },
+        })
     }
 }
 
 impl Strategy for MomentumStrategy {
+    fn name(&self) -> &str {
+        "momentum"
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({
+            "ema_period": self.indicators_config.ema_period(),
+            "bb_period": self.indicators_config.bb_period(),
+            "bb_std_dev": self.indicators_config.bb_std_dev().to_num::<f64>(),
+        })
+    }
+
+    fn indicators_config(&self) -> IndicatorsConfig {
+        self.indicators_config
+    }
+
     fn should_open_position(
         &self,
-        pair_time_price_bars: &TimePriceBars,
-        timestamp: &ResolutionTimestamp,
-    ) -> Result<()> {
-        pair_time_price_bars
-            .time_price_bar(timestamp)
-            .ok_or_else(|| eyre!("No time price bar found for pair",))
-            .and_then(|time_price_bar| {
-                // Ensure the most recent time price bar is positive
-                if time_price_bar
-                    .data()
-                    .map(|d| d.is_negative())
-                    .unwrap_or(true)
-                {
-                    return Err(eyre!("Time price bar is negative"));
-                }
+        ctx: &StrategyContext,
+        pair_state: &mut PairState,
+    ) -> Result<Signal> {
+        let time_price_bar = match ctx.time_price_bar() {
+            Some(time_price_bar) => time_price_bar,
+            None => return Ok(Signal::Hold(SignalReason::MissingTimePriceBar)),
+        };
+
+        if pair_state.get_or_default::<MomentumState>().exited_at == Some(ctx.resolution_timestamp)
+        {
+            return Ok(Signal::Hold(SignalReason::ExitedInBar {
+                resolution_timestamp: ctx.resolution_timestamp.0,
+            }));
+        }
 
-                match time_price_bar.indicators() {
-                    Some(Indicators {
-                        ema: (ema, ema_slope),
-                        bollinger_bands: Some((band_mean, _, _)),
-                    }) => {
-                        let close = time_price_bar.close();
-                        if close < ema {
-                            Err(eyre!("Close price {:?} is below EMA {:?}", close, ema))
-                        } else if band_mean < ema {
-                            Err(eyre!("Band mean {:?} is below EMA {:?}", band_mean, ema))
-                        } else if close < band_mean {
-                            Err(eyre!(
-                                "Close price {:?} is below band mean {:?}",
+        // Ensure the most recent time price bar is positive
+        if time_price_bar
+            .data()
+            .map(|d| d.is_negative())
+            .unwrap_or(true)
+        {
+            return Ok(Signal::Hold(SignalReason::NegativeTimePriceBar));
+        }
+
+        let signal = match ctx.indicators(&self.indicators_config).as_ref() {
+            Some(Indicators {
+                ema: (ema, ema_slope),
+                bollinger_bands: Some((band_mean, upper_band, _)),
+            }) => {
+                let close = *time_price_bar.close();
+                if close < *ema {
+                    Signal::Hold(SignalReason::CloseBelowEma { close, ema: *ema })
+                } else if band_mean < ema {
+                    Signal::Hold(SignalReason::BandMeanBelowEma {
+                        band_mean: *band_mean,
+                        ema: *ema,
+                    })
+                } else if close < *band_mean {
+                    Signal::Hold(SignalReason::CloseBelowBandMean {
+                        close,
+                        band_mean: *band_mean,
+                    })
+                } else if ema_slope.is_negative() {
+                    Signal::Hold(SignalReason::NegativeEmaSlope {
+                        ema_slope: *ema_slope,
+                    })
+                } else {
+                    // Do not chase the price beyond the upper band if it moves
+                    // before the entry executes
+                    Signal::Trade(
+                        TradeSignal::new(
+                            SignalSide::Buy,
+                            SignalReason::MomentumEntry {
                                 close,
-                                band_mean
-                            ))
-                        } else if ema_slope.is_negative() {
-                            Err(eyre!("EMA slope {:?} is negative", ema_slope))
-                        } else {
-                            debug!(
-                                ema = ema.to_string(),
-                                band_mean = band_mean.to_string(),
-                                close = close.to_string(),
-                                ema_slope = ema_slope.to_string(),
-                                "should_open_position"
-                            );
-                            Ok(())
-                        }
-                    }
-                    Some(_) => Err(eyre!("No bollinger bands found for pair.")),
-                    None => Err(eyre!("No indicators found for pair.",)),
+                                ema: *ema,
+                                band_mean: *band_mean,
+                                ema_slope: *ema_slope,
+                            },
+                        )
+                        .with_limit_price(*upper_band),
+                    )
                 }
-            })
+            }
+            _ => Signal::Hold(SignalReason::MissingIndicators),
+        };
+
+        Ok(signal)
     }
 
     fn should_close_position(
         &self,
-        pair_time_price_bars: &TimePriceBars,
-        now_block_resolution_timestamp: &ResolutionTimestamp,
-        open_block_resolution_timestamp: &ResolutionTimestamp,
-    ) -> Result<()> {
-        pair_time_price_bars
-            .time_price_bar(now_block_resolution_timestamp)
-            .ok_or_else(|| eyre!("No time price bar found for pair",))
-            .and_then(|time_price_bar| {
-                match time_price_bar.indicators() {
-                    Some(Indicators { ema: (ema, _), .. }) => {
-                        let close = time_price_bar.close();
-                        if close > ema {
-                            Err(eyre!("Close {:?} is above EMA {:?}", close, ema))
-                        } else {
-                            // Close is below EMA after crossing SMA, close the trade
-                            debug!(
-                                ema = ema.to_string(),
-                                close = close.to_string(),
-                                "should_close_position"
-                            );
-                            Ok(())
-                        }
-                    }
-                    None => Err(eyre!("No indicators found for pair.")),
+        ctx: &StrategyContext,
+        _position: &Position,
+        pair_state: &mut PairState,
+    ) -> Result<Signal> {
+        let time_price_bar = match ctx.time_price_bar() {
+            Some(time_price_bar) => time_price_bar,
+            None => return Ok(Signal::Hold(SignalReason::MissingTimePriceBar)),
+        };
+
+        let signal = match ctx.indicators(&self.indicators_config).as_ref() {
+            Some(Indicators { ema: (ema, _), .. }) => {
+                let close = *time_price_bar.close();
+                if close > *ema {
+                    Signal::Hold(SignalReason::CloseAboveEma { close, ema: *ema })
+                } else {
+                    // Close is below EMA after crossing SMA, close the trade
+                    pair_state.get_or_default::<MomentumState>().exited_at =
+                        Some(ctx.resolution_timestamp);
+                    Signal::sell(SignalReason::MomentumExit { close, ema: *ema })
                 }
-            })
+            }
+            None => Signal::Hold(SignalReason::MissingIndicators),
+        };
+
+        Ok(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MomentumStrategy;
+    use crate::strategies::{
+        PairState, Position, Signal, SignalReason, SignalSide, Strategy, StrategyContext,
+    };
+
+    use pochtecatl_primitives::{
+        constants, IndexedTrade, IndicatorsConfig, Pair, Resolution, ResolutionTimestamp, TickData,
+        TimePriceBars, TradeMetadata, TradeRequestOp, UniswapV2IndexedTrade, UniswapV2Pair,
+    };
+
+    use alloy::primitives::{Address, TxHash, U256};
+    use eyre::Result;
+    use fixed::types::U32F96;
+
+    fn insert_bar(
+        time_price_bars: &mut TimePriceBars,
+        block_number: u64,
+        open: f64,
+        close: f64,
+    ) -> Result<u64> {
+        let block_timestamp = block_number * Resolution::FiveMinutes.offset() + 10000;
+        time_price_bars.insert_data(
+            block_number,
+            TickData::new(
+                U32F96::from_num(open),
+                U32F96::from_num(open.max(close)),
+                U32F96::from_num(open.min(close)),
+                U32F96::from_num(close),
+                0_u128.into(),
+            ),
+            block_timestamp,
+            None,
+        )?;
+
+        Ok(block_timestamp)
+    }
+
+    #[test]
+    fn test_momentum_entry_limit_and_reentry() -> Result<()> {
+        let indicators_config = IndicatorsConfig::Custom {
+            bb_period: 3,
+            bb_std_dev: U32F96::from_num(2),
+            ema_period: 3,
+        };
+        let strategy = MomentumStrategy { indicators_config };
+        let pair = Pair::UniswapV2(UniswapV2Pair::new(
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            constants::WETH_ADDRESS,
+        ));
+        let mut time_price_bars =
+            TimePriceBars::new(Some(10), Resolution::FiveMinutes, Some(indicators_config));
+        let mut pair_state = PairState::default();
+
+        // flat bars put the close on the ema, band mean and upper band
+        let mut block_timestamp = 0;
+        for block_number in 1..=3 {
+            block_timestamp = insert_bar(&mut time_price_bars, block_number, 1.0, 1.0)?;
+        }
+
+        let ctx = StrategyContext {
+            block_number: 3,
+            block_timestamp,
+            resolution_timestamp: ResolutionTimestamp::from_timestamp(
+                block_timestamp,
+                &Resolution::FiveMinutes,
+            ),
+            pair: &pair,
+            time_price_bars: &time_price_bars,
+            sampled_price_bars: &[],
+        };
+        match strategy.should_open_position(&ctx, &mut pair_state)? {
+            Signal::Trade(trade_signal) => {
+                assert_eq!(trade_signal.side, SignalSide::Buy);
+                assert_eq!(trade_signal.limit_price, Some(U32F96::ONE));
+                assert!(!trade_signal.is_within_limit(&U32F96::from_num(1.1)));
+            }
+            signal => panic!("Expected a buy, found {:?}", signal),
+        }
+
+        // a close below the ema exits
+        let block_timestamp = insert_bar(&mut time_price_bars, 4, 1.0, 0.5)?;
+        let ctx = StrategyContext {
+            block_number: 4,
+            block_timestamp,
+            resolution_timestamp: ResolutionTimestamp::from_timestamp(
+                block_timestamp,
+                &Resolution::FiveMinutes,
+            ),
+            pair: &pair,
+            time_price_bars: &time_price_bars,
+            sampled_price_bars: &[],
+        };
+        let open_trade_metadata = TradeMetadata::new(
+            TxHash::ZERO,
+            3,
+            block_timestamp,
+            TradeRequestOp::Open,
+            *pair.token_address(),
+            U256::ZERO,
+            IndexedTrade::UniswapV2(UniswapV2IndexedTrade::new(
+                Address::ZERO,
+                U256::ZERO,
+                U256::from(100),
+                U256::from(100),
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                Address::ZERO,
+            )),
+        );
+        let position = Position::new(&open_trade_metadata, ctx.resolution_timestamp);
+        assert!(matches!(
+            strategy.should_close_position(&ctx, &position, &mut pair_state)?,
+            Signal::Trade(trade_signal) if trade_signal.side == SignalSide::Sell
+        ));
+
+        // and entries are held for the rest of the bar
+        assert!(matches!(
+            strategy.should_open_position(&ctx, &mut pair_state)?,
+            Signal::Hold(SignalReason::ExitedInBar { .. })
+        ));
+
+        Ok(())
     }
 }
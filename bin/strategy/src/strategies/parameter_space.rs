@@ -0,0 +1,167 @@
+use eyre::{eyre, Result};
+use std::collections::BTreeMap;
+
+// The candidate values of a single strategy parameter.
+#[derive(Debug, Clone)]
+pub struct ParameterSpec {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+// The parameters a strategy declares as tunable, along with the values to
+// search over for each.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterSpace(Vec<ParameterSpec>);
+
+impl ParameterSpace {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn with_parameter(mut self, name: &str, values: Vec<f64>) -> Self {
+        self.0.push(ParameterSpec {
+            name: name.to_string(),
+            values,
+        });
+        self
+    }
+
+    pub fn grid_size(&self) -> usize {
+        self.0.iter().map(|spec| spec.values.len()).product()
+    }
+
+    // Every combination of parameter values
+    pub fn grid(&self) -> Vec<ParameterSet> {
+        self.0
+            .iter()
+            .fold(vec![ParameterSet::default()], |sets, spec| {
+                sets.into_iter()
+                    .flat_map(|set| {
+                        spec.values
+                            .iter()
+                            .map(move |value| set.clone().with_value(&spec.name, *value))
+                    })
+                    .collect()
+            })
+    }
+
+    // Up to count distinct combinations drawn uniformly from the grid. The
+    // same seed always yields the same sample.
+    pub fn sample(&self, count: usize, seed: u64) -> Vec<ParameterSet> {
+        let mut grid = self.grid();
+        let mut rng = SplitMix64(seed);
+
+        // Partial Fisher-Yates shuffle of the first count entries
+        let count = count.min(grid.len());
+        for i in 0..count {
+            let j = i + (rng.next() % (grid.len() - i) as u64) as usize;
+            grid.swap(i, j);
+        }
+
+        grid.truncate(count);
+        grid
+    }
+}
+
+// A single assignment of values to a strategy's parameters.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterSet(BTreeMap<String, f64>);
+
+impl ParameterSet {
+    pub fn with_value(mut self, name: &str, value: f64) -> Self {
+        self.0.insert(name.to_string(), value);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.0.get(name).copied()
+    }
+
+    // Reads a whole, positive parameter such as an indicator period
+    pub fn get_period(&self, name: &str) -> Result<Option<u64>> {
+        match self.get(name) {
+            Some(value) if value >= 1.0 && value.fract() == 0.0 => Ok(Some(value as u64)),
+            Some(value) => Err(eyre!("Invalid value {} for parameter {}", value, name)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!(self.0)
+    }
+}
+
+// Small deterministic generator so sweeps are reproducible from a seed
+// without pulling in an rng dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParameterSet, ParameterSpace};
+
+    fn space() -> ParameterSpace {
+        ParameterSpace::new()
+            .with_parameter("a", vec![1.0, 2.0, 3.0])
+            .with_parameter("b", vec![10.0, 20.0])
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = space().grid();
+
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid.len(), space().grid_size());
+        assert_eq!(
+            grid[0],
+            ParameterSet::default()
+                .with_value("a", 1.0)
+                .with_value("b", 10.0)
+        );
+        assert_eq!(
+            grid[5],
+            ParameterSet::default()
+                .with_value("a", 3.0)
+                .with_value("b", 20.0)
+        );
+        assert_eq!(ParameterSpace::new().grid(), vec![ParameterSet::default()]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let sample = space().sample(4, 7);
+
+        assert_eq!(sample.len(), 4);
+        assert_eq!(sample, space().sample(4, 7));
+        for (i, set) in sample.iter().enumerate() {
+            assert!(!sample[i + 1..].contains(set));
+        }
+
+        // never more than the grid
+        assert_eq!(space().sample(100, 7).len(), 6);
+    }
+
+    #[test]
+    fn test_get_period() {
+        let set = ParameterSet::default()
+            .with_value("period", 9.0)
+            .with_value("fractional", 2.5);
+
+        assert_eq!(set.get_period("period").unwrap(), Some(9));
+        assert_eq!(set.get_period("missing").unwrap(), None);
+        assert!(set.get_period("fractional").is_err());
+        assert_eq!(
+            set.to_json(),
+            serde_json::json!({ "fractional": 2.5, "period": 9.0 })
+        );
+    }
+}
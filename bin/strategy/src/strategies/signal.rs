@@ -0,0 +1,251 @@
+use alloy::primitives::U256;
+use fixed::types::{I32F96, U32F96};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalSide {
+    // Swap weth for the token
+    Buy,
+    // Swap the token back to weth
+    Sell,
+}
+
+// Why a strategy did, or did not, signal a trade. Kept structured so that
+// rejections can be aggregated rather than only read from logs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignalReason {
+    MissingTimePriceBar,
+    MissingIndicators,
+    NegativeTimePriceBar,
+    PendingTrade,
+    CloseBelowEma {
+        close: U32F96,
+        ema: I32F96,
+    },
+    CloseAboveEma {
+        close: U32F96,
+        ema: I32F96,
+    },
+    BandMeanBelowEma {
+        band_mean: U32F96,
+        ema: I32F96,
+    },
+    CloseBelowBandMean {
+        close: U32F96,
+        band_mean: U32F96,
+    },
+    NegativeEmaSlope {
+        ema_slope: I32F96,
+    },
+    ExitedInBar {
+        resolution_timestamp: u64,
+    },
+    CapitalAllocationExhausted {
+        deployed: U256,
+        capital_allocation: U256,
+    },
+    LimitPriceExceeded {
+        price: U32F96,
+        limit_price: U32F96,
+    },
+    PositionSizeZero {
+        cash: U256,
+    },
+    MomentumEntry {
+        close: U32F96,
+        ema: I32F96,
+        band_mean: U32F96,
+        ema_slope: I32F96,
+    },
+    MomentumExit {
+        close: U32F96,
+        ema: I32F96,
+    },
+    StopLoss {
+        price: U32F96,
+        stop_price: U32F96,
+    },
+    TakeProfit {
+        price: U32F96,
+        target_price: U32F96,
+    },
+    TrailingStop {
+        price: U32F96,
+        high_water_mark: U32F96,
+        stop_price: U32F96,
+    },
+    BreakevenStop {
+        price: U32F96,
+        entry_price: U32F96,
+    },
+    MaxHoldTime {
+        held_seconds: u64,
+    },
+}
+
+impl fmt::Display for SignalReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingTimePriceBar => write!(f, "no time price bar for pair"),
+            Self::MissingIndicators => write!(f, "no indicators for pair"),
+            Self::NegativeTimePriceBar => write!(f, "time price bar is negative"),
+            Self::PendingTrade => write!(f, "trade is pending"),
+            Self::CloseBelowEma { close, ema } => {
+                write!(f, "close {} is below ema {}", close, ema)
+            }
+            Self::CloseAboveEma { close, ema } => {
+                write!(f, "close {} is above ema {}", close, ema)
+            }
+            Self::BandMeanBelowEma { band_mean, ema } => {
+                write!(f, "band mean {} is below ema {}", band_mean, ema)
+            }
+            Self::CloseBelowBandMean { close, band_mean } => {
+                write!(f, "close {} is below band mean {}", close, band_mean)
+            }
+            Self::NegativeEmaSlope { ema_slope } => {
+                write!(f, "ema slope {} is negative", ema_slope)
+            }
+            Self::ExitedInBar {
+                resolution_timestamp,
+            } => write!(f, "already exited in the bar at {}", resolution_timestamp),
+            Self::CapitalAllocationExhausted {
+                deployed,
+                capital_allocation,
+            } => write!(
+                f,
+                "deployed capital {} has reached allocation {}",
+                deployed, capital_allocation
+            ),
+            Self::LimitPriceExceeded { price, limit_price } => {
+                write!(f, "price {} is beyond limit price {}", price, limit_price)
+            }
+            Self::PositionSizeZero { cash } => {
+                write!(f, "position size is zero with cash {}", cash)
+            }
+            Self::MomentumEntry {
+                close,
+                ema,
+                band_mean,
+                ema_slope,
+            } => write!(
+                f,
+                "momentum entry: close {}, ema {}, band mean {}, ema slope {}",
+                close, ema, band_mean, ema_slope
+            ),
+            Self::MomentumExit { close, ema } => {
+                write!(f, "momentum exit: close {} crossed ema {}", close, ema)
+            }
+            Self::StopLoss { price, stop_price } => {
+                write!(f, "stop loss: price {} hit stop {}", price, stop_price)
+            }
+            Self::TakeProfit {
+                price,
+                target_price,
+            } => write!(
+                f,
+                "take profit: price {} hit target {}",
+                price, target_price
+            ),
+            Self::TrailingStop {
+                price,
+                high_water_mark,
+                stop_price,
+            } => write!(
+                f,
+                "trailing stop: price {} hit stop {} trailing high {}",
+                price, stop_price, high_water_mark
+            ),
+            Self::BreakevenStop { price, entry_price } => write!(
+                f,
+                "breakeven stop: price {} returned to entry {}",
+                price, entry_price
+            ),
+            Self::MaxHoldTime { held_seconds } => {
+                write!(f, "max hold time: held for {}s", held_seconds)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TradeSignal {
+    pub side: SignalSide,
+    // Desired weth amount to trade, None defers to the pair's default sizing
+    pub size: Option<U256>,
+    // Conviction in the range [0, 1]
+    pub confidence: U32F96,
+    // Worst acceptable token price in weth: the ceiling for buys, the floor for sells
+    pub limit_price: Option<U32F96>,
+    pub reason: SignalReason,
+}
+
+impl TradeSignal {
+    pub fn new(side: SignalSide, reason: SignalReason) -> Self {
+        Self {
+            side,
+            size: None,
+            confidence: U32F96::ONE,
+            limit_price: None,
+            reason,
+        }
+    }
+
+    pub fn with_confidence(mut self, confidence: U32F96) -> Self {
+        self.confidence = confidence.min(U32F96::ONE);
+        self
+    }
+
+    pub fn with_limit_price(mut self, limit_price: U32F96) -> Self {
+        self.limit_price = Some(limit_price);
+        self
+    }
+
+    // Whether the given price satisfies the limit price, if any.
+    pub fn is_within_limit(&self, price: &U32F96) -> bool {
+        match (&self.limit_price, self.side) {
+            (None, _) => true,
+            (Some(limit_price), SignalSide::Buy) => price <= limit_price,
+            (Some(limit_price), SignalSide::Sell) => price >= limit_price,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Signal {
+    Hold(SignalReason),
+    Trade(TradeSignal),
+}
+
+impl Signal {
+    pub fn buy(reason: SignalReason) -> Self {
+        Self::Trade(TradeSignal::new(SignalSide::Buy, reason))
+    }
+
+    pub fn sell(reason: SignalReason) -> Self {
+        Self::Trade(TradeSignal::new(SignalSide::Sell, reason))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_within_limit() {
+        let limit_price = U32F96::from_num(2);
+
+        let buy = TradeSignal::new(SignalSide::Buy, SignalReason::PendingTrade)
+            .with_limit_price(limit_price);
+        assert!(buy.is_within_limit(&U32F96::from_num(1)));
+        assert!(buy.is_within_limit(&limit_price));
+        assert!(!buy.is_within_limit(&U32F96::from_num(3)));
+
+        let sell = TradeSignal::new(SignalSide::Sell, SignalReason::PendingTrade)
+            .with_limit_price(limit_price);
+        assert!(!sell.is_within_limit(&U32F96::from_num(1)));
+        assert!(sell.is_within_limit(&U32F96::from_num(3)));
+
+        let unbounded = TradeSignal::new(SignalSide::Buy, SignalReason::PendingTrade);
+        assert!(unbounded.is_within_limit(&U32F96::MAX));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::trade_controller::{closed_trade_pnl, AddressTrades};
+
+use pochtecatl_primitives::{constants, u256_mul_u32f96, TickData, TimePriceBars};
+
+use alloy::primitives::{Address, U256};
+use eyre::{eyre, Report};
+use fixed::types::U32F96;
+use fnv::FnvHashMap;
+use std::str::FromStr;
+
+// Bars averaged into the true range of volatility scaled policies
+const ATR_PERIODS: usize = 14;
+// Closed trades required before sizing by their win rate and payoff
+const KELLY_MIN_TRADES: usize = 10;
+
+// How much weth to spend opening a position when the signal does not specify
+// a size. Fractions are of the cash available to the book.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SizingPolicy {
+    // A fixed amount in wei
+    Fixed(U256),
+    FixedFraction { bps: u64 },
+    // Risks risk_bps of cash against an adverse move of atr_multiple average
+    // true ranges
+    VolatilityScaled { risk_bps: u64, atr_multiple: U32F96 },
+    // The Kelly fraction of the book's closed trades, never more than
+    // cap_bps. Sized at the cap until there is enough trade history.
+    KellyCapped { cap_bps: u64 },
+}
+
+impl Default for SizingPolicy {
+    fn default() -> Self {
+        Self::Fixed(constants::MAX_TRADE_SIZE_WEI)
+    }
+}
+
+fn fraction_of(cash: U256, bps: u64) -> U256 {
+    cash * U256::from(bps).min(constants::BP_FACTOR) / constants::BP_FACTOR
+}
+
+// Average true range of the bars relative to the last close, bars in
+// chronological order.
+fn relative_atr(bars: &[&TickData]) -> Option<U32F96> {
+    let last_close = bars.last()?.close;
+    if last_close == U32F96::ZERO {
+        return None;
+    }
+
+    // Ranges extend to the previous close to account for gaps
+    let true_ranges = bars.iter().enumerate().map(|(i, bar)| {
+        match i.checked_sub(1).map(|prev| bars[prev].close) {
+            Some(prev_close) => bar.high.max(prev_close) - bar.low.min(prev_close),
+            None => bar.high - bar.low,
+        }
+    });
+
+    let atr = true_ranges.sum::<U32F96>() / U32F96::from_num(bars.len());
+    atr.checked_div(last_close)
+}
+
+// Average true range of the pair's most recent bars relative to its last close
+pub fn recent_relative_atr(time_price_bars: &TimePriceBars) -> Option<U32F96> {
+    let mut bars = time_price_bars
+        .data()
+        .values()
+        .rev()
+        .filter_map(|time_price_bar| time_price_bar.data())
+        .take(ATR_PERIODS)
+        .collect::<Vec<_>>();
+    bars.reverse();
+
+    relative_atr(&bars)
+}
+
+// Kelly fraction in basis points from closed trade pnls, None without enough
+// history or without a losing trade to size the payoff against.
+fn kelly_bps(pnls: &[f64]) -> Option<u64> {
+    if pnls.len() < KELLY_MIN_TRADES {
+        return None;
+    }
+
+    let (wins, losses): (Vec<f64>, Vec<f64>) = pnls.iter().copied().partition(|pnl| *pnl > 0.0);
+    if losses.is_empty() || wins.is_empty() {
+        return None;
+    }
+
+    let win_rate = wins.len() as f64 / pnls.len() as f64;
+    let average_win = wins.iter().sum::<f64>() / wins.len() as f64;
+    let average_loss = -losses.iter().sum::<f64>() / losses.len() as f64;
+    if average_loss <= 0.0 {
+        return None;
+    }
+
+    let kelly = win_rate - (1.0 - win_rate) / (average_win / average_loss);
+    Some((kelly.max(0.0) * constants::BP_FACTOR.to::<u64>() as f64).round() as u64)
+}
+
+impl SizingPolicy {
+    // Weth to spend opening a position. Zero if the policy would not trade.
+    pub fn size(
+        &self,
+        cash: U256,
+        time_price_bars: &TimePriceBars,
+        trades: &FnvHashMap<Address, AddressTrades>,
+    ) -> U256 {
+        match self {
+            Self::Fixed(amount) => *amount,
+            Self::FixedFraction { bps } => fraction_of(cash, *bps),
+            Self::VolatilityScaled {
+                risk_bps,
+                atr_multiple,
+            } => recent_relative_atr(time_price_bars)
+                .and_then(|atr| atr.checked_mul(*atr_multiple))
+                .filter(|stop_distance| *stop_distance > U32F96::ZERO)
+                .and_then(|stop_distance| U32F96::ONE.checked_div(stop_distance))
+                .map_or(U256::ZERO, |leverage| {
+                    u256_mul_u32f96(fraction_of(cash, *risk_bps), leverage).min(cash)
+                }),
+            Self::KellyCapped { cap_bps } => {
+                let pnls = trades
+                    .values()
+                    .flat_map(|address_trades| address_trades.closed().iter())
+                    .map(|(open_trade, close_trade)| {
+                        closed_trade_pnl(open_trade, close_trade)
+                            .to_string()
+                            .parse()
+                            .unwrap_or(0.0)
+                    })
+                    .collect::<Vec<f64>>();
+
+                fraction_of(
+                    cash,
+                    kelly_bps(&pnls).map_or(*cap_bps, |bps| bps.min(*cap_bps)),
+                )
+            }
+        }
+    }
+}
+
+// Parses "fixed:<wei>", "fraction:<bps>", "atr:<risk_bps>:<multiple>" or
+// "kelly:<cap_bps>"
+impl FromStr for SizingPolicy {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split(':').collect::<Vec<_>>();
+        let parse_err =
+            |err: &dyn std::fmt::Debug| eyre!("Failed to parse sizing policy {}: {:?}", s, err);
+
+        match parts.as_slice() {
+            ["fixed", amount] => amount
+                .parse()
+                .map(Self::Fixed)
+                .map_err(|err| parse_err(&err)),
+            ["fraction", bps] => bps
+                .parse()
+                .map(|bps| Self::FixedFraction { bps })
+                .map_err(|err| parse_err(&err)),
+            ["atr", risk_bps, atr_multiple] => Ok(Self::VolatilityScaled {
+                risk_bps: risk_bps.parse().map_err(|err| parse_err(&err))?,
+                atr_multiple: U32F96::from_str(atr_multiple).map_err(|err| parse_err(&err))?,
+            }),
+            ["kelly", cap_bps] => cap_bps
+                .parse()
+                .map(|cap_bps| Self::KellyCapped { cap_bps })
+                .map_err(|err| parse_err(&err)),
+            _ => Err(eyre!("Failed to parse sizing policy: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kelly_bps, relative_atr, SizingPolicy};
+
+    use pochtecatl_primitives::TickData;
+
+    use alloy::primitives::U256;
+    use fixed::types::U32F96;
+    use num_bigint::BigUint;
+
+    fn bar(high: f64, low: f64, close: f64) -> TickData {
+        TickData::new(
+            U32F96::from_num(close),
+            U32F96::from_num(high),
+            U32F96::from_num(low),
+            U32F96::from_num(close),
+            BigUint::ZERO,
+        )
+    }
+
+    #[test]
+    fn test_relative_atr() {
+        let bars = [
+            bar(11.0, 9.0, 10.0),
+            bar(10.5, 9.5, 10.0),
+            bar(14.0, 11.0, 12.0),
+            bar(16.5, 15.5, 16.0),
+        ];
+
+        // true ranges of 2, 1, 4 and 4.5, the last two gapped above the
+        // previous close
+        let atr = relative_atr(&bars.iter().collect::<Vec<_>>()).unwrap();
+        assert_eq!(atr, U32F96::from_num(2.875 / 16.0));
+
+        assert!(relative_atr(&[]).is_none());
+    }
+
+    #[test]
+    fn test_kelly_bps() {
+        // Too little history
+        assert_eq!(kelly_bps(&[1.0, -1.0]), None);
+
+        // 60% winners paying 2:1 -> 0.6 - 0.4 / 2 = 0.4
+        let pnls = [2.0, 2.0, 2.0, 2.0, 2.0, 2.0, -1.0, -1.0, -1.0, -1.0];
+        assert_eq!(kelly_bps(&pnls), Some(4000));
+
+        // Negative edge sizes to zero
+        let pnls = [1.0, 1.0, -2.0, -2.0, -2.0, -2.0, -2.0, -2.0, -2.0, -2.0];
+        assert_eq!(kelly_bps(&pnls), Some(0));
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "fixed:1000".parse::<SizingPolicy>().unwrap(),
+            SizingPolicy::Fixed(U256::from(1000))
+        );
+        assert_eq!(
+            "fraction:250".parse::<SizingPolicy>().unwrap(),
+            SizingPolicy::FixedFraction { bps: 250 }
+        );
+        assert_eq!(
+            "atr:100:2.5".parse::<SizingPolicy>().unwrap(),
+            SizingPolicy::VolatilityScaled {
+                risk_bps: 100,
+                atr_multiple: U32F96::from_num(2.5),
+            }
+        );
+        assert_eq!(
+            "kelly:1000".parse::<SizingPolicy>().unwrap(),
+            SizingPolicy::KellyCapped { cap_bps: 1000 }
+        );
+        assert!("kelly".parse::<SizingPolicy>().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use super::sizing_policy::recent_relative_atr;
+
+use pochtecatl_primitives::{constants, TimePriceBars};
+
+use eyre::{eyre, Report};
+use fixed::types::U32F96;
+use std::str::FromStr;
+
+// How far below its quote a trade's output may fill before it reverts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlippagePolicy {
+    Static {
+        bps: u64,
+    },
+    // A multiple of the pair's relative average true range, within min_bps
+    // and max_bps. Falls back to max_bps without enough bars.
+    VolatilityScaled {
+        atr_multiple: U32F96,
+        min_bps: u64,
+        max_bps: u64,
+    },
+    // Widens from base_bps toward max_bps as recent trades revert
+    Adaptive {
+        base_bps: u64,
+        max_bps: u64,
+    },
+}
+
+impl Default for SlippagePolicy {
+    fn default() -> Self {
+        Self::Static { bps: 0 }
+    }
+}
+
+impl SlippagePolicy {
+    pub fn bps(&self, time_price_bars: Option<&TimePriceBars>, revert_rate: f64) -> u64 {
+        match self {
+            Self::Static { bps } => *bps,
+            Self::VolatilityScaled {
+                atr_multiple,
+                min_bps,
+                max_bps,
+            } => time_price_bars
+                .and_then(recent_relative_atr)
+                .and_then(|atr| atr.checked_mul(*atr_multiple))
+                .and_then(|slippage| slippage.checked_mul_int(constants::BP_FACTOR.to::<u128>()))
+                .map_or(*max_bps, |bps| {
+                    bps.to_num::<u64>().clamp(*min_bps, *max_bps)
+                }),
+            Self::Adaptive { base_bps, max_bps } => {
+                let widening =
+                    max_bps.saturating_sub(*base_bps) as f64 * revert_rate.clamp(0.0, 1.0);
+                base_bps + widening.round() as u64
+            }
+        }
+    }
+}
+
+// Parses "static:<bps>", "atr:<multiple>:<min_bps>:<max_bps>" or
+// "adaptive:<base_bps>:<max_bps>"
+impl FromStr for SlippagePolicy {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split(':').collect::<Vec<_>>();
+        let parse_err =
+            |err: &dyn std::fmt::Debug| eyre!("Failed to parse slippage policy {}: {:?}", s, err);
+
+        match parts.as_slice() {
+            ["static", bps] => bps
+                .parse()
+                .map(|bps| Self::Static { bps })
+                .map_err(|err| parse_err(&err)),
+            ["atr", atr_multiple, min_bps, max_bps] => Ok(Self::VolatilityScaled {
+                atr_multiple: U32F96::from_str(atr_multiple).map_err(|err| parse_err(&err))?,
+                min_bps: min_bps.parse().map_err(|err| parse_err(&err))?,
+                max_bps: max_bps.parse().map_err(|err| parse_err(&err))?,
+            }),
+            ["adaptive", base_bps, max_bps] => Ok(Self::Adaptive {
+                base_bps: base_bps.parse().map_err(|err| parse_err(&err))?,
+                max_bps: max_bps.parse().map_err(|err| parse_err(&err))?,
+            }),
+            _ => Err(eyre!("Failed to parse slippage policy: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SlippagePolicy;
+
+    use fixed::types::U32F96;
+
+    #[test]
+    fn test_bps() {
+        assert_eq!(SlippagePolicy::Static { bps: 30 }.bps(None, 1.0), 30);
+
+        // No bars to measure volatility against
+        let volatility_scaled = SlippagePolicy::VolatilityScaled {
+            atr_multiple: U32F96::from_num(2),
+            min_bps: 10,
+            max_bps: 300,
+        };
+        assert_eq!(volatility_scaled.bps(None, 0.0), 300);
+
+        let adaptive = SlippagePolicy::Adaptive {
+            base_bps: 50,
+            max_bps: 250,
+        };
+        assert_eq!(adaptive.bps(None, 0.0), 50);
+        assert_eq!(adaptive.bps(None, 0.25), 100);
+        assert_eq!(adaptive.bps(None, 1.0), 250);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "static:50".parse::<SlippagePolicy>().unwrap(),
+            SlippagePolicy::Static { bps: 50 }
+        );
+        assert_eq!(
+            "atr:1.5:10:300".parse::<SlippagePolicy>().unwrap(),
+            SlippagePolicy::VolatilityScaled {
+                atr_multiple: U32F96::from_num(1.5),
+                min_bps: 10,
+                max_bps: 300,
+            }
+        );
+        assert_eq!(
+            "adaptive:50:250".parse::<SlippagePolicy>().unwrap(),
+            SlippagePolicy::Adaptive {
+                base_bps: 50,
+                max_bps: 250,
+            }
+        );
+        assert!("atr:1.5".parse::<SlippagePolicy>().is_err());
+    }
+}
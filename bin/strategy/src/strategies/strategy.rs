@@ -1,18 +1,62 @@
-use pochtecatl_primitives::{ResolutionTimestamp, TimePriceBars};
+use super::{
+    MomentumStrategy, PairState, ParameterSet, ParameterSpace, Position, Signal, StrategyContext,
+};
+use pochtecatl_primitives::IndicatorsConfig;
 
-use eyre::Result;
+use eyre::{eyre, Result};
 
+// Strategies return a Signal rather than an error when they decline to trade,
+// errors are reserved for failures in evaluating the strategy itself.
 pub trait Strategy: Send + Sync + 'static {
+    // Identifies the strategy in persisted backtest results
+    fn name(&self) -> &str;
+
+    // The parameterization of the strategy, persisted alongside backtest results
+    fn parameters(&self) -> serde_json::Value {
+        serde_json::json!({})
+    }
+
+    // The indicator periods the strategy reads, replayed from the time price
+    // bars when they differ from the indexed periods
+    fn indicators_config(&self) -> IndicatorsConfig {
+        IndicatorsConfig::All
+    }
+
     fn should_open_position(
         &self,
-        pair_time_price_bars: &TimePriceBars,
-        block_resolution_timestamp: &ResolutionTimestamp,
-    ) -> Result<()>;
+        ctx: &StrategyContext,
+        pair_state: &mut PairState,
+    ) -> Result<Signal>;
 
     fn should_close_position(
         &self,
-        pair_time_price_bars: &TimePriceBars,
-        now_block_resolution_timestamp: &ResolutionTimestamp,
-        open_block_resolution_timestamp: &ResolutionTimestamp,
-    ) -> Result<()>;
+        ctx: &StrategyContext,
+        position: &Position,
+        pair_state: &mut PairState,
+    ) -> Result<Signal>;
+}
+
+pub fn new_strategy(name: &str) -> Result<Box<dyn Strategy>> {
+    match name {
+        "momentum" => Ok(Box::new(MomentumStrategy::new())),
+        _ => Err(eyre!("Unknown strategy: {}", name)),
+    }
+}
+
+pub fn new_parameterized_strategy(
+    name: &str,
+    parameters: &ParameterSet,
+) -> Result<Box<dyn Strategy>> {
+    match name {
+        "momentum" => Ok(Box::new(MomentumStrategy::from_parameters(parameters)?)),
+        _ => Err(eyre!("Unknown strategy: {}", name)),
+    }
+}
+
+// The tunable parameters of a strategy, searched over by parameter sweeps
+pub fn strategy_parameter_space(name: &str) -> Result<ParameterSpace> {
+    match name {
+        "momentum" => Ok(MomentumStrategy::parameter_space()),
+        _ => Err(eyre!("Unknown strategy: {}", name)),
+    }
 }
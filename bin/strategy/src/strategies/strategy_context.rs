@@ -0,0 +1,124 @@
+use pochtecatl_primitives::{
+    u256_mul_u32f96, Indicators, IndicatorsConfig, Pair, ResolutionTimestamp, SampledPriceBars,
+    TimePriceBar, TimePriceBars, TradeMetadata,
+};
+
+use alloy::primitives::{BlockNumber, I256, U256};
+use fixed::types::{I32F96, U32F96};
+use std::any::Any;
+
+// Everything a strategy can observe about a pair at the block being evaluated.
+pub struct StrategyContext<'a> {
+    pub block_number: BlockNumber,
+    pub block_timestamp: u64,
+    pub resolution_timestamp: ResolutionTimestamp,
+    pub pair: &'a Pair,
+    pub time_price_bars: &'a TimePriceBars,
+    // One entry per configured bar sampling, empty if none are configured
+    pub sampled_price_bars: &'a [SampledPriceBars],
+}
+
+impl<'a> StrategyContext<'a> {
+    // The time price bar containing the current block
+    pub fn time_price_bar(&self) -> Option<&'a TimePriceBar> {
+        self.time_price_bars
+            .time_price_bar(&self.resolution_timestamp)
+    }
+
+    // The indicators of the time price bar containing the current block with
+    // the strategy's periods, which need not match the indexed periods
+    pub fn indicators(&self, indicators_config: &IndicatorsConfig) -> Option<Indicators> {
+        self.time_price_bars
+            .indicators(&self.resolution_timestamp, indicators_config)
+    }
+}
+
+// The active position in a pair's token, valued against the latest price.
+pub struct Position<'a> {
+    open_trade_metadata: &'a TradeMetadata,
+    opened_at: ResolutionTimestamp,
+}
+
+impl<'a> Position<'a> {
+    pub fn new(open_trade_metadata: &'a TradeMetadata, opened_at: ResolutionTimestamp) -> Self {
+        Self {
+            open_trade_metadata,
+            opened_at,
+        }
+    }
+
+    pub fn open_trade_metadata(&self) -> &TradeMetadata {
+        self.open_trade_metadata
+    }
+
+    // Resolution timestamp of the bar the position was opened in
+    pub fn opened_at(&self) -> &ResolutionTimestamp {
+        &self.opened_at
+    }
+
+    pub fn entry_price(&self) -> U32F96 {
+        self.open_trade_metadata.execution_price()
+    }
+
+    // Weth spent to open the position, including gas
+    pub fn cost_basis(&self) -> U256 {
+        self.open_trade_metadata.weth_amount() + self.open_trade_metadata.gas_fee()
+    }
+
+    pub fn market_value(&self, price: &U32F96) -> U256 {
+        u256_mul_u32f96(self.open_trade_metadata.token_amount(), *price)
+    }
+
+    // Unrealized pnl in weth, excluding the gas and price impact of closing
+    pub fn unrealized_pnl(&self, price: &U32F96) -> I256 {
+        I256::from_raw(self.market_value(price)) - I256::from_raw(self.cost_basis())
+    }
+
+    // Price return relative to the entry price, e.g. 0.1 for +10%
+    pub fn unrealized_return(&self, price: &U32F96) -> I32F96 {
+        I32F96::saturating_from_num(*price)
+            .checked_div(I32F96::saturating_from_num(self.entry_price()))
+            .map(|ratio| ratio - I32F96::ONE)
+            .unwrap_or(I32F96::ZERO)
+    }
+}
+
+// Mutable state a strategy keeps per pair across blocks. The executor owns
+// one per pair and the strategy decides what to store in it; state of a
+// different type is replaced with the default.
+#[derive(Default)]
+pub struct PairState(Option<Box<dyn Any + Send + Sync>>);
+
+impl PairState {
+    pub fn get_or_default<S: Default + Send + Sync + 'static>(&mut self) -> &mut S {
+        if !self.0.as_ref().is_some_and(|state| state.is::<S>()) {
+            self.0 = Some(Box::new(S::default()));
+        }
+
+        self.0
+            .as_mut()
+            .and_then(|state| state.downcast_mut::<S>())
+            .expect("pair state was just initialized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PairState;
+
+    #[derive(Default)]
+    struct Counter(u64);
+
+    #[test]
+    fn test_pair_state_get_or_default() {
+        let mut state = PairState::default();
+
+        state.get_or_default::<Counter>().0 += 1;
+        state.get_or_default::<Counter>().0 += 1;
+        assert_eq!(state.get_or_default::<Counter>().0, 2);
+
+        // a different state type replaces the existing state
+        assert_eq!(*state.get_or_default::<u64>(), 0);
+        assert_eq!(state.get_or_default::<Counter>().0, 0);
+    }
+}
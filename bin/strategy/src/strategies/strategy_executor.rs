@@ -1,26 +1,107 @@
-use super::Strategy;
+use super::{
+    ExitRules, ExitState, PairState, Position, Signal, SignalReason, SignalSide, SizingPolicy,
+    SlippagePolicy, Strategy, StrategyContext,
+};
 
 use crate::{
     indexer::TimePriceBarStore,
     trade_controller::{Trade, TradeController, TradeRequest},
 };
 
-use pochtecatl_primitives::{BlockMessage, ResolutionTimestamp, TradeRequestOp};
+use pochtecatl_db::BacktestStrategyModel;
+use pochtecatl_primitives::{
+    constants, u256_mul_u32f96, BlockMessage, Pair, ResolutionTimestamp, TimePriceBars,
+    TradeMetadata, TradeRequestOp, TradeRequestParams,
+};
 
 use alloy::{
     network::Ethereum,
-    primitives::{address, Address},
+    primitives::{address, Address, BlockNumber, TxHash, I256, U256},
     providers::Provider,
     transports::Transport,
 };
 use chrono::DateTime;
-use eyre::Result;
-use std::sync::Arc;
+use eyre::{Result, WrapErr};
+use fixed::types::U32F96;
+use fnv::{FnvHashMap, FnvHashSet};
+use std::sync::{Arc, Mutex};
 use tokio::task::JoinSet;
 use tracing::{debug, error, info, instrument};
 
 const TARGET_PAIR_ADDRESS: Address = address!("c9034c3E7F58003E6ae0C8438e7c8f4598d5ACAA");
 
+// Exit rule state of a position along with the pair it trades on, so that
+// exits can be evaluated in blocks where the pair did not trade.
+struct TrackedExit {
+    // None until the open trade has been confirmed
+    open_tx_hash: Option<TxHash>,
+    pair: Pair,
+    state: ExitState,
+}
+
+impl TrackedExit {
+    fn new(pair: Pair) -> Self {
+        Self {
+            open_tx_hash: None,
+            pair,
+            state: ExitState::default(),
+        }
+    }
+
+    // Resets the exit state if it was tracking a previous position
+    fn track(&mut self, open_tx_hash: &TxHash) -> &mut ExitState {
+        if self
+            .open_tx_hash
+            .is_some_and(|tx_hash| tx_hash != *open_tx_hash)
+        {
+            self.state = ExitState::default();
+        }
+        self.open_tx_hash = Some(*open_tx_hash);
+
+        &mut self.state
+    }
+}
+
+// An open position priced at the last close of its pair
+#[derive(Debug, Clone)]
+pub struct MarkedPosition {
+    pub open_trade_metadata: TradeMetadata,
+    pub price: U32F96,
+    pub block_number: BlockNumber,
+    pub block_timestamp: u64,
+}
+
+impl MarkedPosition {
+    pub fn market_value(&self) -> U256 {
+        u256_mul_u32f96(self.open_trade_metadata.token_amount(), self.price)
+    }
+
+    // Excludes the gas and price impact of closing
+    pub fn unrealized_pnl(&self) -> I256 {
+        I256::from_raw(self.market_value())
+            - I256::from_raw(
+                self.open_trade_metadata.weth_amount() + self.open_trade_metadata.gas_fee(),
+            )
+    }
+}
+
+// Close of the most recent bar with data for the pair
+fn last_close(
+    time_price_bars: &FnvHashMap<Address, TimePriceBars>,
+    pair_address: &Address,
+) -> Option<U32F96> {
+    time_price_bars
+        .get(pair_address)
+        .and_then(|pair_time_price_bars| {
+            pair_time_price_bars
+                .data()
+                .values()
+                .rev()
+                .find_map(|time_price_bar| time_price_bar.data())
+                .map(|data| data.close)
+        })
+}
+
 pub struct StrategyExecutor<T, P>
 where
     T: Transport + Clone,
@@ -28,6 +109,27 @@ where
 {
     strategy: Box<dyn Strategy>,
     trade_controller: Arc<TradeController<T, P>>,
+    // Strategy state by pair address
+    pair_states: Mutex<FnvHashMap<Address, PairState>>,
+    exit_rules: ExitRules,
+    // Exit rule state by token address
+    tracked_exits: Mutex<FnvHashMap<Address, TrackedExit>>,
+    // Max weth deployed across open positions, None if uncapped
+    capital_allocation: Option<U256>,
+    // Sizes opens whose signal does not specify a size
+    sizing_policy: SizingPolicy,
+    // Slippage tolerated by each trade request
+    slippage_policy: SlippagePolicy,
+    // Seconds each trade request remains valid for
+    deadline_seconds: u64,
+    // Executor contract trades are sent through instead of the dex routers
+    executor_address: Option<Address>,
+    // Open positions by token address, marked as of the last indexed block
+    marked_positions: Mutex<FnvHashMap<Address, MarkedPosition>>,
+    // Block number and timestamp of the last indexed block
+    last_block: Mutex<Option<(BlockNumber, u64)>>,
+    // Whether settling simulates closing positions left open
+    force_close_at_end: bool,
 }
 
 impl<T, P> StrategyExecutor<T, P>
@@ -39,9 +141,92 @@ where
         Self {
             trade_controller,
             strategy,
+            pair_states: Mutex::new(FnvHashMap::default()),
+            exit_rules: ExitRules::default(),
+            tracked_exits: Mutex::new(FnvHashMap::default()),
+            capital_allocation: None,
+            sizing_policy: SizingPolicy::default(),
+            slippage_policy: SlippagePolicy::default(),
+            deadline_seconds: TradeRequestParams::default().deadline_seconds,
+            executor_address: None,
+            marked_positions: Mutex::new(FnvHashMap::default()),
+            last_block: Mutex::new(None),
+            force_close_at_end: false,
         }
     }
 
+    pub fn with_exit_rules(mut self, exit_rules: ExitRules) -> Self {
+        self.exit_rules = exit_rules;
+        self
+    }
+
+    pub fn with_capital_allocation(mut self, capital_allocation: U256) -> Self {
+        self.capital_allocation = Some(capital_allocation);
+        self
+    }
+
+    pub fn with_sizing_policy(mut self, sizing_policy: SizingPolicy) -> Self {
+        self.sizing_policy = sizing_policy;
+        self
+    }
+
+    pub fn with_slippage_policy(mut self, slippage_policy: SlippagePolicy) -> Self {
+        self.slippage_policy = slippage_policy;
+        self
+    }
+
+    pub fn with_deadline_seconds(mut self, deadline_seconds: u64) -> Self {
+        self.deadline_seconds = deadline_seconds;
+        self
+    }
+
+    pub fn with_executor_address(mut self, executor_address: Address) -> Self {
+        self.executor_address = Some(executor_address);
+        self
+    }
+
+    pub fn with_force_close_at_end(mut self, force_close_at_end: bool) -> Self {
+        self.force_close_at_end = force_close_at_end;
+        self
+    }
+
+    pub fn trade_controller(&self) -> &Arc<TradeController<T, P>> {
+        &self.trade_controller
+    }
+
+    // Restores the positions journaled by a previous run, tracking the exits
+    // of those left open
+    pub async fn restore_positions(&self) -> Result<()> {
+        let pairs = self.trade_controller.restore_positions().await?;
+
+        let mut tracked_exits = self.tracked_exits.lock().unwrap();
+        for pair in pairs.into_iter() {
+            tracked_exits.insert(*pair.token_address(), TrackedExit::new(pair));
+        }
+
+        Ok(())
+    }
+
+    // Block number and timestamp of the last indexed block
+    pub fn last_block(&self) -> Option<(BlockNumber, u64)> {
+        *self.last_block.lock().unwrap()
+    }
+
+    pub fn insert_backtest_strategy(
+        &self,
+        tx: &rusqlite::Transaction,
+        backtest_id: i64,
+    ) -> Result<()> {
+        BacktestStrategyModel::new(
+            backtest_id,
+            self.strategy.name().to_string(),
+            self.strategy.parameters(),
+            self.capital_allocation
+                .map(|capital_allocation| capital_allocation.to_string()),
+        )
+        .insert(tx)
+    }
+
     #[instrument(skip_all)]
     pub async fn on_indexed_block_message(
         &self,
@@ -49,20 +234,59 @@ where
         time_price_bar_store: &TimePriceBarStore,
     ) -> Result<()> {
         let mut pending_tx_tasks = JoinSet::new();
+        // Closes are dispatched together once every pair has been evaluated
+        let mut exits = Vec::new();
+        if let Err(err) = self.trade_controller.sync_ledger().await {
+            error!(
+                block_number = block_message.block_number,
+                "failed to sync ledger: {:?}", err
+            );
+        }
+        *self.last_block.lock().unwrap() =
+            Some((block_message.block_number, block_message.block_timestamp));
 
         // Execute core strategy logic
         {
             let trades = self.trade_controller.trades().0.read().unwrap();
             let time_price_bars = time_price_bar_store.time_price_bars().read().unwrap();
+            let sampled_price_bars = time_price_bar_store.sampled_price_bars().read().unwrap();
+            let mut pair_states = self.pair_states.lock().unwrap();
+            let mut tracked_exits = self.tracked_exits.lock().unwrap();
+
+            // Trade requests from the previous block have settled, stop tracking
+            // exits for positions that have since closed or failed to open.
+            tracked_exits.retain(|token_address, _| {
+                trades
+                    .get(token_address)
+                    .is_some_and(|address_trades| address_trades.active().is_some())
+            });
+
+            // Weth committed to open and pending positions, including gas. Opens
+            // queued in this block are added as they are dispatched.
+            let mut deployed_capital = trades
+                .values()
+                .filter_map(|address_trades| match address_trades.active() {
+                    Some(Trade::Open(open_trade_metadata)) => {
+                        Some(open_trade_metadata.weth_amount() + open_trade_metadata.gas_fee())
+                    }
+                    Some(Trade::PendingOpen(weth_amount_in)) => Some(*weth_amount_in),
+                    _ => None,
+                })
+                .fold(U256::ZERO, |acc, weth_amount| acc + weth_amount);
 
             let resolution = time_price_bar_store.resolution();
             let resolution_timestamp =
                 ResolutionTimestamp::from_timestamp(block_message.block_timestamp, &resolution);
+            let datetime = DateTime::from_timestamp(block_message.block_timestamp as i64, 0)
+                .unwrap()
+                .to_rfc2822();
 
+            let mut evaluated_token_addresses = FnvHashSet::default();
             for pair in block_message.pairs.into_iter() {
                 if cfg!(feature = "local") && *pair.address() != TARGET_PAIR_ADDRESS {
                     continue;
                 }
+                evaluated_token_addresses.insert(*pair.token_address());
 
                 // note that time price bars are by pair, trades are by token
                 let pair_time_price_bars =
@@ -72,121 +296,296 @@ where
                             pair.address().to_string()
                         )
                     });
-                let trade_request = match trades
+                let ctx = StrategyContext {
+                    block_number: block_message.block_number,
+                    block_timestamp: block_message.block_timestamp,
+                    resolution_timestamp,
+                    pair: &pair,
+                    time_price_bars: pair_time_price_bars,
+                    sampled_price_bars: sampled_price_bars
+                        .get(pair.address())
+                        .map(|pair_sampled_price_bars| pair_sampled_price_bars.as_slice())
+                        .unwrap_or_default(),
+                };
+                let last_close = ctx
+                    .time_price_bar()
+                    .and_then(|time_price_bar| time_price_bar.data())
+                    .map(|data| data.close);
+                let pair_state = pair_states.entry(*pair.address()).or_default();
+
+                let active_trade = trades
                     .get(pair.token_address())
-                    .and_then(|address_trades| address_trades.active().as_ref())
-                {
-                    None => self
-                        .strategy
-                        .should_open_position(&pair_time_price_bars, &resolution_timestamp)
-                        .inspect_err(|err| {
-                            debug!(
-                                block_number = block_message.block_number,
-                                datetime = DateTime::from_timestamp(
-                                    block_message.block_timestamp as i64,
-                                    0
-                                )
-                                .unwrap()
-                                .to_rfc2822(),
-                                pair_address = pair.address().to_string(),
-                                "skipped open trade execution: {:?}",
-                                err
-                            );
-                        })
-                        .map(|_| {
-                            TradeRequest::open(
-                                block_message.block_number,
-                                block_message.block_timestamp,
-                                pair,
-                            )
-                        }),
-                    Some(Trade::Open(open_trade_metadata)) => self
-                        .strategy
-                        .should_close_position(
-                            &pair_time_price_bars,
-                            &resolution_timestamp,
-                            &ResolutionTimestamp::from_timestamp(
+                    .and_then(|address_trades| address_trades.active().as_ref());
+                let signal = match active_trade {
+                    None => self.strategy.should_open_position(&ctx, pair_state),
+                    Some(Trade::Open(open_trade_metadata)) => {
+                        let position = Position::new(
+                            open_trade_metadata,
+                            ResolutionTimestamp::from_timestamp(
                                 *open_trade_metadata.block_timestamp(),
                                 &resolution,
                             ),
-                        )
-                        .inspect_err(|err| {
+                        );
+
+                        if let Some(price) = &last_close {
                             debug!(
-                                block_number = block_message.block_number,
-                                datetime = DateTime::from_timestamp(
-                                    block_message.block_timestamp as i64,
-                                    0
-                                )
-                                .unwrap()
-                                .to_rfc2822(),
-                                pair_address = pair.address().to_string(),
-                                "skipped close trade execution: {:?}",
-                                err
+                                block_number = ctx.block_number,
+                                datetime = datetime,
+                                pair_address = ctx.pair.address().to_string(),
+                                opened_at = position.opened_at().0,
+                                entry_price = position.entry_price().to_string(),
+                                unrealized_pnl = position.unrealized_pnl(price).to_string(),
+                                unrealized_return = position.unrealized_return(price).to_string(),
+                                "evaluating open position"
                             );
-                        })
-                        .map(|_| {
-                            TradeRequest::close(
-                                block_message.block_number,
-                                block_message.block_timestamp,
-                                pair,
-                                open_trade_metadata.indexed_trade().clone(),
-                                *open_trade_metadata.tx_hash(),
+                        }
+
+                        // Exit rules take precedence over the strategy
+                        let exit_reason = last_close.and_then(|price| {
+                            let tracked_exit = tracked_exits
+                                .entry(*pair.token_address())
+                                .or_insert_with(|| TrackedExit::new(pair));
+                            tracked_exit.pair = pair;
+
+                            self.exit_rules.evaluate(
+                                tracked_exit.track(open_trade_metadata.tx_hash()),
+                                position.entry_price(),
+                                *open_trade_metadata.block_timestamp(),
+                                price,
+                                ctx.block_timestamp,
                             )
-                        }),
-                    Some(trade) => {
-                        Err(eyre::eyre!("unactionable trade state: {:?}", trade.label()))
+                        });
+
+                        match exit_reason {
+                            Some(exit_reason) => Ok(Signal::sell(exit_reason)),
+                            None => self
+                                .strategy
+                                .should_close_position(&ctx, &position, pair_state),
+                        }
                     }
+                    Some(_) => Ok(Signal::Hold(SignalReason::PendingTrade)),
                 };
 
-                // Dispatch the trade request if strategy succeeds
-                if let Ok(trade_request) = trade_request {
-                    let trade_controller = self.trade_controller.clone();
-                    let block_timestamp = block_message.block_timestamp;
-
-                    pending_tx_tasks.spawn(async move {
+                let trade_signal = match signal {
+                    Ok(Signal::Trade(trade_signal)) => trade_signal,
+                    Ok(Signal::Hold(reason)) => {
                         debug!(
-                            datetime = DateTime::from_timestamp(block_timestamp as i64, 0)
-                                .unwrap()
-                                .to_rfc2822(),
-                            "executing trade request: {:?}", trade_request
+                            block_number = ctx.block_number,
+                            datetime = datetime,
+                            pair_address = ctx.pair.address().to_string(),
+                            "holding: {}",
+                            reason
+                        );
+                        continue;
+                    }
+                    Err(err) => {
+                        error!(
+                            block_number = ctx.block_number,
+                            pair_address = ctx.pair.address().to_string(),
+                            "failed to evaluate strategy: {:?}",
+                            err
                         );
+                        continue;
+                    }
+                };
 
-                        let token_address = trade_request.pair.token_address().clone();
-                        let op_label = trade_request.op.label();
-                        let block_number = trade_request.block_number;
+                // Enforce the signal's limit price against the latest close
+                if let Some(price) = last_close.filter(|price| !trade_signal.is_within_limit(price))
+                {
+                    debug!(
+                        block_number = ctx.block_number,
+                        datetime = datetime,
+                        pair_address = ctx.pair.address().to_string(),
+                        "holding: {}",
+                        SignalReason::LimitPriceExceeded {
+                            price,
+                            limit_price: trade_signal.limit_price.unwrap_or_default(),
+                        }
+                    );
+                    continue;
+                }
 
-                        let res = match &trade_request.op {
-                            TradeRequestOp::Open => {
-                                trade_controller.open_position(trade_request).await
-                            }
-                            TradeRequestOp::Close { .. } => {
-                                trade_controller.close_position(trade_request).await
-                            }
-                        };
-
-                        match res {
-                            Ok(_) => {
-                                info!(
-                                    block_number = block_number,
-                                    token_address = token_address.to_string(),
-                                    "executed {} trade request",
-                                    op_label
-                                );
-                            }
-                            Err(err) => {
-                                error!(
-                                    block_number = block_number,
-                                    token_address = token_address.to_string(),
-                                    "failed to execute {} trade request: {:?}",
-                                    op_label,
-                                    err
-                                );
-                            }
+                debug!(
+                    block_number = ctx.block_number,
+                    datetime = datetime,
+                    pair_address = ctx.pair.address().to_string(),
+                    side = ?trade_signal.side,
+                    size = ?trade_signal.size,
+                    confidence = trade_signal.confidence.to_string(),
+                    "signaled: {}",
+                    trade_signal.reason
+                );
+
+                // Size opens by the signal, otherwise by the sizing policy, within
+                // the cash available to the book
+                let size = match trade_signal.side {
+                    SignalSide::Buy => {
+                        let cash = self.trade_controller.cash();
+                        let size = trade_signal.size.unwrap_or_else(|| {
+                            self.sizing_policy.size(
+                                cash.unwrap_or(constants::MAX_TRADE_SIZE_WEI),
+                                ctx.time_price_bars,
+                                &trades,
+                            )
+                        });
+                        let size = cash.map_or(size, |cash| size.min(cash));
+
+                        if size.is_zero() {
+                            debug!(
+                                block_number = ctx.block_number,
+                                datetime = datetime,
+                                pair_address = ctx.pair.address().to_string(),
+                                "holding: {}",
+                                SignalReason::PositionSizeZero {
+                                    cash: cash.unwrap_or_default(),
+                                }
+                            );
+                            continue;
                         }
-                    });
+
+                        size
+                    }
+                    SignalSide::Sell => U256::ZERO,
+                };
+
+                // Keep the book within its capital allocation
+                if let Some(capital_allocation) = self
+                    .capital_allocation
+                    .filter(|_| trade_signal.side == SignalSide::Buy)
+                {
+                    if deployed_capital >= capital_allocation
+                        || deployed_capital + size > capital_allocation
+                    {
+                        debug!(
+                            block_number = ctx.block_number,
+                            datetime = datetime,
+                            pair_address = ctx.pair.address().to_string(),
+                            "holding: {}",
+                            SignalReason::CapitalAllocationExhausted {
+                                deployed: deployed_capital,
+                                capital_allocation,
+                            }
+                        );
+                        continue;
+                    }
+                }
+
+                let execution_params = self.execution_params(Some(ctx.time_price_bars));
+                let trade_request = match (trade_signal.side, active_trade) {
+                    (SignalSide::Buy, None) => {
+                        tracked_exits.insert(*pair.token_address(), TrackedExit::new(pair));
+                        deployed_capital += size;
+                        TradeRequest::open(ctx.block_number, ctx.block_timestamp, pair)
+                            .with_params(execution_params.with_weth_amount_in(size))
+                    }
+                    (SignalSide::Sell, Some(Trade::Open(open_trade_metadata))) => {
+                        TradeRequest::close(
+                            ctx.block_number,
+                            ctx.block_timestamp,
+                            pair,
+                            open_trade_metadata.indexed_trade().clone(),
+                            *open_trade_metadata.tx_hash(),
+                        )
+                        .with_params(execution_params)
+                    }
+                    (side, _) => {
+                        debug!(
+                            block_number = block_message.block_number,
+                            pair_address = pair.address().to_string(),
+                            "ignoring {:?} signal for pair without a matching position",
+                            side
+                        );
+                        continue;
+                    }
+                };
+
+                match trade_request.op {
+                    TradeRequestOp::Open => {
+                        self.dispatch_trade_request(&mut pending_tx_tasks, trade_request)
+                    }
+                    TradeRequestOp::Close { .. } => exits.push(trade_request),
+                }
+            }
+
+            // Exit rules also apply to open positions in pairs that did not
+            // trade in this block, priced at their last close.
+            if !self.exit_rules.is_empty() {
+                for (token_address, address_trades) in trades.iter() {
+                    let open_trade_metadata = match address_trades.active() {
+                        Some(Trade::Open(open_trade_metadata)) => open_trade_metadata,
+                        _ => continue,
+                    };
+                    let tracked_exit = match tracked_exits.get_mut(token_address) {
+                        Some(tracked_exit) => tracked_exit,
+                        None => continue,
+                    };
+                    if evaluated_token_addresses.contains(token_address) {
+                        continue;
+                    }
+
+                    let price = match last_close(&time_price_bars, tracked_exit.pair.address()) {
+                        Some(price) => price,
+                        None => continue,
+                    };
+
+                    let exit_reason = self.exit_rules.evaluate(
+                        tracked_exit.track(open_trade_metadata.tx_hash()),
+                        open_trade_metadata.execution_price(),
+                        *open_trade_metadata.block_timestamp(),
+                        price,
+                        block_message.block_timestamp,
+                    );
+
+                    if let Some(exit_reason) = exit_reason {
+                        debug!(
+                            block_number = block_message.block_number,
+                            datetime = datetime,
+                            pair_address = tracked_exit.pair.address().to_string(),
+                            "signaled: {}",
+                            exit_reason
+                        );
+
+                        exits.push(
+                            TradeRequest::close(
+                                block_message.block_number,
+                                block_message.block_timestamp,
+                                tracked_exit.pair,
+                                open_trade_metadata.indexed_trade().clone(),
+                                *open_trade_metadata.tx_hash(),
+                            )
+                            .with_params(
+                                self.execution_params(
+                                    time_price_bars.get(tracked_exit.pair.address()),
+                                ),
+                            ),
+                        );
+                    }
+                }
+            }
+
+            // Mark open positions to market so that a backtest ending here can
+            // account for them
+            let mut marked_positions = self.marked_positions.lock().unwrap();
+            marked_positions.clear();
+            for (token_address, address_trades) in trades.iter() {
+                if let (Some(Trade::Open(open_trade_metadata)), Some(tracked_exit)) =
+                    (address_trades.active(), tracked_exits.get(token_address))
+                {
+                    if let Some(price) = last_close(&time_price_bars, tracked_exit.pair.address()) {
+                        marked_positions.insert(
+                            *token_address,
+                            MarkedPosition {
+                                open_trade_metadata: open_trade_metadata.clone(),
+                                price,
+                                block_number: block_message.block_number,
+                                block_timestamp: block_message.block_timestamp,
+                            },
+                        );
+                    }
                 }
             }
         }
+        self.dispatch_exits(&mut pending_tx_tasks, exits);
 
         // Await completion of all pending tx submissions
         if !pending_tx_tasks.is_empty() {
@@ -199,4 +598,201 @@ where
 
         Ok(())
     }
+
+    // Positions open in the trade book, marked at the last indexed block.
+    // Positions that opened after it are marked at their entry.
+    pub fn open_positions(&self) -> Vec<MarkedPosition> {
+        let trades = self.trade_controller.trades().0.read().unwrap();
+        let marked_positions = self.marked_positions.lock().unwrap();
+
+        trades
+            .iter()
+            .filter_map(
+                |(token_address, address_trades)| match address_trades.active() {
+                    Some(Trade::Open(open_trade_metadata)) => Some(
+                        marked_positions
+                            .get(token_address)
+                            .filter(|marked_position| {
+                                marked_position.open_trade_metadata.tx_hash()
+                                    == open_trade_metadata.tx_hash()
+                            })
+                            .cloned()
+                            .unwrap_or_else(|| MarkedPosition {
+                                open_trade_metadata: open_trade_metadata.clone(),
+                                price: open_trade_metadata.execution_price(),
+                                block_number: *open_trade_metadata.block_number(),
+                                block_timestamp: *open_trade_metadata.block_timestamp(),
+                            }),
+                    ),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    // Waits for pending trades to settle. If forcing a close at the end, then
+    // closes any position left open at the last indexed block and waits for
+    // those to settle too.
+    pub async fn settle(&self) -> Result<()> {
+        self.trade_controller
+            .pending_handle()
+            .await
+            .with_context(|| "pending_handle failed")?;
+
+        if !self.force_close_at_end {
+            return Ok(());
+        }
+
+        let (block_number, block_timestamp) = match *self.last_block.lock().unwrap() {
+            Some(last_block) => last_block,
+            None => return Ok(()),
+        };
+
+        let mut pending_tx_tasks = JoinSet::new();
+        let mut exits = Vec::new();
+        {
+            let trades = self.trade_controller.trades().0.read().unwrap();
+            let tracked_exits = self.tracked_exits.lock().unwrap();
+            for (token_address, address_trades) in trades.iter() {
+                if let (Some(Trade::Open(open_trade_metadata)), Some(tracked_exit)) =
+                    (address_trades.active(), tracked_exits.get(token_address))
+                {
+                    debug!(
+                        block_number,
+                        pair_address = tracked_exit.pair.address().to_string(),
+                        "force closing open position at end"
+                    );
+
+                    exits.push(
+                        TradeRequest::close(
+                            block_number,
+                            block_timestamp,
+                            tracked_exit.pair,
+                            open_trade_metadata.indexed_trade().clone(),
+                            *open_trade_metadata.tx_hash(),
+                        )
+                        .with_params(self.execution_params(None)),
+                    );
+                }
+            }
+        }
+        self.dispatch_exits(&mut pending_tx_tasks, exits);
+
+        while let Some(pending_tx_result) = pending_tx_tasks.join_next().await {
+            let _ = pending_tx_result.inspect_err(|e| {
+                error!("join set execution error: {:?}", e);
+            });
+        }
+
+        self.trade_controller
+            .pending_handle()
+            .await
+            .with_context(|| "pending_handle failed")
+    }
+
+    // Slippage, deadline and routing of a trade request in a pair with these
+    // bars
+    fn execution_params(&self, time_price_bars: Option<&TimePriceBars>) -> TradeRequestParams {
+        let params = TradeRequestParams::new()
+            .with_slippage_bps(
+                self.slippage_policy
+                    .bps(time_price_bars, self.trade_controller.revert_rate()),
+            )
+            .with_deadline_seconds(self.deadline_seconds);
+
+        match self.executor_address {
+            Some(executor_address) => params.with_executor_address(executor_address),
+            None => params,
+        }
+    }
+
+    // Submits the closes to the trade controller in the background, as a
+    // single executor multicall if trading through the executor
+    fn dispatch_exits(&self, pending_tx_tasks: &mut JoinSet<()>, exits: Vec<TradeRequest>) {
+        if self.executor_address.is_none() || exits.len() < 2 {
+            for trade_request in exits.into_iter() {
+                self.dispatch_trade_request(pending_tx_tasks, trade_request);
+            }
+            return;
+        }
+
+        let trade_controller = self.trade_controller.clone();
+        pending_tx_tasks.spawn(async move {
+            let block_number = exits
+                .iter()
+                .map(|trade_request| trade_request.block_number)
+                .max()
+                .unwrap_or_default();
+            let exit_count = exits.len();
+            debug!(
+                block_number = block_number,
+                "executing {} batched close trade requests: {:?}", exit_count, exits
+            );
+
+            match trade_controller.close_positions(exits).await {
+                Ok(_) => {
+                    info!(
+                        block_number = block_number,
+                        "executed {} batched close trade requests", exit_count
+                    );
+                }
+                Err(err) => {
+                    error!(
+                        block_number = block_number,
+                        "failed to execute {} batched close trade requests: {:?}", exit_count, err
+                    );
+                }
+            }
+        });
+    }
+
+    // Submits the trade request to the trade controller in the background
+    fn dispatch_trade_request(
+        &self,
+        pending_tx_tasks: &mut JoinSet<()>,
+        trade_request: TradeRequest,
+    ) {
+        let trade_controller = self.trade_controller.clone();
+        let block_timestamp = trade_request.block_timestamp;
+
+        pending_tx_tasks.spawn(async move {
+            debug!(
+                datetime = DateTime::from_timestamp(block_timestamp as i64, 0)
+                    .unwrap()
+                    .to_rfc2822(),
+                "executing trade request: {:?}", trade_request
+            );
+
+            let token_address = trade_request.pair.token_address().clone();
+            let op_label = trade_request.op.label();
+            let block_number = trade_request.block_number;
+
+            let res = match &trade_request.op {
+                TradeRequestOp::Open => trade_controller.open_position(trade_request).await,
+                TradeRequestOp::Close { .. } => {
+                    trade_controller.close_position(trade_request).await
+                }
+            };
+
+            match res {
+                Ok(_) => {
+                    info!(
+                        block_number = block_number,
+                        token_address = token_address.to_string(),
+                        "executed {} trade request",
+                        op_label
+                    );
+                }
+                Err(err) => {
+                    error!(
+                        block_number = block_number,
+                        token_address = token_address.to_string(),
+                        "failed to execute {} trade request: {:?}",
+                        op_label,
+                        err
+                    );
+                }
+            }
+        });
+    }
 }
@@ -0,0 +1,304 @@
+use pochtecatl_primitives::{IndexedTrade, Pair, RpcProvider, TradeRequestParams};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{BlockNumber, U256},
+    providers::Provider,
+    rpc::types::eth::{BlockNumberOrTag, TransactionRequest},
+    transports::Transport,
+};
+
+use eyre::{eyre, Result};
+use fnv::FnvHashMap;
+use std::sync::Mutex;
+
+// Base's EIP-1559 parameters since the Canyon upgrade
+const ELASTICITY_MULTIPLIER: u64 = 6;
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 250;
+// Weight of the latest receipt in the learned gas units of a dex
+const GAS_UNITS_EMA_WEIGHT: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Dex {
+    UniswapV2,
+    UniswapV3,
+}
+
+impl From<&Pair> for Dex {
+    fn from(pair: &Pair) -> Self {
+        match pair {
+            Pair::UniswapV2(_) => Self::UniswapV2,
+            Pair::UniswapV3(_) => Self::UniswapV3,
+        }
+    }
+}
+
+impl From<&IndexedTrade> for Dex {
+    fn from(indexed_trade: &IndexedTrade) -> Self {
+        match indexed_trade {
+            IndexedTrade::UniswapV2(_) => Self::UniswapV2,
+            IndexedTrade::UniswapV3(_) => Self::UniswapV3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasFees {
+    pub base_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+    pub max_fee_per_gas: U256,
+}
+
+impl GasFees {
+    // What a transaction included in the predicted block pays per gas
+    pub fn effective_gas_price(&self) -> U256 {
+        (self.base_fee_per_gas + self.max_priority_fee_per_gas).min(self.max_fee_per_gas)
+    }
+}
+
+// Base fee of the block following a parent with these fields
+pub fn predict_base_fee(
+    parent_base_fee: U256,
+    parent_gas_used: U256,
+    parent_gas_limit: U256,
+) -> U256 {
+    let gas_target = parent_gas_limit / U256::from(ELASTICITY_MULTIPLIER);
+    if gas_target.is_zero() || parent_gas_used == gas_target {
+        return parent_base_fee;
+    }
+
+    let denominator = gas_target * U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR);
+    if parent_gas_used > gas_target {
+        let delta = parent_base_fee * (parent_gas_used - gas_target) / denominator;
+        parent_base_fee + delta.max(U256::from(1))
+    } else {
+        let delta = parent_base_fee * (gas_target - parent_gas_used) / denominator;
+        parent_base_fee.saturating_sub(delta)
+    }
+}
+
+// Prices gas for trade transactions, live and in backtests. The priority fee
+// is a percentile of the rewards paid in recent blocks, and the gas units of
+// a trade are learned per dex from our own receipts.
+#[derive(Debug)]
+pub struct GasPolicy {
+    fee_history_blocks: u64,
+    priority_fee_percentile: f64,
+    // Never pay more per gas than this, uncapped if None
+    max_fee_per_gas: Option<U256>,
+    gas_units: Mutex<FnvHashMap<Dex, U256>>,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self {
+            fee_history_blocks: 10,
+            priority_fee_percentile: 50.0,
+            max_fee_per_gas: None,
+            gas_units: Mutex::new(FnvHashMap::default()),
+        }
+    }
+}
+
+impl GasPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_fee_history_blocks(mut self, fee_history_blocks: u64) -> Self {
+        self.fee_history_blocks = fee_history_blocks;
+        self
+    }
+
+    pub fn with_priority_fee_percentile(mut self, priority_fee_percentile: f64) -> Self {
+        self.priority_fee_percentile = priority_fee_percentile;
+        self
+    }
+
+    pub fn with_max_fee_per_gas(mut self, max_fee_per_gas: U256) -> Self {
+        self.max_fee_per_gas = Some(max_fee_per_gas);
+        self
+    }
+
+    pub fn max_fee_per_gas(&self) -> Option<U256> {
+        self.max_fee_per_gas
+    }
+
+    // Gas units a trade on the pair's dex is expected to use, falling back to
+    // the pair's estimate for its routing until a receipt is seen. A run
+    // routes all of its trades the same way, so receipts aren't told apart.
+    pub fn gas_units(&self, pair: &Pair, params: &TradeRequestParams) -> U256 {
+        self.gas_units
+            .lock()
+            .unwrap()
+            .get(&Dex::from(pair))
+            .copied()
+            .unwrap_or_else(|| pair.estimate_trade_gas(params))
+    }
+
+    // Folds the gas used by a confirmed trade into its dex's gas units
+    pub fn record_gas_used(&self, indexed_trade: &IndexedTrade, gas_used: U256) {
+        let mut gas_units = self.gas_units.lock().unwrap();
+        gas_units
+            .entry(Dex::from(indexed_trade))
+            .and_modify(|units| {
+                *units = (*units * U256::from(GAS_UNITS_EMA_WEIGHT - 1) + gas_used)
+                    / U256::from(GAS_UNITS_EMA_WEIGHT)
+            })
+            .or_insert(gas_used);
+    }
+
+    // Fees for a transaction included in the given block, from its parent's
+    // header and the priority fees paid in the blocks leading up to it
+    pub async fn fees<T, P>(
+        &self,
+        block_number: BlockNumber,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<GasFees>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let parent_block_number = block_number.saturating_sub(1);
+        let parent_header = rpc_provider
+            .block_provider()
+            .get_block_header(parent_block_number)
+            .await?
+            .ok_or_else(|| eyre!("block header {} not found", parent_block_number))?;
+        let base_fee_per_gas = predict_base_fee(
+            parent_header
+                .base_fee_per_gas
+                .map(U256::from)
+                .ok_or_else(|| eyre!("block {} has no base fee", parent_block_number))?,
+            U256::from(parent_header.gas_used),
+            U256::from(parent_header.gas_limit),
+        );
+
+        let fee_history = rpc_provider
+            .get_fee_history(
+                self.fee_history_blocks,
+                BlockNumberOrTag::Number(parent_block_number),
+                &[self.priority_fee_percentile],
+            )
+            .await?;
+        let mut rewards = fee_history
+            .reward
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|block_rewards| block_rewards.first().copied().map(U256::from))
+            .collect::<Vec<_>>();
+        rewards.sort();
+        let max_priority_fee_per_gas = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+
+        // Leave headroom for the base fee to rise while the tx is pending
+        let max_fee_per_gas = base_fee_per_gas * U256::from(2) + max_priority_fee_per_gas;
+
+        Ok(GasFees {
+            base_fee_per_gas,
+            max_priority_fee_per_gas,
+            max_fee_per_gas: self
+                .max_fee_per_gas
+                .map_or(max_fee_per_gas, |cap| max_fee_per_gas.min(cap)),
+        })
+    }
+
+    // Estimated fee of a trade on the pair included in the given block
+    pub async fn estimate_gas_fee<T, P>(
+        &self,
+        pair: &Pair,
+        params: &TradeRequestParams,
+        block_number: BlockNumber,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<U256>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        self.fees(block_number, rpc_provider)
+            .await
+            .map(|fees| fees.effective_gas_price() * self.gas_units(pair, params))
+    }
+
+    // Prices a transaction for inclusion in the given block
+    pub async fn apply<T, P>(
+        &self,
+        tx_request: TransactionRequest,
+        block_number: BlockNumber,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<TransactionRequest>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let fees = self.fees(block_number, rpc_provider).await?;
+
+        Ok(TransactionRequest {
+            max_fee_per_gas: Some(fees.max_fee_per_gas.saturating_to()),
+            max_priority_fee_per_gas: Some(fees.max_priority_fee_per_gas.saturating_to()),
+            ..tx_request
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{predict_base_fee, Dex, GasFees, GasPolicy};
+
+    use pochtecatl_primitives::{IndexedTrade, UniswapV2IndexedTrade};
+
+    use alloy::primitives::{Address, U256};
+
+    #[test]
+    fn test_predict_base_fee() {
+        let base_fee = U256::from(1_000_000);
+        let gas_limit = U256::from(60_000_000);
+
+        // At target
+        assert_eq!(
+            predict_base_fee(base_fee, U256::from(10_000_000), gas_limit),
+            base_fee
+        );
+        // Full block: (60m - 10m) / 10m / 250 = +2%
+        assert_eq!(
+            predict_base_fee(base_fee, gas_limit, gas_limit),
+            U256::from(1_020_000)
+        );
+        // Empty block: -0.4%
+        assert_eq!(
+            predict_base_fee(base_fee, U256::ZERO, gas_limit),
+            U256::from(996_000)
+        );
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let fees = GasFees {
+            base_fee_per_gas: U256::from(100),
+            max_priority_fee_per_gas: U256::from(10),
+            max_fee_per_gas: U256::from(105),
+        };
+        assert_eq!(fees.effective_gas_price(), U256::from(105));
+    }
+
+    #[test]
+    fn test_record_gas_used() {
+        let gas_policy = GasPolicy::new();
+        let indexed_trade = IndexedTrade::UniswapV2(UniswapV2IndexedTrade::new(
+            Address::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            U256::ZERO,
+            Address::ZERO,
+        ));
+
+        gas_policy.record_gas_used(&indexed_trade, U256::from(100_000));
+        gas_policy.record_gas_used(&indexed_trade, U256::from(140_000));
+
+        let gas_units = gas_policy.gas_units.lock().unwrap();
+        assert_eq!(gas_units.get(&Dex::UniswapV2), Some(&U256::from(110_000)));
+        assert!(gas_units.get(&Dex::UniswapV3).is_none());
+    }
+}
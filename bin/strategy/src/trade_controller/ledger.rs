@@ -0,0 +1,106 @@
+use alloy::primitives::U256;
+use eyre::{eyre, Result};
+
+// Weth available to open positions. Live it follows the signer's balance,
+// in backtests it starts from a fixed amount and moves with simulated fills.
+// Cash reserved for a pending open is unavailable until the open settles.
+#[derive(Debug, Clone, Default)]
+pub struct Ledger {
+    cash: U256,
+    reserved: U256,
+}
+
+impl Ledger {
+    pub fn new(cash: U256) -> Self {
+        Self {
+            cash,
+            reserved: U256::ZERO,
+        }
+    }
+
+    pub fn cash(&self) -> U256 {
+        self.cash
+    }
+
+    pub fn reserve(&mut self, amount: U256) -> Result<()> {
+        if amount > self.cash {
+            return Err(eyre!(
+                "Insufficient cash: {} requested, {} available",
+                amount,
+                self.cash
+            ));
+        }
+
+        self.cash -= amount;
+        self.reserved += amount;
+        Ok(())
+    }
+
+    // Returns a reservation whose open failed
+    pub fn release(&mut self, amount: U256) {
+        let amount = amount.min(self.reserved);
+        self.reserved -= amount;
+        self.cash += amount;
+    }
+
+    // Settles a reservation against the weth and gas actually spent opening
+    pub fn settle_open(&mut self, reserved: U256, cost: U256) {
+        let reserved = reserved.min(self.reserved);
+        self.reserved -= reserved;
+        if cost > reserved {
+            self.cash = self.cash.saturating_sub(cost - reserved);
+        } else {
+            self.cash += reserved - cost;
+        }
+    }
+
+    // Credits the weth received closing a position, net of gas
+    pub fn settle_close(&mut self, proceeds: U256) {
+        self.cash += proceeds;
+    }
+
+    // Replaces cash with the wallet balance, less outstanding reservations
+    pub fn sync_balance(&mut self, balance: U256) {
+        self.cash = balance.saturating_sub(self.reserved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Ledger;
+
+    use alloy::primitives::U256;
+
+    #[test]
+    fn test_reserve_and_settle() {
+        let mut ledger = Ledger::new(U256::from(100));
+
+        assert!(ledger.reserve(U256::from(150)).is_err());
+
+        ledger.reserve(U256::from(60)).unwrap();
+        assert_eq!(ledger.cash(), U256::from(40));
+
+        // Filled for less than reserved, the remainder returns to cash
+        ledger.settle_open(U256::from(60), U256::from(55));
+        assert_eq!(ledger.cash(), U256::from(45));
+
+        ledger.reserve(U256::from(20)).unwrap();
+        ledger.release(U256::from(20));
+        assert_eq!(ledger.cash(), U256::from(45));
+
+        ledger.settle_close(U256::from(70));
+        assert_eq!(ledger.cash(), U256::from(115));
+    }
+
+    #[test]
+    fn test_sync_balance() {
+        let mut ledger = Ledger::new(U256::from(100));
+        ledger.reserve(U256::from(30)).unwrap();
+
+        ledger.sync_balance(U256::from(200));
+        assert_eq!(ledger.cash(), U256::from(170));
+
+        ledger.sync_balance(U256::from(10));
+        assert_eq!(ledger.cash(), U256::ZERO);
+    }
+}
@@ -1,12 +1,28 @@
-pub use trade_controller::TradeController;
+pub use gas_policy::GasPolicy;
+pub use ledger::Ledger;
+pub use revert_tracker::RevertTracker;
+pub use risk_engine::{RiskEngine, RiskLimits};
+pub use submission_backend::SubmissionBackend;
+pub use trade_controller::{closed_trade_pnl, TradeController};
 pub use trade_controller_request::{TradeControllerRequest, TradeRequest};
+pub use trade_journal::TradeJournal;
 pub use trades::{AddressTrades, Trade, Trades};
 
-pub use transaction::Transaction;
+pub use transaction::{trade_metadata_from_receipt, Transaction};
+pub use transaction_manager::TransactionManager;
+pub use wallet_reconciler::WalletReconciler;
 
+mod gas_policy;
+mod ledger;
+mod revert_tracker;
+mod risk_engine;
+mod submission_backend;
 mod trade_controller;
 mod trade_controller_request;
+mod trade_journal;
 mod trades;
 
 // mod trade_request;
 mod transaction;
+mod transaction_manager;
+mod wallet_reconciler;
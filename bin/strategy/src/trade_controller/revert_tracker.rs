@@ -0,0 +1,53 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+// Trades whose outcome informs the revert rate
+const RECENT_TRADE_COUNT: usize = 20;
+
+// Whether recently submitted trades confirmed or failed, shared with the
+// confirmation callbacks.
+#[derive(Debug, Clone, Default)]
+pub struct RevertTracker(Arc<Mutex<VecDeque<bool>>>);
+
+impl RevertTracker {
+    pub fn record(&self, reverted: bool) {
+        let mut outcomes = self.0.lock().unwrap();
+        if outcomes.len() == RECENT_TRADE_COUNT {
+            outcomes.pop_front();
+        }
+        outcomes.push_back(reverted);
+    }
+
+    // Fraction of recent trades that failed, zero without history
+    pub fn revert_rate(&self) -> f64 {
+        let outcomes = self.0.lock().unwrap();
+        if outcomes.is_empty() {
+            return 0.0;
+        }
+
+        outcomes.iter().filter(|reverted| **reverted).count() as f64 / outcomes.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RevertTracker, RECENT_TRADE_COUNT};
+
+    #[test]
+    fn test_revert_rate() {
+        let revert_tracker = RevertTracker::default();
+        assert_eq!(revert_tracker.revert_rate(), 0.0);
+
+        revert_tracker.record(true);
+        revert_tracker.record(false);
+        assert_eq!(revert_tracker.revert_rate(), 0.5);
+
+        // Older outcomes age out
+        for _ in 0..RECENT_TRADE_COUNT {
+            revert_tracker.record(false);
+        }
+        assert_eq!(revert_tracker.revert_rate(), 0.0);
+    }
+}
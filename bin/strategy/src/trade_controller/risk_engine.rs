@@ -0,0 +1,462 @@
+use super::closed_trade_pnl;
+use super::{AddressTrades, Trade};
+
+use alloy::primitives::{Address, BlockNumber, I256, U256};
+use eyre::{eyre, Result};
+use fnv::FnvHashMap;
+use std::{fmt, sync::Mutex};
+use tracing::warn;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Portfolio limits checked before opening a position. Notional amounts are
+// weth in wei, None disables the limit.
+#[derive(Debug, Clone, Default)]
+pub struct RiskLimits {
+    pub max_open_positions: Option<usize>,
+    pub max_token_notional: Option<U256>,
+    pub max_total_notional: Option<U256>,
+    // Notional opened within a single block
+    pub max_block_notional: Option<U256>,
+    // Realized loss within a UTC day after which entries pause until the next
+    pub daily_loss_limit: Option<U256>,
+    // After a token's position closes at a loss, wait this long to reopen it
+    pub stop_out_cooldown_seconds: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskRejectionReason {
+    StopOutCooldown {
+        stopped_out_at: u64,
+        cooldown_seconds: u64,
+    },
+    DailyLossLimit {
+        daily_loss: U256,
+        daily_loss_limit: U256,
+    },
+    MaxOpenPositions {
+        open_positions: usize,
+        max_open_positions: usize,
+    },
+    MaxTokenNotional {
+        notional: U256,
+        max_token_notional: U256,
+    },
+    MaxTotalNotional {
+        deployed: U256,
+        notional: U256,
+        max_total_notional: U256,
+    },
+    MaxBlockNotional {
+        block_notional: U256,
+        notional: U256,
+        max_block_notional: U256,
+    },
+}
+
+impl RiskRejectionReason {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::StopOutCooldown { .. } => "stop_out_cooldown",
+            Self::DailyLossLimit { .. } => "daily_loss_limit",
+            Self::MaxOpenPositions { .. } => "max_open_positions",
+            Self::MaxTokenNotional { .. } => "max_token_notional",
+            Self::MaxTotalNotional { .. } => "max_total_notional",
+            Self::MaxBlockNotional { .. } => "max_block_notional",
+        }
+    }
+}
+
+impl fmt::Display for RiskRejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StopOutCooldown {
+                stopped_out_at,
+                cooldown_seconds,
+            } => write!(
+                f,
+                "token was stopped out at {}, cooling down for {}s",
+                stopped_out_at, cooldown_seconds
+            ),
+            Self::DailyLossLimit {
+                daily_loss,
+                daily_loss_limit,
+            } => write!(
+                f,
+                "daily loss {} has reached limit {}",
+                daily_loss, daily_loss_limit
+            ),
+            Self::MaxOpenPositions {
+                open_positions,
+                max_open_positions,
+            } => write!(
+                f,
+                "open positions {} has reached max {}",
+                open_positions, max_open_positions
+            ),
+            Self::MaxTokenNotional {
+                notional,
+                max_token_notional,
+            } => write!(
+                f,
+                "notional {} exceeds max token notional {}",
+                notional, max_token_notional
+            ),
+            Self::MaxTotalNotional {
+                deployed,
+                notional,
+                max_total_notional,
+            } => write!(
+                f,
+                "deployed {} plus notional {} exceeds max total notional {}",
+                deployed, notional, max_total_notional
+            ),
+            Self::MaxBlockNotional {
+                block_notional,
+                notional,
+                max_block_notional,
+            } => write!(
+                f,
+                "block notional {} plus notional {} exceeds max block notional {}",
+                block_notional, notional, max_block_notional
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskRejection {
+    pub token_address: Address,
+    pub block_number: BlockNumber,
+    pub block_timestamp: u64,
+    pub reason: RiskRejectionReason,
+}
+
+// State of the trade book that the limits are checked against
+#[derive(Debug, Default)]
+struct Exposure {
+    open_positions: usize,
+    // Weth committed to active positions, including gas
+    deployed: U256,
+    // Realized pnl of trades closed on the same UTC day
+    daily_pnl: I256,
+    // When the token's last position closed, if it closed at a loss
+    token_stopped_out_at: Option<u64>,
+}
+
+impl Exposure {
+    fn new(
+        trades: &FnvHashMap<Address, AddressTrades>,
+        token_address: &Address,
+        block_timestamp: u64,
+    ) -> Self {
+        let day = block_timestamp / SECONDS_PER_DAY;
+        let mut exposure = Exposure::default();
+
+        for address_trades in trades.values() {
+            match address_trades.active() {
+                Some(Trade::Open(open_trade_metadata)) => {
+                    exposure.open_positions += 1;
+                    exposure.deployed +=
+                        open_trade_metadata.weth_amount() + open_trade_metadata.gas_fee();
+                }
+                Some(Trade::PendingOpen(weth_amount)) | Some(Trade::PendingClose(weth_amount)) => {
+                    exposure.open_positions += 1;
+                    exposure.deployed += *weth_amount;
+                }
+                None => {}
+            }
+
+            for (open_trade, close_trade) in address_trades.closed() {
+                if *close_trade.block_timestamp() / SECONDS_PER_DAY == day {
+                    exposure.daily_pnl += closed_trade_pnl(open_trade, close_trade);
+                }
+            }
+        }
+
+        exposure.token_stopped_out_at = trades
+            .get(token_address)
+            .and_then(|address_trades| address_trades.closed().last())
+            .filter(|(open_trade, close_trade)| {
+                closed_trade_pnl(open_trade, close_trade).is_negative()
+            })
+            .map(|(_, close_trade)| *close_trade.block_timestamp());
+
+        exposure
+    }
+}
+
+// Admits or rejects position opens against the risk limits, keeping a
+// record of every rejection.
+#[derive(Default)]
+pub struct RiskEngine {
+    limits: RiskLimits,
+    // Block number and the notional admitted within it
+    block_notional: Mutex<(BlockNumber, U256)>,
+    rejections: Mutex<Vec<RiskRejection>>,
+}
+
+impl RiskEngine {
+    pub fn new(limits: RiskLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    pub fn rejections(&self) -> Vec<RiskRejection> {
+        self.rejections.lock().unwrap().clone()
+    }
+
+    // Errs if opening a position of the notional in the token would breach a
+    // limit, recording the rejection.
+    pub fn check_open(
+        &self,
+        trades: &FnvHashMap<Address, AddressTrades>,
+        token_address: &Address,
+        block_number: BlockNumber,
+        block_timestamp: u64,
+        notional: U256,
+    ) -> Result<()> {
+        let mut block_notional = self.block_notional.lock().unwrap();
+        if block_notional.0 != block_number {
+            *block_notional = (block_number, U256::ZERO);
+        }
+
+        let exposure = Exposure::new(trades, token_address, block_timestamp);
+        match self.evaluate(&exposure, block_notional.1, notional, block_timestamp) {
+            None => {
+                block_notional.1 += notional;
+                Ok(())
+            }
+            Some(reason) => {
+                warn!(
+                    block_number,
+                    token_address = token_address.to_string(),
+                    reason = reason.label(),
+                    "rejected open: {}",
+                    reason
+                );
+
+                let err = eyre!("Risk limit rejected open of {}: {}", token_address, reason);
+                self.rejections.lock().unwrap().push(RiskRejection {
+                    token_address: *token_address,
+                    block_number,
+                    block_timestamp,
+                    reason,
+                });
+
+                Err(err)
+            }
+        }
+    }
+
+    // Returns the notional of an admitted open that did not go through, if
+    // its block is still the one being tracked
+    pub fn release_open(&self, block_number: BlockNumber, notional: U256) {
+        let mut block_notional = self.block_notional.lock().unwrap();
+        if block_notional.0 == block_number {
+            block_notional.1 = block_notional.1.saturating_sub(notional);
+        }
+    }
+
+    fn evaluate(
+        &self,
+        exposure: &Exposure,
+        block_notional: U256,
+        notional: U256,
+        block_timestamp: u64,
+    ) -> Option<RiskRejectionReason> {
+        if let (Some(stopped_out_at), Some(cooldown_seconds)) = (
+            exposure.token_stopped_out_at,
+            self.limits.stop_out_cooldown_seconds,
+        ) {
+            if stopped_out_at.saturating_add(cooldown_seconds) > block_timestamp {
+                return Some(RiskRejectionReason::StopOutCooldown {
+                    stopped_out_at,
+                    cooldown_seconds,
+                });
+            }
+        }
+
+        if let Some(daily_loss_limit) = self.limits.daily_loss_limit {
+            if exposure.daily_pnl.is_negative()
+                && exposure.daily_pnl.unsigned_abs() >= daily_loss_limit
+            {
+                return Some(RiskRejectionReason::DailyLossLimit {
+                    daily_loss: exposure.daily_pnl.unsigned_abs(),
+                    daily_loss_limit,
+                });
+            }
+        }
+
+        if let Some(max_open_positions) = self.limits.max_open_positions {
+            if exposure.open_positions >= max_open_positions {
+                return Some(RiskRejectionReason::MaxOpenPositions {
+                    open_positions: exposure.open_positions,
+                    max_open_positions,
+                });
+            }
+        }
+
+        if let Some(max_token_notional) = self.limits.max_token_notional {
+            if notional > max_token_notional {
+                return Some(RiskRejectionReason::MaxTokenNotional {
+                    notional,
+                    max_token_notional,
+                });
+            }
+        }
+
+        if let Some(max_total_notional) = self.limits.max_total_notional {
+            if exposure.deployed + notional > max_total_notional {
+                return Some(RiskRejectionReason::MaxTotalNotional {
+                    deployed: exposure.deployed,
+                    notional,
+                    max_total_notional,
+                });
+            }
+        }
+
+        if let Some(max_block_notional) = self.limits.max_block_notional {
+            if block_notional + notional > max_block_notional {
+                return Some(RiskRejectionReason::MaxBlockNotional {
+                    block_notional,
+                    notional,
+                    max_block_notional,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AddressTrades, Exposure, RiskEngine, RiskLimits, RiskRejectionReason, Trade};
+
+    use alloy::primitives::{Address, I256, U256};
+    use fnv::FnvHashMap;
+
+    #[test]
+    fn test_evaluate() {
+        let engine = RiskEngine::new(RiskLimits {
+            max_open_positions: Some(2),
+            max_token_notional: Some(U256::from(100)),
+            max_total_notional: Some(U256::from(250)),
+            max_block_notional: Some(U256::from(150)),
+            daily_loss_limit: Some(U256::from(50)),
+            stop_out_cooldown_seconds: Some(60),
+        });
+        let exposure = Exposure {
+            open_positions: 1,
+            deployed: U256::from(100),
+            daily_pnl: I256::try_from(-20).unwrap(),
+            token_stopped_out_at: Some(1000),
+        };
+
+        assert_eq!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(100), 1030),
+            Some(RiskRejectionReason::StopOutCooldown {
+                stopped_out_at: 1000,
+                cooldown_seconds: 60,
+            })
+        );
+        assert_eq!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(100), 1060),
+            None
+        );
+        assert_eq!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(101), 1060),
+            Some(RiskRejectionReason::MaxTokenNotional {
+                notional: U256::from(101),
+                max_token_notional: U256::from(100),
+            })
+        );
+        assert_eq!(
+            engine.evaluate(&exposure, U256::from(60), U256::from(100), 1060),
+            Some(RiskRejectionReason::MaxBlockNotional {
+                block_notional: U256::from(60),
+                notional: U256::from(100),
+                max_block_notional: U256::from(150),
+            })
+        );
+
+        let exposure = Exposure {
+            daily_pnl: I256::try_from(-50).unwrap(),
+            ..Exposure::default()
+        };
+        assert!(matches!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(100), 1060),
+            Some(RiskRejectionReason::DailyLossLimit { .. })
+        ));
+
+        let exposure = Exposure {
+            open_positions: 2,
+            ..Exposure::default()
+        };
+        assert!(matches!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(100), 1060),
+            Some(RiskRejectionReason::MaxOpenPositions { .. })
+        ));
+
+        let exposure = Exposure {
+            open_positions: 1,
+            deployed: U256::from(200),
+            ..Exposure::default()
+        };
+        assert!(matches!(
+            engine.evaluate(&exposure, U256::ZERO, U256::from(100), 1060),
+            Some(RiskRejectionReason::MaxTotalNotional { .. })
+        ));
+    }
+
+    #[test]
+    fn test_exposure_pending_trades() {
+        let mut trades = FnvHashMap::default();
+        for (token_address, active) in [
+            (Address::repeat_byte(1), Trade::PendingOpen(U256::from(40))),
+            (Address::repeat_byte(2), Trade::PendingClose(U256::from(60))),
+        ] {
+            trades
+                .entry(token_address)
+                .or_insert_with(AddressTrades::default)
+                .set_active(Some(active));
+        }
+
+        let exposure = Exposure::new(&trades, &Address::ZERO, 0);
+        assert_eq!(exposure.open_positions, 2);
+        assert_eq!(exposure.deployed, U256::from(100));
+    }
+
+    #[test]
+    fn test_check_open_block_notional() {
+        let engine = RiskEngine::new(RiskLimits {
+            max_block_notional: Some(U256::from(150)),
+            ..RiskLimits::default()
+        });
+        let trades = FnvHashMap::default();
+
+        assert!(engine
+            .check_open(&trades, &Address::ZERO, 1, 10, U256::from(100))
+            .is_ok());
+        assert!(engine
+            .check_open(&trades, &Address::ZERO, 1, 10, U256::from(100))
+            .is_err());
+        // Block notional resets in the next block
+        assert!(engine
+            .check_open(&trades, &Address::ZERO, 2, 12, U256::from(100))
+            .is_ok());
+        // Opens that don't go through give their notional back
+        engine.release_open(2, U256::from(100));
+        assert!(engine
+            .check_open(&trades, &Address::ZERO, 2, 12, U256::from(150))
+            .is_ok());
+
+        let rejections = engine.rejections();
+        assert_eq!(rejections.len(), 1);
+        assert_eq!(rejections[0].block_number, 1);
+        assert_eq!(rejections[0].reason.label(), "max_block_notional");
+    }
+}
@@ -0,0 +1,215 @@
+use pochtecatl_primitives::{send_json_rpc, RpcProvider};
+
+use alloy::{
+    network::{Ethereum, TransactionBuilder},
+    primitives::{Bytes, TxHash},
+    providers::Provider,
+    rpc::types::eth::TransactionRequest,
+    transports::Transport,
+};
+
+use eyre::{eyre, Report, Result, WrapErr};
+use std::str::FromStr;
+use url::Url;
+
+// Blocks each bundle is submitted for, covering the default confirmation
+// timeout
+const DEFAULT_BUNDLE_TARGET_BLOCKS: u64 = 5;
+// Gas of a tx bundled behind another it depends on, e.g. a close behind its
+// approval, which can't be estimated against the current state
+const BUNDLED_TX_GAS_LIMIT: u128 = 500_000;
+
+// Where trade transactions are sent
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SubmissionBackend {
+    // The provider's rpc, which broadcasts to the public mempool
+    #[default]
+    Public,
+    // An rpc that keeps txs out of the public mempool until included
+    PrivateRpc(Url),
+    // A relay accepting eth_sendBundle. Each bundle lands atomically in one
+    // of the target blocks following submission, or not at all.
+    BundleRelay {
+        url: Url,
+        target_blocks: u64,
+    },
+}
+
+// Parses "public", "private:<url>" or "bundle:<url>"
+impl FromStr for SubmissionBackend {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None if s == "public" => Ok(Self::Public),
+            Some(("private", url)) => Ok(Self::PrivateRpc(
+                url.parse().wrap_err("Failed to parse private rpc url")?,
+            )),
+            Some(("bundle", url)) => Ok(Self::BundleRelay {
+                url: url.parse().wrap_err("Failed to parse bundle relay url")?,
+                target_blocks: DEFAULT_BUNDLE_TARGET_BLOCKS,
+            }),
+            _ => Err(eyre!("Invalid submission backend: {}", s)),
+        }
+    }
+}
+
+async fn send_bundle(
+    client: &reqwest::Client,
+    url: &Url,
+    raw_txs: &[Bytes],
+    block_number: u64,
+) -> Result<()> {
+    send_json_rpc::<serde_json::Value>(
+        client,
+        url,
+        "eth_sendBundle",
+        serde_json::json!([{
+            "txs": raw_txs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            "blockNumber": format!("{:#x}", block_number),
+        }]),
+    )
+    .await
+    .map(|_| ())
+}
+
+impl SubmissionBackend {
+    pub fn is_bundle(&self) -> bool {
+        matches!(self, Self::BundleRelay { .. })
+    }
+
+    // Sends the txs in order, as one bundle if bundling, returning the hash
+    // of the last. Inclusion is left to the caller to track.
+    pub async fn submit<T, P>(
+        &self,
+        client: &reqwest::Client,
+        tx_requests: Vec<TransactionRequest>,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<TxHash>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let mut tx_hash = None;
+        match self {
+            Self::Public => {
+                for tx_request in tx_requests.into_iter() {
+                    let pending = rpc_provider.send_transaction(tx_request).await?;
+                    tx_hash = Some(*pending.tx_hash());
+                }
+            }
+            Self::PrivateRpc(url) => {
+                for tx_request in tx_requests.into_iter() {
+                    let (signed_tx_hash, raw_tx) =
+                        rpc_provider.sign_transaction(tx_request).await?;
+                    send_json_rpc::<serde_json::Value>(
+                        client,
+                        url,
+                        "eth_sendRawTransaction",
+                        serde_json::json!([raw_tx.to_string()]),
+                    )
+                    .await?;
+                    tx_hash = Some(signed_tx_hash);
+                }
+            }
+            Self::BundleRelay { url, target_blocks } => {
+                let mut raw_txs = Vec::with_capacity(tx_requests.len());
+                for (index, tx_request) in tx_requests.into_iter().enumerate() {
+                    let tx_request = match tx_request.gas {
+                        None if index > 0 => tx_request.with_gas_limit(BUNDLED_TX_GAS_LIMIT),
+                        _ => tx_request,
+                    };
+                    let (signed_tx_hash, raw_tx) =
+                        rpc_provider.sign_transaction(tx_request).await?;
+                    raw_txs.push(raw_tx);
+                    tx_hash = Some(signed_tx_hash);
+                }
+
+                let latest_block_number = rpc_provider
+                    .block_provider()
+                    .get_latest_block_header()
+                    .await?
+                    .number
+                    .map(|number| number.to::<u64>())
+                    .ok_or_else(|| eyre!("Latest block header has no number"))?;
+                for block_number in latest_block_number + 1..=latest_block_number + target_blocks {
+                    send_bundle(client, url, &raw_txs, block_number).await?;
+                }
+            }
+        }
+
+        tx_hash.ok_or_else(|| eyre!("No txs to submit"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{send_bundle, SubmissionBackend};
+
+    use pochtecatl_primitives::test_utils::mock_json_rpc_server;
+
+    use alloy::primitives::Bytes;
+    use eyre::{eyre, Result};
+
+    #[test]
+    fn test_from_str() -> Result<()> {
+        assert_eq!(
+            "public".parse::<SubmissionBackend>()?,
+            SubmissionBackend::Public
+        );
+        assert_eq!(
+            "private:https://rpc.example.com".parse::<SubmissionBackend>()?,
+            SubmissionBackend::PrivateRpc("https://rpc.example.com".parse()?)
+        );
+        assert!(matches!(
+            "bundle:https://relay.example.com".parse::<SubmissionBackend>()?,
+            SubmissionBackend::BundleRelay {
+                target_blocks: 5,
+                ..
+            }
+        ));
+        assert!("bundle".parse::<SubmissionBackend>().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle() -> Result<()> {
+        let (url, mut requests) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":1,"result":{"bundleHash":"0x1234"}}"#.to_string(),
+        ])
+        .await?;
+
+        let raw_txs = vec![Bytes::from(vec![1, 2]), Bytes::from(vec![3])];
+        send_bundle(&reqwest::Client::new(), &url, &raw_txs, 16).await?;
+
+        let request = requests
+            .recv()
+            .await
+            .ok_or_else(|| eyre!("Relay received no request"))?;
+        assert_eq!(request["method"], "eth_sendBundle");
+        assert_eq!(
+            request["params"],
+            serde_json::json!([{ "txs": ["0x0102", "0x03"], "blockNumber": "0x10" }])
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_rejected() -> Result<()> {
+        let (url, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"bundle rejected"}}"#
+                .to_string(),
+        ])
+        .await?;
+
+        assert!(
+            send_bundle(&reqwest::Client::new(), &url, &[Bytes::from(vec![1])], 16)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+}
@@ -1,14 +1,82 @@
-use super::{AddressTrades, Trade, TradeControllerRequest, Trades, Transaction};
+use super::{
+    risk_engine::RiskRejection, trade_metadata_from_receipt, AddressTrades, GasPolicy, Ledger,
+    RevertTracker, RiskEngine, RiskLimits, Trade, TradeControllerRequest, TradeJournal, Trades,
+    Transaction, TransactionManager,
+};
 use crate::config;
 
-use pochtecatl_db::NewBacktestClosedTradeModel;
-use pochtecatl_primitives::{constants, RpcProvider, TradeMetadata};
+use pochtecatl_db::{NewBacktestClosedTradeModel, NewBacktestRiskRejectionModel};
+use pochtecatl_primitives::{
+    constants, IndexedTrade, Pair, PairInput, QuotedTransactionRequest, RpcProvider, TradeMetadata,
+    TradeRequestOp, UniswapV2PairInput, UniswapV3PairInput,
+};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{Address, BlockNumber, I256, U256},
+    providers::Provider,
+    rpc::types::eth::Header,
+    transports::Transport,
+};
+
+use eyre::{eyre, Report, Result};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::{debug, error, info, warn};
+
+// Journal writes made once a tx is sent are logged rather than failing the
+// trade
+fn log_journal_err(token_address: &Address, res: Result<()>) {
+    if let Err(err) = res {
+        error!(
+            token_address = token_address.to_string(),
+            "Failed to journal trade: {:?}", err
+        );
+    }
+}
+
+// Weth returned by closing the trade less the weth spent opening it, net of
+// the gas paid for both.
+pub fn closed_trade_pnl(open_trade: &TradeMetadata, close_trade: &TradeMetadata) -> I256 {
+    I256::from_raw(close_trade.weth_amount())
+        - I256::from_raw(open_trade.weth_amount())
+        - I256::from_raw(*open_trade.gas_fee())
+        - I256::from_raw(*close_trade.gas_fee())
+}
+
+pub(super) fn pair_input(indexed_trade: &IndexedTrade) -> PairInput {
+    match indexed_trade {
+        IndexedTrade::UniswapV2(_) => {
+            PairInput::UniswapV2(UniswapV2PairInput::new(*indexed_trade.pair_address()))
+        }
+        IndexedTrade::UniswapV3(_) => {
+            PairInput::UniswapV3(UniswapV3PairInput::new(*indexed_trade.pair_address()))
+        }
+    }
+}
 
-use alloy::{network::Ethereum, providers::Provider, transports::Transport};
+async fn wait_for_block_header<T, P>(
+    block_number: BlockNumber,
+    rpc_provider: &RpcProvider<T, P>,
+) -> Result<Header>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    loop {
+        if let Some(header) = rpc_provider
+            .block_provider()
+            .get_block_header(block_number)
+            .await?
+        {
+            return Ok(header);
+        }
 
-use eyre::{eyre, Result};
-use std::sync::Arc;
-use tracing::{error, info};
+        tokio::time::sleep(Duration::from_secs(constants::AVERAGE_BLOCK_TIME_SECONDS)).await;
+    }
+}
 
 pub struct TradeController<T, P>
 where
@@ -17,6 +85,14 @@ where
 {
     rpc_provider: Arc<RpcProvider<T, P>>,
     trades: Trades,
+    risk_engine: Arc<RiskEngine>,
+    // Cash available to open positions, untracked if None
+    ledger: Option<Arc<Mutex<Ledger>>>,
+    revert_tracker: RevertTracker,
+    gas_policy: Arc<GasPolicy>,
+    transaction_manager: Arc<TransactionManager>,
+    // Live positions are journaled to the db if set
+    journal: Option<Arc<TradeJournal>>,
 }
 
 impl<T, P> TradeController<T, P>
@@ -28,6 +104,190 @@ where
         TradeController {
             rpc_provider,
             trades: Trades::default(),
+            risk_engine: Arc::new(RiskEngine::default()),
+            ledger: None,
+            revert_tracker: RevertTracker::default(),
+            gas_policy: Arc::new(GasPolicy::default()),
+            transaction_manager: Arc::new(TransactionManager::default()),
+            journal: None,
+        }
+    }
+
+    pub fn with_risk_limits(mut self, risk_limits: RiskLimits) -> Self {
+        self.risk_engine = Arc::new(RiskEngine::new(risk_limits));
+        self
+    }
+
+    pub fn with_gas_policy(mut self, gas_policy: GasPolicy) -> Self {
+        self.gas_policy = Arc::new(gas_policy);
+        self
+    }
+
+    // Strategies sending from the same signer must share a manager
+    pub fn with_transaction_manager(
+        mut self,
+        transaction_manager: Arc<TransactionManager>,
+    ) -> Self {
+        self.transaction_manager = transaction_manager;
+        self
+    }
+
+    pub fn with_journal(mut self, journal: TradeJournal) -> Self {
+        self.journal = Some(Arc::new(journal));
+        self
+    }
+
+    pub fn with_ledger(mut self, ledger: Ledger) -> Self {
+        self.ledger = Some(Arc::new(Mutex::new(ledger)));
+        self
+    }
+
+    // Strategies spending from the same signer must share a ledger, so each
+    // sees the cash reserved by the others
+    pub fn with_shared_ledger(mut self, ledger: Arc<Mutex<Ledger>>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    // Cash available to open positions, None if untracked
+    pub fn cash(&self) -> Option<U256> {
+        self.ledger
+            .as_ref()
+            .map(|ledger| ledger.lock().unwrap().cash())
+    }
+
+    // Syncs the ledger to the signer's balance. Backtests and paper trading
+    // keep their simulated cash.
+    pub async fn sync_ledger(&self) -> Result<()> {
+        let ledger = match &self.ledger {
+            Some(ledger) if !*config::IS_BACKTEST && !*config::IS_PAPER_TRADING => ledger,
+            _ => return Ok(()),
+        };
+
+        let balance = self
+            .rpc_provider
+            .get_balance(*self.rpc_provider.signer_address(), None)
+            .await?;
+        ledger.lock().unwrap().sync_balance(balance);
+
+        Ok(())
+    }
+
+    // Restores the positions journaled by a previous run, reconciled against
+    // the signer's token balances. Must run before any block is processed.
+    // Resolves to the pairs of the restored open positions.
+    pub async fn restore_positions(&self) -> Result<Vec<Pair>> {
+        let journal = match &self.journal {
+            Some(journal) => journal,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut pair_inputs = Vec::new();
+        for live_trade in journal.restore()?.into_iter() {
+            let token_address = Address::from(live_trade.token_address.0);
+            let balance = self
+                .rpc_provider
+                .get_token_balance(token_address, None)
+                .await?;
+
+            // A pending open landed if any tx sent at its nonce confirmed while
+            // we were stopped
+            let open_trade = match live_trade.open_trade_metadata.clone() {
+                Some(open_trade_metadata) => Some(serde_json::from_value::<TradeMetadata>(
+                    open_trade_metadata,
+                )?),
+                None => {
+                    let mut receipt = None;
+                    for tx_hash in journal.tx_hashes(&live_trade)?.into_iter() {
+                        receipt = self.rpc_provider.get_transaction_receipt(tx_hash).await?;
+                        if receipt.is_some() {
+                            break;
+                        }
+                    }
+
+                    match receipt {
+                        Some(receipt) => trade_metadata_from_receipt(
+                            &receipt,
+                            TradeRequestOp::Open,
+                            token_address,
+                            &self.rpc_provider,
+                        )
+                        .await
+                        .ok(),
+                        None => None,
+                    }
+                }
+            };
+
+            match open_trade {
+                Some(open_trade) if !balance.is_zero() => {
+                    info!(
+                        token_address = token_address.to_string(),
+                        status = live_trade.status.as_str(),
+                        "restored open position"
+                    );
+                    journal.opened(&token_address, &open_trade)?;
+                    pair_inputs.push(pair_input(open_trade.indexed_trade()));
+                    self.trades
+                        .0
+                        .write()
+                        .unwrap()
+                        .entry(token_address)
+                        .or_default()
+                        .set_active(Some(Trade::Open(open_trade)));
+                }
+                Some(_) => {
+                    warn!(
+                        token_address = token_address.to_string(),
+                        status = live_trade.status.as_str(),
+                        "journaled position has no token balance"
+                    );
+                    journal.closed_elsewhere(&token_address, "No token balance at restore")?;
+                }
+                None => {
+                    if !balance.is_zero() {
+                        warn!(
+                            token_address = token_address.to_string(),
+                            balance = balance.to_string(),
+                            "token balance without a confirmed open"
+                        );
+                    }
+                    journal.open_failed(
+                        &token_address,
+                        &eyre!("Open did not confirm before restore"),
+                    )?;
+                }
+            }
+        }
+
+        if pair_inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .rpc_provider
+            .dex_provider()
+            .get_pairs(pair_inputs, None)
+            .await?
+            .into_values()
+            .collect())
+    }
+
+    // Fraction of recently submitted trades that failed to confirm
+    pub fn revert_rate(&self) -> f64 {
+        self.revert_tracker.revert_rate()
+    }
+
+    // Returns the cash reservation and block notional of an open that did
+    // not go through
+    fn release_open(&self, token_address: &Address, block_number: BlockNumber, amount: U256) {
+        self.risk_engine.release_open(block_number, amount);
+        if let Some(ledger) = &self.ledger {
+            ledger.lock().unwrap().release(amount);
+            debug!(
+                token_address = token_address.to_string(),
+                amount = amount.to_string(),
+                "released cash reservation"
+            );
         }
     }
 
@@ -46,7 +306,7 @@ where
                     if !trades.values().any(|trade| {
                         matches!(
                             trade.active(),
-                            Some(Trade::PendingOpen) | Some(Trade::PendingClose)
+                            Some(Trade::PendingOpen(_)) | Some(Trade::PendingClose(_))
                         )
                     }) {
                         break;
@@ -61,6 +321,42 @@ where
         })
     }
 
+    // The open and close trade of every closed position, across all tokens
+    pub fn closed_trades(&self) -> Vec<(TradeMetadata, TradeMetadata)> {
+        self.trades
+            .0
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|address_trades| address_trades.closed().iter().cloned())
+            .collect()
+    }
+
+    // Opens refused by the risk engine
+    pub fn risk_rejections(&self) -> Vec<RiskRejection> {
+        self.risk_engine.rejections()
+    }
+
+    pub fn insert_backtest_risk_rejections(
+        &self,
+        tx: &rusqlite::Transaction,
+        backtest_id: i64,
+    ) -> Result<()> {
+        for rejection in self.risk_rejections().into_iter() {
+            NewBacktestRiskRejectionModel::new(
+                backtest_id,
+                rejection.token_address,
+                rejection.block_number,
+                rejection.block_timestamp,
+                rejection.reason.label().to_string(),
+                rejection.reason.to_string(),
+            )
+            .insert(tx)?;
+        }
+
+        Ok(())
+    }
+
     pub fn insert_backtest_closed_trades(
         &self,
         tx: &rusqlite::Transaction,
@@ -96,39 +392,136 @@ where
     {
         if cfg!(test) {
             let rpc_provider = rpc_provider.clone();
+            let gas_policy = self.gas_policy.clone();
             tokio::spawn(async move {
-                on_confirmed(request.simulate_trade_request(&rpc_provider).await);
+                on_confirmed(
+                    request
+                        .simulate_trade_request(&rpc_provider, &gas_policy)
+                        .await,
+                );
             });
 
             Ok(())
         } else if *config::IS_BACKTEST {
-            on_confirmed(request.simulate_trade_request(&rpc_provider).await);
+            on_confirmed(
+                request
+                    .simulate_trade_request(&rpc_provider, &self.gas_policy)
+                    .await,
+            );
+            Ok(())
+        } else if *config::IS_PAPER_TRADING {
+            // Fills against the block the trade would have confirmed in, once
+            // it has been mined
+            let gas_policy = self.gas_policy.clone();
+            tokio::spawn(async move {
+                let confirmation_block_number = request.block_number() + 1;
+                let metadata =
+                    match wait_for_block_header(confirmation_block_number, &rpc_provider).await {
+                        Ok(header) => {
+                            request
+                                .at_block(confirmation_block_number, header.timestamp.to::<u64>())
+                                .simulate_trade_request(&rpc_provider, &gas_policy)
+                                .await
+                        }
+                        Err(err) => Err(err),
+                    };
+                on_confirmed(metadata);
+            });
+
             Ok(())
         } else {
-            let transaction_request = request
+            let quoted_transaction_request = request
                 .make_trade_transaction_request(&rpc_provider)
                 .await?;
-            Transaction::send(transaction_request, &rpc_provider)
+            let quoted_amount_out = quoted_transaction_request.quoted_amount_out;
+            let approval = quoted_transaction_request.approval;
+            // Priced for inclusion in the block after the one the request was
+            // made in
+            let tx_request = self
+                .gas_policy
+                .apply(
+                    quoted_transaction_request.tx_request,
+                    request.block_number() + 1,
+                    &rpc_provider,
+                )
+                .await?;
+            self.transaction_manager
+                .send(tx_request, approval, &rpc_provider)
                 .await
                 .map(|tx| {
+                    if let Some(journal) = &self.journal {
+                        log_journal_err(
+                            request.token_address(),
+                            journal.submitted(request.token_address(), request.op(), *tx.tx_hash()),
+                        );
+                    }
+
                     let rpc_provider = rpc_provider.clone();
+                    let gas_policy = self.gas_policy.clone();
+                    let transaction_manager = self.transaction_manager.clone();
+                    let journal = self.journal.clone();
                     tokio::spawn(async move {
+                        let token_address = *request.token_address();
+                        // Every tx sent at the nonce may be the one that lands
+                        let on_replaced = |tx_hash| {
+                            if let Some(journal) = &journal {
+                                log_journal_err(
+                                    &token_address,
+                                    journal.sent(&token_address, tx_hash),
+                                );
+                            }
+                        };
                         let metadata = tx
                             .into_trade_metadata(
                                 request.op().clone(),
-                                *request.token_address(),
+                                token_address,
                                 &rpc_provider,
+                                &gas_policy,
+                                &transaction_manager,
+                                on_replaced,
                             )
-                            .await;
+                            .await
+                            .map(|metadata| metadata.with_quoted_amount_out(quoted_amount_out));
                         on_confirmed(metadata);
                     });
                 })
         }
     }
 
-    pub async fn close_position<R>(&self, close_trade_request: R) -> Result<()>
+    // Sells tokens held outside of any position, e.g. dust left by a close or
+    // an orphaned balance. Trades are left untouched.
+    pub async fn liquidate<R>(&self, liquidate_request: R) -> Result<()>
     where
         R: TradeControllerRequest + Send + 'static,
+    {
+        liquidate_request.trace(&self.rpc_provider).await?;
+
+        let token_address = *liquidate_request.token_address();
+        self.send_tx(
+            liquidate_request,
+            self.rpc_provider.clone(),
+            move |res| match res {
+                Ok(committed_trade) => info!(
+                    token_address = token_address.to_string(),
+                    tx_hash = committed_trade.tx_hash().to_string(),
+                    weth_amount = committed_trade.weth_amount().to_string(),
+                    "committed liquidation"
+                ),
+                Err(err) => error!(
+                    token_address = token_address.to_string(),
+                    "Failed to liquidate: {:?}", err
+                ),
+            },
+        )
+        .await
+    }
+
+    // Marks the open trade for the request's token as pending close, once the
+    // request traces and is journaled. Resolves to the open trade being
+    // closed, left open if the request can't be made.
+    async fn begin_close<R>(&self, close_trade_request: &R) -> Result<TradeMetadata>
+    where
+        R: TradeControllerRequest,
     {
         // ensure that an existing open trade exists for this token, update it to pending close
 
@@ -140,8 +533,13 @@ where
                     eyre!("No open trade for {}", close_trade_request.token_address())
                 })?;
 
-            match address_trades.set_active(Some(Trade::PendingClose)) {
-                Some(Trade::Open(open_trade)) => Ok(open_trade),
+            match address_trades.set_active(None) {
+                Some(Trade::Open(open_trade)) => {
+                    address_trades.set_active(Some(Trade::PendingClose(
+                        open_trade.weth_amount() + open_trade.gas_fee(),
+                    )));
+                    Ok(open_trade)
+                }
                 existing => {
                     // Unexpected state, revert the active trade to whatever was there before and
                     // Err
@@ -164,92 +562,344 @@ where
             }
         }?;
 
-        let address = close_trade_request.token_address().clone();
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.pending_close(close_trade_request.token_address()) {
+                self.trades.set_active(
+                    close_trade_request.token_address(),
+                    Some(Trade::Open(open_trade)),
+                )?;
+                return Err(err);
+            }
+        }
+
+        Ok(open_trade)
+    }
+
+    // Settles the pending close of the open trade once its tx confirms or
+    // fails
+    fn on_close_confirmed(
+        &self,
+        address: Address,
+        open_trade: TradeMetadata,
+    ) -> impl FnOnce(Result<TradeMetadata>) + Send + 'static {
         let trades = self.trades.clone();
-        let moved_open_trade = open_trade.clone();
+        let ledger = self.ledger.clone();
+        let revert_tracker = self.revert_tracker.clone();
+        let journal = self.journal.clone();
+
+        move |res| match res {
+            Ok(committed_trade) => {
+                info!(
+                    token_address = address.to_string(),
+                    tx_hash = committed_trade.tx_hash().to_string(),
+                    realized_slippage_bps = ?committed_trade.realized_slippage_bps(),
+                    "committed close trade"
+                );
+                revert_tracker.record(false);
 
-        match self
-            .send_tx(
-                close_trade_request,
-                self.rpc_provider.clone(),
-                move |res| match res {
-                    Ok(committed_trade) => {
-                        info!(
-                            token_address = address.to_string(),
-                            tx_hash = committed_trade.tx_hash().to_string(),
-                            "committed close trade"
-                        );
+                if let Some(journal) = &journal {
+                    log_journal_err(&address, journal.closed(&address, &committed_trade));
+                }
 
-                        // Backtest success: update trade closed, clear active trade
-                        if let Err(err) = trades.close(&address, moved_open_trade, committed_trade)
-                        {
-                            error!(
-                                address = address.to_string(),
-                                "Failed to update trades for closed trade: {:?}", err
-                            );
-                        }
-                    }
-                    Err(err) => {
-                        // Backtest failed: revert the pending close active state
-                        if let Err(revert_err) =
-                            trades.set_active(&address, Some(Trade::Open(moved_open_trade)))
-                        {
-                            error!(
-                                address = address.to_string(),
-                                "Failed to revert pending close: {:?}, original error: {:?}",
-                                revert_err,
-                                err
-                            );
-                        } else {
-                            error!(
-                                address = address.to_string(),
-                                "Failed to close trade: {:?}", err
-                            );
-                        }
-                    }
-                },
-            )
+                if let Some(ledger) = ledger {
+                    ledger.lock().unwrap().settle_close(
+                        committed_trade
+                            .weth_amount()
+                            .saturating_sub(*committed_trade.gas_fee()),
+                    );
+                }
+
+                // Backtest success: update trade closed, clear active trade
+                if let Err(err) = trades.close(&address, open_trade, committed_trade) {
+                    error!(
+                        address = address.to_string(),
+                        "Failed to update trades for closed trade: {:?}", err
+                    );
+                }
+            }
+            Err(err) => {
+                revert_tracker.record(true);
+                if let Some(journal) = &journal {
+                    log_journal_err(&address, journal.close_failed(&address, &err));
+                }
+
+                // Backtest failed: revert the pending close active state
+                if let Err(revert_err) = trades.set_active(&address, Some(Trade::Open(open_trade)))
+                {
+                    error!(
+                        address = address.to_string(),
+                        "Failed to revert pending close: {:?}, original error: {:?}",
+                        revert_err,
+                        err
+                    );
+                } else {
+                    error!(
+                        address = address.to_string(),
+                        "Failed to close trade: {:?}", err
+                    );
+                }
+            }
+        }
+    }
+
+    // Reverts a pending close whose tx failed to send
+    fn revert_close(
+        &self,
+        address: &Address,
+        open_trade: TradeMetadata,
+        err: &Report,
+    ) -> Result<()> {
+        error!(
+            address = address.to_string(),
+            "Failed to submit pending tx: {:?}", err
+        );
+
+        if let Some(journal) = &self.journal {
+            log_journal_err(address, journal.close_failed(address, err));
+        }
+        self.trades
+            .set_active(address, Some(Trade::Open(open_trade)))
+    }
+
+    pub async fn close_position<R>(&self, close_trade_request: R) -> Result<()>
+    where
+        R: TradeControllerRequest + Send + 'static,
+    {
+        let open_trade = self.begin_close(&close_trade_request).await?;
+
+        let address = *close_trade_request.token_address();
+        let on_confirmed = self.on_close_confirmed(address, open_trade.clone());
+        match self
+            .send_tx(close_trade_request, self.rpc_provider.clone(), on_confirmed)
             .await
         {
             Ok(_) => Ok(()),
             Err(err) => {
-                error!(
-                    address = address.to_string(),
-                    "Failed to submit pending tx: {:?}", err
+                // Tx failed to send - revert the pending close
+                self.revert_close(&address, open_trade, &err)?;
+                Err(err)
+            }
+        }
+    }
+
+    // Closes the positions in a single multicall to the executor they were
+    // all made against, so that they land together or not at all. Closes are
+    // made one at a time instead when backtesting or paper trading, or when
+    // they aren't all made against the same executor.
+    pub async fn close_positions<R>(&self, close_trade_requests: Vec<R>) -> Result<()>
+    where
+        R: TradeControllerRequest + Send + 'static,
+    {
+        let executor_address = close_trade_requests
+            .first()
+            .and_then(|close_trade_request| close_trade_request.params().executor_address);
+        if cfg!(test)
+            || *config::IS_BACKTEST
+            || *config::IS_PAPER_TRADING
+            || executor_address.is_none()
+            || close_trade_requests.iter().any(|close_trade_request| {
+                close_trade_request.params().executor_address != executor_address
+            })
+        {
+            for close_trade_request in close_trade_requests.into_iter() {
+                let token_address = *close_trade_request.token_address();
+                if let Err(err) = self.close_position(close_trade_request).await {
+                    error!(
+                        token_address = token_address.to_string(),
+                        "Failed to close position: {:?}", err
+                    );
+                }
+            }
+
+            return Ok(());
+        }
+
+        // Closes that can't be made are left out of the batch
+        let mut pending_closes = Vec::with_capacity(close_trade_requests.len());
+        for close_trade_request in close_trade_requests.into_iter() {
+            match self.begin_close(&close_trade_request).await {
+                Ok(open_trade) => pending_closes.push((close_trade_request, open_trade)),
+                Err(err) => error!(
+                    token_address = close_trade_request.token_address().to_string(),
+                    "Failed to close position: {:?}", err
+                ),
+            }
+        }
+        if pending_closes.is_empty() {
+            return Ok(());
+        }
+
+        let (tx, quoted_amounts_out) = match self.send_batched_exits(&pending_closes).await {
+            Ok(sent) => sent,
+            Err(err) => {
+                // Tx failed to send - revert every pending close
+                for (close_trade_request, open_trade) in pending_closes.into_iter() {
+                    self.revert_close(close_trade_request.token_address(), open_trade, &err)?;
+                }
+                return Err(err);
+            }
+        };
+
+        let token_addresses = pending_closes
+            .iter()
+            .map(|(close_trade_request, _)| *close_trade_request.token_address())
+            .collect::<Vec<_>>();
+        if let Some(journal) = &self.journal {
+            for (close_trade_request, _) in pending_closes.iter() {
+                log_journal_err(
+                    close_trade_request.token_address(),
+                    journal.submitted(
+                        close_trade_request.token_address(),
+                        close_trade_request.op(),
+                        *tx.tx_hash(),
+                    ),
                 );
+            }
+        }
 
-                // Tx failed to send - revert the pending close
-                self.trades
-                    .set_active(&address, Some(Trade::Open(open_trade)))?;
+        let exits = pending_closes
+            .iter()
+            .map(|(close_trade_request, _)| {
+                (
+                    close_trade_request.op().clone(),
+                    *close_trade_request.token_address(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let on_confirmed = pending_closes
+            .into_iter()
+            .map(|(close_trade_request, open_trade)| {
+                self.on_close_confirmed(*close_trade_request.token_address(), open_trade)
+            })
+            .collect::<Vec<_>>();
 
-                Err(err)
+        let rpc_provider = self.rpc_provider.clone();
+        let gas_policy = self.gas_policy.clone();
+        let transaction_manager = self.transaction_manager.clone();
+        let journal = self.journal.clone();
+        tokio::spawn(async move {
+            // Every tx sent at the nonce may be the one that lands
+            let on_replaced = |tx_hash| {
+                if let Some(journal) = &journal {
+                    for token_address in token_addresses.iter() {
+                        log_journal_err(token_address, journal.sent(token_address, tx_hash));
+                    }
+                }
+            };
+            match tx
+                .into_batched_trade_metadata(
+                    exits,
+                    &rpc_provider,
+                    &gas_policy,
+                    &transaction_manager,
+                    on_replaced,
+                )
+                .await
+            {
+                Ok(metadata) => {
+                    for ((metadata, quoted_amount_out), on_confirmed) in metadata
+                        .into_iter()
+                        .zip(quoted_amounts_out)
+                        .zip(on_confirmed)
+                    {
+                        on_confirmed(Ok(metadata.with_quoted_amount_out(quoted_amount_out)));
+                    }
+                }
+                Err(err) => {
+                    for on_confirmed in on_confirmed.into_iter() {
+                        on_confirmed(Err(eyre!("Batched close failed: {:?}", err)));
+                    }
+                }
             }
+        });
+
+        Ok(())
+    }
+
+    // Sends the closes as one executor multicall, priced for inclusion in the
+    // block after the latest they were made in. Resolves to the sent tx and
+    // the output each close was quoted at.
+    async fn send_batched_exits<R>(
+        &self,
+        pending_closes: &[(R, TradeMetadata)],
+    ) -> Result<(Transaction, Vec<U256>)>
+    where
+        R: TradeControllerRequest,
+    {
+        let mut exits = Vec::with_capacity(pending_closes.len());
+        for (close_trade_request, _) in pending_closes.iter() {
+            exits.push(
+                close_trade_request
+                    .make_trade_transaction_request(&self.rpc_provider)
+                    .await?,
+            );
         }
+        let quoted_amounts_out = exits
+            .iter()
+            .map(|exit| exit.quoted_amount_out)
+            .collect::<Vec<_>>();
+        let block_number = pending_closes
+            .iter()
+            .map(|(close_trade_request, _)| close_trade_request.block_number())
+            .max()
+            .unwrap_or_default();
+
+        let tx_request = self
+            .gas_policy
+            .apply(
+                QuotedTransactionRequest::batch_exits(exits)?.tx_request,
+                block_number + 1,
+                &self.rpc_provider,
+            )
+            .await?;
+        let tx = self
+            .transaction_manager
+            .send(tx_request, None, &self.rpc_provider)
+            .await?;
+
+        Ok((tx, quoted_amounts_out))
     }
 
     pub async fn open_position<R>(&self, open_position_request: R) -> Result<()>
     where
         R: TradeControllerRequest + Send + 'static,
     {
-        // ensure that we do not already have a position for this token and add
-        // the position to the store
+        // ensure that we do not already have a position for this token, that
+        // the position is within the risk limits and that there is cash to
+        // fund it, then add it to the store
+        let weth_amount_in = open_position_request.params().open_weth_amount_in();
+        let block_number = open_position_request.block_number();
         {
             let mut trades = self.trades.0.write().unwrap();
-            let address_trades = trades
-                .entry(open_position_request.token_address().clone())
-                .or_insert_with(|| AddressTrades::default());
+            if trades
+                .get(open_position_request.token_address())
+                .is_some_and(|address_trades| address_trades.active().is_some())
+            {
+                return Err(eyre!(
+                    "Position already exists for token {}",
+                    open_position_request.token_address()
+                ));
+            }
 
-            match address_trades.active() {
-                None => { /* expected state */ }
-                Some(_) => {
-                    return Err(eyre!(
-                        "Position already exists for token {}",
-                        open_position_request.token_address()
-                    ));
+            self.risk_engine.check_open(
+                &trades,
+                open_position_request.token_address(),
+                block_number,
+                open_position_request.block_timestamp(),
+                weth_amount_in,
+            )?;
+
+            if let Some(ledger) = &self.ledger {
+                if let Err(err) = ledger.lock().unwrap().reserve(weth_amount_in) {
+                    self.risk_engine.release_open(block_number, weth_amount_in);
+                    return Err(err);
                 }
-            };
+            }
 
-            address_trades.set_active(Some(Trade::PendingOpen));
+            trades
+                .entry(open_position_request.token_address().clone())
+                .or_insert_with(|| AddressTrades::default())
+                .set_active(Some(Trade::PendingOpen(weth_amount_in)));
         }
 
         // Ensure the position is valid, otherwise remove the pending position from
@@ -261,10 +911,33 @@ where
                 let _ = self
                     .trades
                     .set_active(open_position_request.token_address(), None);
+                self.release_open(
+                    open_position_request.token_address(),
+                    block_number,
+                    weth_amount_in,
+                );
             })?;
 
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.pending_open(open_position_request.token_address()) {
+                let _ = self
+                    .trades
+                    .set_active(open_position_request.token_address(), None);
+                self.release_open(
+                    open_position_request.token_address(),
+                    block_number,
+                    weth_amount_in,
+                );
+                return Err(err);
+            }
+        }
+
         let token_address = open_position_request.token_address().clone();
         let trades = self.trades.clone();
+        let ledger = self.ledger.clone();
+        let risk_engine = self.risk_engine.clone();
+        let revert_tracker = self.revert_tracker.clone();
+        let journal = self.journal.clone();
 
         match self
             .send_tx(
@@ -275,8 +948,24 @@ where
                         info!(
                             token_address = token_address.to_string(),
                             tx_hash = committed_trade.tx_hash().to_string(),
+                            realized_slippage_bps = ?committed_trade.realized_slippage_bps(),
                             "committed open trade"
                         );
+                        revert_tracker.record(false);
+
+                        if let Some(journal) = &journal {
+                            log_journal_err(
+                                &token_address,
+                                journal.opened(&token_address, &committed_trade),
+                            );
+                        }
+
+                        if let Some(ledger) = &ledger {
+                            ledger.lock().unwrap().settle_open(
+                                weth_amount_in,
+                                committed_trade.weth_amount() + committed_trade.gas_fee(),
+                            );
+                        }
 
                         if let Err(err) =
                             trades.set_active(&token_address, Some(Trade::Open(committed_trade)))
@@ -288,6 +977,18 @@ where
                         }
                     }
                     Err(err) => {
+                        revert_tracker.record(true);
+                        if let Some(journal) = &journal {
+                            log_journal_err(
+                                &token_address,
+                                journal.open_failed(&token_address, &err),
+                            );
+                        }
+                        risk_engine.release_open(block_number, weth_amount_in);
+                        if let Some(ledger) = &ledger {
+                            ledger.lock().unwrap().release(weth_amount_in);
+                        }
+
                         if let Err(revert_err) = trades.set_active(&token_address, None) {
                             error!(
                                 token_address = token_address.to_string(),
@@ -314,6 +1015,10 @@ where
                 );
 
                 // Tx failed to send - remove the pending position from the store
+                if let Some(journal) = &self.journal {
+                    log_journal_err(&token_address, journal.open_failed(&token_address, &err));
+                }
+                self.release_open(&token_address, block_number, weth_amount_in);
                 if let Err(err) = self.trades.set_active(&token_address, None) {
                     error!(
                         token_address = token_address.to_string(),
@@ -333,12 +1038,12 @@ mod tests {
 
     use crate::{
         config,
-        trade_controller::{Trade, TradeControllerRequest},
+        trade_controller::{GasPolicy, Ledger, Trade, TradeControllerRequest},
     };
 
     use pochtecatl_primitives::{
-        new_http_signer_provider, IndexedTrade, RpcProvider, TradeMetadata, TradeRequestOp,
-        UniswapV2IndexedTrade,
+        new_http_signer_provider, IndexedTrade, QuotedTransactionRequest, RpcProvider,
+        SignerSource, TradeMetadata, TradeRequestOp, TradeRequestParams, UniswapV2IndexedTrade,
     };
 
     use eyre::{eyre, Result};
@@ -350,7 +1055,6 @@ mod tests {
         network::Ethereum,
         primitives::{Address, BlockNumber, TxHash, B256, U256},
         providers::Provider,
-        rpc::types::eth::TransactionRequest,
         transports::Transport,
     };
 
@@ -359,6 +1063,7 @@ mod tests {
         block_number: BlockNumber,
         confirmed_lock: Arc<Mutex<()>>,
         should_revert: bool,
+        params: TradeRequestParams,
     }
 
     impl MockTradeRequest {
@@ -368,6 +1073,7 @@ mod tests {
                 block_number,
                 confirmed_lock: Arc::new(Mutex::new(())),
                 should_revert,
+                params: TradeRequestParams::default(),
             }
         }
 
@@ -385,10 +1091,29 @@ mod tests {
             &TradeRequestOp::Open
         }
 
+        fn params(&self) -> &TradeRequestParams {
+            &self.params
+        }
+
+        fn block_number(&self) -> BlockNumber {
+            self.block_number
+        }
+
+        fn block_timestamp(&self) -> u64 {
+            0
+        }
+
+        fn at_block(self, block_number: BlockNumber, _block_timestamp: u64) -> Self {
+            Self {
+                block_number,
+                ..self
+            }
+        }
+
         async fn make_trade_transaction_request<T, P>(
             &self,
             _rpc_provider: &RpcProvider<T, P>,
-        ) -> Result<TransactionRequest>
+        ) -> Result<QuotedTransactionRequest>
         where
             T: Transport + Clone,
             P: Provider<T, Ethereum> + 'static,
@@ -407,6 +1132,7 @@ mod tests {
         async fn simulate_trade_request<T, P>(
             &self,
             _rpc_provider: &RpcProvider<T, P>,
+            _gas_policy: &GasPolicy,
         ) -> Result<TradeMetadata>
         where
             T: Transport + Clone,
@@ -451,7 +1177,9 @@ mod tests {
         let controller = TradeController::new(Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -473,7 +1201,7 @@ mod tests {
                 .and_then(|trades| trades.active().as_ref())
                 .ok_or_else(|| eyre!("Expected active trade"))?;
 
-            assert!(matches!(active_trade, Trade::PendingOpen));
+            assert!(matches!(active_trade, Trade::PendingOpen(_)));
         }
 
         // Wait for trade confirmation to unlock
@@ -499,12 +1227,43 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_open_position_insufficient_cash() -> Result<()> {
+        let controller = TradeController::new(Arc::new(
+            new_http_signer_provider(
+                url::Url::parse(config::RPC_URL.as_str())?,
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
+                None,
+                true,
+            )
+            .await?,
+        ))
+        .with_ledger(Ledger::new(U256::from(100)));
+
+        let mut req = MockTradeRequest::new(Address::ZERO, 0, false);
+        req.params = TradeRequestParams::new().with_weth_amount_in(U256::from(101));
+        assert!(controller.open_position(req).await.is_err());
+        assert_eq!(controller.cash(), Some(U256::from(100)));
+
+        let trades = controller.trades().0.read().unwrap();
+        assert!(trades
+            .get(&Address::ZERO)
+            .and_then(|trades| trades.active().as_ref())
+            .is_none());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_close_position() -> Result<()> {
         let controller = TradeController::new(Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -535,7 +1294,7 @@ mod tests {
                 .and_then(|trades| trades.active().as_ref())
                 .ok_or_else(|| eyre!("Expected active trade"))?;
 
-            assert!(matches!(active_trade, Trade::PendingClose));
+            assert!(matches!(active_trade, Trade::PendingClose(_)));
         }
 
         // Wait for trade confirmation to unlock
@@ -561,12 +1320,69 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_close_positions() -> Result<()> {
+        let controller = TradeController::new(Arc::new(
+            new_http_signer_provider(
+                url::Url::parse(config::RPC_URL.as_str())?,
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
+                None,
+                true,
+            )
+            .await?,
+        ));
+        let token_addresses = [Address::with_last_byte(1), Address::with_last_byte(2)];
+
+        // open a position in each token
+        for token_address in token_addresses.iter() {
+            controller
+                .open_position(MockTradeRequest::new(*token_address, 0, false))
+                .await?;
+        }
+        // Wait for trade confirmation
+        sleep(Duration::from_millis(100)).await;
+
+        // Closes are made one at a time outside of live trading
+        controller
+            .close_positions(
+                token_addresses
+                    .iter()
+                    .map(|token_address| MockTradeRequest::new(*token_address, 1, false))
+                    .collect(),
+            )
+            .await?;
+        // Wait for trade confirmation
+        sleep(Duration::from_millis(100)).await;
+
+        let trades = controller.trades().0.read().unwrap();
+        for token_address in token_addresses.iter() {
+            let address_trades = trades
+                .get(token_address)
+                .ok_or_else(|| eyre!("Expected trades"))?;
+
+            assert!(address_trades.active().is_none());
+            match address_trades.closed().first() {
+                Some((open_trade, close_trade)) => {
+                    assert_eq!(*open_trade.block_number(), 0);
+                    assert_eq!(*close_trade.block_number(), 1);
+                }
+                _ => return Err(eyre!("Expected closed trade")),
+            }
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_open_position_revert() -> Result<()> {
         let controller = TradeController::new(Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -599,7 +1415,9 @@ mod tests {
         let controller = TradeController::new(Arc::new(
             new_http_signer_provider(
                 url::Url::parse(config::RPC_URL.as_str())?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -1,18 +1,29 @@
-use pochtecatl_primitives::{IndexedTrade, Pair, RpcProvider, TradeMetadata, TradeRequestOp};
+use super::GasPolicy;
+
+use pochtecatl_primitives::{
+    IndexedTrade, Pair, QuotedTransactionRequest, RpcProvider, TradeMetadata, TradeRequestOp,
+    TradeRequestParams,
+};
 
 use alloy::{
     network::Ethereum,
-    primitives::{Address, BlockNumber, TxHash, U256},
+    primitives::{Address, BlockNumber, TxHash},
     providers::Provider,
-    rpc::types::eth::{Block, BlockTransactions, TransactionRequest},
     transports::Transport,
 };
 
-use eyre::{eyre, OptionExt, Result};
+use eyre::{eyre, Result};
 
 pub trait TradeControllerRequest {
     fn token_address(&self) -> &Address;
     fn op(&self) -> &TradeRequestOp;
+    fn params(&self) -> &TradeRequestParams;
+    fn block_number(&self) -> BlockNumber;
+    fn block_timestamp(&self) -> u64;
+
+    // The same request made at a later block, e.g. the block a paper trade
+    // would have confirmed in
+    fn at_block(self, block_number: BlockNumber, block_timestamp: u64) -> Self;
 
     async fn trace<T, P>(&self, rpc_provider: &RpcProvider<T, P>) -> Result<()>
     where
@@ -22,6 +33,7 @@ pub trait TradeControllerRequest {
     fn simulate_trade_request<T, P>(
         &self,
         rpc_provider: &RpcProvider<T, P>,
+        gas_policy: &GasPolicy,
     ) -> impl std::future::Future<Output = Result<TradeMetadata>> + Send
     where
         T: Transport + Clone,
@@ -30,7 +42,7 @@ pub trait TradeControllerRequest {
     fn make_trade_transaction_request<T, P>(
         &self,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> impl std::future::Future<Output = Result<TransactionRequest>> + Send
+    ) -> impl std::future::Future<Output = Result<QuotedTransactionRequest>> + Send
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static;
@@ -42,6 +54,7 @@ pub struct TradeRequest {
     pub block_timestamp: u64,
     pub op: TradeRequestOp,
     pub pair: Pair,
+    pub params: TradeRequestParams,
 }
 
 impl TradeRequest {
@@ -51,6 +64,7 @@ impl TradeRequest {
             block_timestamp,
             pair,
             op: TradeRequestOp::Open,
+            params: TradeRequestParams::default(),
         }
     }
 
@@ -69,8 +83,14 @@ impl TradeRequest {
                 open_trade,
                 open_trade_tx_hash,
             },
+            params: TradeRequestParams::default(),
         }
     }
+
+    pub fn with_params(mut self, params: TradeRequestParams) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 impl TradeControllerRequest for TradeRequest {
@@ -82,60 +102,59 @@ impl TradeControllerRequest for TradeRequest {
         &self.op
     }
 
+    fn params(&self) -> &TradeRequestParams {
+        &self.params
+    }
+
+    fn block_number(&self) -> BlockNumber {
+        self.block_number
+    }
+
+    fn block_timestamp(&self) -> u64 {
+        self.block_timestamp
+    }
+
+    fn at_block(self, block_number: BlockNumber, block_timestamp: u64) -> Self {
+        Self {
+            block_number,
+            block_timestamp,
+            ..self
+        }
+    }
+
     async fn trace<T, P>(&self, rpc_provider: &RpcProvider<T, P>) -> Result<()>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
     {
         self.pair
-            .trace_trade_request(&self.op, self.block_number, rpc_provider)
+            .trace_trade_request(&self.op, &self.params, self.block_number, rpc_provider)
             .await
     }
 
     async fn simulate_trade_request<T, P>(
         &self,
         rpc_provider: &RpcProvider<T, P>,
+        gas_policy: &GasPolicy,
     ) -> Result<TradeMetadata>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
     {
-        if !*crate::config::IS_BACKTEST {
-            return Err(eyre!("Only avaialble in backtest"));
+        if !*crate::config::IS_BACKTEST && !*crate::config::IS_PAPER_TRADING {
+            return Err(eyre!("Only avaialble in backtest or paper trading"));
         }
 
         let indexed_trade = self
             .pair
-            .simulate_trade_request(&self.op, self.block_number, rpc_provider)
+            .simulate_trade_request(&self.op, &self.params, self.block_number, rpc_provider)
             .await?;
 
-        // Gets the effective_gas_price of the tx in the middle of the block we would've
-        // confirmed in while backtesting, and multiples it by an estimate of the gas used
-        // by the trade to get the estimated gas fee.
-        let estimated_gas_fee = {
-            let median_tx_hash = rpc_provider
-                .block_provider()
-                .get_block(self.block_number)
-                .await
-                .ok()
-                .and_then(|block| match block {
-                    Some(Block {
-                        transactions: BlockTransactions::Hashes(hashes),
-                        ..
-                    }) => hashes.get(hashes.len() / 2).cloned(),
-                    _ => None,
-                })
-                .ok_or_eyre("Failed to get median tx hash")?;
-
-            let median_tx_effictive_gas_price = rpc_provider
-                .get_transaction_receipt(median_tx_hash)
-                .await
-                .ok()
-                .and_then(|receipt| receipt.map(|receipt| receipt.effective_gas_price))
-                .ok_or_eyre("Failed to get median tx receipt")?;
-
-            U256::from(median_tx_effictive_gas_price) * self.pair.estimate_trade_gas()
-        };
+        // Prices the trade as if it were included in the block we would've
+        // confirmed in while backtesting or paper trading
+        let estimated_gas_fee = gas_policy
+            .estimate_gas_fee(&self.pair, &self.params, self.block_number, rpc_provider)
+            .await?;
 
         Ok(TradeMetadata::new(
             TxHash::random(),
@@ -151,7 +170,7 @@ impl TradeControllerRequest for TradeRequest {
     async fn make_trade_transaction_request<T, P>(
         &self,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<TransactionRequest>
+    ) -> Result<QuotedTransactionRequest>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
@@ -159,6 +178,7 @@ impl TradeControllerRequest for TradeRequest {
         self.pair
             .make_trade_transaction_request(
                 &self.op,
+                &self.params,
                 self.block_number,
                 self.block_timestamp,
                 rpc_provider,
@@ -0,0 +1,251 @@
+use pochtecatl_db::{LiveTradeModel, LiveTradeUpdateModel, NewLiveTradeModel};
+use pochtecatl_primitives::{TradeMetadata, TradeRequestOp};
+
+use alloy::primitives::{Address, TxHash};
+
+use eyre::{eyre, Report, Result};
+use fnv::FnvHashMap;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::sync::{Arc, Mutex};
+
+// Journals a live strategy's positions to the db as they move through their
+// lifecycle, so that they can be restored after a restart.
+pub struct TradeJournal {
+    db_pool: Arc<Pool<SqliteConnectionManager>>,
+    strategy_name: String,
+    // Journal id of each token's active position
+    live_trade_ids: Mutex<FnvHashMap<Address, i64>>,
+}
+
+impl TradeJournal {
+    pub fn new(db_pool: Arc<Pool<SqliteConnectionManager>>, strategy_name: String) -> Self {
+        Self {
+            db_pool,
+            strategy_name,
+            live_trade_ids: Mutex::new(FnvHashMap::default()),
+        }
+    }
+
+    fn update<F>(&self, token_address: &Address, make_update: F) -> Result<()>
+    where
+        F: FnOnce(i64) -> LiveTradeUpdateModel,
+    {
+        let id = self
+            .live_trade_id(token_address)
+            .ok_or_else(|| eyre!("No journaled trade for {}", token_address))?;
+
+        let mut conn = self.db_pool.get()?;
+        let tx = conn.transaction()?;
+        make_update(id).update(&tx)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    // Positions left pending or open by a previous run, tracked from here on
+    pub fn restore(&self) -> Result<Vec<LiveTradeModel>> {
+        let live_trades = {
+            let mut conn = self.db_pool.get()?;
+            let tx = conn.transaction()?;
+            LiveTradeModel::query_active_by_strategy_name(&tx, &self.strategy_name)?
+        };
+
+        let mut live_trade_ids = self.live_trade_ids.lock().unwrap();
+        for live_trade in live_trades.iter() {
+            live_trade_ids.insert(live_trade.token_address.0.into(), live_trade.id);
+        }
+
+        Ok(live_trades)
+    }
+
+    pub fn pending_open(&self, token_address: &Address) -> Result<()> {
+        let id = {
+            let mut conn = self.db_pool.get()?;
+            let tx = conn.transaction()?;
+            let id =
+                NewLiveTradeModel::new(self.strategy_name.clone(), *token_address).insert(&tx)?;
+            tx.commit()?;
+            id
+        };
+
+        self.live_trade_ids
+            .lock()
+            .unwrap()
+            .insert(*token_address, id);
+        Ok(())
+    }
+
+    pub fn pending_close(&self, token_address: &Address) -> Result<()> {
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "pending_close")
+        })
+    }
+
+    fn live_trade_id(&self, token_address: &Address) -> Option<i64> {
+        self.live_trade_ids
+            .lock()
+            .unwrap()
+            .get(token_address)
+            .copied()
+    }
+
+    // Records the tx sent for a pending open or close. Sales of holdings
+    // outside of a position are not journaled.
+    pub fn submitted(
+        &self,
+        token_address: &Address,
+        op: &TradeRequestOp,
+        tx_hash: TxHash,
+    ) -> Result<()> {
+        if self.live_trade_id(token_address).is_none() {
+            return Ok(());
+        }
+
+        let status = match op {
+            TradeRequestOp::Open => "pending_open",
+            TradeRequestOp::Close { .. } => "pending_close",
+        };
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, status).with_tx_hash(tx_hash)
+        })?;
+        self.sent(token_address, tx_hash)
+    }
+
+    // Records a tx sent at the nonce of the position's pending tx, e.g. a
+    // replacement or cancellation, as any of them may be the one that lands
+    pub fn sent(&self, token_address: &Address, tx_hash: TxHash) -> Result<()> {
+        let id = match self.live_trade_id(token_address) {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let mut conn = self.db_pool.get()?;
+        let tx = conn.transaction()?;
+        LiveTradeModel::insert_tx_hash(&tx, id, tx_hash)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    // Every tx sent for the journaled position, oldest first
+    pub fn tx_hashes(&self, live_trade: &LiveTradeModel) -> Result<Vec<TxHash>> {
+        let mut conn = self.db_pool.get()?;
+        let tx = conn.transaction()?;
+        let mut tx_hashes = LiveTradeModel::query_tx_hashes_by_id(&tx, live_trade.id)?
+            .into_iter()
+            .map(|tx_hash| tx_hash.0)
+            .collect::<Vec<_>>();
+
+        // Positions journaled before every sent tx was recorded only have
+        // their latest
+        if let Some(tx_hash) = live_trade.tx_hash.as_ref() {
+            if !tx_hashes.contains(&tx_hash.0) {
+                tx_hashes.push(tx_hash.0);
+            }
+        }
+
+        Ok(tx_hashes)
+    }
+
+    pub fn opened(&self, token_address: &Address, open_trade: &TradeMetadata) -> Result<()> {
+        let open_trade_metadata = serde_json::to_value(open_trade)?;
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "open")
+                .with_tx_hash(*open_trade.tx_hash())
+                .with_open_trade_metadata(open_trade_metadata)
+        })
+    }
+
+    pub fn closed(&self, token_address: &Address, close_trade: &TradeMetadata) -> Result<()> {
+        let close_trade_metadata = serde_json::to_value(close_trade)?;
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "closed")
+                .with_tx_hash(*close_trade.tx_hash())
+                .with_close_trade_metadata(close_trade_metadata)
+        })?;
+
+        self.live_trade_ids.lock().unwrap().remove(token_address);
+        Ok(())
+    }
+
+    // A position that was closed without a close trade of ours, e.g. while
+    // the strategy was stopped
+    pub fn closed_elsewhere(&self, token_address: &Address, reason: &str) -> Result<()> {
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "closed").with_error(reason.to_string())
+        })?;
+
+        self.live_trade_ids.lock().unwrap().remove(token_address);
+        Ok(())
+    }
+
+    // The close failed and the position remains open
+    pub fn close_failed(&self, token_address: &Address, err: &Report) -> Result<()> {
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "open").with_error(format!("{:?}", err))
+        })
+    }
+
+    pub fn open_failed(&self, token_address: &Address, err: &Report) -> Result<()> {
+        self.update(token_address, |id| {
+            LiveTradeUpdateModel::new(id, "failed").with_error(format!("{:?}", err))
+        })?;
+
+        self.live_trade_ids.lock().unwrap().remove(token_address);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TradeJournal;
+
+    use pochtecatl_db::connect as connect_db;
+    use pochtecatl_primitives::TradeRequestOp;
+
+    use alloy::primitives::{Address, TxHash};
+    use eyre::{eyre, Result};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_restore() -> Result<()> {
+        let db_pool = Arc::new(connect_db(&String::from(":memory:"))?);
+        let journal = TradeJournal::new(Arc::clone(&db_pool), "momentum".to_string());
+
+        let pending_token_address = Address::repeat_byte(1);
+        journal.pending_open(&pending_token_address)?;
+        journal.submitted(
+            &pending_token_address,
+            &TradeRequestOp::Open,
+            TxHash::repeat_byte(1),
+        )?;
+        // The open is replaced at the same nonce
+        journal.sent(&pending_token_address, TxHash::repeat_byte(3))?;
+
+        let failed_token_address = Address::repeat_byte(2);
+        journal.pending_open(&failed_token_address)?;
+        journal.open_failed(&failed_token_address, &eyre!("reverted"))?;
+        assert!(journal
+            .open_failed(&failed_token_address, &eyre!("reverted"))
+            .is_err());
+
+        // A new run picks up the pending open, with the tx it sent
+        let restored_journal = TradeJournal::new(db_pool, "momentum".to_string());
+        let live_trades = restored_journal.restore()?;
+        assert_eq!(live_trades.len(), 1);
+        assert_eq!(live_trades[0].status, "pending_open");
+        assert_eq!(
+            live_trades[0].tx_hash.as_ref().map(|tx_hash| tx_hash.0),
+            Some(TxHash::repeat_byte(1))
+        );
+        assert_eq!(
+            restored_journal.tx_hashes(&live_trades[0])?,
+            vec![TxHash::repeat_byte(1), TxHash::repeat_byte(3)]
+        );
+        restored_journal.closed_elsewhere(&pending_token_address, "No token balance")?;
+        assert!(restored_journal.restore()?.is_empty());
+
+        Ok(())
+    }
+}
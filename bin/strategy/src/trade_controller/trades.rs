@@ -1,6 +1,6 @@
 use pochtecatl_primitives::TradeMetadata;
 
-use alloy::primitives::Address;
+use alloy::primitives::{Address, U256};
 
 use eyre::{eyre, Result};
 use fnv::FnvHashMap;
@@ -8,17 +8,19 @@ use std::sync::{Arc, RwLock};
 
 #[derive(Clone, Debug)]
 pub enum Trade {
-    PendingOpen,
+    // Weth requested by the open
+    PendingOpen(U256),
     Open(TradeMetadata),
-    PendingClose,
+    // Weth committed to the position being closed, including gas
+    PendingClose(U256),
 }
 
 impl Trade {
     pub fn label(&self) -> &str {
         match self {
-            Trade::PendingOpen => "Pending Open",
+            Trade::PendingOpen(_) => "Pending Open",
             Trade::Open(_) => "Open",
-            Trade::PendingClose => "Pending Close",
+            Trade::PendingClose(_) => "Pending Close",
         }
     }
 }
@@ -1,97 +1,240 @@
+use super::{GasPolicy, TransactionManager};
+
 use pochtecatl_primitives::{IndexedTrade, RpcProvider, TradeMetadata, TradeRequestOp};
 
 use alloy::{
     network::Ethereum,
     primitives::{TxHash, U256, Address},
-    providers::{PendingTransactionBuilder, Provider},
-    rpc::types::eth::TransactionRequest,
+    providers::Provider,
+    rpc::types::eth::{TransactionReceipt, TransactionRequest},
     transports::Transport,
 };
 
 use eyre::{eyre, Result, WrapErr};
-use std::{fmt::Display, time::Duration};
+use std::fmt::Display;
 
+// A sent trade transaction, as first sent at its nonce
 #[derive(Debug)]
-pub struct Transaction(TxHash);
+pub struct Transaction {
+    nonce: u64,
+    tx_request: TransactionRequest,
+    tx_hash: TxHash,
+    // Approval bundled ahead of the trade at the previous nonce
+    approval: Option<TransactionRequest>,
+}
 impl Display for Transaction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Transaction({})", self.0)
+        write!(f, "Transaction({})", self.tx_hash)
     }
 }
 impl Transaction {
-    pub async fn send<T, P>(
-        tx_request: TransactionRequest,
+    pub fn new(nonce: u64, tx_request: TransactionRequest, tx_hash: TxHash) -> Self {
+        Self {
+            nonce,
+            tx_request,
+            tx_hash,
+            approval: None,
+        }
+    }
+
+    pub fn with_approval(mut self, approval: TransactionRequest) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    pub fn tx_request(&self) -> &TransactionRequest {
+        &self.tx_request
+    }
+
+    pub fn tx_hash(&self) -> &TxHash {
+        &self.tx_hash
+    }
+
+    pub fn approval(&self) -> Option<&TransactionRequest> {
+        self.approval.as_ref()
+    }
+
+    pub async fn into_trade_metadata<T, P, F>(
+        self,
+        op: TradeRequestOp,
+        token_address: Address,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<Self>
+        gas_policy: &GasPolicy,
+        transaction_manager: &TransactionManager,
+        on_replaced: F,
+    ) -> Result<TradeMetadata>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
+        F: Fn(TxHash),
     {
-        rpc_provider
-            .send_transaction(tx_request)
+        // Wait for the tx, or whatever replaced it, to confirm or be cancelled
+        let confirmed_receipt = transaction_manager
+            .wait_for_receipt(&self, gas_policy, rpc_provider, on_replaced)
             .await
-            .map(|pending| Self(pending.tx_hash().clone()))
-            .wrap_err("Failed to send_transaction")
+            .wrap_err_with(|| format!("Failed to get receipt for tx hash {:?}", self.tx_hash))?;
+
+        let trade_metadata =
+            trade_metadata_from_receipt(&confirmed_receipt, op, token_address, rpc_provider)
+                .await?;
+        if let Some(gas_used) = confirmed_receipt.gas_used {
+            gas_policy.record_gas_used(trade_metadata.indexed_trade(), U256::from(gas_used));
+        }
+
+        Ok(trade_metadata)
     }
 
-    pub async fn into_trade_metadata<T, P>(
+    // Metadata of each close batched into the transaction, in order, once it
+    // confirms. Each is taken to have used an even share of its gas.
+    pub async fn into_batched_trade_metadata<T, P, F>(
         self,
-        op: TradeRequestOp,
-        token_address: Address,
+        exits: Vec<(TradeRequestOp, Address)>,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<TradeMetadata>
+        gas_policy: &GasPolicy,
+        transaction_manager: &TransactionManager,
+        on_replaced: F,
+    ) -> Result<Vec<TradeMetadata>>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
+        F: Fn(TxHash),
     {
-        let tx_hash = self.0.clone();
-
-        // Wait for the tx to confirm or reject
-        let confirmed_receipt = PendingTransactionBuilder::new(rpc_provider.inner().root(), self.0)
-            .with_timeout(Some(Duration::from_secs(10)))
-            .with_required_confirmations(1)
-            .get_receipt()
-            .await
-            .wrap_err_with(|| format!("Failed to get receipt for tx hash {:?}", tx_hash))?;
-
-        let block_number = confirmed_receipt
-            .block_number
-            .ok_or_else(|| eyre!("Block number not found"))?;
-        let gas_fee = U256::from(
-            confirmed_receipt
-                .gas_used
-                .ok_or_else(|| eyre!("Gas used not found"))?,
-        ) * U256::from(confirmed_receipt.effective_gas_price);
-        let block_timestamp = rpc_provider
-            .block_provider()
-            .get_block_header(block_number)
+        let confirmed_receipt = transaction_manager
+            .wait_for_receipt(&self, gas_policy, rpc_provider, on_replaced)
             .await
-            .and_then(|header| {
-                header.ok_or_else(|| {
-                    eyre!(
-                        "block header {:?} not found",
-                        confirmed_receipt.block_number
-                    )
-                })
+            .wrap_err_with(|| format!("Failed to get receipt for tx hash {:?}", self.tx_hash))?;
+
+        let trade_metadata =
+            batched_trade_metadata_from_receipt(&confirmed_receipt, exits, rpc_provider).await?;
+        if let Some(gas_used) = confirmed_receipt.gas_used {
+            let gas_used = U256::from(gas_used) / U256::from(trade_metadata.len().max(1));
+            for trade_metadata in trade_metadata.iter() {
+                gas_policy.record_gas_used(trade_metadata.indexed_trade(), gas_used);
+            }
+        }
+
+        Ok(trade_metadata)
+    }
+}
+
+// Block number, block timestamp and gas fee of a confirmed receipt
+async fn receipt_block_and_gas_fee<T, P>(
+    confirmed_receipt: &TransactionReceipt,
+    rpc_provider: &RpcProvider<T, P>,
+) -> Result<(u64, u64, U256)>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let block_number = confirmed_receipt
+        .block_number
+        .ok_or_else(|| eyre!("Block number not found"))?;
+    let gas_used = U256::from(
+        confirmed_receipt
+            .gas_used
+            .ok_or_else(|| eyre!("Gas used not found"))?,
+    );
+    let gas_fee = gas_used * U256::from(confirmed_receipt.effective_gas_price);
+    let block_timestamp = rpc_provider
+        .block_provider()
+        .get_block_header(block_number)
+        .await
+        .and_then(|header| {
+            header.ok_or_else(|| {
+                eyre!(
+                    "block header {:?} not found",
+                    confirmed_receipt.block_number
+                )
             })
-            .map(|header| header.timestamp.to::<u64>())?;
+        })
+        .map(|header| header.timestamp.to::<u64>())?;
 
-        let indexed_trade = {
-            let tx_hash = confirmed_receipt.transaction_hash;
-            IndexedTrade::from_receipt(&confirmed_receipt)
-                .first()
+    Ok((block_number, block_timestamp, gas_fee))
+}
+
+// Metadata of the trade in a confirmed receipt
+pub async fn trade_metadata_from_receipt<T, P>(
+    confirmed_receipt: &TransactionReceipt,
+    op: TradeRequestOp,
+    token_address: Address,
+    rpc_provider: &RpcProvider<T, P>,
+) -> Result<TradeMetadata>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let (block_number, block_timestamp, gas_fee) =
+        receipt_block_and_gas_fee(confirmed_receipt, rpc_provider).await?;
+
+    let indexed_trade = {
+        let tx_hash = confirmed_receipt.transaction_hash;
+        IndexedTrade::from_receipt(confirmed_receipt)
+            .first()
+            .cloned()
+            .ok_or_else(|| eyre!("No indexed trade found in receipt {:?}", tx_hash))?
+    };
+
+    Ok(TradeMetadata::new(
+        confirmed_receipt.transaction_hash,
+        block_number,
+        block_timestamp,
+        op,
+        token_address,
+        gas_fee,
+        indexed_trade,
+    ))
+}
+
+// Metadata of each close batched into a confirmed executor multicall, in
+// order, matched to its swap by pair. The gas fee is split evenly between
+// them.
+async fn batched_trade_metadata_from_receipt<T, P>(
+    confirmed_receipt: &TransactionReceipt,
+    exits: Vec<(TradeRequestOp, Address)>,
+    rpc_provider: &RpcProvider<T, P>,
+) -> Result<Vec<TradeMetadata>>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    let (block_number, block_timestamp, gas_fee) =
+        receipt_block_and_gas_fee(confirmed_receipt, rpc_provider).await?;
+    let gas_fee = gas_fee / U256::from(exits.len().max(1));
+
+    let tx_hash = confirmed_receipt.transaction_hash;
+    let indexed_trades = IndexedTrade::from_receipt(confirmed_receipt);
+    exits
+        .into_iter()
+        .map(|(op, token_address)| {
+            let pair_address = match &op {
+                TradeRequestOp::Close { open_trade, .. } => *open_trade.pair_address(),
+                TradeRequestOp::Open => return Err(eyre!("Only closes are batched")),
+            };
+            let indexed_trade = indexed_trades
+                .iter()
+                .find(|indexed_trade| *indexed_trade.pair_address() == pair_address)
                 .cloned()
-                .ok_or_else(|| eyre!("No indexed trade found in receipt {:?}", tx_hash))?
-        };
-
-        Ok(TradeMetadata::new(
-            confirmed_receipt.transaction_hash,
-            block_number,
-            block_timestamp,
-            op,
-            token_address,
-            gas_fee,
-            indexed_trade,
-        ))
-    }
+                .ok_or_else(|| {
+                    eyre!(
+                        "No indexed trade for pair {} found in receipt {:?}",
+                        pair_address,
+                        tx_hash
+                    )
+                })?;
+
+            Ok(TradeMetadata::new(
+                tx_hash,
+                block_number,
+                block_timestamp,
+                op,
+                token_address,
+                gas_fee,
+                indexed_trade,
+            ))
+        })
+        .collect()
 }
@@ -0,0 +1,471 @@
+use super::{GasPolicy, SubmissionBackend, Transaction};
+
+use pochtecatl_primitives::{constants, RpcProvider};
+
+use alloy::{
+    network::{Ethereum, TransactionBuilder},
+    primitives::{Address, TxHash, TxKind, U256},
+    providers::Provider,
+    rpc::types::eth::{BlockId, BlockNumberOrTag, TransactionReceipt, TransactionRequest},
+    transports::Transport,
+};
+
+use eyre::{eyre, Result};
+use fnv::FnvHashMap;
+use std::{sync::Mutex, time::Duration};
+use tokio::time::{sleep, Instant};
+use tracing::{info, warn};
+
+// Gas of a plain transfer, all a cancellation needs
+const CANCEL_GAS_LIMIT: u128 = 21_000;
+
+// Raises a fee by bump_bps, and by at least a wei so that a replacement is
+// never priced the same as the tx it replaces
+fn bump_fee(fee: u128, bump_bps: u64) -> u128 {
+    let bp_factor = constants::BP_FACTOR.to::<u128>();
+    let bumped = fee.saturating_mul(bp_factor + u128::from(bump_bps)) / bp_factor;
+    bumped.max(fee.saturating_add(1))
+}
+
+// Bumps both fees, keeping them within the max fee per gas if capped. A tx
+// already at the cap can't be outbid, and its replacement is refused.
+fn bump_fees(
+    tx_request: TransactionRequest,
+    bump_bps: u64,
+    max_fee_per_gas: Option<u128>,
+) -> TransactionRequest {
+    let cap = |fee: u128| max_fee_per_gas.map_or(fee, |cap| fee.min(cap));
+    let bumped_max_fee_per_gas = tx_request
+        .max_fee_per_gas
+        .map(|fee| cap(bump_fee(fee, bump_bps)));
+
+    TransactionRequest {
+        max_fee_per_gas: bumped_max_fee_per_gas,
+        max_priority_fee_per_gas: tx_request.max_priority_fee_per_gas.map(|fee| {
+            let fee = cap(bump_fee(fee, bump_bps));
+            bumped_max_fee_per_gas.map_or(fee, |max_fee_per_gas| fee.min(max_fee_per_gas))
+        }),
+        ..tx_request
+    }
+}
+
+// A zero value transfer to ourselves at the nonce of the tx it cancels
+fn cancel_tx_request(
+    tx_request: &TransactionRequest,
+    signer_address: Address,
+    nonce: u64,
+) -> TransactionRequest {
+    TransactionRequest {
+        max_fee_per_gas: tx_request.max_fee_per_gas,
+        max_priority_fee_per_gas: tx_request.max_priority_fee_per_gas,
+        ..TransactionRequest::default()
+            .with_from(signer_address)
+            .with_to(Into::<TxKind>::into(signer_address))
+            .with_value(U256::ZERO)
+            .with_nonce(nonce)
+            .with_gas_limit(CANCEL_GAS_LIMIT)
+    }
+}
+
+// Assigns nonces to trade transactions and sees each through to its final
+// on-chain outcome. A tx that doesn't confirm in time is replaced with higher
+// fees, then cancelled with a self transfer at the same nonce. If no
+// cancellation lands either the nonce is left to wallet reconciliation.
+// Bundles are resubmitted instead, and given up on as they can't land once
+// expired.
+#[derive(Debug)]
+pub struct TransactionManager {
+    // Next nonce to assign, read from the chain when None
+    next_nonce: tokio::sync::Mutex<Option<u64>>,
+    // Hashes sent at each pending nonce, replacements included
+    pending: Mutex<FnvHashMap<u64, Vec<TxHash>>>,
+    confirmation_timeout: Duration,
+    max_replacements: u32,
+    max_cancellations: u32,
+    fee_bump_bps: u64,
+    submission_backend: SubmissionBackend,
+    http_client: reqwest::Client,
+}
+
+impl Default for TransactionManager {
+    fn default() -> Self {
+        Self {
+            next_nonce: tokio::sync::Mutex::new(None),
+            pending: Mutex::new(FnvHashMap::default()),
+            confirmation_timeout: Duration::from_secs(10),
+            max_replacements: 2,
+            max_cancellations: 3,
+            // Nodes require replacements to pay at least 10% more
+            fee_bump_bps: 1250,
+            submission_backend: SubmissionBackend::default(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl TransactionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_confirmation_timeout(mut self, confirmation_timeout: Duration) -> Self {
+        self.confirmation_timeout = confirmation_timeout;
+        self
+    }
+
+    pub fn with_max_replacements(mut self, max_replacements: u32) -> Self {
+        self.max_replacements = max_replacements;
+        self
+    }
+
+    pub fn with_max_cancellations(mut self, max_cancellations: u32) -> Self {
+        self.max_cancellations = max_cancellations;
+        self
+    }
+
+    pub fn with_fee_bump_bps(mut self, fee_bump_bps: u64) -> Self {
+        self.fee_bump_bps = fee_bump_bps;
+        self
+    }
+
+    pub fn with_submission_backend(mut self, submission_backend: SubmissionBackend) -> Self {
+        self.submission_backend = submission_backend;
+        self
+    }
+
+    // Sends a trade, bundled behind its approval if the backend bundles.
+    // Otherwise the approval is dropped and the trade relies on an existing
+    // allowance.
+    pub async fn send<T, P>(
+        &self,
+        tx_request: TransactionRequest,
+        approval: Option<TransactionRequest>,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<Transaction>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let mut next_nonce = self.next_nonce.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => {
+                rpc_provider
+                    .get_transaction_count(
+                        *rpc_provider.signer_address(),
+                        BlockId::Number(BlockNumberOrTag::Pending),
+                    )
+                    .await?
+            }
+        };
+
+        // Priced the same as the trade it is bundled with
+        let approval = approval
+            .filter(|_| self.submission_backend.is_bundle())
+            .map(|approval| TransactionRequest {
+                max_fee_per_gas: tx_request.max_fee_per_gas,
+                max_priority_fee_per_gas: tx_request.max_priority_fee_per_gas,
+                ..approval.with_nonce(nonce)
+            });
+        let nonce = if approval.is_some() { nonce + 1 } else { nonce };
+        let tx_request = tx_request.with_nonce(nonce);
+
+        let tx_requests = approval
+            .iter()
+            .cloned()
+            .chain([tx_request.clone()])
+            .collect();
+        match self
+            .submission_backend
+            .submit(&self.http_client, tx_requests, rpc_provider)
+            .await
+        {
+            Ok(tx_hash) => {
+                *next_nonce = Some(nonce + 1);
+                self.pending.lock().unwrap().insert(nonce, vec![tx_hash]);
+
+                let transaction = Transaction::new(nonce, tx_request, tx_hash);
+                Ok(match approval {
+                    Some(approval) => transaction.with_approval(approval),
+                    None => transaction,
+                })
+            }
+            Err(err) => {
+                // Whether the nonce was used is unknown, resync it from the chain
+                *next_nonce = None;
+                Err(err)
+            }
+        }
+    }
+
+    // Sends a replacement for the tx at the nonce, along with the approval
+    // bundled ahead of it if any. A failed send is not an error, the tx being
+    // replaced may have confirmed in the meantime.
+    async fn replace<T, P>(
+        &self,
+        nonce: u64,
+        tx_requests: Vec<TransactionRequest>,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Option<TxHash>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        match self
+            .submission_backend
+            .submit(&self.http_client, tx_requests, rpc_provider)
+            .await
+        {
+            Ok(tx_hash) => {
+                if let Some(tx_hashes) = self.pending.lock().unwrap().get_mut(&nonce) {
+                    tx_hashes.push(tx_hash);
+                }
+                Some(tx_hash)
+            }
+            Err(err) => {
+                warn!(nonce, "Failed to send replacement tx: {:?}", err);
+                None
+            }
+        }
+    }
+
+    // Receipt of any tx sent at the nonce
+    async fn get_receipt<T, P>(
+        &self,
+        nonce: u64,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<Option<TransactionReceipt>>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let tx_hashes = self
+            .pending
+            .lock()
+            .unwrap()
+            .get(&nonce)
+            .cloned()
+            .unwrap_or_default();
+        for tx_hash in tx_hashes.into_iter() {
+            if let Some(receipt) = rpc_provider.get_transaction_receipt(tx_hash).await? {
+                return Ok(Some(receipt));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Polls for a receipt of any tx sent at the nonce until the deadline. The
+    // tx is still in flight when polling fails, so errors are retried rather
+    // than ending the wait.
+    async fn poll_receipt<T, P>(
+        &self,
+        nonce: u64,
+        deadline: Instant,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<Option<TransactionReceipt>>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        loop {
+            match self.get_receipt(nonce, rpc_provider).await {
+                Ok(Some(receipt)) => return Ok(Some(receipt)),
+                Ok(None) => match rpc_provider
+                    .get_transaction_count(
+                        *rpc_provider.signer_address(),
+                        BlockId::Number(BlockNumberOrTag::Latest),
+                    )
+                    .await
+                {
+                    // The nonce was consumed. One of ours may have landed since
+                    // its receipt was checked, otherwise it was a tx we did not
+                    // send from here.
+                    Ok(confirmed_nonce) if confirmed_nonce > nonce => {
+                        match self.get_receipt(nonce, rpc_provider).await {
+                            Ok(Some(receipt)) => return Ok(Some(receipt)),
+                            Ok(None) => {
+                                return Err(eyre!("Nonce {} used by an untracked tx", nonce))
+                            }
+                            Err(err) => warn!(nonce, "Failed to poll receipt: {:?}", err),
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!(nonce, "Failed to poll confirmed nonce: {:?}", err),
+                },
+                Err(err) => warn!(nonce, "Failed to poll receipt: {:?}", err),
+            }
+
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            sleep(Duration::from_secs(constants::AVERAGE_BLOCK_TIME_SECONDS)).await;
+        }
+    }
+
+    // Waits for the final outcome at the transaction's nonce, replacing and
+    // then cancelling it as it times out. Resolves to the receipt of the
+    // trade, or errs if it was cancelled or never landed. Bumped fees stay
+    // within the gas policy's max fee per gas. Each replacement or cancel tx
+    // sent at the nonce is passed to on_replaced.
+    pub async fn wait_for_receipt<T, P, F>(
+        &self,
+        transaction: &Transaction,
+        gas_policy: &GasPolicy,
+        rpc_provider: &RpcProvider<T, P>,
+        on_replaced: F,
+    ) -> Result<TransactionReceipt>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+        F: Fn(TxHash),
+    {
+        let nonce = transaction.nonce();
+        let mut tx_request = transaction.tx_request().clone();
+        let mut approval = transaction.approval().cloned();
+        let max_fee_per_gas = gas_policy
+            .max_fee_per_gas()
+            .map(|max_fee_per_gas| max_fee_per_gas.saturating_to::<u128>());
+        let mut replacements = 0;
+        let mut cancellations = 0;
+        let mut cancel_tx_hashes = Vec::new();
+
+        let outcome = loop {
+            let deadline = Instant::now() + self.confirmation_timeout;
+            match self.poll_receipt(nonce, deadline, rpc_provider).await {
+                Ok(Some(receipt)) => break Ok(receipt),
+                Ok(None) => {}
+                Err(err) => break Err(err),
+            }
+
+            tx_request = bump_fees(tx_request, self.fee_bump_bps, max_fee_per_gas);
+            approval =
+                approval.map(|approval| bump_fees(approval, self.fee_bump_bps, max_fee_per_gas));
+            if replacements < self.max_replacements {
+                replacements += 1;
+                info!(
+                    nonce,
+                    tx_hash = transaction.tx_hash().to_string(),
+                    replacements,
+                    "replacing unconfirmed tx"
+                );
+                let tx_requests = approval
+                    .iter()
+                    .cloned()
+                    .chain([tx_request.clone()])
+                    .collect();
+                if let Some(tx_hash) = self.replace(nonce, tx_requests, rpc_provider).await {
+                    on_replaced(tx_hash);
+                }
+            } else if self.submission_backend.is_bundle() {
+                // An expired bundle used none of its nonces, nothing to cancel
+                break Err(eyre!(
+                    "Bundle with tx {} not included after {} submissions",
+                    transaction.tx_hash(),
+                    replacements + 1
+                ));
+            } else if cancellations < self.max_cancellations {
+                cancellations += 1;
+                info!(
+                    nonce,
+                    tx_hash = transaction.tx_hash().to_string(),
+                    cancellations,
+                    "cancelling unconfirmed tx"
+                );
+                let cancel_tx_request =
+                    cancel_tx_request(&tx_request, *rpc_provider.signer_address(), nonce);
+                if let Some(tx_hash) = self
+                    .replace(nonce, vec![cancel_tx_request], rpc_provider)
+                    .await
+                {
+                    on_replaced(tx_hash);
+                    cancel_tx_hashes.push(tx_hash);
+                }
+            } else {
+                // Stop outbidding, the wallet reconciliation picks up
+                // whatever eventually lands at the nonce
+                break Err(eyre!(
+                    "Tx {} at nonce {} still unconfirmed after {} cancellations",
+                    transaction.tx_hash(),
+                    nonce,
+                    cancellations
+                ));
+            }
+        };
+
+        self.pending.lock().unwrap().remove(&nonce);
+        // Nonces assigned after an expired bundle are out of order, resync
+        if outcome.is_err() && self.submission_backend.is_bundle() {
+            *self.next_nonce.lock().await = None;
+        }
+
+        let receipt = outcome?;
+        if cancel_tx_hashes.contains(&receipt.transaction_hash) {
+            Err(eyre!(
+                "Tx {} cancelled at nonce {}",
+                transaction.tx_hash(),
+                nonce
+            ))
+        } else {
+            Ok(receipt)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bump_fee, bump_fees, cancel_tx_request};
+
+    use alloy::{
+        primitives::{Address, TxKind, U256},
+        rpc::types::eth::TransactionRequest,
+    };
+
+    #[test]
+    fn test_bump_fee() {
+        assert_eq!(bump_fee(1_000, 1250), 1_125);
+        // Always bumps by at least a wei
+        assert_eq!(bump_fee(1, 1250), 2);
+        assert_eq!(bump_fee(0, 1250), 1);
+    }
+
+    #[test]
+    fn test_bump_fees_capped() {
+        let tx_request = TransactionRequest {
+            max_fee_per_gas: Some(200),
+            max_priority_fee_per_gas: Some(190),
+            ..TransactionRequest::default()
+        };
+
+        let bumped = bump_fees(tx_request.clone(), 1250, Some(210));
+        assert_eq!(bumped.max_fee_per_gas, Some(210));
+        // The priority fee never exceeds the max fee
+        assert_eq!(bumped.max_priority_fee_per_gas, Some(210));
+
+        let bumped = bump_fees(tx_request, 1250, None);
+        assert_eq!(bumped.max_fee_per_gas, Some(225));
+        assert_eq!(bumped.max_priority_fee_per_gas, Some(213));
+    }
+
+    #[test]
+    fn test_cancel_tx_request() {
+        let signer_address = Address::repeat_byte(1);
+        let tx_request = bump_fees(
+            TransactionRequest {
+                max_fee_per_gas: Some(200),
+                max_priority_fee_per_gas: Some(8),
+                value: Some(U256::from(100)),
+                ..TransactionRequest::default()
+            },
+            1250,
+            None,
+        );
+
+        let cancel = cancel_tx_request(&tx_request, signer_address, 7);
+        assert_eq!(cancel.to, Some(TxKind::Call(signer_address)));
+        assert_eq!(cancel.value, Some(U256::ZERO));
+        assert_eq!(cancel.nonce, Some(7));
+        assert_eq!(cancel.max_fee_per_gas, Some(225));
+        assert_eq!(cancel.max_priority_fee_per_gas, Some(9));
+    }
+}
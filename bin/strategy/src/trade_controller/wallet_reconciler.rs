@@ -0,0 +1,375 @@
+use super::{trade_controller::pair_input, Trade, TradeController, TradeRequest, Trades};
+
+use pochtecatl_primitives::{constants, RpcProvider, TradeMetadata, TradeRequestParams};
+
+use alloy::{
+    network::Ethereum,
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+
+use eyre::{eyre, Result};
+use fnv::FnvHashMap;
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info, warn};
+
+// How a token held by the signer disagrees with the books
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletDiscrepancy {
+    // Held without an active position in any book, e.g. dust left by a close
+    Orphaned {
+        token_address: Address,
+        balance: U256,
+    },
+    // Open positions that the signer's balance does not match
+    Mismatch {
+        token_address: Address,
+        expected: U256,
+        actual: U256,
+    },
+}
+
+#[derive(Debug)]
+pub struct WalletReconciliation {
+    pub native_balance: U256,
+    pub weth_balance: U256,
+    pub discrepancies: Vec<WalletDiscrepancy>,
+}
+
+// Compares the signer's token balances to the open positions across books.
+// Tokens with a pending trade are skipped while their balance is in flux.
+fn find_discrepancies(
+    books: &[Trades],
+    balances: &FnvHashMap<Address, U256>,
+    mismatch_tolerance_bps: u64,
+) -> Vec<WalletDiscrepancy> {
+    let mut expected_balances = FnvHashMap::<Address, Option<U256>>::default();
+    for trades in books.iter() {
+        for (token_address, address_trades) in trades.0.read().unwrap().iter() {
+            let expected = expected_balances
+                .entry(*token_address)
+                .or_insert(Some(U256::ZERO));
+            *expected = match (address_trades.active(), *expected) {
+                (Some(Trade::PendingOpen(_)) | Some(Trade::PendingClose(_)), _) => None,
+                (Some(Trade::Open(open_trade)), Some(amount)) => {
+                    Some(amount + open_trade.token_amount())
+                }
+                (_, expected) => expected,
+            };
+        }
+    }
+
+    let mut discrepancies = balances
+        .iter()
+        .filter(|(token_address, _)| **token_address != constants::WETH_ADDRESS)
+        .filter_map(|(token_address, actual)| {
+            match expected_balances.get(token_address) {
+                // A trade is pending
+                Some(None) => None,
+                Some(Some(expected)) if !expected.is_zero() => {
+                    let expected = *expected;
+                    let difference = expected.max(*actual) - expected.min(*actual);
+                    (difference * constants::BP_FACTOR
+                        > expected * U256::from(mismatch_tolerance_bps))
+                    .then_some(WalletDiscrepancy::Mismatch {
+                        token_address: *token_address,
+                        expected,
+                        actual: *actual,
+                    })
+                }
+                _ => (!actual.is_zero()).then_some(WalletDiscrepancy::Orphaned {
+                    token_address: *token_address,
+                    balance: *actual,
+                }),
+            }
+        })
+        .collect::<Vec<_>>();
+    discrepancies.sort_by_key(|discrepancy| match discrepancy {
+        WalletDiscrepancy::Orphaned { token_address, .. }
+        | WalletDiscrepancy::Mismatch { token_address, .. } => *token_address,
+    });
+
+    discrepancies
+}
+
+// Periodically checks what the books of every strategy believe the signer
+// holds against its on-chain balances. Orphaned holdings may be sold back to
+// weth.
+pub struct WalletReconciler<T, P>
+where
+    T: Transport + Clone,
+    P: Provider<T, Ethereum> + 'static,
+{
+    rpc_provider: Arc<RpcProvider<T, P>>,
+    books: Vec<Trades>,
+    // Difference from the expected balance tolerated before flagging
+    mismatch_tolerance_bps: u64,
+    // Sells orphaned holdings through this controller if set
+    liquidator: Option<Arc<TradeController<T, P>>>,
+    liquidation_slippage_bps: u64,
+}
+
+impl<T, P> WalletReconciler<T, P>
+where
+    T: Transport + Clone + 'static,
+    P: Provider<T, Ethereum> + 'static,
+{
+    pub fn new(rpc_provider: Arc<RpcProvider<T, P>>, books: Vec<Trades>) -> Self {
+        Self {
+            rpc_provider,
+            books,
+            mismatch_tolerance_bps: 100,
+            liquidator: None,
+            liquidation_slippage_bps: 100,
+        }
+    }
+
+    pub fn with_mismatch_tolerance_bps(mut self, mismatch_tolerance_bps: u64) -> Self {
+        self.mismatch_tolerance_bps = mismatch_tolerance_bps;
+        self
+    }
+
+    pub fn with_liquidator(
+        mut self,
+        liquidator: Arc<TradeController<T, P>>,
+        liquidation_slippage_bps: u64,
+    ) -> Self {
+        self.liquidator = Some(liquidator);
+        self.liquidation_slippage_bps = liquidation_slippage_bps;
+        self
+    }
+
+    // The most recent closed trade of each token across books, used to find
+    // the pair an orphaned holding trades on
+    fn last_closed_trades(&self) -> FnvHashMap<Address, TradeMetadata> {
+        let mut last_closed_trades = FnvHashMap::<Address, TradeMetadata>::default();
+        for trades in self.books.iter() {
+            for (token_address, address_trades) in trades.0.read().unwrap().iter() {
+                if let Some((open_trade, _)) = address_trades.closed().last() {
+                    last_closed_trades
+                        .entry(*token_address)
+                        .and_modify(|last_open_trade| {
+                            if open_trade.block_number() > last_open_trade.block_number() {
+                                *last_open_trade = open_trade.clone();
+                            }
+                        })
+                        .or_insert_with(|| open_trade.clone());
+                }
+            }
+        }
+
+        last_closed_trades
+    }
+
+    pub async fn reconcile(&self) -> Result<WalletReconciliation> {
+        let mut token_addresses = self
+            .books
+            .iter()
+            .flat_map(|trades| trades.0.read().unwrap().keys().copied().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        token_addresses.push(constants::WETH_ADDRESS);
+        token_addresses.sort();
+        token_addresses.dedup();
+
+        let (native_balance, balances) = tokio::try_join!(
+            self.rpc_provider
+                .get_balance(*self.rpc_provider.signer_address(), None),
+            self.rpc_provider.get_token_balances(&token_addresses, None)
+        )?;
+
+        Ok(WalletReconciliation {
+            native_balance,
+            weth_balance: balances
+                .get(&constants::WETH_ADDRESS)
+                .copied()
+                .unwrap_or_default(),
+            discrepancies: find_discrepancies(&self.books, &balances, self.mismatch_tolerance_bps),
+        })
+    }
+
+    // Sells an orphaned holding on the pair its last position traded on
+    async fn liquidate(
+        &self,
+        liquidator: &TradeController<T, P>,
+        open_trade: &TradeMetadata,
+        balance: U256,
+    ) -> Result<()> {
+        let header = self
+            .rpc_provider
+            .block_provider()
+            .get_latest_block_header()
+            .await?;
+        let block_number = header
+            .number
+            .map(|number| number.to::<u64>())
+            .ok_or_else(|| eyre!("Latest block header has no number"))?;
+
+        let pair_address = open_trade.indexed_trade().pair_address();
+        let pair = self
+            .rpc_provider
+            .dex_provider()
+            .get_pairs(
+                vec![pair_input(open_trade.indexed_trade())],
+                Some(block_number.into()),
+            )
+            .await?
+            .remove(pair_address)
+            .ok_or_else(|| eyre!("Pair {} not found", pair_address))?;
+
+        liquidator
+            .liquidate(
+                TradeRequest::close(
+                    block_number,
+                    header.timestamp.to::<u64>(),
+                    pair,
+                    open_trade.indexed_trade().clone(),
+                    *open_trade.tx_hash(),
+                )
+                .with_params(
+                    TradeRequestParams::new()
+                        .with_token_amount_in(balance)
+                        .with_slippage_bps(self.liquidation_slippage_bps),
+                ),
+            )
+            .await
+    }
+
+    async fn reconcile_and_liquidate(&self) -> Result<()> {
+        let reconciliation = self.reconcile().await?;
+        info!(
+            native_balance = reconciliation.native_balance.to_string(),
+            weth_balance = reconciliation.weth_balance.to_string(),
+            discrepancies = reconciliation.discrepancies.len(),
+            "reconciled wallet"
+        );
+
+        let last_closed_trades = self.last_closed_trades();
+        for discrepancy in reconciliation.discrepancies.iter() {
+            warn!(?discrepancy, "wallet discrepancy");
+
+            if let (
+                Some(liquidator),
+                WalletDiscrepancy::Orphaned {
+                    token_address,
+                    balance,
+                },
+            ) = (&self.liquidator, discrepancy)
+            {
+                match last_closed_trades.get(token_address) {
+                    Some(open_trade) => {
+                        if let Err(err) = self.liquidate(liquidator, open_trade, *balance).await {
+                            error!(
+                                token_address = token_address.to_string(),
+                                "Failed to liquidate orphaned holding: {:?}", err
+                            );
+                        }
+                    }
+                    None => warn!(
+                        token_address = token_address.to_string(),
+                        "No pair known to liquidate orphaned holding on"
+                    ),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn spawn(self, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        T: Send + Sync,
+        P: Send + Sync,
+    {
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = self.reconcile_and_liquidate().await {
+                    error!("Failed to reconcile wallet: {:?}", err);
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_discrepancies, WalletDiscrepancy};
+    use crate::trade_controller::{AddressTrades, Trade, Trades};
+
+    use pochtecatl_primitives::{
+        constants, IndexedTrade, TradeMetadata, TradeRequestOp, UniswapV2IndexedTrade,
+    };
+
+    use alloy::primitives::{Address, TxHash, U256};
+    use fnv::FnvHashMap;
+
+    fn open_trade(token_address: Address, token_amount: u64) -> TradeMetadata {
+        // Buys token_amount of a token that sorts below weth, making it token0
+        TradeMetadata::new(
+            TxHash::ZERO,
+            0,
+            0,
+            TradeRequestOp::Open,
+            token_address,
+            U256::ZERO,
+            IndexedTrade::UniswapV2(UniswapV2IndexedTrade::new(
+                Address::ZERO,
+                U256::ZERO,
+                U256::from(100),
+                U256::from(token_amount),
+                U256::ZERO,
+                U256::ZERO,
+                U256::ZERO,
+                Address::ZERO,
+            )),
+        )
+    }
+
+    #[test]
+    fn test_find_discrepancies() {
+        let held = Address::repeat_byte(1);
+        let short = Address::repeat_byte(2);
+        let orphaned = Address::repeat_byte(3);
+        let pending = Address::repeat_byte(4);
+
+        let trades = Trades::default();
+        {
+            let mut trades = trades.0.write().unwrap();
+            for (token_address, active) in [
+                (held, Trade::Open(open_trade(held, 1_000))),
+                (short, Trade::Open(open_trade(short, 1_000))),
+                (pending, Trade::PendingOpen(U256::ZERO)),
+            ] {
+                trades
+                    .entry(token_address)
+                    .or_insert_with(AddressTrades::default)
+                    .set_active(Some(active));
+            }
+        }
+
+        let balances = FnvHashMap::from_iter([
+            (held, U256::from(995)),
+            (short, U256::from(900)),
+            (orphaned, U256::from(7)),
+            (pending, U256::from(1)),
+            (constants::WETH_ADDRESS, U256::from(10)),
+        ]);
+
+        assert_eq!(
+            find_discrepancies(&[trades], &balances, 100),
+            vec![
+                WalletDiscrepancy::Mismatch {
+                    token_address: short,
+                    expected: U256::from(1_000),
+                    actual: U256::from(900),
+                },
+                WalletDiscrepancy::Orphaned {
+                    token_address: orphaned,
+                    balance: U256::from(7),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.24;
+
+// Swaps eth directly against uniswap v2 pairs and v3 pools without going
+// through the routers. The interface is mirrored by IExecutor in
+// crates/primitives/src/abi/executor.rs.
+
+interface IERC20 {
+    function balanceOf(address account) external view returns (uint256);
+    function transfer(address to, uint256 amount) external returns (bool);
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+}
+
+interface IWETH is IERC20 {
+    function deposit() external payable;
+    function withdraw(uint256 amount) external;
+}
+
+interface IUniswapV2Pair {
+    function token0() external view returns (address);
+    function getReserves() external view returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+    function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata data) external;
+}
+
+interface IUniswapV3Pool {
+    function token0() external view returns (address);
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 sqrtPriceLimitX96,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1);
+}
+
+contract Executor {
+    error Expired();
+    error InsufficientOutput();
+    error UnexpectedCallback();
+    error TransferFailed();
+
+    // Bounds of the v3 sqrt price, exclusive
+    uint160 internal constant MIN_SQRT_RATIO = 4295128739;
+    uint160 internal constant MAX_SQRT_RATIO = 1461446703485210103287273052203988822378723970342;
+
+    IWETH public immutable weth;
+
+    // Pool whose swap is in progress, the only caller allowed into the swap
+    // callback
+    address private activePool;
+
+    constructor(IWETH weth_) {
+        weth = weth_;
+    }
+
+    // Weth unwrapped by sells
+    receive() external payable {
+        if (msg.sender != address(weth)) revert TransferFailed();
+    }
+
+    modifier checkDeadline(uint256 deadline) {
+        if (block.timestamp > deadline) revert Expired();
+        _;
+    }
+
+    function buyV2(address pair, address token, uint256 amountOutMin, address recipient, uint256 deadline)
+        external
+        payable
+        checkDeadline(deadline)
+        returns (uint256 amountOut)
+    {
+        weth.deposit{value: msg.value}();
+        _transfer(address(weth), pair, msg.value);
+
+        uint256 balanceBefore = IERC20(token).balanceOf(recipient);
+        _swapV2(pair, address(weth), msg.value, recipient);
+        amountOut = IERC20(token).balanceOf(recipient) - balanceBefore;
+        if (amountOut < amountOutMin) revert InsufficientOutput();
+    }
+
+    function sellV2(
+        address pair,
+        address token,
+        uint256 amountIn,
+        uint256 amountOutMin,
+        address recipient,
+        uint256 deadline
+    ) external checkDeadline(deadline) returns (uint256 amountOut) {
+        _transferFrom(token, msg.sender, pair, amountIn);
+
+        uint256 balanceBefore = weth.balanceOf(address(this));
+        _swapV2(pair, token, amountIn, address(this));
+        amountOut = weth.balanceOf(address(this)) - balanceBefore;
+        if (amountOut < amountOutMin) revert InsufficientOutput();
+
+        _sendEth(recipient, amountOut);
+    }
+
+    function buyV3(address pool, address token, uint256 amountOutMin, address recipient, uint256 deadline)
+        external
+        payable
+        checkDeadline(deadline)
+        returns (uint256 amountOut)
+    {
+        weth.deposit{value: msg.value}();
+
+        amountOut = _swapV3(pool, address(weth), token, msg.value, recipient, address(this));
+        if (amountOut < amountOutMin) revert InsufficientOutput();
+    }
+
+    function sellV3(
+        address pool,
+        address token,
+        uint256 amountIn,
+        uint256 amountOutMin,
+        address recipient,
+        uint256 deadline
+    ) external checkDeadline(deadline) returns (uint256 amountOut) {
+        amountOut = _swapV3(pool, token, address(weth), amountIn, address(this), msg.sender);
+        if (amountOut < amountOutMin) revert InsufficientOutput();
+
+        _sendEth(recipient, amountOut);
+    }
+
+    /// @notice Executes each call against this contract, reverting if any does
+    function multicall(bytes[] calldata data) external payable returns (bytes[] memory results) {
+        results = new bytes[](data.length);
+        for (uint256 i = 0; i < data.length; i++) {
+            (bool success, bytes memory result) = address(this).delegatecall(data[i]);
+            if (!success) {
+                assembly {
+                    revert(add(result, 32), mload(result))
+                }
+            }
+            results[i] = result;
+        }
+    }
+
+    // Pays the input a v3 swap owes the pool, from this contract for buys or
+    // from the seller for sells
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata data) external {
+        if (msg.sender != activePool) revert UnexpectedCallback();
+
+        (address tokenIn, address payer) = abi.decode(data, (address, address));
+        uint256 amountIn = uint256(amount0Delta > 0 ? amount0Delta : amount1Delta);
+        if (payer == address(this)) {
+            _transfer(tokenIn, msg.sender, amountIn);
+        } else {
+            _transferFrom(tokenIn, payer, msg.sender, amountIn);
+        }
+    }
+
+    // Swaps the input already sent to the pair, priced off its reserves as
+    // the v2 router does
+    function _swapV2(address pair, address tokenIn, uint256 amountIn, address to) internal {
+        (uint256 reserve0, uint256 reserve1,) = IUniswapV2Pair(pair).getReserves();
+        bool zeroForOne = IUniswapV2Pair(pair).token0() == tokenIn;
+        (uint256 reserveIn, uint256 reserveOut) = zeroForOne ? (reserve0, reserve1) : (reserve1, reserve0);
+
+        // Taxed tokens deliver less than was sent
+        amountIn = IERC20(tokenIn).balanceOf(pair) - reserveIn;
+        uint256 amountInWithFee = amountIn * 997;
+        uint256 amountOut = (amountInWithFee * reserveOut) / (reserveIn * 1000 + amountInWithFee);
+
+        (uint256 amount0Out, uint256 amount1Out) = zeroForOne ? (uint256(0), amountOut) : (amountOut, uint256(0));
+        IUniswapV2Pair(pair).swap(amount0Out, amount1Out, to, new bytes(0));
+    }
+
+    function _swapV3(address pool, address tokenIn, address tokenOut, uint256 amountIn, address to, address payer)
+        internal
+        returns (uint256 amountOut)
+    {
+        bool zeroForOne = IUniswapV3Pool(pool).token0() == tokenIn;
+
+        uint256 balanceBefore = IERC20(tokenOut).balanceOf(to);
+        activePool = pool;
+        IUniswapV3Pool(pool).swap(
+            to,
+            zeroForOne,
+            int256(amountIn),
+            zeroForOne ? MIN_SQRT_RATIO + 1 : MAX_SQRT_RATIO - 1,
+            abi.encode(tokenIn, payer)
+        );
+        activePool = address(0);
+
+        amountOut = IERC20(tokenOut).balanceOf(to) - balanceBefore;
+    }
+
+    function _sendEth(address to, uint256 amount) internal {
+        weth.withdraw(amount);
+        (bool success,) = to.call{value: amount}("");
+        if (!success) revert TransferFailed();
+    }
+
+    function _transfer(address token, address to, uint256 amount) internal {
+        (bool success, bytes memory result) = token.call(abi.encodeCall(IERC20.transfer, (to, amount)));
+        if (!success || (result.length > 0 && !abi.decode(result, (bool)))) revert TransferFailed();
+    }
+
+    function _transferFrom(address token, address from, address to, uint256 amount) internal {
+        (bool success, bytes memory result) = token.call(abi.encodeCall(IERC20.transferFrom, (from, to, amount)));
+        if (!success || (result.length > 0 && !abi.decode(result, (bool)))) revert TransferFailed();
+    }
+}
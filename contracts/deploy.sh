@@ -0,0 +1,17 @@
+#!/usr/bin/env bash
+# Deploys the executor with foundry, reading RPC_URL, WALLET_PRIVATE_KEY and
+# WETH_ADDRESS from the environment or the strategy's .env. Set
+# EXECUTOR_ADDRESS to the deployed address to route trades through it.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+if [ -f ../bin/strategy/.env ]; then
+  set -a
+  . ../bin/strategy/.env
+  set +a
+fi
+
+forge create Executor.sol:Executor \
+  --rpc-url "$RPC_URL" \
+  --private-key "$WALLET_PRIVATE_KEY" \
+  --constructor-args "$WETH_ADDRESS"
@@ -42,6 +42,15 @@ const INIT_MIGRATIONS: &'static str = concat!(
     include_str!("migrations/up-1-blocks.sql"),
     include_str!("migrations/up-2-backtests.sql"),
     include_str!("migrations/up-3-backtest-closed-trades.sql"),
+    include_str!("migrations/up-4-time-price-bars.sql"),
+    include_str!("migrations/up-5-backtest-strategies.sql"),
+    include_str!("migrations/up-6-walk-forwards.sql"),
+    include_str!("migrations/up-7-backtest-metrics.sql"),
+    include_str!("migrations/up-8-backtest-open-trades.sql"),
+    include_str!("migrations/up-9-backtest-risk-rejections.sql"),
+    include_str!("migrations/up-10-live-trades.sql"),
+    include_str!("migrations/up-11-live-trade-tx-hashes.sql"),
+    include_str!("migrations/up-12-block-chunks.sql"),
 );
 
 pub fn connect(url: &String) -> Result<Pool<SqliteConnectionManager>> {
@@ -1,8 +1,15 @@
 pub use client::connect;
 pub use models::{
     Backtest as BacktestModel, BacktestClosedTrade as BacktestClosedTradeModel,
-    Block as BlockModel, NewBacktest as NewBacktestModel,
+    BacktestMetrics as BacktestMetricsModel, BacktestOpenTrade as BacktestOpenTradeModel,
+    BacktestRiskRejection as BacktestRiskRejectionModel, BacktestStrategy as BacktestStrategyModel,
+    Block as BlockModel, BlockChunk as BlockChunkModel, LiveTrade as LiveTradeModel,
+    LiveTradeUpdate as LiveTradeUpdateModel, NewBacktest as NewBacktestModel,
     NewBacktestClosedTrade as NewBacktestClosedTradeModel,
+    NewBacktestOpenTrade as NewBacktestOpenTradeModel,
+    NewBacktestRiskRejection as NewBacktestRiskRejectionModel, NewLiveTrade as NewLiveTradeModel,
+    NewWalkForward as NewWalkForwardModel, TimePriceBar as TimePriceBarModel,
+    WalkForward as WalkForwardModel, WalkForwardWindow as WalkForwardWindowModel,
 };
 pub use queries::{
     BacktestBlockRange as BacktestBlockRangeQuery, BacktestPair as BacktestPairQuery,
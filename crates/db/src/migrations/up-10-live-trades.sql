@@ -0,0 +1,17 @@
+-- Journal of the positions taken by live strategies, so that open positions
+-- survive a restart. A row per position, updated as it moves from
+-- pending_open to open, pending_close and closed, or to failed. tx_hash is
+-- the latest tx sent for the position.
+CREATE TABLE IF NOT EXISTS live_trades (
+  id INTEGER NOT NULL PRIMARY KEY,
+  strategy_name TEXT NOT NULL,
+  token_address BLOB NOT NULL,
+  status TEXT NOT NULL,
+  tx_hash BLOB,
+  open_trade_metadata JSONB,
+  close_trade_metadata JSONB,
+  error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS live_trades__strategy_name_status
+  ON live_trades (strategy_name, status);
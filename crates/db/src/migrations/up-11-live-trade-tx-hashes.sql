@@ -0,0 +1,8 @@
+-- Every tx sent for a live trade's pending open or close, replacements and
+-- cancellations at the same nonce included. Any of them may be the one that
+-- lands, so all are checked when restoring the trade.
+CREATE TABLE IF NOT EXISTS live_trade_tx_hashes (
+  live_trade_id BIGINT NOT NULL,
+  tx_hash BLOB NOT NULL,
+  PRIMARY KEY (live_trade_id, tx_hash)
+);
@@ -0,0 +1,13 @@
+-- Block ranges indexed into blocks. Only blocks covered by a complete chunk
+-- are trusted, anything else may be missing logs and is re-fetched. A chunk
+-- whose fetch failed is kept as incomplete along with the error.
+CREATE TABLE IF NOT EXISTS block_chunks (
+  start_block_number BIGINT NOT NULL,
+  end_block_number BIGINT NOT NULL,
+  is_complete BOOLEAN NOT NULL,
+  error TEXT,
+  PRIMARY KEY (start_block_number, end_block_number)
+);
+
+CREATE INDEX IF NOT EXISTS block_chunks__end_block_number
+  ON block_chunks (end_block_number);
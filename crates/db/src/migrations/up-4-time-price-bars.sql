@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS time_price_bars (
+  pair_address BLOB NOT NULL,
+  resolution TEXT NOT NULL,
+  timestamp BIGINT NOT NULL,
+  start_block_number BIGINT NOT NULL,
+  end_block_number BIGINT NOT NULL,
+  data JSONB NOT NULL,
+  indicators JSONB,
+  PRIMARY KEY (pair_address, resolution, timestamp)
+);
+
+CREATE INDEX IF NOT EXISTS time_price_bars__resolution_timestamp
+  ON time_price_bars (resolution, timestamp);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS backtest_strategies (
+  backtest_id INTEGER NOT NULL PRIMARY KEY,
+  name TEXT NOT NULL,
+  parameters JSONB NOT NULL,
+  -- weth in wei as a decimal string, null if uncapped
+  capital_allocation TEXT
+);
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS walk_forwards (
+  id INTEGER NOT NULL PRIMARY KEY,
+  created_at BIGINT NOT NULL,
+  strategy_name TEXT NOT NULL,
+  start_block_number INTEGER NOT NULL,
+  end_block_number INTEGER NOT NULL,
+  -- stitched out of sample equity curve, cumulative pnl in wei by block timestamp
+  equity_curve JSONB NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS walk_forward_windows (
+  walk_forward_id INTEGER NOT NULL,
+  window_index INTEGER NOT NULL,
+  in_sample_start_block_number INTEGER NOT NULL,
+  in_sample_end_block_number INTEGER NOT NULL,
+  out_of_sample_start_block_number INTEGER NOT NULL,
+  out_of_sample_end_block_number INTEGER NOT NULL,
+  -- parameters chosen on the in sample window
+  parameters JSONB NOT NULL,
+  in_sample_backtest_id INTEGER NOT NULL,
+  out_of_sample_backtest_id INTEGER NOT NULL,
+  PRIMARY KEY (walk_forward_id, window_index)
+);
@@ -0,0 +1,14 @@
+-- Wei amounts are stored as decimal strings, ratios are null when undefined
+CREATE TABLE IF NOT EXISTS backtest_metrics (
+  backtest_id INTEGER NOT NULL PRIMARY KEY,
+  realized_pnl TEXT NOT NULL,
+  trade_count INTEGER NOT NULL,
+  win_rate REAL NOT NULL,
+  average_win TEXT NOT NULL,
+  average_loss TEXT NOT NULL,
+  profit_factor REAL,
+  max_drawdown TEXT NOT NULL,
+  sharpe_ratio REAL,
+  sortino_ratio REAL,
+  exposure_seconds INTEGER NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Positions still open when a backtest ended, marked to market at the last
+-- block the strategy saw. Wei amounts and prices are stored as decimal strings.
+CREATE TABLE IF NOT EXISTS backtest_open_trades (
+  id INTEGER NOT NULL PRIMARY KEY,
+  backtest_id BIGINT NOT NULL,
+  pair_address BLOB NOT NULL,
+  open_trade_metadata JSONB NOT NULL,
+  mark_price TEXT NOT NULL,
+  market_value TEXT NOT NULL,
+  unrealized_pnl TEXT NOT NULL,
+  marked_at_block_number BIGINT NOT NULL,
+  marked_at_block_timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS backtest_open_trades__backtest_id
+  ON backtest_open_trades (backtest_id);
@@ -0,0 +1,13 @@
+-- Position opens refused by the risk engine during a backtest
+CREATE TABLE IF NOT EXISTS backtest_risk_rejections (
+  id INTEGER NOT NULL PRIMARY KEY,
+  backtest_id BIGINT NOT NULL,
+  token_address BLOB NOT NULL,
+  block_number BIGINT NOT NULL,
+  block_timestamp BIGINT NOT NULL,
+  reason TEXT NOT NULL,
+  message TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS backtest_risk_rejections__backtest_id
+  ON backtest_risk_rejections (backtest_id);
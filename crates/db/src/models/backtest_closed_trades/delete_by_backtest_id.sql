@@ -0,0 +1,4 @@
+-- Params: [backtest_id]
+DELETE FROM backtest_closed_trades
+WHERE
+  backtest_id = :backtest_id;
@@ -51,6 +51,15 @@ impl BacktestClosedTrade {
             .collect()
             .map_err(Into::into)
     }
+
+    pub fn delete_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<()> {
+        tx.prepare_cached(include_str!("./delete_by_backtest_id.sql"))?
+            .execute(named_params! {
+                ":backtest_id": backtest_id,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
 }
 
 impl NewBacktestClosedTrade {
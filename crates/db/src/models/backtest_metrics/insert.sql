@@ -0,0 +1,28 @@
+INSERT INTO backtest_metrics
+  (
+    backtest_id,
+    realized_pnl,
+    trade_count,
+    win_rate,
+    average_win,
+    average_loss,
+    profit_factor,
+    max_drawdown,
+    sharpe_ratio,
+    sortino_ratio,
+    exposure_seconds
+  )
+VALUES
+  (
+    :backtest_id,
+    :realized_pnl,
+    :trade_count,
+    :win_rate,
+    :average_win,
+    :average_loss,
+    :profit_factor,
+    :max_drawdown,
+    :sharpe_ratio,
+    :sortino_ratio,
+    :exposure_seconds
+  );
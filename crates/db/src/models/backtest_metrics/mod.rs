@@ -0,0 +1,140 @@
+use crate::primitives::U64;
+
+use eyre::Result;
+use rusqlite::{named_params, OptionalExtension, Transaction};
+
+// Performance of a backtest's closed trades. Wei amounts are decimal strings.
+#[derive(Debug)]
+pub struct BacktestMetrics {
+    pub backtest_id: i64,
+    pub realized_pnl: String,
+    pub trade_count: U64,
+    pub win_rate: f64,
+    pub average_win: String,
+    pub average_loss: String,
+    // None if there were no losing trades
+    pub profit_factor: Option<f64>,
+    pub max_drawdown: String,
+    // None if there were too few trades to compute a deviation
+    pub sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    pub exposure_seconds: U64,
+}
+
+impl BacktestMetrics {
+    pub fn insert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .execute(named_params! {
+                ":backtest_id": self.backtest_id,
+                ":realized_pnl": self.realized_pnl,
+                ":trade_count": self.trade_count,
+                ":win_rate": self.win_rate,
+                ":average_win": self.average_win,
+                ":average_loss": self.average_loss,
+                ":profit_factor": self.profit_factor,
+                ":max_drawdown": self.max_drawdown,
+                ":sharpe_ratio": self.sharpe_ratio,
+                ":sortino_ratio": self.sortino_ratio,
+                ":exposure_seconds": self.exposure_seconds,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows inserted: {}", n))
+                }
+            })
+    }
+
+    pub fn query_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<Option<Self>> {
+        tx.prepare_cached(include_str!("./query_by_backtest_id.sql"))?
+            .query_row(
+                named_params! {
+                    ":backtest_id": backtest_id,
+                },
+                |row| BacktestMetrics::try_from(row),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    pub fn delete_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<()> {
+        tx.prepare_cached(include_str!("./delete_by_backtest_id.sql"))?
+            .execute(named_params! {
+                ":backtest_id": backtest_id,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for BacktestMetrics {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            backtest_id: row.get(0)?,
+            realized_pnl: row.get(1)?,
+            trade_count: row.get(2)?,
+            win_rate: row.get(3)?,
+            average_win: row.get(4)?,
+            average_loss: row.get(5)?,
+            profit_factor: row.get(6)?,
+            max_drawdown: row.get(7)?,
+            sharpe_ratio: row.get(8)?,
+            sortino_ratio: row.get(9)?,
+            exposure_seconds: row.get(10)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BacktestMetrics;
+    use crate::{connect as connect_db, models::NewBacktest};
+
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_and_query() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+        let mut conn = pool.get()?;
+
+        let backtest_id = {
+            let tx = conn.transaction()?;
+            let backtest_id = NewBacktest::new(1, 2).insert(&tx)?;
+            BacktestMetrics {
+                backtest_id,
+                realized_pnl: "-1500".to_string(),
+                trade_count: 4.into(),
+                win_rate: 0.25,
+                average_win: "500".to_string(),
+                average_loss: "-666".to_string(),
+                profit_factor: Some(0.25),
+                max_drawdown: "2000".to_string(),
+                sharpe_ratio: Some(-0.5),
+                sortino_ratio: None,
+                exposure_seconds: 3600.into(),
+            }
+            .insert(&tx)?;
+            tx.commit()?;
+            backtest_id
+        };
+
+        {
+            let tx = conn.transaction()?;
+            let metrics = BacktestMetrics::query_by_backtest_id(&tx, backtest_id)?
+                .expect("expected backtest metrics");
+            assert_eq!(metrics.realized_pnl, "-1500");
+            assert_eq!(metrics.trade_count.0, 4);
+            assert_eq!(metrics.profit_factor, Some(0.25));
+            assert_eq!(metrics.sortino_ratio, None);
+            assert_eq!(metrics.exposure_seconds.0, 3600);
+            assert!(BacktestMetrics::query_by_backtest_id(&tx, backtest_id + 1)?.is_none());
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+SELECT
+  backtest_id,
+  realized_pnl,
+  trade_count,
+  win_rate,
+  average_win,
+  average_loss,
+  profit_factor,
+  max_drawdown,
+  sharpe_ratio,
+  sortino_ratio,
+  exposure_seconds
+FROM backtest_metrics
+WHERE backtest_id = :backtest_id
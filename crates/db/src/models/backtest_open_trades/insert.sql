@@ -0,0 +1,20 @@
+INSERT INTO backtest_open_trades (
+  backtest_id,
+  pair_address,
+  open_trade_metadata,
+  mark_price,
+  market_value,
+  unrealized_pnl,
+  marked_at_block_number,
+  marked_at_block_timestamp
+)
+VALUES (
+  :backtest_id,
+  :pair_address,
+  :open_trade_metadata,
+  :mark_price,
+  :market_value,
+  :unrealized_pnl,
+  :marked_at_block_number,
+  :marked_at_block_timestamp
+);
@@ -0,0 +1,168 @@
+use crate::primitives::{FixedBytes, U64};
+
+use alloy::primitives::{Address, BlockNumber};
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+// A position still open at the end of a backtest, marked to market
+pub struct BacktestOpenTrade {
+    pub id: i64,
+    pub backtest_id: i64,
+    pub pair_address: FixedBytes<20>,
+    pub open_trade_metadata: serde_json::Value,
+    pub mark_price: String,
+    pub market_value: String,
+    pub unrealized_pnl: String,
+    pub marked_at_block_number: U64,
+    pub marked_at_block_timestamp: U64,
+}
+
+pub struct NewBacktestOpenTrade {
+    pub backtest_id: i64,
+    pub pair_address: FixedBytes<20>,
+    pub open_trade_metadata: serde_json::Value,
+    pub mark_price: String,
+    pub market_value: String,
+    pub unrealized_pnl: String,
+    pub marked_at_block_number: U64,
+    pub marked_at_block_timestamp: U64,
+}
+
+impl BacktestOpenTrade {
+    pub fn query_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_backtest_id.sql"))?
+            .query(named_params! {
+                ":backtest_id": backtest_id,
+            })?
+            .map(|row| BacktestOpenTrade::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+
+    pub fn delete_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<()> {
+        tx.prepare_cached(include_str!("./delete_by_backtest_id.sql"))?
+            .execute(named_params! {
+                ":backtest_id": backtest_id,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+impl NewBacktestOpenTrade {
+    pub fn new(
+        backtest_id: i64,
+        pair_address: Address,
+        open_trade_metadata: serde_json::Value,
+        mark_price: String,
+        market_value: String,
+        unrealized_pnl: String,
+        // (block number, block timestamp) of the mark
+        marked_at: (BlockNumber, u64),
+    ) -> Self {
+        Self {
+            backtest_id,
+            pair_address: pair_address.into(),
+            open_trade_metadata,
+            mark_price,
+            market_value,
+            unrealized_pnl,
+            marked_at_block_number: marked_at.0.into(),
+            marked_at_block_timestamp: marked_at.1.into(),
+        }
+    }
+
+    pub fn insert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .execute(named_params! {
+                ":backtest_id": self.backtest_id,
+                ":pair_address": self.pair_address,
+                ":open_trade_metadata": self.open_trade_metadata,
+                ":mark_price": self.mark_price,
+                ":market_value": self.market_value,
+                ":unrealized_pnl": self.unrealized_pnl,
+                ":marked_at_block_number": self.marked_at_block_number,
+                ":marked_at_block_timestamp": self.marked_at_block_timestamp,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows inserted: {}", n))
+                }
+            })
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for BacktestOpenTrade {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            backtest_id: row.get(1)?,
+            pair_address: row.get(2)?,
+            open_trade_metadata: row.get(3)?,
+            mark_price: row.get(4)?,
+            market_value: row.get(5)?,
+            unrealized_pnl: row.get(6)?,
+            marked_at_block_number: row.get(7)?,
+            marked_at_block_timestamp: row.get(8)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BacktestOpenTrade, NewBacktestOpenTrade};
+    use crate::connect as connect_db;
+
+    use alloy::primitives::Address;
+    use eyre::Result;
+
+    fn new_open_trade(backtest_id: i64, unrealized_pnl: &str) -> NewBacktestOpenTrade {
+        NewBacktestOpenTrade::new(
+            backtest_id,
+            Address::ZERO,
+            serde_json::json!({}),
+            "0.5".to_string(),
+            "1000".to_string(),
+            unrealized_pnl.to_string(),
+            (100, 200),
+        )
+    }
+
+    #[test]
+    pub fn test_insert_and_query_by_backtest_id() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+            new_open_trade(1, "-50").insert(&tx)?;
+            new_open_trade(1, "25").insert(&tx)?;
+            new_open_trade(2, "10").insert(&tx)?;
+            tx.commit()?;
+        }
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+            let open_trades = BacktestOpenTrade::query_by_backtest_id(&tx, 1)?;
+
+            assert_eq!(open_trades.len(), 2);
+            assert_eq!(open_trades[0].unrealized_pnl, "-50");
+            assert_eq!(open_trades[1].unrealized_pnl, "25");
+            assert_eq!(open_trades[0].mark_price, "0.5");
+            assert_eq!(open_trades[0].marked_at_block_number.0, 100);
+            assert_eq!(open_trades[0].marked_at_block_timestamp.0, 200);
+            assert!(BacktestOpenTrade::query_by_backtest_id(&tx, 3)?.is_empty());
+
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
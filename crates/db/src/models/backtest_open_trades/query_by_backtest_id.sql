@@ -0,0 +1,15 @@
+-- Params: [backtest_id]
+SELECT
+  id,
+  backtest_id,
+  pair_address,
+  open_trade_metadata,
+  mark_price,
+  market_value,
+  unrealized_pnl,
+  marked_at_block_number,
+  marked_at_block_timestamp
+FROM backtest_open_trades
+WHERE
+  backtest_id = :backtest_id
+ORDER BY id ASC;
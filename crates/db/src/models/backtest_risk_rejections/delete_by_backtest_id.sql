@@ -0,0 +1,4 @@
+-- Params: [backtest_id]
+DELETE FROM backtest_risk_rejections
+WHERE
+  backtest_id = :backtest_id;
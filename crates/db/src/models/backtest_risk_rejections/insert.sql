@@ -0,0 +1,16 @@
+INSERT INTO backtest_risk_rejections (
+  backtest_id,
+  token_address,
+  block_number,
+  block_timestamp,
+  reason,
+  message
+)
+VALUES (
+  :backtest_id,
+  :token_address,
+  :block_number,
+  :block_timestamp,
+  :reason,
+  :message
+);
@@ -0,0 +1,155 @@
+use crate::primitives::{FixedBytes, U64};
+
+use alloy::primitives::{Address, BlockNumber};
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+// An open refused by the risk engine. The reason is a stable label and the
+// message describes the breached limit.
+pub struct BacktestRiskRejection {
+    pub id: i64,
+    pub backtest_id: i64,
+    pub token_address: FixedBytes<20>,
+    pub block_number: U64,
+    pub block_timestamp: U64,
+    pub reason: String,
+    pub message: String,
+}
+
+pub struct NewBacktestRiskRejection {
+    pub backtest_id: i64,
+    pub token_address: FixedBytes<20>,
+    pub block_number: U64,
+    pub block_timestamp: U64,
+    pub reason: String,
+    pub message: String,
+}
+
+impl BacktestRiskRejection {
+    pub fn query_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_backtest_id.sql"))?
+            .query(named_params! {
+                ":backtest_id": backtest_id,
+            })?
+            .map(|row| BacktestRiskRejection::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+
+    pub fn delete_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<()> {
+        tx.prepare_cached(include_str!("./delete_by_backtest_id.sql"))?
+            .execute(named_params! {
+                ":backtest_id": backtest_id,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+impl NewBacktestRiskRejection {
+    pub fn new(
+        backtest_id: i64,
+        token_address: Address,
+        block_number: BlockNumber,
+        block_timestamp: u64,
+        reason: String,
+        message: String,
+    ) -> Self {
+        Self {
+            backtest_id,
+            token_address: token_address.into(),
+            block_number: block_number.into(),
+            block_timestamp: block_timestamp.into(),
+            reason,
+            message,
+        }
+    }
+
+    pub fn insert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .execute(named_params! {
+                ":backtest_id": self.backtest_id,
+                ":token_address": self.token_address,
+                ":block_number": self.block_number,
+                ":block_timestamp": self.block_timestamp,
+                ":reason": self.reason,
+                ":message": self.message,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows inserted: {}", n))
+                }
+            })
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for BacktestRiskRejection {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            backtest_id: row.get(1)?,
+            token_address: row.get(2)?,
+            block_number: row.get(3)?,
+            block_timestamp: row.get(4)?,
+            reason: row.get(5)?,
+            message: row.get(6)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BacktestRiskRejection, NewBacktestRiskRejection};
+    use crate::connect as connect_db;
+
+    use alloy::primitives::Address;
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_and_query_by_backtest_id() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+            for (backtest_id, block_number, reason) in [
+                (1, 20, "max_open_positions"),
+                (1, 10, "daily_loss_limit"),
+                (2, 10, "max_block_notional"),
+            ] {
+                NewBacktestRiskRejection::new(
+                    backtest_id,
+                    Address::ZERO,
+                    block_number,
+                    block_number * 2,
+                    reason.to_string(),
+                    format!("rejected by {}", reason),
+                )
+                .insert(&tx)?;
+            }
+            tx.commit()?;
+        }
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+            let rejections = BacktestRiskRejection::query_by_backtest_id(&tx, 1)?;
+
+            assert_eq!(rejections.len(), 2);
+            assert_eq!(rejections[0].block_number.0, 10);
+            assert_eq!(rejections[0].block_timestamp.0, 20);
+            assert_eq!(rejections[0].reason, "daily_loss_limit");
+            assert_eq!(rejections[1].message, "rejected by max_open_positions");
+
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+-- Params: [backtest_id]
+SELECT
+  id,
+  backtest_id,
+  token_address,
+  block_number,
+  block_timestamp,
+  reason,
+  message
+FROM backtest_risk_rejections
+WHERE
+  backtest_id = :backtest_id
+ORDER BY block_number ASC, id ASC;
@@ -0,0 +1,4 @@
+INSERT INTO backtest_strategies
+  (backtest_id, name, parameters, capital_allocation)
+VALUES
+  (:backtest_id, :name, :parameters, :capital_allocation);
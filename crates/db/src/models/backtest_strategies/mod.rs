@@ -0,0 +1,120 @@
+use eyre::Result;
+use rusqlite::{named_params, OptionalExtension, Transaction};
+
+#[derive(Debug)]
+pub struct BacktestStrategy {
+    pub backtest_id: i64,
+    pub name: String,
+    pub parameters: serde_json::Value,
+    // weth in wei as a decimal string, None if uncapped
+    pub capital_allocation: Option<String>,
+}
+
+impl BacktestStrategy {
+    pub fn new(
+        backtest_id: i64,
+        name: String,
+        parameters: serde_json::Value,
+        capital_allocation: Option<String>,
+    ) -> Self {
+        Self {
+            backtest_id,
+            name,
+            parameters,
+            capital_allocation,
+        }
+    }
+
+    pub fn insert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .execute(named_params! {
+                ":backtest_id": self.backtest_id,
+                ":name": self.name,
+                ":parameters": self.parameters,
+                ":capital_allocation": self.capital_allocation,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows inserted: {}", n))
+                }
+            })
+    }
+
+    pub fn query_by_backtest_id(tx: &Transaction, backtest_id: i64) -> Result<Option<Self>> {
+        tx.prepare_cached(include_str!("./query_by_backtest_id.sql"))?
+            .query_row(
+                named_params! {
+                    ":backtest_id": backtest_id,
+                },
+                |row| BacktestStrategy::try_from(row),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for BacktestStrategy {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            backtest_id: row.get(0)?,
+            name: row.get(1)?,
+            parameters: row.get(2)?,
+            capital_allocation: row.get(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BacktestStrategy;
+    use crate::{
+        connect as connect_db,
+        models::{Backtest, NewBacktest},
+    };
+
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_and_query() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+        let mut conn = pool.get()?;
+
+        {
+            let tx = conn.transaction()?;
+            let backtest_id = NewBacktest::new(1, 2).insert(&tx)?;
+            BacktestStrategy::new(
+                backtest_id,
+                "momentum".to_string(),
+                serde_json::json!({ "ema_period": 9 }),
+                Some("1000000000000000000".to_string()),
+            )
+            .insert(&tx)?;
+            NewBacktest::new(3, 4).insert(&tx)?;
+            tx.commit()?;
+        }
+
+        {
+            let tx = conn.transaction()?;
+
+            let backtest_strategy = BacktestStrategy::query_by_backtest_id(&tx, 1)?
+                .expect("expected backtest strategy");
+            assert_eq!(backtest_strategy.name, "momentum");
+            assert_eq!(backtest_strategy.parameters["ema_period"], 9);
+            assert!(BacktestStrategy::query_by_backtest_id(&tx, 2)?.is_none());
+
+            let backtests = Backtest::query_all(&tx)?;
+            assert_eq!(backtests.len(), 2);
+            assert_eq!(backtests[0].strategy_name.as_deref(), Some("momentum"));
+            assert_eq!(backtests[1].strategy_name, None);
+
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
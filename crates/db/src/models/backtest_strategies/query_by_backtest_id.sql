@@ -0,0 +1,7 @@
+SELECT
+  backtest_id,
+  name,
+  parameters,
+  capital_allocation
+FROM backtest_strategies
+WHERE backtest_id = :backtest_id
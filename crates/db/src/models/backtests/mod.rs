@@ -11,6 +11,8 @@ pub struct Backtest {
     pub created_at: U64,
     pub start_block_number: U64,
     pub end_block_number: U64,
+    // Name of the strategy the backtest ran, if recorded
+    pub strategy_name: Option<String>,
 }
 
 impl Backtest {
@@ -21,6 +23,27 @@ impl Backtest {
             .collect()
             .map_err(Into::into)
     }
+
+    // Extends a backtest that is persisted again as it grows
+    pub fn update_end_block_number(
+        tx: &Transaction,
+        id: i64,
+        end_block_number: BlockNumber,
+    ) -> Result<()> {
+        tx.prepare_cached(include_str!("./update_end_block_number.sql"))?
+            .execute(named_params! {
+                ":id": id,
+                ":end_block_number": U64::from(end_block_number),
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows updated: {}", n))
+                }
+            })
+    }
 }
 
 impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for Backtest {
@@ -32,6 +55,7 @@ impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for Backtest {
             created_at: row.get(1)?,
             start_block_number: row.get(2)?,
             end_block_number: row.get(3)?,
+            strategy_name: row.get(4)?,
         })
     }
 }
@@ -90,6 +114,24 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_update_end_block_number() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+        let mut conn = pool.get()?;
+
+        let tx = conn.transaction()?;
+        let id = NewBacktest::new(1, 2).insert(&tx)?;
+        Backtest::update_end_block_number(&tx, id, 10)?;
+        assert!(Backtest::update_end_block_number(&tx, id + 1, 10).is_err());
+
+        let backtests = Backtest::query_all(&tx)?;
+        assert_eq!(backtests[0].start_block_number.0, 1);
+        assert_eq!(backtests[0].end_block_number.0, 10);
+        tx.rollback()?;
+
+        Ok(())
+    }
+
     #[test]
     pub fn test_query_all() -> Result<()> {
         let pool = connect_db(&String::from(":memory:"))?;
@@ -118,6 +160,7 @@ mod tests {
             assert_eq!(backtests[0].id, 1);
             assert_eq!(backtests[0].start_block_number.0, 1);
             assert_eq!(backtests[0].end_block_number.0, 2);
+            assert_eq!(backtests[0].strategy_name, None);
             tx.rollback()?;
         }
 
@@ -1,6 +1,8 @@
 SELECT
-  id,
-  created_at,
-  start_block_number,
-  end_block_number
+  backtests.id,
+  backtests.created_at,
+  backtests.start_block_number,
+  backtests.end_block_number,
+  backtest_strategies.name
 FROM backtests
+LEFT JOIN backtest_strategies ON backtest_strategies.backtest_id = backtests.id
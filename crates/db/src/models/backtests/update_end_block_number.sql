@@ -0,0 +1,6 @@
+-- Params: [id, end_block_number]
+UPDATE backtests
+SET
+  end_block_number = :end_block_number
+WHERE
+  id = :id;
@@ -0,0 +1,140 @@
+use crate::primitives::U64;
+
+use alloy::primitives::BlockNumber;
+
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+// A range of blocks indexed into blocks, complete once every block in it was
+// built from the range's full set of logs
+#[derive(Debug, Clone)]
+pub struct BlockChunk {
+    pub start_block_number: U64,
+    pub end_block_number: U64,
+    pub is_complete: bool,
+    pub error: Option<String>,
+}
+
+impl BlockChunk {
+    pub fn complete(start_block_number: BlockNumber, end_block_number: BlockNumber) -> Self {
+        Self {
+            start_block_number: start_block_number.into(),
+            end_block_number: end_block_number.into(),
+            is_complete: true,
+            error: None,
+        }
+    }
+
+    pub fn incomplete(
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+        error: String,
+    ) -> Self {
+        Self {
+            start_block_number: start_block_number.into(),
+            end_block_number: end_block_number.into(),
+            is_complete: false,
+            error: Some(error),
+        }
+    }
+
+    pub fn upsert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./upsert.sql"))?
+            .execute(named_params! {
+                ":start_block_number": self.start_block_number,
+                ":end_block_number": self.end_block_number,
+                ":is_complete": self.is_complete,
+                ":error": self.error,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub fn query_by_number_range(
+        tx: &Transaction,
+        min_number: u64,
+        max_number: u64,
+    ) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_number_range.sql"))?
+            .query(named_params! {
+                ":min_number": min_number,
+                ":max_number": max_number,
+            })?
+            .map(|row| BlockChunk::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+
+    // Whether complete chunks cover every block in the range
+    pub fn is_range_complete(tx: &Transaction, min_number: u64, max_number: u64) -> Result<bool> {
+        let mut next_number = min_number;
+        for chunk in Self::query_by_number_range(tx, min_number, max_number)? {
+            if !chunk.is_complete {
+                continue;
+            }
+            if u64::from(chunk.start_block_number) > next_number {
+                break;
+            }
+
+            next_number = next_number.max(u64::from(chunk.end_block_number) + 1);
+            if next_number > max_number {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for BlockChunk {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            start_block_number: row.get(0)?,
+            end_block_number: row.get(1)?,
+            is_complete: row.get(2)?,
+            error: row.get(3)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockChunk;
+    use crate::connect as connect_db;
+    use eyre::Result;
+
+    #[test]
+    pub fn test_is_range_complete() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+
+        BlockChunk::complete(1, 10).upsert(&tx)?;
+        BlockChunk::complete(11, 20).upsert(&tx)?;
+        BlockChunk::incomplete(21, 30, String::from("get_logs failed")).upsert(&tx)?;
+        BlockChunk::complete(25, 40).upsert(&tx)?;
+
+        assert!(BlockChunk::is_range_complete(&tx, 1, 20)?);
+        assert!(BlockChunk::is_range_complete(&tx, 5, 15)?);
+        assert!(BlockChunk::is_range_complete(&tx, 25, 40)?);
+        assert!(!BlockChunk::is_range_complete(&tx, 15, 25)?);
+        assert!(!BlockChunk::is_range_complete(&tx, 35, 45)?);
+
+        // A failed refetch marks the chunk as no longer trusted
+        BlockChunk::incomplete(1, 10, String::from("get_logs failed")).upsert(&tx)?;
+        assert!(!BlockChunk::is_range_complete(&tx, 1, 20)?);
+
+        let chunks = BlockChunk::query_by_number_range(&tx, 1, 10)?;
+        assert_eq!(chunks.len(), 1);
+        assert!(!chunks[0].is_complete);
+        assert_eq!(chunks[0].error.as_deref(), Some("get_logs failed"));
+
+        tx.rollback()?;
+
+        Ok(())
+    }
+}
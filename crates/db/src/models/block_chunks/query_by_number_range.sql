@@ -0,0 +1,11 @@
+-- Params: [min_number, max_number]
+-- Chunks overlapping the range
+SELECT
+  start_block_number,
+  end_block_number,
+  is_complete,
+  error
+FROM block_chunks
+WHERE
+  end_block_number >= :min_number AND start_block_number <= :max_number
+ORDER BY start_block_number ASC;
@@ -0,0 +1,7 @@
+INSERT INTO block_chunks
+  (start_block_number, end_block_number, is_complete, error)
+VALUES
+  (:start_block_number, :end_block_number, :is_complete, :error)
+ON CONFLICT (start_block_number, end_block_number) DO UPDATE SET
+  is_complete = excluded.is_complete,
+  error = excluded.error;
@@ -41,6 +41,18 @@ impl Block {
             })
     }
 
+    // Inserts the block, replacing any already indexed with the same number
+    pub fn upsert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./upsert.sql"))?
+            .execute(named_params! {
+                ":number": self.number,
+                ":timestamp": self.timestamp,
+                ":pair_ticks": self.pair_ticks,
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
     pub fn query_by_number_range(
         tx: &Transaction,
         min_number: u64,
@@ -111,6 +123,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_upsert() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+
+        Block {
+            number: 1.into(),
+            timestamp: 1.into(),
+            pair_ticks: serde_json::json!({}),
+        }
+        .insert(&tx)?;
+        Block {
+            number: 1.into(),
+            timestamp: 2.into(),
+            pair_ticks: serde_json::json!({ "foo": "bar" }),
+        }
+        .upsert(&tx)?;
+
+        let blocks = Block::query_by_number_range(&tx, 1, 1)?;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].timestamp, 2.into());
+        assert_eq!(blocks[0].pair_ticks, serde_json::json!({ "foo": "bar" }));
+
+        tx.rollback()?;
+
+        Ok(())
+    }
+
     #[test]
     pub fn test_query_by_number_range() -> Result<()> {
         let pool = connect_db(&String::from(":memory:"))?;
@@ -0,0 +1,8 @@
+-- Replaces a block previously indexed from an incomplete chunk
+INSERT INTO blocks
+  (number, timestamp, pair_ticks)
+VALUES
+  (:number, :timestamp, :pair_ticks)
+ON CONFLICT (number) DO UPDATE SET
+  timestamp = excluded.timestamp,
+  pair_ticks = excluded.pair_ticks;
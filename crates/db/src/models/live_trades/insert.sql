@@ -0,0 +1,10 @@
+INSERT INTO live_trades (
+  strategy_name,
+  token_address,
+  status
+)
+VALUES (
+  :strategy_name,
+  :token_address,
+  :status
+);
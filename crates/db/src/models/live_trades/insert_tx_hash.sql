@@ -0,0 +1,8 @@
+INSERT OR IGNORE INTO live_trade_tx_hashes (
+  live_trade_id,
+  tx_hash
+)
+VALUES (
+  :live_trade_id,
+  :tx_hash
+);
@@ -0,0 +1,230 @@
+use crate::primitives::FixedBytes;
+
+use alloy::primitives::{Address, TxHash};
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+// A position taken by a live strategy. Status is one of pending_open, open,
+// pending_close, closed or failed.
+pub struct LiveTrade {
+    pub id: i64,
+    pub strategy_name: String,
+    pub token_address: FixedBytes<20>,
+    pub status: String,
+    pub tx_hash: Option<FixedBytes<32>>,
+    pub open_trade_metadata: Option<serde_json::Value>,
+    pub close_trade_metadata: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+pub struct NewLiveTrade {
+    pub strategy_name: String,
+    pub token_address: FixedBytes<20>,
+}
+
+// A status change of a live trade, along with whatever became known with it
+pub struct LiveTradeUpdate {
+    pub id: i64,
+    pub status: String,
+    pub tx_hash: Option<FixedBytes<32>>,
+    pub open_trade_metadata: Option<serde_json::Value>,
+    pub close_trade_metadata: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+impl LiveTrade {
+    // Positions of the strategy that are pending or open
+    pub fn query_active_by_strategy_name(
+        tx: &Transaction,
+        strategy_name: &str,
+    ) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_active_by_strategy_name.sql"))?
+            .query(named_params! {
+                ":strategy_name": strategy_name,
+            })?
+            .map(|row| LiveTrade::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+
+    // Records a tx sent for the trade, a no-op if already recorded
+    pub fn insert_tx_hash(tx: &Transaction, id: i64, tx_hash: TxHash) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert_tx_hash.sql"))?
+            .execute(named_params! {
+                ":live_trade_id": id,
+                ":tx_hash": FixedBytes::from(tx_hash),
+            })
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    // Every tx sent for the trade, in the order they were sent
+    pub fn query_tx_hashes_by_id(tx: &Transaction, id: i64) -> Result<Vec<FixedBytes<32>>> {
+        tx.prepare_cached(include_str!("./query_tx_hashes_by_id.sql"))?
+            .query(named_params! {
+                ":live_trade_id": id,
+            })?
+            .map(|row| row.get(0))
+            .collect()
+            .map_err(Into::into)
+    }
+}
+
+impl NewLiveTrade {
+    pub fn new(strategy_name: String, token_address: Address) -> Self {
+        Self {
+            strategy_name,
+            token_address: token_address.into(),
+        }
+    }
+
+    // Journals a pending open, returning its id
+    pub fn insert(self, tx: &Transaction) -> Result<i64> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .insert(named_params! {
+                ":strategy_name": self.strategy_name,
+                ":token_address": self.token_address,
+                ":status": "pending_open",
+            })
+            .map_err(Into::into)
+    }
+}
+
+impl LiveTradeUpdate {
+    pub fn new(id: i64, status: &str) -> Self {
+        Self {
+            id,
+            status: status.to_string(),
+            tx_hash: None,
+            open_trade_metadata: None,
+            close_trade_metadata: None,
+            error: None,
+        }
+    }
+
+    pub fn with_tx_hash(mut self, tx_hash: TxHash) -> Self {
+        self.tx_hash = Some(tx_hash.into());
+        self
+    }
+
+    pub fn with_open_trade_metadata(mut self, open_trade_metadata: serde_json::Value) -> Self {
+        self.open_trade_metadata = Some(open_trade_metadata);
+        self
+    }
+
+    pub fn with_close_trade_metadata(mut self, close_trade_metadata: serde_json::Value) -> Self {
+        self.close_trade_metadata = Some(close_trade_metadata);
+        self
+    }
+
+    pub fn with_error(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    pub fn update(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./update.sql"))?
+            .execute(named_params! {
+                ":id": self.id,
+                ":status": self.status,
+                ":tx_hash": self.tx_hash,
+                ":open_trade_metadata": self.open_trade_metadata,
+                ":close_trade_metadata": self.close_trade_metadata,
+                ":error": self.error,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows updated: {}", n))
+                }
+            })
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for LiveTrade {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            strategy_name: row.get(1)?,
+            token_address: row.get(2)?,
+            status: row.get(3)?,
+            tx_hash: row.get(4)?,
+            open_trade_metadata: row.get(5)?,
+            close_trade_metadata: row.get(6)?,
+            error: row.get(7)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LiveTrade, LiveTradeUpdate, NewLiveTrade};
+    use crate::connect as connect_db;
+
+    use alloy::primitives::{Address, TxHash};
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_update_and_query_active() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+
+            let open_id = NewLiveTrade::new("momentum".to_string(), Address::ZERO).insert(&tx)?;
+            LiveTradeUpdate::new(open_id, "open")
+                .with_tx_hash(TxHash::repeat_byte(1))
+                .with_open_trade_metadata(serde_json::json!({ "block_number": 1 }))
+                .update(&tx)?;
+            // Unset fields are left as they were
+            LiveTradeUpdate::new(open_id, "pending_close").update(&tx)?;
+
+            let closed_id =
+                NewLiveTrade::new("momentum".to_string(), Address::repeat_byte(2)).insert(&tx)?;
+            LiveTradeUpdate::new(closed_id, "closed").update(&tx)?;
+
+            NewLiveTrade::new("other".to_string(), Address::ZERO).insert(&tx)?;
+            tx.commit()?;
+        }
+
+        {
+            let mut conn = pool.get()?;
+            let tx = conn.transaction()?;
+            let live_trades = LiveTrade::query_active_by_strategy_name(&tx, "momentum")?;
+
+            assert_eq!(live_trades.len(), 1);
+            assert_eq!(live_trades[0].status, "pending_close");
+            assert_eq!(
+                live_trades[0].tx_hash.as_ref().map(|tx_hash| tx_hash.0),
+                Some(TxHash::repeat_byte(1))
+            );
+            assert_eq!(
+                live_trades[0].open_trade_metadata,
+                Some(serde_json::json!({ "block_number": 1 }))
+            );
+            assert!(live_trades[0].close_trade_metadata.is_none());
+            assert!(LiveTradeUpdate::new(100, "open").update(&tx).is_err());
+
+            LiveTrade::insert_tx_hash(&tx, live_trades[0].id, TxHash::repeat_byte(1))?;
+            LiveTrade::insert_tx_hash(&tx, live_trades[0].id, TxHash::repeat_byte(2))?;
+            LiveTrade::insert_tx_hash(&tx, live_trades[0].id, TxHash::repeat_byte(1))?;
+            assert_eq!(
+                LiveTrade::query_tx_hashes_by_id(&tx, live_trades[0].id)?
+                    .into_iter()
+                    .map(|tx_hash| tx_hash.0)
+                    .collect::<Vec<_>>(),
+                vec![TxHash::repeat_byte(1), TxHash::repeat_byte(2)]
+            );
+
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+-- Params: [strategy_name]
+SELECT
+  id,
+  strategy_name,
+  token_address,
+  status,
+  tx_hash,
+  open_trade_metadata,
+  close_trade_metadata,
+  error
+FROM live_trades
+WHERE
+  strategy_name = :strategy_name
+  AND status IN ('pending_open', 'open', 'pending_close')
+ORDER BY id ASC;
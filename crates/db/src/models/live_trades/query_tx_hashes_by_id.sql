@@ -0,0 +1,7 @@
+-- Params: [live_trade_id]
+SELECT
+  tx_hash
+FROM live_trade_tx_hashes
+WHERE
+  live_trade_id = :live_trade_id
+ORDER BY rowid ASC;
@@ -0,0 +1,10 @@
+-- Unset fields keep their current value
+UPDATE live_trades
+SET
+  status = :status,
+  tx_hash = COALESCE(:tx_hash, tx_hash),
+  open_trade_metadata = COALESCE(:open_trade_metadata, open_trade_metadata),
+  close_trade_metadata = COALESCE(:close_trade_metadata, close_trade_metadata),
+  error = COALESCE(:error, error)
+WHERE
+  id = :id;
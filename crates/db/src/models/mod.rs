@@ -1,7 +1,25 @@
-pub use backtest_closed_trades::{NewBacktestClosedTrade, BacktestClosedTrade};
-pub use backtests::{NewBacktest, Backtest};
+pub use backtest_closed_trades::{BacktestClosedTrade, NewBacktestClosedTrade};
+pub use backtest_metrics::BacktestMetrics;
+pub use backtest_open_trades::{BacktestOpenTrade, NewBacktestOpenTrade};
+pub use backtest_risk_rejections::{BacktestRiskRejection, NewBacktestRiskRejection};
+pub use backtest_strategies::BacktestStrategy;
+pub use backtests::{Backtest, NewBacktest};
+pub use block_chunks::BlockChunk;
 pub use blocks::Block;
+pub use live_trades::{LiveTrade, LiveTradeUpdate, NewLiveTrade};
+pub use time_price_bars::TimePriceBar;
+pub use walk_forward_windows::WalkForwardWindow;
+pub use walk_forwards::{NewWalkForward, WalkForward};
 
 mod backtest_closed_trades;
+mod backtest_metrics;
+mod backtest_open_trades;
+mod backtest_risk_rejections;
+mod backtest_strategies;
 mod backtests;
+mod block_chunks;
 mod blocks;
+mod live_trades;
+mod time_price_bars;
+mod walk_forward_windows;
+mod walk_forwards;
@@ -0,0 +1,207 @@
+use crate::primitives::{FixedBytes, U64};
+
+use alloy::primitives::{Address, BlockNumber};
+
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+#[derive(Debug)]
+pub struct TimePriceBar {
+    pub pair_address: FixedBytes<20>,
+    pub resolution: String,
+    pub timestamp: U64,
+    pub start_block_number: U64,
+    pub end_block_number: U64,
+    pub data: serde_json::Value,
+    pub indicators: Option<serde_json::Value>,
+}
+
+impl TimePriceBar {
+    pub fn new(
+        pair_address: Address,
+        resolution: String,
+        timestamp: u64,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+        data: serde_json::Value,
+        indicators: Option<serde_json::Value>,
+    ) -> Self {
+        Self {
+            pair_address: pair_address.into(),
+            resolution,
+            timestamp: timestamp.into(),
+            start_block_number: start_block_number.into(),
+            end_block_number: end_block_number.into(),
+            data,
+            indicators,
+        }
+    }
+
+    // Bars are derived deterministically from blocks, so re-finalizing the same
+    // bar (e.g. when re-running a backtest) replaces the existing row.
+    pub fn upsert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./upsert.sql"))?
+            .execute(named_params! {
+                ":pair_address": self.pair_address,
+                ":resolution": self.resolution,
+                ":timestamp": self.timestamp,
+                ":start_block_number": self.start_block_number,
+                ":end_block_number": self.end_block_number,
+                ":data": self.data,
+                ":indicators": self.indicators,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows upserted: {}", n))
+                }
+            })
+    }
+
+    pub fn query_by_pair_timestamp_range(
+        tx: &Transaction,
+        pair_address: Address,
+        resolution: &str,
+        min_timestamp: u64,
+        max_timestamp: u64,
+    ) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_pair_timestamp_range.sql"))?
+            .query(named_params! {
+                ":pair_address": FixedBytes::from(pair_address),
+                ":resolution": resolution,
+                ":min_timestamp": U64::from(min_timestamp),
+                ":max_timestamp": U64::from(max_timestamp),
+            })?
+            .map(|row| TimePriceBar::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+
+    pub fn query_by_timestamp_range(
+        tx: &Transaction,
+        resolution: &str,
+        min_timestamp: u64,
+        max_timestamp: u64,
+    ) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_timestamp_range.sql"))?
+            .query(named_params! {
+                ":resolution": resolution,
+                ":min_timestamp": U64::from(min_timestamp),
+                ":max_timestamp": U64::from(max_timestamp),
+            })?
+            .map(|row| TimePriceBar::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for TimePriceBar {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            pair_address: row.get(0)?,
+            resolution: row.get(1)?,
+            timestamp: row.get(2)?,
+            start_block_number: row.get(3)?,
+            end_block_number: row.get(4)?,
+            data: row.get(5)?,
+            indicators: row.get(6)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TimePriceBar;
+    use crate::connect as connect_db;
+
+    use alloy::primitives::{address, Address};
+    use eyre::Result;
+
+    #[test]
+    pub fn test_upsert() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+
+        TimePriceBar::new(
+            Address::ZERO,
+            String::from("5m"),
+            300,
+            1,
+            2,
+            serde_json::json!({ "foo": "bar" }),
+            None,
+        )
+        .upsert(&tx)?;
+        TimePriceBar::new(
+            Address::ZERO,
+            String::from("5m"),
+            300,
+            1,
+            3,
+            serde_json::json!({ "foo": "baz" }),
+            Some(serde_json::json!({ "ema": 1 })),
+        )
+        .upsert(&tx)?;
+
+        let time_price_bars =
+            TimePriceBar::query_by_pair_timestamp_range(&tx, Address::ZERO, "5m", 0, 600)?;
+        assert_eq!(time_price_bars.len(), 1);
+        assert_eq!(time_price_bars[0].end_block_number, 3.into());
+        assert!(time_price_bars[0].indicators.is_some());
+
+        tx.rollback()?;
+
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_query_by_timestamp_range() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+
+        let pair_address = address!("c1c52be5c93429be50f5518a582f690d0fc0528a");
+        for (pair_address, timestamp) in vec![
+            (Address::ZERO, 300),
+            (pair_address, 300),
+            (pair_address, 600),
+            (pair_address, 900),
+        ] {
+            TimePriceBar::new(
+                pair_address,
+                String::from("5m"),
+                timestamp,
+                timestamp,
+                timestamp,
+                serde_json::json!({}),
+                None,
+            )
+            .upsert(&tx)?;
+        }
+
+        assert_eq!(
+            TimePriceBar::query_by_timestamp_range(&tx, "5m", 300, 600)?.len(),
+            3
+        );
+        assert_eq!(
+            TimePriceBar::query_by_pair_timestamp_range(&tx, pair_address, "5m", 600, 900)?.len(),
+            2
+        );
+        assert_eq!(
+            TimePriceBar::query_by_timestamp_range(&tx, "1h", 0, 900)?.len(),
+            0
+        );
+
+        tx.rollback()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+-- Params: [pair_address, resolution, min_timestamp, max_timestamp]
+SELECT
+  pair_address,
+  resolution,
+  timestamp,
+  start_block_number,
+  end_block_number,
+  data,
+  indicators
+FROM time_price_bars
+WHERE
+  pair_address = :pair_address
+  AND resolution = :resolution
+  AND timestamp >= :min_timestamp
+  AND timestamp <= :max_timestamp
+ORDER BY timestamp ASC;
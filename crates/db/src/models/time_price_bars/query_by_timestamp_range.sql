@@ -0,0 +1,15 @@
+-- Params: [resolution, min_timestamp, max_timestamp]
+SELECT
+  pair_address,
+  resolution,
+  timestamp,
+  start_block_number,
+  end_block_number,
+  data,
+  indicators
+FROM time_price_bars
+WHERE
+  resolution = :resolution
+  AND timestamp >= :min_timestamp
+  AND timestamp <= :max_timestamp
+ORDER BY timestamp ASC;
@@ -0,0 +1,18 @@
+INSERT OR REPLACE INTO time_price_bars (
+  pair_address,
+  resolution,
+  timestamp,
+  start_block_number,
+  end_block_number,
+  data,
+  indicators
+)
+VALUES (
+  :pair_address,
+  :resolution,
+  :timestamp,
+  :start_block_number,
+  :end_block_number,
+  :data,
+  :indicators
+);
@@ -0,0 +1,158 @@
+use crate::primitives::U64;
+
+use alloy::primitives::BlockNumber;
+use eyre::Result;
+use fallible_iterator::FallibleIterator;
+use rusqlite::{named_params, Transaction};
+
+// A single in sample optimization and the out of sample run of the
+// parameters it chose.
+#[derive(Debug)]
+pub struct WalkForwardWindow {
+    pub walk_forward_id: i64,
+    pub window_index: i64,
+    pub in_sample_start_block_number: U64,
+    pub in_sample_end_block_number: U64,
+    pub out_of_sample_start_block_number: U64,
+    pub out_of_sample_end_block_number: U64,
+    pub parameters: serde_json::Value,
+    pub in_sample_backtest_id: i64,
+    pub out_of_sample_backtest_id: i64,
+}
+
+impl WalkForwardWindow {
+    pub fn new(
+        walk_forward_id: i64,
+        window_index: i64,
+        in_sample: (BlockNumber, BlockNumber),
+        out_of_sample: (BlockNumber, BlockNumber),
+        parameters: serde_json::Value,
+        in_sample_backtest_id: i64,
+        out_of_sample_backtest_id: i64,
+    ) -> Self {
+        Self {
+            walk_forward_id,
+            window_index,
+            in_sample_start_block_number: in_sample.0.into(),
+            in_sample_end_block_number: in_sample.1.into(),
+            out_of_sample_start_block_number: out_of_sample.0.into(),
+            out_of_sample_end_block_number: out_of_sample.1.into(),
+            parameters,
+            in_sample_backtest_id,
+            out_of_sample_backtest_id,
+        }
+    }
+
+    pub fn insert(self, tx: &Transaction) -> Result<()> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .execute(named_params! {
+                ":walk_forward_id": self.walk_forward_id,
+                ":window_index": self.window_index,
+                ":in_sample_start_block_number": self.in_sample_start_block_number,
+                ":in_sample_end_block_number": self.in_sample_end_block_number,
+                ":out_of_sample_start_block_number": self.out_of_sample_start_block_number,
+                ":out_of_sample_end_block_number": self.out_of_sample_end_block_number,
+                ":parameters": self.parameters,
+                ":in_sample_backtest_id": self.in_sample_backtest_id,
+                ":out_of_sample_backtest_id": self.out_of_sample_backtest_id,
+            })
+            .map_err(Into::into)
+            .and_then(|n| {
+                if n == 1 {
+                    Ok(())
+                } else {
+                    Err(eyre::eyre!("Unexpected number of rows inserted: {}", n))
+                }
+            })
+    }
+
+    pub fn query_by_walk_forward_id(tx: &Transaction, walk_forward_id: i64) -> Result<Vec<Self>> {
+        tx.prepare_cached(include_str!("./query_by_walk_forward_id.sql"))?
+            .query(named_params! {
+                ":walk_forward_id": walk_forward_id,
+            })?
+            .map(|row| WalkForwardWindow::try_from(row))
+            .collect()
+            .map_err(Into::into)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for WalkForwardWindow {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            walk_forward_id: row.get(0)?,
+            window_index: row.get(1)?,
+            in_sample_start_block_number: row.get(2)?,
+            in_sample_end_block_number: row.get(3)?,
+            out_of_sample_start_block_number: row.get(4)?,
+            out_of_sample_end_block_number: row.get(5)?,
+            parameters: row.get(6)?,
+            in_sample_backtest_id: row.get(7)?,
+            out_of_sample_backtest_id: row.get(8)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WalkForwardWindow;
+    use crate::{connect as connect_db, models::NewWalkForward};
+
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_and_query() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+        let mut conn = pool.get()?;
+
+        let walk_forward_id = {
+            let tx = conn.transaction()?;
+            let walk_forward_id =
+                NewWalkForward::new("momentum".to_string(), 1, 300, serde_json::json!([]))
+                    .insert(&tx)?;
+
+            // Inserted out of order to check ordering by window index
+            WalkForwardWindow::new(
+                walk_forward_id,
+                1,
+                (101, 200),
+                (201, 300),
+                serde_json::json!({ "ema_period": 13.0 }),
+                3,
+                4,
+            )
+            .insert(&tx)?;
+            WalkForwardWindow::new(
+                walk_forward_id,
+                0,
+                (1, 100),
+                (101, 200),
+                serde_json::json!({ "ema_period": 9.0 }),
+                1,
+                2,
+            )
+            .insert(&tx)?;
+            tx.commit()?;
+            walk_forward_id
+        };
+
+        {
+            let tx = conn.transaction()?;
+            let windows = WalkForwardWindow::query_by_walk_forward_id(&tx, walk_forward_id)?;
+            assert_eq!(windows.len(), 2);
+            assert_eq!(windows[0].window_index, 0);
+            assert_eq!(windows[0].out_of_sample_start_block_number.0, 101);
+            assert_eq!(windows[0].parameters["ema_period"], 9.0);
+            assert_eq!(windows[1].window_index, 1);
+            assert_eq!(windows[1].out_of_sample_backtest_id, 4);
+            assert!(
+                WalkForwardWindow::query_by_walk_forward_id(&tx, walk_forward_id + 1)?.is_empty()
+            );
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,13 @@
+SELECT
+  walk_forward_id,
+  window_index,
+  in_sample_start_block_number,
+  in_sample_end_block_number,
+  out_of_sample_start_block_number,
+  out_of_sample_end_block_number,
+  parameters,
+  in_sample_backtest_id,
+  out_of_sample_backtest_id
+FROM walk_forward_windows
+WHERE walk_forward_id = :walk_forward_id
+ORDER BY window_index ASC
@@ -0,0 +1,4 @@
+INSERT INTO walk_forwards
+  (created_at, strategy_name, start_block_number, end_block_number, equity_curve)
+VALUES
+  (:created_at, :strategy_name, :start_block_number, :end_block_number, :equity_curve);
@@ -0,0 +1,123 @@
+use crate::primitives::U64;
+
+use alloy::primitives::BlockNumber;
+use eyre::Result;
+use rusqlite::{named_params, OptionalExtension, Transaction};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug)]
+pub struct WalkForward {
+    pub id: i64,
+    pub created_at: U64,
+    pub strategy_name: String,
+    pub start_block_number: U64,
+    pub end_block_number: U64,
+    pub equity_curve: serde_json::Value,
+}
+
+impl WalkForward {
+    pub fn query_by_id(tx: &Transaction, id: i64) -> Result<Option<Self>> {
+        tx.prepare_cached(include_str!("./query_by_id.sql"))?
+            .query_row(named_params! { ":id": id }, |row| {
+                WalkForward::try_from(row)
+            })
+            .optional()
+            .map_err(Into::into)
+    }
+}
+
+impl<'stmt> TryFrom<&rusqlite::Row<'stmt>> for WalkForward {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &rusqlite::Row<'stmt>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            created_at: row.get(1)?,
+            strategy_name: row.get(2)?,
+            start_block_number: row.get(3)?,
+            end_block_number: row.get(4)?,
+            equity_curve: row.get(5)?,
+        })
+    }
+}
+
+pub struct NewWalkForward {
+    pub created_at: U64,
+    pub strategy_name: String,
+    pub start_block_number: U64,
+    pub end_block_number: U64,
+    pub equity_curve: serde_json::Value,
+}
+
+impl NewWalkForward {
+    pub fn new(
+        strategy_name: String,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+        equity_curve: serde_json::Value,
+    ) -> Self {
+        Self {
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .into(),
+            strategy_name,
+            start_block_number: start_block_number.into(),
+            end_block_number: end_block_number.into(),
+            equity_curve,
+        }
+    }
+
+    pub fn insert(self, tx: &Transaction) -> Result<i64> {
+        tx.prepare_cached(include_str!("./insert.sql"))?
+            .insert(named_params! {
+                ":created_at": self.created_at,
+                ":strategy_name": self.strategy_name,
+                ":start_block_number": self.start_block_number,
+                ":end_block_number": self.end_block_number,
+                ":equity_curve": self.equity_curve,
+            })
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NewWalkForward, WalkForward};
+    use crate::connect as connect_db;
+
+    use eyre::Result;
+
+    #[test]
+    pub fn test_insert_and_query() -> Result<()> {
+        let pool = connect_db(&String::from(":memory:"))?;
+        let mut conn = pool.get()?;
+
+        let id = {
+            let tx = conn.transaction()?;
+            let id = NewWalkForward::new(
+                "momentum".to_string(),
+                100,
+                200,
+                serde_json::json!([{ "block_timestamp": 10, "pnl": "-5" }]),
+            )
+            .insert(&tx)?;
+            tx.commit()?;
+            id
+        };
+
+        {
+            let tx = conn.transaction()?;
+            let walk_forward = WalkForward::query_by_id(&tx, id)?.expect("expected walk forward");
+            assert_eq!(walk_forward.strategy_name, "momentum");
+            assert_eq!(walk_forward.start_block_number.0, 100);
+            assert_eq!(walk_forward.end_block_number.0, 200);
+            assert_eq!(walk_forward.equity_curve[0]["pnl"], "-5");
+            assert!(WalkForward::query_by_id(&tx, id + 1)?.is_none());
+            tx.rollback()?;
+        }
+
+        Ok(())
+    }
+}
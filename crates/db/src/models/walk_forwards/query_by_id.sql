@@ -0,0 +1,9 @@
+SELECT
+  id,
+  created_at,
+  strategy_name,
+  start_block_number,
+  end_block_number,
+  equity_curve
+FROM walk_forwards
+WHERE id = :id
@@ -1,5 +1,5 @@
-pub use u64::U64;
 pub use fixed_bytes::FixedBytes;
+pub use u64::U64;
 
-mod u64;
 mod fixed_bytes;
+mod u64;
@@ -1,6 +1,5 @@
-pub use backtest_pair::BacktestPair;
 pub use backtest_block_range::BacktestBlockRange;
+pub use backtest_pair::BacktestPair;
 
-mod backtest_pair;
 mod backtest_block_range;
-
+mod backtest_pair;
@@ -0,0 +1,78 @@
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes, TxKind, U256},
+    rpc::types::eth::TransactionRequest,
+    sol,
+    sol_types::SolCall,
+};
+
+// Our executor contract, which swaps eth directly against a pair without
+// going through the dex's router. Each swap reverts past its deadline or
+// below its min out, and sells pull the tokens from the caller.
+// Source and deploy script are in contracts/, keep the two in sync.
+sol! {
+    interface IExecutor {
+        function buyV2(
+            address pair,
+            address token,
+            uint256 amountOutMin,
+            address recipient,
+            uint256 deadline
+        ) external payable returns (uint256 amountOut);
+
+        function sellV2(
+            address pair,
+            address token,
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address recipient,
+            uint256 deadline
+        ) external returns (uint256 amountOut);
+
+        function buyV3(
+            address pool,
+            address token,
+            uint256 amountOutMin,
+            address recipient,
+            uint256 deadline
+        ) external payable returns (uint256 amountOut);
+
+        function sellV3(
+            address pool,
+            address token,
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address recipient,
+            uint256 deadline
+        ) external returns (uint256 amountOut);
+
+        /// @notice Executes each call against this contract, reverting if any does
+        function multicall(bytes[] calldata data) external payable returns (bytes[] memory results);
+    }
+}
+
+pub fn call_tx_request<C: SolCall>(
+    signer_address: Address,
+    executor_address: Address,
+    call: C,
+    value: U256,
+) -> TransactionRequest {
+    TransactionRequest::default()
+        .with_from(signer_address)
+        .with_to(Into::<TxKind>::into(executor_address))
+        .with_value(value)
+        .with_input(call.abi_encode().into())
+}
+
+pub fn multicall_tx_request(
+    signer_address: Address,
+    executor_address: Address,
+    data: Vec<Bytes>,
+) -> TransactionRequest {
+    call_tx_request(
+        signer_address,
+        executor_address,
+        IExecutor::multicallCall { data },
+        U256::ZERO,
+    )
+}
@@ -1,2 +1,3 @@
 pub mod erc20;
+pub mod executor;
 pub mod multicall3;
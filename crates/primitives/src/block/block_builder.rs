@@ -19,12 +19,8 @@ pub struct BlockBuilder {
 }
 
 impl BlockBuilder {
-    pub fn new(block_number: BlockNumber, default_block_timestamp: u64, logs: &Vec<Log>) -> Self {
+    pub fn new(block_number: BlockNumber, block_timestamp: u64, logs: &Vec<Log>) -> Self {
         let block_hash = logs.iter().find_map(|l| l.block_hash);
-        let block_timestamp = logs
-            .iter()
-            .find_map(|l| l.block_timestamp)
-            .unwrap_or(default_block_timestamp);
         let indexed_trades = IndexedTrade::from_logs(&logs);
 
         Self {
@@ -97,7 +93,7 @@ mod tests {
     use super::BlockBuilder;
     use crate::{
         constants, new_http_signer_provider, DexIndexedTrade, IndexedTrade, PairBlockTick,
-        TickData, UniswapV2IndexedTrade, UniswapV2Pair, UniswapV2PairBlockTick,
+        SignerSource, TickData, UniswapV2IndexedTrade, UniswapV2Pair, UniswapV2PairBlockTick,
     };
 
     use alloy::{
@@ -108,7 +104,7 @@ mod tests {
     use hex_literal::hex;
     use eyre::Result;
     use num_bigint::BigUint;
-    use std::sync::Arc;
+    use std::{collections::BTreeSet, sync::Arc};
 
     #[tokio::test]
     async fn test_build_many() -> Result<()> {
@@ -117,7 +113,9 @@ mod tests {
                 url::Url::parse(
                     "https://base-mainnet.g.alchemy.com/v2/GHF2kp-FpiiuNzmfpdP_dnms5WkewVQ-",
                 )?,
-                &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                &SignerSource::PrivateKey(
+                    hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+                ),
                 None,
                 true,
             )
@@ -181,13 +179,23 @@ mod tests {
                 ],
                 reserve0: uint!(24241863659908185248_U256),
                 reserve1: uint!(43340478928260732_U256),
-                tick: TickData::new(
-                    expected_trades[0].token_price_before(&token_address),
-                    expected_trades[0].token_price_before(&token_address),
-                    expected_trades[0].token_price_after(&token_address),
-                    expected_trades[1].token_price_after(&token_address),
-                    BigUint::from(117594173315701195_u128)
-                )
+                tick: TickData {
+                    buy_weth_volume: BigUint::from(7500000000000000_u128),
+                    sell_weth_volume: BigUint::from(110094173315701195_u128),
+                    trade_count: 2,
+                    unique_maker_count: 2,
+                    makers: BTreeSet::from([
+                        address!("1Fba6b0BBae2B74586fBA407Fb45Bd4788B7b130"),
+                        address!("7381C38985dA304eBA18fCef5E1f6e9fA0798b84")
+                    ]),
+                    ..TickData::new(
+                        expected_trades[0].token_price_before(&token_address),
+                        expected_trades[0].token_price_before(&token_address),
+                        expected_trades[0].token_price_after(&token_address),
+                        expected_trades[1].token_price_after(&token_address),
+                        BigUint::from(117594173315701195_u128)
+                    )
+                }
             })
         );
 
@@ -2,6 +2,7 @@ use super::{Block, Pair};
 
 use alloy::primitives::BlockNumber;
 
+#[derive(Clone)]
 pub struct BlockMessage {
     pub block_number: BlockNumber,
     pub block_timestamp: u64,
@@ -1,6 +1,9 @@
 use alloy::primitives::{address, uint, Address, U256};
 
 pub const MAX_TRADE_SIZE_WEI: U256 = uint!(1000000000000000000_U256);
+// Opens are sized down so as to move the pair's price by at most this much
+pub const MAX_TRADE_SIZE_PRICE_IMPACT_BP: U256 = uint!(50_U256);
+pub const BP_FACTOR: U256 = uint!(10000_U256);
 
 // TODO: These are chain dependent, but assume Base for now
 pub const AVERAGE_BLOCK_TIME_SECONDS: u64 = 2;
@@ -15,6 +15,12 @@ pub trait DexIndexedTrade {
     fn token_price_after(&self, token_address: &Address) -> U32F96;
     // get the total weth volume of the trade
     fn weth_volume(&self, token_address: &Address) -> U256;
+    // get the total token volume of the trade
+    fn token_volume(&self, token_address: &Address) -> U256;
+    // whether weth was swapped in for the token
+    fn is_buy(&self, token_address: &Address) -> bool;
+    // get the address that initiated the trade
+    fn maker(&self) -> &Address;
     // get the pair address
     fn pair_address(&self) -> &Address;
     // get the event signature hashes required for parsing the trade
@@ -64,6 +70,27 @@ impl IndexedTrade {
         }
     }
 
+    pub fn token_volume(&self, token_address: &Address) -> U256 {
+        match self {
+            IndexedTrade::UniswapV2(trade) => trade.token_volume(token_address),
+            IndexedTrade::UniswapV3(trade) => trade.token_volume(token_address),
+        }
+    }
+
+    pub fn is_buy(&self, token_address: &Address) -> bool {
+        match self {
+            IndexedTrade::UniswapV2(trade) => trade.is_buy(token_address),
+            IndexedTrade::UniswapV3(trade) => trade.is_buy(token_address),
+        }
+    }
+
+    pub fn maker(&self) -> &Address {
+        match self {
+            IndexedTrade::UniswapV2(trade) => trade.maker(),
+            IndexedTrade::UniswapV3(trade) => trade.maker(),
+        }
+    }
+
     pub fn token_price_before(&self, token_address: &Address) -> U32F96 {
         match self {
             IndexedTrade::UniswapV2(trade) => trade.token_price_before(token_address),
@@ -9,6 +9,7 @@ pub use uniswap_v3::{
     UniswapV3IndexedTrade, UniswapV3Pair, UniswapV3PairBlockTick, UniswapV3PairInput,
 };
 pub use trade_request_op::TradeRequestOp;
+pub use trade_request_params::{QuotedTransactionRequest, TradeRequestParams};
 
 // dex providers
 mod uniswap_v2;
@@ -19,3 +20,4 @@ mod indexed_trade;
 mod pair;
 mod pair_block_tick;
 mod trade_request_op;
+mod trade_request_params;
@@ -1,6 +1,6 @@
 use super::{
-    IndexedTrade, TradeRequestOp, UniswapV2Pair, UniswapV2PairInput, UniswapV3Pair,
-    UniswapV3PairInput,
+    IndexedTrade, QuotedTransactionRequest, TradeRequestOp, TradeRequestParams, UniswapV2Pair,
+    UniswapV2PairInput, UniswapV3Pair, UniswapV3PairInput,
 };
 
 use crate::{abi::multicall3, RpcProvider};
@@ -9,7 +9,6 @@ use alloy::{
     network::Ethereum,
     primitives::{Address, BlockNumber, U256},
     providers::Provider,
-    rpc::types::eth::TransactionRequest,
     transports::Transport,
 };
 
@@ -82,11 +81,12 @@ where
 {
     fn address(&self) -> &Address;
     fn token_address(&self) -> &Address;
-    fn estimate_trade_gas(&self) -> U256;
+    fn estimate_trade_gas(&self, params: &TradeRequestParams) -> U256;
 
     fn simulate_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> impl std::future::Future<Output = Result<I>> + Send
@@ -97,6 +97,7 @@ where
     fn trace_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> impl std::future::Future<Output = Result<()>> + Send
@@ -107,10 +108,11 @@ where
     fn make_trade_transaction_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         block_timestamp: u64,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> impl std::future::Future<Output = Result<TransactionRequest>> + Send
+    ) -> impl std::future::Future<Output = Result<QuotedTransactionRequest>> + Send
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static;
@@ -137,16 +139,17 @@ impl Pair {
         }
     }
 
-    pub fn estimate_trade_gas(&self) -> U256 {
+    pub fn estimate_trade_gas(&self, params: &TradeRequestParams) -> U256 {
         match self {
-            Self::UniswapV2(pair) => pair.estimate_trade_gas(),
-            Self::UniswapV3(pair) => pair.estimate_trade_gas(),
+            Self::UniswapV2(pair) => pair.estimate_trade_gas(params),
+            Self::UniswapV3(pair) => pair.estimate_trade_gas(params),
         }
     }
 
     pub async fn simulate_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> Result<IndexedTrade>
@@ -156,11 +159,11 @@ impl Pair {
     {
         match self {
             Self::UniswapV2(pair) => pair
-                .simulate_trade_request(op, block_number, rpc_provider)
+                .simulate_trade_request(op, params, block_number, rpc_provider)
                 .await
                 .map(Into::into),
             Self::UniswapV3(pair) => pair
-                .simulate_trade_request(op, block_number, rpc_provider)
+                .simulate_trade_request(op, params, block_number, rpc_provider)
                 .await
                 .map(Into::into),
         }
@@ -169,6 +172,7 @@ impl Pair {
     pub async fn trace_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> Result<()>
@@ -178,11 +182,11 @@ impl Pair {
     {
         match self {
             Self::UniswapV2(pair) => {
-                pair.trace_trade_request(op, block_number, rpc_provider)
+                pair.trace_trade_request(op, params, block_number, rpc_provider)
                     .await
             }
             Self::UniswapV3(pair) => {
-                pair.trace_trade_request(op, block_number, rpc_provider)
+                pair.trace_trade_request(op, params, block_number, rpc_provider)
                     .await
             }
         }
@@ -191,22 +195,35 @@ impl Pair {
     pub async fn make_trade_transaction_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         block_timestamp: u64,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<TransactionRequest>
+    ) -> Result<QuotedTransactionRequest>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
     {
         match self {
             Self::UniswapV2(pair) => {
-                pair.make_trade_transaction_request(op, block_number, block_timestamp, rpc_provider)
-                    .await
+                pair.make_trade_transaction_request(
+                    op,
+                    params,
+                    block_number,
+                    block_timestamp,
+                    rpc_provider,
+                )
+                .await
             }
             Self::UniswapV3(pair) => {
-                pair.make_trade_transaction_request(op, block_number, block_timestamp, rpc_provider)
-                    .await
+                pair.make_trade_transaction_request(
+                    op,
+                    params,
+                    block_number,
+                    block_timestamp,
+                    rpc_provider,
+                )
+                .await
             }
         }
     }
@@ -0,0 +1,267 @@
+use crate::{abi::executor, constants};
+
+use alloy::{
+    primitives::{Address, TxKind, U256},
+    rpc::types::eth::TransactionRequest,
+};
+
+use eyre::{eyre, Result};
+
+// Caller chosen parameters of a trade request, independent of the dex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TradeRequestParams {
+    // Weth to spend opening a position, None for the max trade size
+    pub weth_amount_in: Option<U256>,
+    // Tokens to sell closing a position, None for all the open bought
+    pub token_amount_in: Option<U256>,
+    // Shortfall of the output from its quote tolerated before reverting
+    pub slippage_bps: u64,
+    // Seconds past the request's block timestamp the swap remains valid
+    pub deadline_seconds: u64,
+    // Executor contract swapping directly against the pair, or None to go
+    // through the dex's router
+    pub executor_address: Option<Address>,
+}
+
+impl Default for TradeRequestParams {
+    fn default() -> Self {
+        Self {
+            weth_amount_in: None,
+            token_amount_in: None,
+            slippage_bps: 0,
+            deadline_seconds: constants::AVERAGE_BLOCK_TIME_SECONDS * 2,
+            executor_address: None,
+        }
+    }
+}
+
+impl TradeRequestParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_weth_amount_in(mut self, weth_amount_in: U256) -> Self {
+        self.weth_amount_in = Some(weth_amount_in);
+        self
+    }
+
+    pub fn with_token_amount_in(mut self, token_amount_in: U256) -> Self {
+        self.token_amount_in = Some(token_amount_in);
+        self
+    }
+
+    pub fn with_slippage_bps(mut self, slippage_bps: u64) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    pub fn with_deadline_seconds(mut self, deadline_seconds: u64) -> Self {
+        self.deadline_seconds = deadline_seconds;
+        self
+    }
+
+    pub fn with_executor_address(mut self, executor_address: Address) -> Self {
+        self.executor_address = Some(executor_address);
+        self
+    }
+
+    // Weth to spend opening a position, never more than the max trade size.
+    // Pairs may reduce it further to limit price impact.
+    pub fn open_weth_amount_in(&self) -> U256 {
+        self.weth_amount_in
+            .unwrap_or(constants::MAX_TRADE_SIZE_WEI)
+            .min(constants::MAX_TRADE_SIZE_WEI)
+    }
+
+    // Tokens to sell closing a position whose open bought the given amount
+    pub fn close_token_amount_in(&self, open_token_amount_out: U256) -> U256 {
+        self.token_amount_in.unwrap_or(open_token_amount_out)
+    }
+
+    // The least output accepted for a quote
+    pub fn min_amount_out(&self, quoted_amount_out: U256) -> U256 {
+        let slippage_bps = U256::from(self.slippage_bps).min(constants::BP_FACTOR);
+        quoted_amount_out * (constants::BP_FACTOR - slippage_bps) / constants::BP_FACTOR
+    }
+
+    pub fn deadline(&self, block_timestamp: u64) -> U256 {
+        U256::from(block_timestamp + self.deadline_seconds)
+    }
+}
+
+// A trade transaction along with the output it was quoted at
+#[derive(Debug, Clone)]
+pub struct QuotedTransactionRequest {
+    pub tx_request: TransactionRequest,
+    pub quoted_amount_out: U256,
+    // Approval of the tokens a close sells to the router or executor, to be
+    // sent ahead of the swap where the two can land atomically
+    pub approval: Option<TransactionRequest>,
+}
+
+impl QuotedTransactionRequest {
+    pub fn new(tx_request: TransactionRequest, quoted_amount_out: U256) -> Self {
+        Self {
+            tx_request,
+            quoted_amount_out,
+            approval: None,
+        }
+    }
+
+    pub fn with_approval(mut self, approval: TransactionRequest) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    // Combines closes built against the same executor into a single tx,
+    // quoted at their total output. Each close keeps its own min out and
+    // deadline. Their approvals are dropped, so the executor must already be
+    // allowed to pull each token.
+    pub fn batch_exits(exits: Vec<Self>) -> Result<Self> {
+        let first = exits.first().ok_or_else(|| eyre!("No exits to batch"))?;
+        let (signer_address, executor_address) = match (first.tx_request.from, first.tx_request.to)
+        {
+            (Some(signer_address), Some(TxKind::Call(executor_address))) => {
+                (signer_address, executor_address)
+            }
+            _ => return Err(eyre!("Exit is missing its sender or executor")),
+        };
+
+        let mut data = Vec::with_capacity(exits.len());
+        let mut quoted_amount_out = U256::ZERO;
+        for exit in exits.iter() {
+            if exit.tx_request.from != Some(signer_address)
+                || exit.tx_request.to != Some(TxKind::Call(executor_address))
+            {
+                return Err(eyre!(
+                    "Exits are not all sent to executor {}",
+                    executor_address
+                ));
+            }
+            if exit.tx_request.value.is_some_and(|value| !value.is_zero()) {
+                return Err(eyre!("Exits can't send value"));
+            }
+
+            data.push(
+                exit.tx_request
+                    .input
+                    .input()
+                    .cloned()
+                    .ok_or_else(|| eyre!("Exit is missing its input"))?,
+            );
+            quoted_amount_out += exit.quoted_amount_out;
+        }
+
+        Ok(Self::new(
+            executor::multicall_tx_request(signer_address, executor_address, data),
+            quoted_amount_out,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{QuotedTransactionRequest, TradeRequestParams};
+    use crate::{abi::executor, constants};
+
+    use alloy::{
+        primitives::{Address, TxKind, U256},
+        sol_types::SolCall,
+    };
+    use eyre::Result;
+
+    fn make_exit(executor_address: Address, amount_out: u64) -> QuotedTransactionRequest {
+        QuotedTransactionRequest::new(
+            executor::call_tx_request(
+                Address::with_last_byte(1),
+                executor_address,
+                executor::IExecutor::sellV2Call {
+                    pair: Address::with_last_byte(2),
+                    token: Address::with_last_byte(3),
+                    amountIn: U256::from(100),
+                    amountOutMin: U256::from(amount_out),
+                    recipient: Address::with_last_byte(1),
+                    deadline: U256::from(1000),
+                },
+                U256::ZERO,
+            ),
+            U256::from(amount_out),
+        )
+    }
+
+    #[test]
+    fn test_open_weth_amount_in() {
+        assert_eq!(
+            TradeRequestParams::new().open_weth_amount_in(),
+            constants::MAX_TRADE_SIZE_WEI
+        );
+        assert_eq!(
+            TradeRequestParams::new()
+                .with_weth_amount_in(U256::from(100))
+                .open_weth_amount_in(),
+            U256::from(100)
+        );
+        assert_eq!(
+            TradeRequestParams::new()
+                .with_weth_amount_in(constants::MAX_TRADE_SIZE_WEI * U256::from(2))
+                .open_weth_amount_in(),
+            constants::MAX_TRADE_SIZE_WEI
+        );
+    }
+
+    #[test]
+    fn test_min_amount_out() {
+        let quoted_amount_out = U256::from(10_000);
+
+        assert_eq!(
+            TradeRequestParams::new().min_amount_out(quoted_amount_out),
+            quoted_amount_out
+        );
+        assert_eq!(
+            TradeRequestParams::new()
+                .with_slippage_bps(50)
+                .min_amount_out(quoted_amount_out),
+            U256::from(9_950)
+        );
+        assert_eq!(
+            TradeRequestParams::new()
+                .with_slippage_bps(20_000)
+                .min_amount_out(quoted_amount_out),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn test_batch_exits() -> Result<()> {
+        let executor_address = Address::with_last_byte(9);
+        let exits = vec![
+            make_exit(executor_address, 10),
+            make_exit(executor_address, 20),
+        ];
+        let exit_inputs = exits
+            .iter()
+            .map(|exit| exit.tx_request.input.input().cloned().unwrap())
+            .collect::<Vec<_>>();
+
+        let batch = QuotedTransactionRequest::batch_exits(exits)?;
+        assert_eq!(batch.quoted_amount_out, U256::from(30));
+        assert_eq!(batch.tx_request.to, Some(TxKind::Call(executor_address)));
+        assert!(batch.approval.is_none());
+
+        let call = executor::IExecutor::multicallCall::abi_decode(
+            batch.tx_request.input.input().unwrap(),
+            true,
+        )?;
+        assert_eq!(call.data, exit_inputs);
+
+        // Exits against different executors can't be batched
+        assert!(QuotedTransactionRequest::batch_exits(vec![
+            make_exit(executor_address, 10),
+            make_exit(Address::with_last_byte(8), 20),
+        ])
+        .is_err());
+        assert!(QuotedTransactionRequest::batch_exits(vec![]).is_err());
+
+        Ok(())
+    }
+}
@@ -72,6 +72,28 @@ impl DexIndexedTrade for UniswapV2IndexedTrade {
         }
     }
 
+    fn token_volume(&self, token_address: &Address) -> U256 {
+        if *token_address < constants::WETH_ADDRESS {
+            // token0 is token
+            self.amount0_in + self.amount0_out
+        } else {
+            // token1 is token
+            self.amount1_in + self.amount1_out
+        }
+    }
+
+    fn is_buy(&self, token_address: &Address) -> bool {
+        if *token_address < constants::WETH_ADDRESS {
+            self.amount1_in > U256::ZERO
+        } else {
+            self.amount0_in > U256::ZERO
+        }
+    }
+
+    fn maker(&self) -> &Address {
+        &self.maker
+    }
+
     fn token_price_before(&self, token_address: &Address) -> U32F96 {
         let reserve0_before = self.reserve0 - self.amount0_in + self.amount0_out;
         let reserve1_before = self.reserve1 - self.amount1_in + self.amount1_out;
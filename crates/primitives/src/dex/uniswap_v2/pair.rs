@@ -1,14 +1,20 @@
 use super::{
-    super::{DexPair, DexPairInput, IndexedTrade, TradeRequestOp},
+    super::{
+        DexPair, DexPairInput, IndexedTrade, QuotedTransactionRequest, TradeRequestOp,
+        TradeRequestParams,
+    },
     abi, UniswapV2IndexedTrade,
 };
-use crate::{abi::multicall3, constants, RpcProvider};
+use crate::{
+    abi::{erc20, executor, multicall3},
+    constants, RpcProvider,
+};
 
 use alloy::{
     network::Ethereum,
     primitives::{uint, Address, BlockNumber, U256},
     providers::Provider,
-    rpc::types::eth::{BlockId, TransactionRequest},
+    rpc::types::eth::BlockId,
     sol_types::SolCall,
     transports::Transport,
 };
@@ -107,16 +113,10 @@ pub struct UniswapV2Pair {
     token1: Address,
 }
 
-const BP_FACTOR: U256 = uint!(10000_U256);
-const MAX_TRADE_SIZE_PRICE_IMPACT_BP: U256 = uint!(50_U256);
-
-fn get_eth_amount_in(weth_reserve: U256) -> U256 {
-    let max_for_price_impact = (MAX_TRADE_SIZE_PRICE_IMPACT_BP * weth_reserve) / BP_FACTOR;
-    if max_for_price_impact < constants::MAX_TRADE_SIZE_WEI {
-        max_for_price_impact
-    } else {
-        constants::MAX_TRADE_SIZE_WEI
-    }
+fn get_eth_amount_in(weth_reserve: U256, params: &TradeRequestParams) -> U256 {
+    let max_for_price_impact =
+        (constants::MAX_TRADE_SIZE_PRICE_IMPACT_BP * weth_reserve) / constants::BP_FACTOR;
+    params.open_weth_amount_in().min(max_for_price_impact)
 }
 
 impl UniswapV2Pair {
@@ -178,13 +178,18 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
         &self.address
     }
 
-    fn estimate_trade_gas(&self) -> U256 {
-        uint!(130000_U256)
+    fn estimate_trade_gas(&self, params: &TradeRequestParams) -> U256 {
+        // The executor saves the router's path handling and transfers
+        match params.executor_address {
+            Some(_) => uint!(95000_U256),
+            None => uint!(130000_U256),
+        }
     }
 
     async fn simulate_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> Result<UniswapV2IndexedTrade>
@@ -197,7 +202,7 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
             .await?;
         let (amount0_in, amount1_in, amount0_out, amount1_out, reserve0, reserve1) = match op {
             TradeRequestOp::Open => {
-                let eth_amount_in = get_eth_amount_in(weth_reserve);
+                let eth_amount_in = get_eth_amount_in(weth_reserve, params);
                 let output_token_amount_min = abi::uniswap_v2_router::get_amount_out(
                     eth_amount_in,
                     weth_reserve,
@@ -228,7 +233,8 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
                 ..
             } => {
                 if self.token0 == constants::WETH_ADDRESS {
-                    let open_trade_token_amount_out = trade.amount1_out;
+                    let open_trade_token_amount_out =
+                        params.close_token_amount_in(trade.amount1_out);
                     let min_eth_amount_out = abi::uniswap_v2_router::get_amount_out(
                         open_trade_token_amount_out, // token amount received from trade
                         token_reserve,
@@ -244,7 +250,8 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
                         token_reserve + open_trade_token_amount_out,
                     )
                 } else {
-                    let open_trade_token_amount_out = trade.amount0_out;
+                    let open_trade_token_amount_out =
+                        params.close_token_amount_in(trade.amount0_out);
                     let close_min_eth_amount_out = abi::uniswap_v2_router::get_amount_out(
                         open_trade_token_amount_out,
                         token_reserve,
@@ -281,10 +288,11 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
     async fn make_trade_transaction_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         block_timestamp: u64,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<TransactionRequest>
+    ) -> Result<QuotedTransactionRequest>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
@@ -295,44 +303,89 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
 
         match op {
             TradeRequestOp::Open => {
-                let eth_amount_in = get_eth_amount_in(weth_reserve);
-                let output_token_amount_min = abi::uniswap_v2_router::get_amount_out(
+                let eth_amount_in = get_eth_amount_in(weth_reserve, params);
+                let token_amount_out = abi::uniswap_v2_router::get_amount_out(
                     eth_amount_in,
                     weth_reserve,
                     token_reserve,
                 );
-                Ok(
-                    abi::uniswap_v2_router::swap_exact_eth_for_tokens_tx_request(
+                let tx_request = match params.executor_address {
+                    Some(executor_address) => executor::call_tx_request(
                         *rpc_provider.signer_address(),
+                        executor_address,
+                        executor::IExecutor::buyV2Call {
+                            pair: self.address,
+                            token: *self.token_address(),
+                            amountOutMin: params.min_amount_out(token_amount_out),
+                            recipient: *rpc_provider.signer_address(),
+                            deadline: params.deadline(block_timestamp),
+                        },
                         eth_amount_in,
-                        output_token_amount_min,
+                    ),
+                    None => abi::uniswap_v2_router::swap_exact_eth_for_tokens_tx_request(
+                        *rpc_provider.signer_address(),
+                        eth_amount_in,
+                        params.min_amount_out(token_amount_out),
                         *self.token_address(),
-                        U256::from(block_timestamp + (constants::AVERAGE_BLOCK_TIME_SECONDS * 2)),
+                        params.deadline(block_timestamp),
                     ),
-                )
+                };
+
+                Ok(QuotedTransactionRequest::new(tx_request, token_amount_out))
             }
             TradeRequestOp::Close {
                 open_trade: IndexedTrade::UniswapV2(trade),
                 ..
             } => {
-                let open_trade_token_amount_out = if self.token0 == constants::WETH_ADDRESS {
-                    trade.amount1_out
-                } else {
-                    trade.amount0_out
-                };
-                let min_eth_amount_out = abi::uniswap_v2_router::get_amount_out(
+                let open_trade_token_amount_out =
+                    params.close_token_amount_in(if self.token0 == constants::WETH_ADDRESS {
+                        trade.amount1_out
+                    } else {
+                        trade.amount0_out
+                    });
+                let eth_amount_out = abi::uniswap_v2_router::get_amount_out(
                     open_trade_token_amount_out,
                     token_reserve,
                     weth_reserve,
                 );
 
+                let (tx_request, spender) = match params.executor_address {
+                    Some(executor_address) => (
+                        executor::call_tx_request(
+                            *rpc_provider.signer_address(),
+                            executor_address,
+                            executor::IExecutor::sellV2Call {
+                                pair: self.address,
+                                token: *self.token_address(),
+                                amountIn: open_trade_token_amount_out,
+                                amountOutMin: params.min_amount_out(eth_amount_out),
+                                recipient: *rpc_provider.signer_address(),
+                                deadline: params.deadline(block_timestamp),
+                            },
+                            U256::ZERO,
+                        ),
+                        executor_address,
+                    ),
+                    None => (
+                        abi::uniswap_v2_router::swap_exact_tokens_for_eth_tx_request(
+                            *rpc_provider.signer_address(),
+                            open_trade_token_amount_out,
+                            params.min_amount_out(eth_amount_out),
+                            *self.token_address(),
+                            params.deadline(block_timestamp),
+                        ),
+                        constants::UNISWAP_V2_ROUTER_02_ADDRESS,
+                    ),
+                };
+
                 Ok(
-                    abi::uniswap_v2_router::swap_exact_tokens_for_eth_tx_request(
-                        *rpc_provider.signer_address(),
-                        open_trade_token_amount_out,
-                        min_eth_amount_out,
-                        *self.token_address(),
-                        U256::from(block_timestamp + (constants::AVERAGE_BLOCK_TIME_SECONDS * 2)),
+                    QuotedTransactionRequest::new(tx_request, eth_amount_out).with_approval(
+                        erc20::approve_tx_request(
+                            *rpc_provider.signer_address(),
+                            *self.token_address(),
+                            spender,
+                            open_trade_token_amount_out,
+                        ),
                     ),
                 )
             }
@@ -346,6 +399,7 @@ impl DexPair<UniswapV2IndexedTrade> for UniswapV2Pair {
     async fn trace_trade_request<T, P>(
         &self,
         _op: &TradeRequestOp,
+        _params: &TradeRequestParams,
         _block_number: BlockNumber,
         _rpc_provider: &RpcProvider<T, P>,
     ) -> Result<()>
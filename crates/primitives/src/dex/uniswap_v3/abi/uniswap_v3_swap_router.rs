@@ -18,5 +18,12 @@ sol! {
         /// @param params The parameters necessary for the swap, encoded as `ExactInputSingleParams` in calldata
         /// @return amountOut The amount of the received token
         function exactInputSingle(ExactInputSingleParams calldata params) external payable returns (uint256 amountOut);
+
+        /// @notice Call multiple functions in the current contract and return the data from all of them if they all succeed
+        /// @dev The `msg.value` should not be trusted for any method callable from multicall.
+        /// @param deadline The time by which this function must be called before failing
+        /// @param data The encoded function data for each of the calls to make to this contract
+        /// @return results The results from each of the calls passed in via data
+        function multicall(uint256 deadline, bytes[] calldata data) external payable returns (bytes[] memory results);
     }
 }
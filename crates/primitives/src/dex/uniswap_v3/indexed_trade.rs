@@ -103,6 +103,29 @@ impl DexIndexedTrade for UniswapV3IndexedTrade {
         }
     }
 
+    fn token_volume(&self, token_address: &Address) -> U256 {
+        if *token_address < constants::WETH_ADDRESS {
+            let (_, amount0_value) = self.amount0.into_sign_and_abs();
+            amount0_value
+        } else {
+            let (_, amount1_value) = self.amount1.into_sign_and_abs();
+            amount1_value
+        }
+    }
+
+    fn is_buy(&self, token_address: &Address) -> bool {
+        // pool deltas are positive when flowing into the pool
+        if *token_address < constants::WETH_ADDRESS {
+            self.amount1.is_positive()
+        } else {
+            self.amount0.is_positive()
+        }
+    }
+
+    fn maker(&self) -> &Address {
+        &self.maker
+    }
+
     fn pair_address(&self) -> &Address {
         &self.pair_address
     }
@@ -139,7 +162,7 @@ impl TryFrom<&IndexedTradeParseContext<'_>> for UniswapV3IndexedTrade {
 
 #[cfg(test)]
 mod tests {
-    use crate::{new_http_signer_provider, IndexedTrade};
+    use crate::{new_http_signer_provider, IndexedTrade, SignerSource};
 
     use alloy::primitives::{address, fixed_bytes};
 
@@ -152,7 +175,9 @@ mod tests {
             url::Url::parse(
                 "https://base-mainnet.g.alchemy.com/v2/GHF2kp-FpiiuNzmfpdP_dnms5WkewVQ-",
             )?,
-            &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            &SignerSource::PrivateKey(
+                hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            ),
             None,
             true,
         )
@@ -1,16 +1,22 @@
 use super::{
-    super::{DexPair, DexPairInput, IndexedTrade, TradeRequestOp},
+    super::{
+        DexPair, DexPairInput, IndexedTrade, QuotedTransactionRequest, TradeRequestOp,
+        TradeRequestParams,
+    },
     abi, UniswapV3IndexedTrade,
 };
 
 use crate::{
-    abi::multicall3::{self, multicall_tx_request},
+    abi::{
+        erc20, executor,
+        multicall3::{self, multicall_tx_request},
+    },
     constants, RpcProvider,
 };
 
 use alloy::{
     network::{Ethereum, TransactionBuilder},
-    primitives::{uint, Address, BlockNumber, Signed, TxKind, U256},
+    primitives::{uint, Address, BlockNumber, Signed, TxKind, U256, U512},
     providers::Provider,
     rpc::types::eth::TransactionRequest,
     sol_types::SolCall,
@@ -151,6 +157,31 @@ impl UniswapV3PairInput {
     }
 }
 
+// Swaps through the router's multicall, which enforces the deadline that
+// exactInputSingle lacks
+fn exact_input_single_tx_request(
+    params: abi::uniswap_v3_swap_router::ISwapRouter::ExactInputSingleParams,
+    deadline: U256,
+    signer_address: Address,
+) -> TransactionRequest {
+    let exact_input_single_data =
+        abi::uniswap_v3_swap_router::ISwapRouter::exactInputSingleCall { params }.abi_encode();
+
+    TransactionRequest::default()
+        .with_from(signer_address)
+        .with_to(Into::<TxKind>::into(
+            constants::UNISWAP_V3_ROUTER_02_ADDRESS,
+        ))
+        .with_input(
+            abi::uniswap_v3_swap_router::ISwapRouter::multicallCall {
+                deadline,
+                data: vec![exact_input_single_data.into()],
+            }
+            .abi_encode()
+            .into(),
+        )
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct UniswapV3Pair {
     address: Address,
@@ -169,10 +200,99 @@ impl UniswapV3Pair {
         }
     }
 
-    fn open_eth_amount_in(&self) -> U256 {
-        // TODO: use a static size for now, but should probably be dependent on
-        // price impact
-        constants::MAX_TRADE_SIZE_WEI
+    // Weth in that moves the price by about MAX_TRADE_SIZE_PRICE_IMPACT_BP. The
+    // sqrt price moves by half the price impact, and the swap is assumed to stay
+    // within the liquidity of the current tick.
+    fn max_eth_amount_in_for_price_impact(&self, sqrt_price_x96: U256, liquidity: U256) -> U256 {
+        if sqrt_price_x96.is_zero() {
+            return U256::ZERO;
+        }
+
+        let max_eth_amount_in = if self.token0 == constants::WETH_ADDRESS {
+            // token0 in lowers the sqrt price: amount0 = liquidity * d(1 / sqrt price)
+            (U512::from(liquidity) << 96) * U512::from(constants::MAX_TRADE_SIZE_PRICE_IMPACT_BP)
+                / (U512::from(sqrt_price_x96) * U512::from(constants::BP_FACTOR) * uint!(2_U512))
+        } else {
+            // token1 in raises the sqrt price: amount1 = liquidity * d(sqrt price)
+            ((U512::from(liquidity) * U512::from(sqrt_price_x96))
+                * U512::from(constants::MAX_TRADE_SIZE_PRICE_IMPACT_BP)
+                / (U512::from(constants::BP_FACTOR) * uint!(2_U512)))
+                >> 96
+        };
+
+        U256::saturating_from(max_eth_amount_in)
+    }
+
+    // returns (sqrt_price_x96, liquidity)
+    async fn get_sqrt_price_x96_and_liquidity<T, P>(
+        &self,
+        block_number: BlockNumber,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<(U256, U256)>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let multicall_tx_request = multicall_tx_request(vec![
+            multicall3::Call3 {
+                target: self.address,
+                allowFailure: false,
+                callData: abi::uniswap_v3_pool::IUniswapV3Pool::slot0Call {}
+                    .abi_encode()
+                    .into(),
+            },
+            multicall3::Call3 {
+                target: self.address,
+                allowFailure: false,
+                callData: abi::uniswap_v3_pool::IUniswapV3Pool::liquidityCall {}
+                    .abi_encode()
+                    .into(),
+            },
+        ]);
+
+        let multicall_results = rpc_provider
+            .inner()
+            .call(&multicall_tx_request, Some(block_number.into()))
+            .await
+            .with_context(|| "multicall failed")
+            .and_then(|res| {
+                multicall3::aggregate3Call::abi_decode_returns(&res, cfg!(debug_assertions))
+                    .wrap_err("failed to decode multicall returns")
+            })?;
+
+        let sqrt_price_x96 = abi::uniswap_v3_pool::IUniswapV3Pool::slot0Call::abi_decode_returns(
+            &multicall_results.returnData[0].returnData,
+            cfg!(debug_assertions),
+        )
+        .map(|res| U256::from(res.sqrtPriceX96))?;
+
+        let liquidity = abi::uniswap_v3_pool::IUniswapV3Pool::liquidityCall::abi_decode_returns(
+            &multicall_results.returnData[1].returnData,
+            cfg!(debug_assertions),
+        )
+        .map(|res| U256::from(res._0))?;
+
+        Ok((sqrt_price_x96, liquidity))
+    }
+
+    // The requested open size, capped to limit price impact
+    async fn open_eth_amount_in<T, P>(
+        &self,
+        params: &TradeRequestParams,
+        block_number: BlockNumber,
+        rpc_provider: &RpcProvider<T, P>,
+    ) -> Result<U256>
+    where
+        T: Transport + Clone,
+        P: Provider<T, Ethereum> + 'static,
+    {
+        let (sqrt_price_x96, liquidity) = self
+            .get_sqrt_price_x96_and_liquidity(block_number, rpc_provider)
+            .await?;
+
+        Ok(params
+            .open_weth_amount_in()
+            .min(self.max_eth_amount_in_for_price_impact(sqrt_price_x96, liquidity)))
     }
 
     async fn quote_exact_input_single_call<T, P>(
@@ -280,13 +400,18 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
         &self.address
     }
 
-    fn estimate_trade_gas(&self) -> U256 {
-        uint!(130000_U256)
+    fn estimate_trade_gas(&self, params: &TradeRequestParams) -> U256 {
+        // The executor saves the router's multicall and transfers
+        match params.executor_address {
+            Some(_) => uint!(110000_U256),
+            None => uint!(130000_U256),
+        }
     }
 
     async fn simulate_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> Result<UniswapV3IndexedTrade>
@@ -296,7 +421,9 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
     {
         let (amount0, amount1, sqrt_price_x96, liquidity) = match op {
             TradeRequestOp::Open => {
-                let eth_amount_in = self.open_eth_amount_in();
+                let eth_amount_in = self
+                    .open_eth_amount_in(params, block_number, rpc_provider)
+                    .await?;
 
                 let (liquidity, amount_out, sqrt_price_x96) = self
                     .quote_exact_input_single_price_multicall(
@@ -339,6 +466,8 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
                     let (_, amount0_value) = open_trade.amount0.into_sign_and_abs();
                     amount0_value
                 };
+                let open_trade_token_amount_out =
+                    params.close_token_amount_in(open_trade_token_amount_out);
 
                 let (liquidity, amount_out, sqrt_price_x96) = self
                     .quote_exact_input_single_price_multicall(
@@ -388,6 +517,7 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
     async fn trace_trade_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        _params: &TradeRequestParams,
         block_number: BlockNumber,
         rpc_provider: &RpcProvider<T, P>,
     ) -> Result<()>
@@ -402,17 +532,20 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
     async fn make_trade_transaction_request<T, P>(
         &self,
         op: &TradeRequestOp,
+        params: &TradeRequestParams,
         block_number: BlockNumber,
-        _block_timestamp: u64,
+        block_timestamp: u64,
         rpc_provider: &RpcProvider<T, P>,
-    ) -> Result<TransactionRequest>
+    ) -> Result<QuotedTransactionRequest>
     where
         T: Transport + Clone,
         P: Provider<T, Ethereum> + 'static,
     {
         match op {
             TradeRequestOp::Open => {
-                let eth_amount_in = self.open_eth_amount_in();
+                let eth_amount_in = self
+                    .open_eth_amount_in(params, block_number, rpc_provider)
+                    .await?;
                 let token_amount_out = self
                     .quote_exact_input_single_call(
                         abi::uniswap_v3_quoter_v2::IQuoterV2::QuoteExactInputSingleParams {
@@ -428,29 +561,35 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
                     .await
                     .map(|res| res.amountOut)?;
 
-                let tx_request = TransactionRequest::default()
-                    .with_from(rpc_provider.signer_address().clone())
-                    .with_to(Into::<TxKind>::into(
-                        constants::UNISWAP_V3_ROUTER_02_ADDRESS,
-                    ))
-                    .with_input(
-                        abi::uniswap_v3_swap_router::ISwapRouter::exactInputSingleCall {
-                            params:
-                                abi::uniswap_v3_swap_router::ISwapRouter::ExactInputSingleParams {
-                                    tokenIn: constants::WETH_ADDRESS,
-                                    tokenOut: self.token_address().clone(),
-                                    fee: self.fee,
-                                    recipient: rpc_provider.signer_address().clone(),
-                                    amountIn: eth_amount_in,
-                                    amountOutMinimum: token_amount_out,
-                                    sqrtPriceLimitX96: U256::ZERO,
-                                },
-                        }
-                        .abi_encode()
-                        .into(),
-                    );
-
-                Ok(tx_request)
+                let tx_request = match params.executor_address {
+                    Some(executor_address) => executor::call_tx_request(
+                        *rpc_provider.signer_address(),
+                        executor_address,
+                        executor::IExecutor::buyV3Call {
+                            pool: self.address,
+                            token: *self.token_address(),
+                            amountOutMin: params.min_amount_out(token_amount_out),
+                            recipient: *rpc_provider.signer_address(),
+                            deadline: params.deadline(block_timestamp),
+                        },
+                        eth_amount_in,
+                    ),
+                    None => exact_input_single_tx_request(
+                        abi::uniswap_v3_swap_router::ISwapRouter::ExactInputSingleParams {
+                            tokenIn: constants::WETH_ADDRESS,
+                            tokenOut: self.token_address().clone(),
+                            fee: self.fee,
+                            recipient: rpc_provider.signer_address().clone(),
+                            amountIn: eth_amount_in,
+                            amountOutMinimum: params.min_amount_out(token_amount_out),
+                            sqrtPriceLimitX96: U256::ZERO,
+                        },
+                        params.deadline(block_timestamp),
+                        *rpc_provider.signer_address(),
+                    ),
+                };
+
+                Ok(QuotedTransactionRequest::new(tx_request, token_amount_out))
             }
             TradeRequestOp::Close {
                 open_trade: IndexedTrade::UniswapV3(open_trade),
@@ -463,6 +602,8 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
                     let (_, amount0_value) = open_trade.amount0.into_sign_and_abs();
                     amount0_value
                 };
+                let open_trade_token_amount_out =
+                    params.close_token_amount_in(open_trade_token_amount_out);
 
                 let eth_amount_out = self
                     .quote_exact_input_single_call(
@@ -479,29 +620,49 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
                     .await
                     .map(|res| res.amountOut)?;
 
-                let tx_request = TransactionRequest::default()
-                    .with_from(rpc_provider.signer_address().clone())
-                    .with_to(Into::<TxKind>::into(
+                let (tx_request, spender) = match params.executor_address {
+                    Some(executor_address) => (
+                        executor::call_tx_request(
+                            *rpc_provider.signer_address(),
+                            executor_address,
+                            executor::IExecutor::sellV3Call {
+                                pool: self.address,
+                                token: *self.token_address(),
+                                amountIn: open_trade_token_amount_out,
+                                amountOutMin: params.min_amount_out(eth_amount_out),
+                                recipient: *rpc_provider.signer_address(),
+                                deadline: params.deadline(block_timestamp),
+                            },
+                            U256::ZERO,
+                        ),
+                        executor_address,
+                    ),
+                    None => (
+                        exact_input_single_tx_request(
+                            abi::uniswap_v3_swap_router::ISwapRouter::ExactInputSingleParams {
+                                tokenOut: constants::WETH_ADDRESS,
+                                tokenIn: self.token_address().clone(),
+                                fee: self.fee,
+                                recipient: rpc_provider.signer_address().clone(),
+                                amountIn: open_trade_token_amount_out.try_into().unwrap(),
+                                amountOutMinimum: params.min_amount_out(eth_amount_out),
+                                sqrtPriceLimitX96: U256::ZERO,
+                            },
+                            params.deadline(block_timestamp),
+                            *rpc_provider.signer_address(),
+                        ),
                         constants::UNISWAP_V3_ROUTER_02_ADDRESS,
-                    ))
-                    .with_input(
-                        abi::uniswap_v3_swap_router::ISwapRouter::exactInputSingleCall {
-                            params:
-                                abi::uniswap_v3_swap_router::ISwapRouter::ExactInputSingleParams {
-                                    tokenOut: constants::WETH_ADDRESS,
-                                    tokenIn: self.token_address().clone(),
-                                    fee: self.fee,
-                                    recipient: rpc_provider.signer_address().clone(),
-                                    amountIn: open_trade_token_amount_out.try_into().unwrap(),
-                                    amountOutMinimum: eth_amount_out,
-                                    sqrtPriceLimitX96: U256::ZERO,
-                                },
-                        }
-                        .abi_encode()
-                        .into(),
-                    );
-
-                Ok(tx_request)
+                    ),
+                };
+                let approval = erc20::approve_tx_request(
+                    *rpc_provider.signer_address(),
+                    *self.token_address(),
+                    spender,
+                    open_trade_token_amount_out,
+                );
+
+                Ok(QuotedTransactionRequest::new(tx_request, eth_amount_out)
+                    .with_approval(approval))
             }
             TradeRequestOp::Close { .. } => {
                 eyre::bail!("invalid trade request op for uniswap v3 pair: {:?}", op)
@@ -512,20 +673,61 @@ impl DexPair<UniswapV3IndexedTrade> for UniswapV3Pair {
 
 #[cfg(test)]
 mod tests {
-    use crate::{new_http_signer_provider, DexPair, TradeRequestOp, UniswapV3Pair};
+    use crate::{
+        new_http_signer_provider, DexPair, SignerSource, TradeRequestOp, TradeRequestParams,
+        UniswapV3Pair,
+    };
 
-    use alloy::primitives::address;
+    use alloy::primitives::{address, uint, Address, U256};
 
     use eyre::Result;
     use hex_literal::hex;
 
+    #[test]
+    fn test_max_eth_amount_in_for_price_impact() {
+        let token_address = address!("4ed4E862860beD51a9570b96d89aF5E1B0Efefed");
+        let weth_address = address!("4200000000000000000000000000000000000006");
+        let liquidity = uint!(1000000000000000000_U256);
+        // a price of 1
+        let sqrt_price_x96 = U256::from(1) << 96;
+
+        // 50bp moves the sqrt price by 25bp of the liquidity in either direction
+        let weth_token0 = UniswapV3Pair::new(Address::ZERO, weth_address, token_address, 3000);
+        assert_eq!(
+            weth_token0.max_eth_amount_in_for_price_impact(sqrt_price_x96, liquidity),
+            uint!(2500000000000000_U256)
+        );
+        let weth_token1 = UniswapV3Pair::new(Address::ZERO, token_address, weth_address, 3000);
+        assert_eq!(
+            weth_token1.max_eth_amount_in_for_price_impact(sqrt_price_x96, liquidity),
+            uint!(2500000000000000_U256)
+        );
+
+        // a higher token1 per token0 price needs less token0 and more token1
+        let sqrt_price_x96 = U256::from(2) << 96;
+        assert_eq!(
+            weth_token0.max_eth_amount_in_for_price_impact(sqrt_price_x96, liquidity),
+            uint!(1250000000000000_U256)
+        );
+        assert_eq!(
+            weth_token1.max_eth_amount_in_for_price_impact(sqrt_price_x96, liquidity),
+            uint!(5000000000000000_U256)
+        );
+        assert_eq!(
+            weth_token1.max_eth_amount_in_for_price_impact(U256::ZERO, liquidity),
+            U256::ZERO
+        );
+    }
+
     #[tokio::test]
     async fn test_simulate_trade_request() -> Result<()> {
         let rpc_provider = new_http_signer_provider(
             url::Url::parse(
                 "https://base-mainnet.g.alchemy.com/v2/GHF2kp-FpiiuNzmfpdP_dnms5WkewVQ-",
             )?,
-            &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            &SignerSource::PrivateKey(
+                hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            ),
             None,
             true,
         )
@@ -539,7 +741,12 @@ mod tests {
         );
 
         let open_trade = pair
-            .simulate_trade_request(&TradeRequestOp::Open, block_number, &rpc_provider)
+            .simulate_trade_request(
+                &TradeRequestOp::Open,
+                &TradeRequestParams::new(),
+                block_number,
+                &rpc_provider,
+            )
             .await?;
 
         // price_before from last swap in this block:
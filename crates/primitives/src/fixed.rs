@@ -43,6 +43,11 @@ pub fn u32f96_from_u256_frac(numerator: U256, denominator: U256) -> U32F96 {
     FixedU128::from_le_bytes(own)
 }
 
+// Values an amount at a Q32.96 price, e.g. a token balance in terms of WETH.
+pub fn u256_mul_u32f96(value: U256, price: U32F96) -> U256 {
+    U256::from((U512::from(value) * U512::from(price.to_bits())) >> 96)
+}
+
 pub fn u32f96_from_sqrt_x96(sqrt_price_x96: U256, inverse: bool) -> U32F96 {
     if inverse {
         let r = *INVERSE_FIXED_POINT_FACTOR / U512::from(sqrt_price_x96).pow(*TWO);
@@ -79,6 +84,14 @@ mod tests {
         assert_eq!(price.to_string(), "0.33333333333333333333333333333");
     }
 
+    #[test]
+    fn test_u256_mul_u32f96() {
+        let price = u32f96_from_u256_frac(U256::from(1), U256::from(4));
+
+        assert_eq!(u256_mul_u32f96(U256::from(1000), price), U256::from(250));
+        assert_eq!(u256_mul_u32f96(U256::from(1000), U32F96::ZERO), U256::ZERO);
+    }
+
     #[test]
     fn test_from_sqrt_x96() {
         // test pricing, where the price value from uniswap is in terms of weth
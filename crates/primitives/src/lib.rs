@@ -1,19 +1,25 @@
 pub use block::{Block, BlockBuilder};
 
 pub use dex::{
-    DexIndexedTrade, DexPair, IndexedTrade, Pair, PairBlockTick, PairInput, TradeRequestOp,
-    UniswapV2IndexedTrade, UniswapV2Pair, UniswapV2PairBlockTick, UniswapV2PairInput,
-    UniswapV3IndexedTrade, UniswapV3Pair, UniswapV3PairInput,
+    DexIndexedTrade, DexPair, IndexedTrade, Pair, PairBlockTick, PairInput,
+    QuotedTransactionRequest, TradeRequestOp, TradeRequestParams, UniswapV2IndexedTrade,
+    UniswapV2Pair, UniswapV2PairBlockTick, UniswapV2PairInput, UniswapV3IndexedTrade,
+    UniswapV3Pair, UniswapV3PairInput,
 };
 
 pub use block_id::BlockId;
 pub use block_message::BlockMessage;
 pub use fixed::*;
-pub use rpc_provider::{new_http_signer_provider, RpcProvider, TTLCache};
+pub use rpc_provider::{
+    new_failover_signer_provider, new_http_signer_provider, send_json_rpc, EndpointHealth,
+    FailoverTransport, RemoteSigner, RpcPolicy, RpcPolicyLayer, RpcPolicyTransport, RpcProvider,
+    SignerRpcProvider, SignerSource, TTLCache, DEFAULT_DERIVATION_PATH,
+};
 pub use tick_data::TickData;
 pub use time_price_bars::{
-    FinalizedTimePriceBar, Indicators, IndicatorsConfig, PendingTimePriceBar, Resolution,
-    ResolutionTimestamp, TimePriceBar, TimePriceBars,
+    BarSampling, FinalizedTimePriceBar, Indicators, IndicatorsConfig, PendingTimePriceBar,
+    PriceBars, Resolution, ResolutionTimestamp, SampledPriceBar, SampledPriceBars, TimePriceBar,
+    TimePriceBars,
 };
 pub use trade_metadata::TradeMetadata;
 
@@ -29,3 +35,6 @@ mod trade_metadata;
 
 pub mod constants;
 pub mod fixed;
+
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
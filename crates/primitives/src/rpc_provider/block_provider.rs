@@ -5,11 +5,12 @@ use alloy::{
     primitives::BlockNumber,
     providers::Provider,
     rpc::types::eth::{Block, BlockNumberOrTag, Header},
-    transports::Transport,
+    transports::{Transport, TransportResult},
 };
 
 use eyre::{eyre, Result, WrapErr};
 use std::{
+    cmp::min,
     collections::BTreeMap,
     ops::Deref,
     sync::{Arc, RwLock},
@@ -18,6 +19,8 @@ use std::{
 use tracing::instrument;
 
 const BLOCK_CACHE_SIZE: usize = 100;
+// Headers requested per json rpc batch
+const HEADER_BATCH_SIZE: u64 = 100;
 
 pub struct BlockProvider<T, P>
 where
@@ -86,6 +89,85 @@ where
             .map(|block| block.map(|block| block.header))
     }
 
+    // Timestamps of every block in the range, in order, fetched header by
+    // header in batches. Each header must be the block requested and no
+    // earlier than its parent.
+    #[instrument(skip(self))]
+    pub async fn get_block_timestamps(
+        &self,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+    ) -> Result<Vec<u64>> {
+        let mut timestamps =
+            Vec::with_capacity((end_block_number - start_block_number + 1) as usize);
+        for batch_start_block_number in
+            (start_block_number..=end_block_number).step_by(HEADER_BATCH_SIZE as usize)
+        {
+            let batch_block_numbers = batch_start_block_number
+                ..=min(
+                    batch_start_block_number + HEADER_BATCH_SIZE - 1,
+                    end_block_number,
+                );
+
+            let client = self.inner.client();
+            let mut batch = client.new_batch();
+            let waiters = batch_block_numbers
+                .clone()
+                .map(|block_number| {
+                    batch.add_call::<_, Option<Block>>(
+                        "eth_getBlockByNumber",
+                        &(BlockNumberOrTag::Number(block_number), false),
+                    )
+                })
+                .collect::<TransportResult<Vec<_>>>()?;
+            batch
+                .send()
+                .await
+                .wrap_err("eth_getBlockByNumber batch failed")?;
+
+            for (block_number, waiter) in batch_block_numbers.zip(waiters) {
+                let header = waiter
+                    .await
+                    .wrap_err_with(|| format!("get_block_by_number {} failed", block_number))?
+                    .ok_or_else(|| eyre!("Expected block {} but found None", block_number))?
+                    .header;
+                if header.number.map(|number| number.to::<u64>()) != Some(block_number) {
+                    return Err(eyre!(
+                        "Expected block {} but found {:?}",
+                        block_number,
+                        header.number
+                    ));
+                }
+
+                let timestamp = header.timestamp.to::<u64>();
+                if timestamps
+                    .last()
+                    .is_some_and(|parent_timestamp| *parent_timestamp > timestamp)
+                {
+                    return Err(eyre!(
+                        "Block {} timestamp {} precedes its parent's",
+                        block_number,
+                        timestamp
+                    ));
+                }
+                timestamps.push(timestamp);
+            }
+        }
+
+        Ok(timestamps)
+    }
+
+    pub async fn get_latest_block_header(&self) -> Result<Header> {
+        self.inner
+            .get_block_by_number(BlockNumberOrTag::Latest, false)
+            .await
+            .wrap_err("Failed to get latest block")
+            .and_then(|block| match block {
+                Some(block) => Ok(block.header),
+                None => Err(eyre!("No latest block header")),
+            })
+    }
+
     pub async fn get_finalized_block_header(&self) -> Result<Header> {
         // Return value from cache if it exists
         {
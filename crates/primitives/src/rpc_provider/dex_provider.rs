@@ -124,8 +124,8 @@ where
 #[cfg(test)]
 mod tests {
     use crate::{
-        new_http_signer_provider, Pair, UniswapV2Pair, UniswapV2PairInput, UniswapV3Pair,
-        UniswapV3PairInput,
+        new_http_signer_provider, Pair, SignerSource, UniswapV2Pair, UniswapV2PairInput,
+        UniswapV3Pair, UniswapV3PairInput,
     };
 
     use alloy::primitives::address;
@@ -138,7 +138,9 @@ mod tests {
             url::Url::parse(
                 "https://base-mainnet.g.alchemy.com/v2/GHF2kp-FpiiuNzmfpdP_dnms5WkewVQ-",
             )?,
-            &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            &SignerSource::PrivateKey(
+                hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+            ),
             None,
             false,
         )
@@ -0,0 +1,487 @@
+use alloy::{
+    primitives::U64,
+    rpc::json_rpc::{Id, Request, RequestPacket, ResponsePacket, ResponsePayload},
+    transports::{http::Http, TransportError, TransportErrorKind, TransportFut},
+};
+
+use eyre::{eyre, Result};
+use std::{
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+use tower::Service;
+use tracing::{debug, warn};
+use url::Url;
+
+// Weight of the latest request in an endpoint's latency and error rate
+const HEALTH_EMA_WEIGHT: f64 = 0.2;
+// Latency an endpoint that always fails is penalized by
+const ERROR_PENALTY_MS: f64 = 5_000.0;
+// Latency each block an endpoint is behind the best head is penalized by
+const HEAD_LAG_PENALTY_MS: f64 = 500.0;
+
+// Observed health of an rpc endpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EndpointHealth {
+    // Moving averages over recent requests
+    pub latency_ms: f64,
+    pub error_rate: f64,
+    // Latest block number the endpoint has reported
+    pub head_block_number: Option<u64>,
+    pub request_count: u64,
+}
+
+impl EndpointHealth {
+    fn record(&mut self, latency: Duration, is_error: bool) {
+        let latency_ms = latency.as_secs_f64() * 1_000.0;
+        let error = if is_error { 1.0 } else { 0.0 };
+
+        if self.request_count == 0 {
+            self.latency_ms = latency_ms;
+            self.error_rate = error;
+        } else {
+            self.latency_ms += (latency_ms - self.latency_ms) * HEALTH_EMA_WEIGHT;
+            self.error_rate += (error - self.error_rate) * HEALTH_EMA_WEIGHT;
+        }
+        self.request_count += 1;
+    }
+
+    fn head_lag(&self, best_head_block_number: Option<u64>) -> u64 {
+        match (best_head_block_number, self.head_block_number) {
+            (Some(best), Some(head)) => best.saturating_sub(head),
+            _ => 0,
+        }
+    }
+
+    // Lower is healthier
+    fn score(&self, head_lag: u64) -> f64 {
+        self.latency_ms + self.error_rate * ERROR_PENALTY_MS + head_lag as f64 * HEAD_LAG_PENALTY_MS
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: Url,
+    transport: Http<reqwest::Client>,
+    health: Mutex<EndpointHealth>,
+}
+
+// Spreads requests over several http rpc endpoints, sending each to the
+// healthiest first and failing over to the next when it errors, is rate
+// limited or can't serve the request. Raw transactions are broadcast to
+// every endpoint at once.
+#[derive(Debug, Clone)]
+pub struct FailoverTransport {
+    endpoints: Arc<Vec<Endpoint>>,
+    // Endpoints further behind the best head are only tried after the rest
+    max_head_lag: u64,
+}
+
+// Messages of endpoints rejecting a get_logs whose range or result is too
+// large for them
+const LOG_LIMIT_MESSAGES: &[&str] = &[
+    "query returned more than",
+    "response size",
+    "range too large",
+    "range is too large",
+    "range is too wide",
+    "is limited to",
+    "exceed maximum block range",
+];
+
+pub(super) fn is_log_limit_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    LOG_LIMIT_MESSAGES
+        .iter()
+        .any(|limit_message| message.contains(limit_message))
+}
+
+// Whether an error response reflects on the endpoint rather than the request,
+// e.g. a rate limit or a block it hasn't synced yet. Log limits share codes
+// with rate limits but need a smaller request instead.
+pub(super) fn is_endpoint_error(response: &ResponsePacket) -> bool {
+    let responses = match response {
+        ResponsePacket::Single(response) => std::slice::from_ref(response),
+        ResponsePacket::Batch(responses) => responses.as_slice(),
+    };
+
+    responses.iter().any(|response| match &response.payload {
+        ResponsePayload::Failure(error) if is_log_limit_message(&error.message) => false,
+        ResponsePayload::Failure(error) => {
+            let message = error.message.to_lowercase();
+            matches!(error.code, 429 | -32005 | -32603)
+                || message.contains("rate limit")
+                || message.contains("too many requests")
+                || message.contains("header not found")
+                || message.contains("unknown block")
+        }
+        ResponsePayload::Success(_) => false,
+    })
+}
+
+// Whether every response in the packet succeeded
+fn is_success(response: &ResponsePacket) -> bool {
+    let responses = match response {
+        ResponsePacket::Single(response) => std::slice::from_ref(response),
+        ResponsePacket::Batch(responses) => responses.as_slice(),
+    };
+
+    responses
+        .iter()
+        .all(|response| matches!(response.payload, ResponsePayload::Success(_)))
+}
+
+fn request_method(request: &RequestPacket) -> Option<&str> {
+    match request {
+        RequestPacket::Single(request) => Some(request.method()),
+        RequestPacket::Batch(_) => None,
+    }
+}
+
+impl FailoverTransport {
+    pub fn new(urls: impl IntoIterator<Item = Url>) -> Result<Self> {
+        let client = reqwest::Client::new();
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                transport: Http::with_client(client.clone(), url.clone()),
+                url,
+                health: Mutex::new(EndpointHealth::default()),
+            })
+            .collect::<Vec<_>>();
+
+        if endpoints.is_empty() {
+            return Err(eyre!("No rpc endpoints"));
+        }
+
+        Ok(Self {
+            endpoints: Arc::new(endpoints),
+            max_head_lag: 2,
+        })
+    }
+
+    pub fn with_max_head_lag(mut self, max_head_lag: u64) -> Self {
+        self.max_head_lag = max_head_lag;
+        self
+    }
+
+    pub fn health(&self) -> Vec<(Url, EndpointHealth)> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| (endpoint.url.clone(), *endpoint.health.lock().unwrap()))
+            .collect()
+    }
+
+    // Indices of the endpoints from healthiest to least healthy
+    fn ranked_endpoints(&self) -> Vec<usize> {
+        let health = self
+            .endpoints
+            .iter()
+            .map(|endpoint| *endpoint.health.lock().unwrap())
+            .collect::<Vec<_>>();
+        let best_head_block_number = health
+            .iter()
+            .filter_map(|health| health.head_block_number)
+            .max();
+
+        let mut ranked = health
+            .iter()
+            .enumerate()
+            .map(|(index, health)| {
+                let head_lag = health.head_lag(best_head_block_number);
+                (index, head_lag > self.max_head_lag, health.score(head_lag))
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|(_, a_lagging, a_score), (_, b_lagging, b_score)| {
+            a_lagging
+                .cmp(b_lagging)
+                .then_with(|| a_score.total_cmp(b_score))
+        });
+
+        ranked.into_iter().map(|(index, _, _)| index).collect()
+    }
+
+    // Sends the request to a single endpoint, recording how it went
+    async fn send_to(
+        endpoints: &[Endpoint],
+        index: usize,
+        request: RequestPacket,
+    ) -> Result<ResponsePacket, TransportError> {
+        let endpoint = &endpoints[index];
+        let is_block_number = request_method(&request) == Some("eth_blockNumber");
+
+        let started_at = Instant::now();
+        let result = endpoint.transport.clone().call(request).await;
+
+        let mut health = endpoint.health.lock().unwrap();
+        health.record(
+            started_at.elapsed(),
+            result.as_ref().map_or(true, is_endpoint_error),
+        );
+        if let Ok(ResponsePacket::Single(response)) = &result {
+            if let (true, ResponsePayload::Success(block_number)) =
+                (is_block_number, &response.payload)
+            {
+                if let Ok(block_number) = serde_json::from_str::<U64>(block_number.get()) {
+                    health.head_block_number = Some(block_number.to::<u64>());
+                }
+            }
+        }
+
+        result
+    }
+
+    async fn failover(&self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let mut last_result = None;
+        for index in self.ranked_endpoints() {
+            let url = &self.endpoints[index].url;
+            let result = Self::send_to(&self.endpoints, index, request.clone()).await;
+            match &result {
+                Ok(response) if !is_endpoint_error(response) => return result,
+                Ok(_) => warn!(%url, "rpc endpoint rejected request"),
+                Err(err) => warn!(%url, "rpc endpoint failed: {:?}", err),
+            }
+            last_result = Some(result);
+        }
+
+        last_result.unwrap_or_else(|| Err(TransportErrorKind::custom_str("No rpc endpoints")))
+    }
+
+    // Sends the request to every endpoint, resolving with the first successful
+    // response while the rest complete in the background. Fails only once
+    // every endpoint has answered without one.
+    async fn broadcast(&self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let (result_sender, mut result_receiver) = mpsc::channel(self.endpoints.len());
+        for index in 0..self.endpoints.len() {
+            let endpoints = Arc::clone(&self.endpoints);
+            let request = request.clone();
+            let result_sender = result_sender.clone();
+            tokio::spawn(async move {
+                let result = Self::send_to(&endpoints, index, request).await;
+                let _ = result_sender.send(result).await;
+            });
+        }
+        drop(result_sender);
+
+        let mut rejected_result = None;
+        let mut last_result = None;
+        while let Some(result) = result_receiver.recv().await {
+            match &result {
+                Ok(response) if is_success(response) => return result,
+                // A rejection of the request itself says more than an
+                // endpoint error
+                Ok(response) if !is_endpoint_error(response) => rejected_result = Some(result),
+                _ => last_result = Some(result),
+            }
+        }
+
+        rejected_result
+            .or(last_result)
+            .unwrap_or_else(|| Err(TransportErrorKind::custom_str("No rpc endpoints")))
+    }
+
+    // Polls the head of every endpoint so lagging ones are ranked down
+    pub async fn refresh_heads(&self) {
+        let request = match Request::new("eth_blockNumber", Id::Number(0), ()).serialize() {
+            Ok(request) => RequestPacket::Single(request),
+            Err(err) => {
+                warn!("Failed to serialize eth_blockNumber request: {:?}", err);
+                return;
+            }
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+        for index in 0..self.endpoints.len() {
+            let endpoints = Arc::clone(&self.endpoints);
+            let request = request.clone();
+            tasks.spawn(async move { Self::send_to(&endpoints, index, request).await });
+        }
+        while tasks.join_next().await.is_some() {}
+    }
+
+    pub fn spawn_health_check(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let transport = self.clone();
+        tokio::spawn(async move {
+            loop {
+                transport.refresh_heads().await;
+                for (url, health) in transport.health() {
+                    debug!(
+                        %url,
+                        latency_ms = health.latency_ms,
+                        error_rate = health.error_rate,
+                        head_block_number = health.head_block_number,
+                        "rpc endpoint health"
+                    );
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let transport = self.clone();
+        Box::pin(async move {
+            match request_method(&request) {
+                Some("eth_sendRawTransaction") => transport.broadcast(request).await,
+                _ => transport.failover(request).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_endpoint_error, is_log_limit_message, EndpointHealth, FailoverTransport};
+    use crate::test_utils::mock_json_rpc_server;
+
+    use alloy::rpc::json_rpc::{Id, Request, RequestPacket, ResponsePacket, ResponsePayload};
+    use eyre::Result;
+    use std::time::Duration;
+    use tower::Service;
+
+    fn block_number_request() -> Result<RequestPacket> {
+        Ok(RequestPacket::Single(
+            Request::new("eth_blockNumber", Id::Number(0), ()).serialize()?,
+        ))
+    }
+
+    fn send_raw_transaction_request() -> Result<RequestPacket> {
+        Ok(RequestPacket::Single(
+            Request::new("eth_sendRawTransaction", Id::Number(0), ("0x00",)).serialize()?,
+        ))
+    }
+
+    #[test]
+    fn test_score() {
+        let mut health = EndpointHealth::default();
+        health.record(Duration::from_millis(100), false);
+        assert_eq!(health.latency_ms, 100.0);
+        assert_eq!(health.score(0), 100.0);
+
+        health.record(Duration::from_millis(200), true);
+        assert_eq!(health.latency_ms, 120.0);
+        assert_eq!(health.error_rate, 0.2);
+        assert_eq!(health.score(0), 1_120.0);
+        assert_eq!(health.score(2), 2_120.0);
+
+        health.head_block_number = Some(10);
+        assert_eq!(health.head_lag(Some(12)), 2);
+        assert_eq!(health.head_lag(None), 0);
+    }
+
+    #[test]
+    fn test_is_endpoint_error() -> Result<()> {
+        let rate_limited: ResponsePacket = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":-32005,"message":"Too many requests"}}"#,
+        )?;
+        assert!(is_endpoint_error(&rate_limited));
+
+        let log_limited: ResponsePacket = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":-32005,"message":"query returned more than 10000 results"}}"#,
+        )?;
+        assert!(!is_endpoint_error(&log_limited));
+        assert!(is_log_limit_message(
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover() -> Result<()> {
+        let (rate_limited, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":429,"message":"Too many requests"}}"#
+                .to_string(),
+        ])
+        .await?;
+        let (healthy, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x10"}"#.to_string(),
+        ])
+        .await?;
+
+        let mut transport = FailoverTransport::new(vec![rate_limited.clone(), healthy.clone()])?;
+        let response = transport.call(block_number_request()?).await?;
+        match response {
+            ResponsePacket::Single(response) => {
+                assert!(matches!(response.payload, ResponsePayload::Success(_)))
+            }
+            ResponsePacket::Batch(_) => panic!("expected a single response"),
+        }
+
+        let health = transport.health();
+        assert_eq!(health[0].0, rate_limited);
+        assert_eq!(health[0].1.error_rate, 1.0);
+        assert_eq!(health[1].1.head_block_number, Some(16));
+
+        // The rate limited endpoint is now tried last
+        assert_eq!(transport.ranked_endpoints()[0], 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broadcast() -> Result<()> {
+        let (rejecting, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"error":{"code":-32000,"message":"nonce too low"}}"#
+                .to_string(),
+        ])
+        .await?;
+        let (accepting, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x01"}"#.to_string(),
+        ])
+        .await?;
+
+        // Resolves with the endpoint that accepted the tx, whichever answers
+        // first
+        let mut transport = FailoverTransport::new(vec![rejecting.clone(), accepting])?;
+        match transport.call(send_raw_transaction_request()?).await? {
+            ResponsePacket::Single(response) => {
+                assert!(matches!(response.payload, ResponsePayload::Success(_)))
+            }
+            ResponsePacket::Batch(_) => panic!("expected a single response"),
+        }
+
+        // Fails with the rejection once no endpoint accepted the tx
+        let mut transport = FailoverTransport::new(vec![rejecting])?;
+        match transport.call(send_raw_transaction_request()?).await? {
+            ResponsePacket::Single(response) => {
+                assert!(matches!(response.payload, ResponsePayload::Failure(_)))
+            }
+            ResponsePacket::Batch(_) => panic!("expected a single response"),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lagging_endpoint_ranked_last() -> Result<()> {
+        let (lagging, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x10"}"#.to_string(),
+        ])
+        .await?;
+        let (synced, _) = mock_json_rpc_server(vec![
+            r#"{"jsonrpc":"2.0","id":0,"result":"0x20"}"#.to_string()
+        ])
+        .await?;
+
+        let transport = FailoverTransport::new(vec![lagging, synced])?.with_max_head_lag(2);
+        transport.refresh_heads().await;
+
+        assert_eq!(transport.ranked_endpoints(), vec![1, 0]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use eyre::{eyre, Result, WrapErr};
+use reqwest::header::CONTENT_TYPE;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+// Posts a single json rpc request to a service outside the provider's
+// transport, e.g. a relay or signing service, returning its parsed result
+pub async fn send_json_rpc<R: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &Url,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<R> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+
+    let response = client
+        .post(url.clone())
+        .header(CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(&body)?)
+        .send()
+        .await
+        .wrap_err_with(|| format!("{} request failed", method))?
+        .error_for_status()
+        .wrap_err_with(|| format!("{} request failed", method))?
+        .bytes()
+        .await?;
+    let mut response = serde_json::from_slice::<serde_json::Value>(&response)
+        .wrap_err_with(|| format!("Failed to parse {} response", method))?;
+
+    if let Some(error) = response.get("error") {
+        return Err(eyre!("{} failed: {}", method, error));
+    }
+    match response.get_mut("result") {
+        Some(result) => serde_json::from_value(result.take())
+            .wrap_err_with(|| format!("Failed to parse {} result", method)),
+        None => Err(eyre!("{} returned no result", method)),
+    }
+}
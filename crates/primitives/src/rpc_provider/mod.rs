@@ -1,12 +1,24 @@
 pub use async_value::{AsyncValue, AsyncReceiverOrValue};
 use block_provider::BlockProvider;
 pub use dex_provider::DexProvider;
-pub use rpc_provider::{new_http_signer_provider, RpcProvider};
+pub use failover_transport::{EndpointHealth, FailoverTransport};
+pub use json_rpc::send_json_rpc;
+pub use policy_transport::{RpcPolicy, RpcPolicyLayer, RpcPolicyTransport};
+pub use remote_signer::RemoteSigner;
+pub use rpc_provider::{
+    new_failover_signer_provider, new_http_signer_provider, RpcProvider, SignerRpcProvider,
+};
+pub use signer_source::{SignerSource, DEFAULT_DERIVATION_PATH};
 pub use ttl_cache::TTLCache;
 
 mod block_provider;
 mod dex_provider;
+mod failover_transport;
+mod json_rpc;
+mod policy_transport;
+mod remote_signer;
 mod rpc_provider;
+mod signer_source;
 
 mod async_value;
 mod multicall;
@@ -0,0 +1,590 @@
+use super::failover_transport::is_endpoint_error;
+
+use alloy::{
+    primitives::FixedBytes,
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{Transport, TransportError, TransportErrorKind, TransportFut},
+};
+
+use eyre::{eyre, Report, Result};
+use fnv::FnvHashMap;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+use tower::{Layer, Service};
+use tracing::warn;
+
+// Compute units charged by common providers for the methods we call, with
+// anything else charged the default policy's units
+const METHOD_COMPUTE_UNITS: &[(&str, u32)] = &[
+    ("eth_blockNumber", 10),
+    ("eth_call", 26),
+    ("eth_chainId", 0),
+    ("eth_estimateGas", 87),
+    ("eth_feeHistory", 10),
+    ("eth_getBalance", 19),
+    ("eth_getBlockByNumber", 16),
+    ("eth_getLogs", 75),
+    ("eth_getTransactionCount", 26),
+    ("eth_getTransactionReceipt", 15),
+    ("eth_sendRawTransaction", 250),
+    ("trace_callMany", 75),
+];
+
+// Methods never retried unless given a policy. A resent raw transaction is
+// rejected as already known or its nonce as too low, hiding that the first
+// send went through.
+const UNRETRIED_METHODS: &[&str] = &["eth_sendRawTransaction"];
+
+// Methods sent through an open circuit breaker, so that transactions already
+// in flight are still seen to confirm
+const CIRCUIT_BREAKER_EXEMPT_METHODS: &[&str] =
+    &["eth_getTransactionCount", "eth_getTransactionReceipt"];
+
+// How calls to a method are retried and what they cost against the compute
+// unit limit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RpcPolicy {
+    pub max_retries: u32,
+    pub compute_units: u32,
+}
+
+impl Default for RpcPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            compute_units: 20,
+        }
+    }
+}
+
+// Parses "<max_retries>:<compute_units>"
+impl FromStr for RpcPolicy {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (max_retries, compute_units) = s
+            .trim()
+            .split_once(':')
+            .ok_or_else(|| eyre!("Failed to parse rpc policy: {}", s))?;
+
+        Ok(Self {
+            max_retries: max_retries
+                .parse()
+                .map_err(|err| eyre!("Failed to parse rpc policy {}: {:?}", s, err))?,
+            compute_units: compute_units
+                .parse()
+                .map_err(|err| eyre!("Failed to parse rpc policy {}: {:?}", s, err))?,
+        })
+    }
+}
+
+// Refills continuously up to a second's worth of tokens
+#[derive(Debug)]
+struct TokenBucket {
+    tokens_per_second: f64,
+    // Available tokens, negative when reserved ahead of the refill
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(tokens_per_second: u32) -> Self {
+        Self {
+            tokens_per_second: tokens_per_second as f64,
+            state: Mutex::new((tokens_per_second as f64, Instant::now())),
+        }
+    }
+
+    // Takes the tokens, returning how long until the refill covers them
+    fn reserve(&self, tokens: u32) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let (available, last_refill) = &mut *state;
+
+        let now = Instant::now();
+        *available = (*available
+            + now.duration_since(*last_refill).as_secs_f64() * self.tokens_per_second)
+            .min(self.tokens_per_second);
+        *last_refill = now;
+        *available -= tokens as f64;
+
+        if *available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-*available / self.tokens_per_second)
+        }
+    }
+
+    async fn acquire(&self, tokens: u32) {
+        let wait = self.reserve(tokens);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// Opens after enough consecutive failed requests, failing requests fast until
+// the cooldown passes. The first request after that decides whether it closes
+// again or reopens.
+#[derive(Debug)]
+struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    // Consecutive failures and when the breaker next lets a request through
+    state: Mutex<(u32, Option<Instant>)>,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold,
+            cooldown,
+            state: Mutex::new((0, None)),
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        let (_, open_until) = *self.state.lock().unwrap();
+        open_until.is_some_and(|open_until| Instant::now() < open_until)
+    }
+
+    fn record(&self, is_failure: bool) {
+        let mut state = self.state.lock().unwrap();
+        let (consecutive_failures, open_until) = &mut *state;
+
+        if !is_failure {
+            *consecutive_failures = 0;
+            *open_until = None;
+            return;
+        }
+
+        *consecutive_failures += 1;
+        if *consecutive_failures >= self.failure_threshold {
+            if *consecutive_failures == self.failure_threshold {
+                warn!(
+                    consecutive_failures = *consecutive_failures,
+                    "rpc circuit breaker opened"
+                );
+            }
+            *open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+fn request_methods(request: &RequestPacket) -> Vec<&str> {
+    match request {
+        RequestPacket::Single(request) => vec![request.method()],
+        RequestPacket::Batch(requests) => requests.iter().map(|request| request.method()).collect(),
+    }
+}
+
+// Retries transient rpc errors with exponential backoff, limits the requests
+// and compute units sent per second, and stops sending requests for a while
+// once they keep failing
+#[derive(Debug, Clone)]
+pub struct RpcPolicyLayer {
+    default_policy: RpcPolicy,
+    method_policies: FnvHashMap<String, RpcPolicy>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_requests_per_second: Option<u32>,
+    max_compute_units_per_second: Option<u32>,
+    circuit_breaker_threshold: u32,
+    circuit_breaker_cooldown: Duration,
+}
+
+impl RpcPolicyLayer {
+    pub fn new() -> Self {
+        Self {
+            default_policy: RpcPolicy::default(),
+            method_policies: FnvHashMap::default(),
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            max_requests_per_second: None,
+            max_compute_units_per_second: None,
+            circuit_breaker_threshold: 20,
+            circuit_breaker_cooldown: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.default_policy.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_method_policy(mut self, method: impl Into<String>, policy: RpcPolicy) -> Self {
+        self.method_policies.insert(method.into(), policy);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_max_requests_per_second(mut self, max_requests_per_second: u32) -> Self {
+        self.max_requests_per_second = Some(max_requests_per_second);
+        self
+    }
+
+    pub fn with_max_compute_units_per_second(mut self, max_compute_units_per_second: u32) -> Self {
+        self.max_compute_units_per_second = Some(max_compute_units_per_second);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breaker_threshold = failure_threshold;
+        self.circuit_breaker_cooldown = cooldown;
+        self
+    }
+
+    fn method_policy(&self, method: &str) -> RpcPolicy {
+        self.method_policies
+            .get(method)
+            .copied()
+            .unwrap_or_else(|| RpcPolicy {
+                max_retries: if UNRETRIED_METHODS.contains(&method) {
+                    0
+                } else {
+                    self.default_policy.max_retries
+                },
+                compute_units: METHOD_COMPUTE_UNITS
+                    .iter()
+                    .find(|(name, _)| *name == method)
+                    .map_or(self.default_policy.compute_units, |(_, units)| *units),
+            })
+    }
+
+    // A batch is retried as little as its strictest method allows and costs
+    // the sum of its methods
+    fn policy(&self, request: &RequestPacket) -> RpcPolicy {
+        request_methods(request)
+            .into_iter()
+            .map(|method| self.method_policy(method))
+            .reduce(|acc, policy| RpcPolicy {
+                max_retries: acc.max_retries.min(policy.max_retries),
+                compute_units: acc.compute_units + policy.compute_units,
+            })
+            .unwrap_or(self.default_policy)
+    }
+
+    // Doubles with each retry, jittered between half and the full backoff so
+    // concurrent retries spread out
+    fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff);
+        let jitter = u64::from_be_bytes(FixedBytes::<8>::random().0) as f64 / u64::MAX as f64;
+
+        backoff.mul_f64(0.5 + jitter * 0.5)
+    }
+}
+
+impl Default for RpcPolicyLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> Layer<S> for RpcPolicyLayer {
+    type Service = RpcPolicyTransport<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcPolicyTransport {
+            inner,
+            request_bucket: self
+                .max_requests_per_second
+                .map(|limit| Arc::new(TokenBucket::new(limit))),
+            compute_unit_bucket: self
+                .max_compute_units_per_second
+                .map(|limit| Arc::new(TokenBucket::new(limit))),
+            circuit_breaker: Arc::new(CircuitBreaker::new(
+                self.circuit_breaker_threshold,
+                self.circuit_breaker_cooldown,
+            )),
+            config: Arc::new(self.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcPolicyTransport<S> {
+    inner: S,
+    config: Arc<RpcPolicyLayer>,
+    request_bucket: Option<Arc<TokenBucket>>,
+    compute_unit_bucket: Option<Arc<TokenBucket>>,
+    circuit_breaker: Arc<CircuitBreaker>,
+}
+
+impl<S> RpcPolicyTransport<S>
+where
+    S: Transport + Clone,
+{
+    async fn throttle(&self, compute_units: u32) {
+        if let Some(request_bucket) = &self.request_bucket {
+            request_bucket.acquire(1).await;
+        }
+        if let Some(compute_unit_bucket) = &self.compute_unit_bucket {
+            compute_unit_bucket.acquire(compute_units).await;
+        }
+    }
+
+    async fn send(mut self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let policy = self.config.policy(&request);
+        let is_exempt = request_methods(&request)
+            .iter()
+            .all(|method| CIRCUIT_BREAKER_EXEMPT_METHODS.contains(method));
+
+        let mut retry = 0;
+        loop {
+            if !is_exempt && self.circuit_breaker.is_open() {
+                return Err(TransportErrorKind::custom_str("rpc circuit breaker open"));
+            }
+            self.throttle(policy.compute_units).await;
+
+            let result = self.inner.call(request.clone()).await;
+            let is_transient = result.as_ref().map_or(true, is_endpoint_error);
+            self.circuit_breaker.record(is_transient);
+            if !is_transient || retry >= policy.max_retries {
+                return result;
+            }
+
+            let backoff = self.config.backoff(retry);
+            warn!(
+                methods = ?request_methods(&request),
+                retry,
+                ?backoff,
+                "retrying rpc request"
+            );
+            tokio::time::sleep(backoff).await;
+            retry += 1;
+        }
+    }
+}
+
+impl<S> Service<RequestPacket> for RpcPolicyTransport<S>
+where
+    S: Transport + Clone,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        Box::pin(self.clone().send(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitBreaker, RpcPolicy, RpcPolicyLayer, TokenBucket};
+
+    use alloy::{
+        rpc::json_rpc::{Id, Request, RequestPacket, ResponsePacket, ResponsePayload},
+        transports::{TransportError, TransportFut},
+    };
+    use eyre::Result;
+    use std::{
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::Duration,
+    };
+    use tower::{Layer, Service};
+
+    // Answers each request with the next response, repeating the last
+    #[derive(Clone)]
+    struct MockTransport {
+        responses: Arc<Mutex<Vec<&'static str>>>,
+        call_count: Arc<Mutex<usize>>,
+    }
+
+    impl MockTransport {
+        fn new(mut responses: Vec<&'static str>) -> Self {
+            responses.reverse();
+            Self {
+                responses: Arc::new(Mutex::new(responses)),
+                call_count: Arc::new(Mutex::new(0)),
+            }
+        }
+
+        fn call_count(&self) -> usize {
+            *self.call_count.lock().unwrap()
+        }
+    }
+
+    impl Service<RequestPacket> for MockTransport {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: RequestPacket) -> Self::Future {
+            *self.call_count.lock().unwrap() += 1;
+            let mut responses = self.responses.lock().unwrap();
+            let response = if responses.len() > 1 {
+                responses.pop().unwrap()
+            } else {
+                responses[0]
+            };
+
+            Box::pin(async move { Ok(serde_json::from_str(response).unwrap()) })
+        }
+    }
+
+    const RATE_LIMITED: &str =
+        r#"{"jsonrpc":"2.0","id":0,"error":{"code":429,"message":"Too many requests"}}"#;
+    const SUCCESS: &str = r#"{"jsonrpc":"2.0","id":0,"result":"0x10"}"#;
+
+    fn request(method: &'static str) -> Result<RequestPacket> {
+        Ok(RequestPacket::Single(
+            Request::new(method, Id::Number(0), ()).serialize()?,
+        ))
+    }
+
+    fn is_success(response: &ResponsePacket) -> bool {
+        match response {
+            ResponsePacket::Single(response) => {
+                matches!(response.payload, ResponsePayload::Success(_))
+            }
+            ResponsePacket::Batch(_) => false,
+        }
+    }
+
+    #[test]
+    fn test_policy() -> Result<()> {
+        assert_eq!(
+            "3:75".parse::<RpcPolicy>()?,
+            RpcPolicy {
+                max_retries: 3,
+                compute_units: 75
+            }
+        );
+        assert!("3".parse::<RpcPolicy>().is_err());
+        assert!("three:75".parse::<RpcPolicy>().is_err());
+
+        let layer = RpcPolicyLayer::new();
+        assert_eq!(layer.policy(&request("eth_getLogs")?).compute_units, 75);
+        assert_eq!(layer.policy(&request("eth_getLogs")?).max_retries, 5);
+        assert_eq!(layer.policy(&request("eth_unknown")?).compute_units, 20);
+        assert_eq!(
+            layer.policy(&request("eth_sendRawTransaction")?),
+            RpcPolicy {
+                max_retries: 0,
+                compute_units: 250,
+            }
+        );
+
+        let layer = layer.with_method_policy(
+            "eth_sendRawTransaction",
+            RpcPolicy {
+                max_retries: 2,
+                compute_units: 250,
+            },
+        );
+        assert_eq!(
+            layer
+                .policy(&request("eth_sendRawTransaction")?)
+                .max_retries,
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backoff() {
+        let layer = RpcPolicyLayer::new()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(1_000));
+
+        for _ in 0..10 {
+            let backoff = layer.backoff(0);
+            assert!(backoff >= Duration::from_millis(50) && backoff <= Duration::from_millis(100));
+
+            let backoff = layer.backoff(2);
+            assert!(backoff >= Duration::from_millis(200) && backoff <= Duration::from_millis(400));
+
+            let backoff = layer.backoff(10);
+            assert!(
+                backoff >= Duration::from_millis(500) && backoff <= Duration::from_millis(1_000)
+            );
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = TokenBucket::new(10);
+        assert_eq!(bucket.reserve(10), Duration::ZERO);
+
+        // Reserving past the bucket waits for the refill
+        let wait = bucket.reserve(5);
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let circuit_breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        circuit_breaker.record(true);
+        assert!(!circuit_breaker.is_open());
+        circuit_breaker.record(true);
+        assert!(circuit_breaker.is_open());
+
+        circuit_breaker.record(false);
+        assert!(!circuit_breaker.is_open());
+    }
+
+    #[tokio::test]
+    async fn test_retry() -> Result<()> {
+        let inner = MockTransport::new(vec![RATE_LIMITED, RATE_LIMITED, SUCCESS]);
+        let mut transport = RpcPolicyLayer::new()
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .layer(inner.clone());
+
+        assert!(is_success(
+            &transport.call(request("eth_blockNumber")?).await?
+        ));
+        assert_eq!(inner.call_count(), 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() -> Result<()> {
+        let inner = MockTransport::new(vec![RATE_LIMITED]);
+        let mut transport = RpcPolicyLayer::new()
+            .with_max_retries(2)
+            .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+            .with_circuit_breaker(5, Duration::from_secs(60))
+            .layer(inner.clone());
+
+        // The last error response is passed through
+        assert!(!is_success(
+            &transport.call(request("eth_blockNumber")?).await?
+        ));
+        assert_eq!(inner.call_count(), 3);
+
+        // Which trips the breaker on the next request's failures
+        assert!(transport.call(request("eth_blockNumber")?).await.is_err());
+        assert_eq!(inner.call_count(), 5);
+
+        // Receipts of txs in flight are still polled through the open breaker
+        assert!(!is_success(
+            &transport
+                .call(request("eth_getTransactionReceipt")?)
+                .await?
+        ));
+        assert_eq!(inner.call_count(), 8);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use super::send_json_rpc;
+
+use alloy::{
+    consensus::SignableTransaction,
+    primitives::{Address, Bytes, Signature, B256},
+    signers::TxSigner,
+};
+
+use async_trait::async_trait;
+use eyre::{eyre, Result, WrapErr};
+use url::Url;
+
+// Signs with a key held by a signing service over http, keeping it out of
+// this process. The service speaks json rpc, listing its account from
+// eth_accounts and signing raw hashes with signer_signHash.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: Url,
+    address: Address,
+}
+
+impl RemoteSigner {
+    // Connects to the service, signing as its first account
+    pub async fn connect(url: Url) -> Result<Self> {
+        let client = reqwest::Client::new();
+        let accounts: Vec<Address> =
+            send_json_rpc(&client, &url, "eth_accounts", serde_json::json!([])).await?;
+        let address = accounts
+            .first()
+            .copied()
+            .ok_or_else(|| eyre!("Remote signer at {} has no accounts", url))?;
+
+        Ok(Self {
+            client,
+            url,
+            address,
+        })
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    pub async fn sign_hash(&self, hash: &B256) -> Result<Signature> {
+        let signature: Bytes = send_json_rpc(
+            &self.client,
+            &self.url,
+            "signer_signHash",
+            serde_json::json!([self.address, hash]),
+        )
+        .await?;
+
+        Signature::try_from(signature.as_ref()).wrap_err("Failed to parse remote signature")
+    }
+}
+
+#[async_trait]
+impl TxSigner<Signature> for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        let signature = self
+            .sign_hash(&tx.signature_hash())
+            .await
+            .map_err(alloy::signers::Error::other)?;
+
+        // Legacy txs fold the chain id into v
+        match tx.chain_id() {
+            Some(chain_id) if tx.use_eip155() => Ok(signature.with_chain_id(chain_id)),
+            _ => Ok(signature),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RemoteSigner;
+    use crate::test_utils::mock_json_rpc_server;
+
+    use alloy::{
+        primitives::b256,
+        signers::{wallet::LocalWallet, Signer},
+    };
+    use eyre::Result;
+    use hex_literal::hex;
+
+    #[tokio::test]
+    async fn test_sign_hash() -> Result<()> {
+        let wallet = LocalWallet::from_bytes(
+            &hex!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").into(),
+        )?;
+        let hash = b256!("0000000000000000000000000000000000000000000000000000000000000001");
+        let signature = wallet.sign_hash(&hash).await?;
+
+        let (url, _) = mock_json_rpc_server(vec![
+            format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":["{}"]}}"#,
+                wallet.address()
+            ),
+            format!(
+                r#"{{"jsonrpc":"2.0","id":1,"result":"0x{}"}}"#,
+                alloy::primitives::hex::encode(signature.as_bytes())
+            ),
+        ])
+        .await?;
+
+        let remote_signer = RemoteSigner::connect(url).await?;
+        assert_eq!(*remote_signer.address(), wallet.address());
+
+        let remote_signature = remote_signer.sign_hash(&hash).await?;
+        assert_eq!(remote_signature, signature);
+        assert_eq!(
+            remote_signature.recover_address_from_prehash(&hash)?,
+            wallet.address()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connect_without_accounts() -> Result<()> {
+        let (url, _) =
+            mock_json_rpc_server(vec![r#"{"jsonrpc":"2.0","id":1,"result":[]}"#.to_string()])
+                .await?;
+        assert!(RemoteSigner::connect(url).await.is_err());
+
+        Ok(())
+    }
+}
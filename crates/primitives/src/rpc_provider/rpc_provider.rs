@@ -1,75 +1,104 @@
-use super::{BlockProvider, DexProvider, TTLCache};
+use super::{
+    failover_transport::is_log_limit_message, multicall::multicall, BlockProvider, DexProvider,
+    FailoverTransport, RpcPolicyLayer, RpcPolicyTransport, SignerSource, TTLCache,
+};
+use crate::abi::{erc20, multicall3};
 
 use alloy::{
-    network::{Ethereum, EthereumSigner},
-    primitives::{Address, TxHash, B256},
+    eips::eip2718::Encodable2718,
+    network::{Ethereum, EthereumSigner, TransactionBuilder},
+    primitives::{Address, BlockNumber, Bytes, TxHash, U256},
     providers::{
         layers::{GasEstimatorProvider, ManagedNonceProvider, SignerProvider},
         PendingTransactionBuilder, Provider, ProviderBuilder, RootProvider,
     },
-    rpc::types::{
-        eth::{BlockId, Filter, Header, Log, TransactionReceipt, TransactionRequest},
-        trace::parity::{TraceResults, TraceType},
+    rpc::{
+        client::RpcClient,
+        types::{
+            eth::{
+                BlockId, BlockNumberOrTag, FeeHistory, Filter, Header, Log, TransactionReceipt,
+                TransactionRequest,
+            },
+            trace::parity::{TraceResults, TraceType},
+        },
     },
-    signers::wallet::LocalWallet,
-    transports::{http::Http, Transport, TransportResult},
+    sol_types::SolCall,
+    transports::{http::Http, RpcError, Transport, TransportResult},
 };
 
 use eyre::{eyre, Result, WrapErr};
+use fnv::FnvHashMap;
 use std::sync::Arc;
-use tracing::instrument;
+use tower::Layer;
+use tracing::{debug, instrument};
 
 pub struct RpcProvider<T: Transport + Clone, P: Provider<T, Ethereum>> {
     signer_address: Address,
+    // Signs txs submitted outside of the provider
+    signer: EthereumSigner,
     inner: Arc<P>,
 
     dex_provider: DexProvider<T, P>,
     block_provider: BlockProvider<T, P>,
 }
 
+// Provider signing with the configured signer over the transport
+pub type SignerRpcProvider<T> = RpcProvider<
+    T,
+    SignerProvider<
+        T,
+        GasEstimatorProvider<T, ManagedNonceProvider<T, RootProvider<T>>, Ethereum>,
+        EthereumSigner,
+    >,
+>;
+
 pub async fn new_http_signer_provider(
     rpc_url: url::Url,
-    signer_wallet_private_key: &B256,
+    signer_source: &SignerSource,
     finalized_block_header_cache: Option<TTLCache<Header>>,
     is_backtest: bool,
-) -> Result<
-    RpcProvider<
-        Http<reqwest::Client>,
-        SignerProvider<
-            Http<reqwest::Client>,
-            GasEstimatorProvider<
-                Http<reqwest::Client>,
-                ManagedNonceProvider<Http<reqwest::Client>, RootProvider<Http<reqwest::Client>>>,
-                Ethereum,
-            >,
-            EthereumSigner,
-        >,
-    >,
-> {
-    let signer = LocalWallet::from_bytes(signer_wallet_private_key)?;
-    let signer_address = signer.address();
-    let inner = Arc::new(
-        ProviderBuilder::new()
-            .signer(EthereumSigner::from(signer))
-            .with_gas_estimation()
-            .with_nonce_management()
-            .on_reqwest_http(rpc_url)
-            .map_err(|err| eyre!("Failed to create provider: {:?}", err))?,
-    );
-
-    let dex_provider = DexProvider::new(Arc::clone(&inner));
-    let block_provider = BlockProvider::new(
-        Arc::clone(&inner),
+) -> Result<SignerRpcProvider<Http<reqwest::Client>>> {
+    let (signer_address, signer) = signer_source.signer().await?;
+    let inner = ProviderBuilder::new()
+        .signer(signer.clone())
+        .with_gas_estimation()
+        .with_nonce_management()
+        .on_reqwest_http(rpc_url)
+        .map_err(|err| eyre!("Failed to create provider: {:?}", err))?;
+
+    Ok(RpcProvider::from_inner(
+        Arc::new(inner),
+        signer_address,
+        signer,
         finalized_block_header_cache,
         is_backtest,
-    );
+    ))
+}
 
-    Ok(RpcProvider {
-        inner,
+// Like new_http_signer_provider, but spreading requests over several
+// endpoints, with every request going through the policy layer's retries,
+// rate limits and circuit breaker
+pub async fn new_failover_signer_provider(
+    transport: FailoverTransport,
+    policy_layer: RpcPolicyLayer,
+    signer_source: &SignerSource,
+    finalized_block_header_cache: Option<TTLCache<Header>>,
+    is_backtest: bool,
+) -> Result<SignerRpcProvider<RpcPolicyTransport<FailoverTransport>>> {
+    let (signer_address, signer) = signer_source.signer().await?;
+    let inner = ProviderBuilder::new()
+        .signer(signer.clone())
+        .with_gas_estimation()
+        .with_nonce_management()
+        .on_client(RpcClient::new(policy_layer.layer(transport), false));
+
+    Ok(RpcProvider::from_inner(
+        Arc::new(inner),
         signer_address,
-        dex_provider,
-        block_provider,
-    })
+        signer,
+        finalized_block_header_cache,
+        is_backtest,
+    ))
 }
 
 impl<T, P> RpcProvider<T, P>
@@ -77,6 +106,26 @@ where
     T: Transport + Clone,
     P: Provider<T, Ethereum> + 'static,
 {
+    fn from_inner(
+        inner: Arc<P>,
+        signer_address: Address,
+        signer: EthereumSigner,
+        finalized_block_header_cache: Option<TTLCache<Header>>,
+        is_backtest: bool,
+    ) -> Self {
+        Self {
+            dex_provider: DexProvider::new(Arc::clone(&inner)),
+            block_provider: BlockProvider::new(
+                Arc::clone(&inner),
+                finalized_block_header_cache,
+                is_backtest,
+            ),
+            inner,
+            signer_address,
+            signer,
+        }
+    }
+
     pub fn block_provider(&self) -> &BlockProvider<T, P> {
         &self.block_provider
     }
@@ -109,11 +158,80 @@ where
             .wrap_err("send_transaction failed")
     }
 
+    // Signs a tx for submission elsewhere, e.g. to a private rpc or a bundle
+    // relay, returning its hash and raw encoding. The gas limit is estimated
+    // if not set, so it must be set if the tx depends on another in a bundle.
+    pub async fn sign_transaction(
+        &self,
+        tx_request: TransactionRequest,
+    ) -> Result<(TxHash, Bytes)> {
+        let tx_request = match tx_request.gas {
+            Some(_) => tx_request,
+            None => {
+                let gas_limit = self
+                    .inner
+                    .estimate_gas(&tx_request, None)
+                    .await
+                    .wrap_err("estimate_gas failed")?;
+                tx_request.with_gas_limit(gas_limit.to::<u128>())
+            }
+        };
+        let chain_id = self
+            .inner
+            .get_chain_id()
+            .await
+            .wrap_err("get_chain_id failed")?;
+
+        let tx_envelope = tx_request
+            .with_chain_id(chain_id.to::<u64>())
+            .build(&self.signer)
+            .await
+            .map_err(|err| eyre!("Failed to sign tx: {:?}", err))?;
+
+        Ok((*tx_envelope.tx_hash(), tx_envelope.encoded_2718().into()))
+    }
+
     #[instrument(skip(self))]
     pub async fn get_logs(&self, filter: &Filter) -> TransportResult<Vec<Log>> {
         self.inner.get_logs(filter).await
     }
 
+    // Fetches the filter's logs over the block range, bisecting any part of
+    // the range the endpoint rejects as too large
+    #[instrument(skip(self, filter))]
+    pub async fn get_logs_in_range(
+        &self,
+        filter: &Filter,
+        start_block_number: BlockNumber,
+        end_block_number: BlockNumber,
+    ) -> Result<Vec<Log>> {
+        let mut logs = Vec::new();
+        let mut ranges = vec![(start_block_number, end_block_number)];
+        while let Some((start, end)) = ranges.pop() {
+            let range_filter = filter.clone().from_block(start).to_block(end);
+            match self.inner.get_logs(&range_filter).await {
+                Ok(range_logs) => logs.extend(range_logs),
+                Err(RpcError::ErrorResp(err))
+                    if start < end && is_log_limit_message(&err.message) =>
+                {
+                    debug!(start, end, "Splitting get_logs range: {}", err.message);
+
+                    // The first half is popped first so logs stay in order
+                    let mid = start + (end - start) / 2;
+                    ranges.push((mid + 1, end));
+                    ranges.push((start, mid));
+                }
+                Err(err) => {
+                    return Err(err).wrap_err_with(|| {
+                        format!("get_logs failed for blocks {} to {}", start, end)
+                    })
+                }
+            }
+        }
+
+        Ok(logs)
+    }
+
     pub async fn get_transaction_receipt(
         &self,
         hash: TxHash,
@@ -124,6 +242,93 @@ where
             .wrap_err(format!("get_transaction_receipt {} failed", hash))
     }
 
+    pub async fn get_balance(&self, address: Address, block_id: Option<BlockId>) -> Result<U256> {
+        self.inner
+            .get_balance(address, block_id)
+            .await
+            .wrap_err(format!("get_balance {} failed", address))
+    }
+
+    // The signer's balance of an ERC20 token
+    pub async fn get_token_balance(
+        &self,
+        token_address: Address,
+        block_id: Option<BlockId>,
+    ) -> Result<U256> {
+        self.inner
+            .call(
+                &erc20::balance_of_tx_request(self.signer_address, token_address),
+                block_id,
+            )
+            .await
+            .wrap_err(format!("balanceOf {} failed", token_address))
+            .and_then(|res| {
+                erc20::IERC20::balanceOfCall::abi_decode_returns(&res, cfg!(debug_assertions))
+                    .map(|res| res._0)
+                    .wrap_err("failed to decode balanceOf returns")
+            })
+    }
+
+    // The signer's balances of many ERC20 tokens in batched multicalls.
+    // Tokens whose balanceOf fails are left out.
+    pub async fn get_token_balances(
+        &self,
+        token_addresses: &[Address],
+        block_id: Option<BlockId>,
+    ) -> Result<FnvHashMap<Address, U256>>
+    where
+        T: 'static,
+    {
+        let calls = token_addresses
+            .iter()
+            .map(|token_address| multicall3::Call3 {
+                target: *token_address,
+                allowFailure: true,
+                callData: erc20::IERC20::balanceOfCall {
+                    account: self.signer_address,
+                }
+                .abi_encode()
+                .into(),
+            })
+            .collect::<Vec<_>>();
+
+        let results = multicall(Arc::clone(&self.inner), calls, block_id).await?;
+
+        Ok(token_addresses
+            .iter()
+            .zip(results.into_iter())
+            .filter(|(_, result)| result.success)
+            .filter_map(|(token_address, result)| {
+                erc20::IERC20::balanceOfCall::abi_decode_returns(
+                    &result.returnData,
+                    cfg!(debug_assertions),
+                )
+                .ok()
+                .map(|res| (*token_address, res._0))
+            })
+            .collect())
+    }
+
+    pub async fn get_transaction_count(&self, address: Address, block_id: BlockId) -> Result<u64> {
+        self.inner
+            .get_transaction_count(address, Some(block_id))
+            .await
+            .map(|count| count.to::<u64>())
+            .wrap_err(format!("get_transaction_count {} failed", address))
+    }
+
+    pub async fn get_fee_history(
+        &self,
+        block_count: u64,
+        last_block: BlockNumberOrTag,
+        reward_percentiles: &[f64],
+    ) -> Result<FeeHistory> {
+        self.inner
+            .get_fee_history(block_count, last_block, reward_percentiles)
+            .await
+            .wrap_err(format!("get_fee_history {} failed", last_block))
+    }
+
     // custom api
     pub fn signer_address(&self) -> &Address {
         &self.signer_address
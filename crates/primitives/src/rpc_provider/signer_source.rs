@@ -0,0 +1,126 @@
+use super::RemoteSigner;
+
+use alloy::{
+    network::EthereumSigner,
+    primitives::{Address, B256},
+    signers::wallet::LocalWallet,
+};
+
+#[cfg(feature = "mnemonic")]
+use alloy::signers::wallet::{coins_bip39::English, MnemonicBuilder};
+
+use eyre::{Result, WrapErr};
+use url::Url;
+
+// First account of the standard ethereum derivation path
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+// Where the key signing our txs comes from
+#[derive(Clone)]
+pub enum SignerSource {
+    PrivateKey(B256),
+    // An encrypted json keystore and its password
+    #[cfg(feature = "keystore")]
+    Keystore {
+        path: std::path::PathBuf,
+        password: String,
+    },
+    #[cfg(feature = "mnemonic")]
+    Mnemonic {
+        phrase: String,
+        derivation_path: String,
+    },
+    // A signing service over http holding the key
+    Remote(Url),
+}
+
+impl SignerSource {
+    // The signer's address along with a signer for its txs
+    pub async fn signer(&self) -> Result<(Address, EthereumSigner)> {
+        let wallet = match self {
+            Self::PrivateKey(private_key) => LocalWallet::from_bytes(private_key)?,
+            #[cfg(feature = "keystore")]
+            Self::Keystore { path, password } => LocalWallet::decrypt_keystore(path, password)
+                .wrap_err_with(|| format!("Failed to decrypt keystore {}", path.display()))?,
+            #[cfg(feature = "mnemonic")]
+            Self::Mnemonic {
+                phrase,
+                derivation_path,
+            } => MnemonicBuilder::<English>::default()
+                .phrase(phrase.as_str())
+                .derivation_path(derivation_path.as_str())?
+                .build()
+                .wrap_err("Failed to derive wallet from mnemonic")?,
+            Self::Remote(url) => {
+                let remote_signer = RemoteSigner::connect(url.clone())
+                    .await
+                    .wrap_err_with(|| format!("Failed to connect to remote signer {}", url))?;
+                let signer_address = *remote_signer.address();
+
+                return Ok((signer_address, EthereumSigner::from(remote_signer)));
+            }
+        };
+
+        Ok((wallet.address(), EthereumSigner::from(wallet)))
+    }
+}
+
+impl From<B256> for SignerSource {
+    fn from(private_key: B256) -> Self {
+        Self::PrivateKey(private_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SignerSource;
+
+    use alloy::primitives::address;
+    use eyre::Result;
+    use hex_literal::hex;
+
+    #[tokio::test]
+    async fn test_private_key_signer() -> Result<()> {
+        let (signer_address, _) = SignerSource::PrivateKey(
+            hex!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").into(),
+        )
+        .signer()
+        .await?;
+        assert_eq!(
+            signer_address,
+            address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mnemonic")]
+    #[tokio::test]
+    async fn test_mnemonic_signer() -> Result<()> {
+        let phrase = "test test test test test test test test test test test junk";
+
+        let (signer_address, _) = SignerSource::Mnemonic {
+            phrase: phrase.to_string(),
+            derivation_path: super::DEFAULT_DERIVATION_PATH.to_string(),
+        }
+        .signer()
+        .await?;
+        assert_eq!(
+            signer_address,
+            address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266")
+        );
+
+        let (signer_address, _) = SignerSource::Mnemonic {
+            phrase: phrase.to_string(),
+            derivation_path: "m/44'/60'/0'/0/1".to_string(),
+        }
+        .signer()
+        .await?;
+        assert_eq!(
+            signer_address,
+            address!("70997970C51812dc3A010C7d01b50e0d17dc79C8")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use eyre::Result;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc::{self, UnboundedReceiver},
+};
+use url::Url;
+
+// Serves the json rpc response bodies in order, one per connection, repeating
+// the last once the rest are used up. Each request body is forwarded to the
+// returned receiver.
+pub async fn mock_json_rpc_server(
+    responses: Vec<String>,
+) -> Result<(Url, UnboundedReceiver<serde_json::Value>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}", listener.local_addr()?).parse()?;
+    let (request_sender, request_receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut index = 0;
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            let (headers_len, content_length) = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+
+                if let Some(index) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let headers = String::from_utf8_lossy(&request[..index]).to_lowercase();
+                    let content_length = headers
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length:"))
+                        .map(|value| value.trim().parse::<usize>().unwrap())
+                        .unwrap_or(0);
+                    break (index + 4, content_length);
+                }
+            };
+            while request.len() < headers_len + content_length {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            let response = &responses[index.min(responses.len() - 1)];
+            index += 1;
+            stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        response.len(),
+                        response
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+
+            // The test may have stopped listening for requests
+            let _ = request_sender.send(
+                serde_json::from_slice(&request[headers_len..headers_len + content_length])
+                    .unwrap_or_default(),
+            );
+        }
+    });
+
+    Ok((url, request_receiver))
+}
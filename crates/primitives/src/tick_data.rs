@@ -5,6 +5,7 @@ use alloy::primitives::Address;
 use fixed::types::U32F96;
 use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct TickData {
@@ -12,7 +13,27 @@ pub struct TickData {
     pub high: U32F96,
     pub low: U32F96,
     pub close: U32F96,
+    // total weth traded through the pair, buys and sells alike. Unlike the
+    // per-block ticks this now also accumulates when ticks are reduced into
+    // a bar, so a bar's volume is the sum of its ticks rather than its first
     pub weth_volume: BigUint,
+
+    // order flow, defaulted so ticks persisted before these were tracked
+    // still deserialize
+    #[serde(default)]
+    pub buy_weth_volume: BigUint,
+    #[serde(default)]
+    pub sell_weth_volume: BigUint,
+    #[serde(default)]
+    pub trade_count: u64,
+    #[serde(default)]
+    pub unique_maker_count: u64,
+    // the maker set is only kept in memory so that unique makers aggregate
+    // correctly across blocks and bars. Persisting it would grow every stored
+    // bar with its trader count, so only the count is stored and ticks
+    // reduced after a reload fall back to the largest count seen
+    #[serde(skip)]
+    pub makers: BTreeSet<Address>,
 }
 
 impl TickData {
@@ -29,6 +50,11 @@ impl TickData {
             low,
             close,
             weth_volume,
+            buy_weth_volume: BigUint::ZERO,
+            sell_weth_volume: BigUint::ZERO,
+            trade_count: 0,
+            unique_maker_count: 0,
+            makers: BTreeSet::new(),
         }
     }
 
@@ -44,11 +70,32 @@ impl TickData {
                 }
                 acc.close = price_bar.close;
 
+                acc.weth_volume += &price_bar.weth_volume;
+                acc.buy_weth_volume += &price_bar.buy_weth_volume;
+                acc.sell_weth_volume += &price_bar.sell_weth_volume;
+                acc.trade_count += price_bar.trade_count;
+                acc.makers.extend(price_bar.makers.iter().copied());
+                acc.unique_maker_count = (acc.makers.len() as u64)
+                    .max(acc.unique_maker_count)
+                    .max(price_bar.unique_maker_count);
+
                 Some(acc)
             }
         })
     }
 
+    // A flat tick at this tick's close with no volume, used to pad blocks in
+    // which the pair did not trade.
+    pub fn carry_forward(&self) -> Self {
+        Self::new(
+            self.close,
+            self.close,
+            self.close,
+            self.close,
+            BigUint::ZERO,
+        )
+    }
+
     pub fn is_negative(&self) -> bool {
         self.close < self.open
     }
@@ -66,12 +113,24 @@ impl TickData {
             (price_after.clone(), price_before.clone())
         };
 
+        let weth_volume: BigUint = indexed_trade.weth_volume(token_address).try_into().unwrap();
+        let (buy_weth_volume, sell_weth_volume) = if indexed_trade.is_buy(token_address) {
+            (weth_volume.clone(), BigUint::ZERO)
+        } else {
+            (BigUint::ZERO, weth_volume.clone())
+        };
+
         Self {
             open: price_before,
             close: price_after,
             high,
             low,
-            weth_volume: indexed_trade.weth_volume(token_address).try_into().unwrap(),
+            weth_volume,
+            buy_weth_volume,
+            sell_weth_volume,
+            trade_count: 1,
+            unique_maker_count: 1,
+            makers: BTreeSet::from([*indexed_trade.maker()]),
         }
     }
 
@@ -90,7 +149,17 @@ impl TickData {
 
         let indexed_trade_weth_volume: BigUint =
             indexed_trade.weth_volume(token_address).try_into().unwrap();
+        if indexed_trade.is_buy(token_address) {
+            self.buy_weth_volume += &indexed_trade_weth_volume;
+        } else {
+            self.sell_weth_volume += &indexed_trade_weth_volume;
+        }
         self.weth_volume += indexed_trade_weth_volume;
+
+        self.trade_count += 1;
+        if self.makers.insert(*indexed_trade.maker()) {
+            self.unique_maker_count += 1;
+        }
     }
 }
 
@@ -101,6 +170,9 @@ mod tests {
     use crate::{DexIndexedTrade, UniswapV2IndexedTrade};
 
     use alloy::primitives::{address, uint, Address};
+    use fixed::types::U32F96;
+    use num_bigint::BigUint;
+    use std::collections::BTreeSet;
 
     #[test]
     fn test_from_uniswap_v2_pair() {
@@ -135,5 +207,89 @@ mod tests {
         assert_eq!(tick_data.close, trades[1].token_price_after(&token_address));
         assert_eq!(tick_data.high, trades[0].token_price_before(&token_address));
         assert_eq!(tick_data.low, trades[0].token_price_after(&token_address));
+
+        assert_eq!(tick_data.trade_count, 2);
+        assert_eq!(tick_data.unique_maker_count, 2);
+        assert_eq!(
+            tick_data.buy_weth_volume,
+            BigUint::from(7500000000000000_u128)
+        );
+        assert_eq!(
+            tick_data.sell_weth_volume,
+            BigUint::from(110094173315701195_u128)
+        );
+        assert_eq!(
+            tick_data.weth_volume,
+            &tick_data.buy_weth_volume + &tick_data.sell_weth_volume
+        );
+    }
+
+    #[test]
+    fn test_reduce_aggregates_flow() {
+        let maker_a = address!("1Fba6b0BBae2B74586fBA407Fb45Bd4788B7b130");
+        let maker_b = address!("7381C38985dA304eBA18fCef5E1f6e9fA0798b84");
+
+        let mut first = TickData::new(
+            U32F96::from_num(2),
+            U32F96::from_num(3),
+            U32F96::from_num(1),
+            U32F96::from_num(2),
+            BigUint::from(10_u64),
+        );
+        first.buy_weth_volume = BigUint::from(10_u64);
+        first.trade_count = 2;
+        first.unique_maker_count = 1;
+        first.makers = BTreeSet::from([maker_a]);
+
+        let mut second = TickData::new(
+            U32F96::from_num(2),
+            U32F96::from_num(4),
+            U32F96::from_num(2),
+            U32F96::from_num(4),
+            BigUint::from(5_u64),
+        );
+        second.sell_weth_volume = BigUint::from(5_u64);
+        second.trade_count = 1;
+        second.unique_maker_count = 2;
+        second.makers = BTreeSet::from([maker_a, maker_b]);
+
+        let padding = second.carry_forward();
+        let reduced = TickData::reduce(vec![first, second, padding].iter()).unwrap();
+
+        assert_eq!(reduced.open, U32F96::from_num(2));
+        assert_eq!(reduced.high, U32F96::from_num(4));
+        assert_eq!(reduced.low, U32F96::from_num(1));
+        assert_eq!(reduced.close, U32F96::from_num(4));
+        assert_eq!(reduced.weth_volume, BigUint::from(15_u64));
+        assert_eq!(reduced.buy_weth_volume, BigUint::from(10_u64));
+        assert_eq!(reduced.sell_weth_volume, BigUint::from(5_u64));
+        assert_eq!(reduced.trade_count, 3);
+        assert_eq!(reduced.unique_maker_count, 2);
+    }
+
+    #[test]
+    fn test_persists_maker_count_without_makers() {
+        let mut tick_data = TickData::new(
+            U32F96::from_num(1),
+            U32F96::from_num(1),
+            U32F96::from_num(1),
+            U32F96::from_num(1),
+            BigUint::ZERO,
+        );
+        tick_data.unique_maker_count = 2;
+        tick_data.makers = BTreeSet::from([
+            address!("1Fba6b0BBae2B74586fBA407Fb45Bd4788B7b130"),
+            address!("7381C38985dA304eBA18fCef5E1f6e9fA0798b84"),
+        ]);
+
+        let value = serde_json::to_value(&tick_data).unwrap();
+        assert!(value.get("makers").is_none());
+
+        let restored: TickData = serde_json::from_value(value).unwrap();
+        assert_eq!(restored.unique_maker_count, 2);
+        assert!(restored.makers.is_empty());
+
+        let reduced = TickData::reduce(vec![restored, tick_data.carry_forward()].iter()).unwrap();
+        assert_eq!(reduced.unique_maker_count, 2);
     }
 }
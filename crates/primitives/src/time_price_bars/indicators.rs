@@ -5,6 +5,7 @@ use fixed::{
     FixedU128,
 };
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use tracing::warn;
 
@@ -12,18 +13,56 @@ pub const INDICATOR_BB_PERIOD: u64 = 20;
 pub const INDICATOR_EMA_PERIOD: u64 = 9;
 
 lazy_static! {
-    pub static ref Q_INDICATOR_BB_PERIOD: U32F96 = FixedU128::from_num(INDICATOR_BB_PERIOD);
     pub static ref Q_INDICATOR_BB_STD_DEV: U32F96 = FixedU128::from_num(2.0);
     pub static ref Q_INDICATOR_EMA_SMOOTHING_FACTOR: I32F96 =
         I32F96::from_num(2) / (I32F96::from_num(INDICATOR_EMA_PERIOD) + I32F96::ONE);
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IndicatorsConfig {
+    // All indicators with the default periods
     All,
+    // All indicators with the given periods, e.g. when sweeping strategy parameters
+    Custom {
+        bb_period: u64,
+        bb_std_dev: U32F96,
+        ema_period: u64,
+    },
 }
 
-#[derive(Debug, Clone, Copy)]
+impl IndicatorsConfig {
+    pub fn bb_period(&self) -> u64 {
+        match self {
+            Self::All => INDICATOR_BB_PERIOD,
+            Self::Custom { bb_period, .. } => *bb_period,
+        }
+    }
+
+    pub fn bb_std_dev(&self) -> U32F96 {
+        match self {
+            Self::All => *Q_INDICATOR_BB_STD_DEV,
+            Self::Custom { bb_std_dev, .. } => *bb_std_dev,
+        }
+    }
+
+    pub fn ema_period(&self) -> u64 {
+        match self {
+            Self::All => INDICATOR_EMA_PERIOD,
+            Self::Custom { ema_period, .. } => *ema_period,
+        }
+    }
+
+    fn ema_smoothing_factor(&self) -> I32F96 {
+        match self {
+            Self::All => *Q_INDICATOR_EMA_SMOOTHING_FACTOR,
+            Self::Custom { ema_period, .. } => {
+                I32F96::from_num(2) / (I32F96::from_num(*ema_period) + I32F96::ONE)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Indicators {
     // sma, upper band, lower band
     pub bollinger_bands: Option<(U32F96, U32F96, U32F96)>,
@@ -36,15 +75,22 @@ impl Indicators {
         timestamp: &ResolutionTimestamp,
         resolution: &Resolution,
         data: &BTreeMap<ResolutionTimestamp, TimePriceBar>,
+        config: &IndicatorsConfig,
     ) -> Option<(U32F96, U32F96, U32F96)> {
+        let bb_period = config.bb_period();
+        if bb_period == 0 {
+            return None;
+        }
+
         let close_prices = data
-            .range(timestamp.decrement(resolution, INDICATOR_BB_PERIOD - 1)..=*timestamp)
+            .range(timestamp.decrement(resolution, bb_period - 1)..=*timestamp)
             .filter_map(|(_, time_price_bar)| time_price_bar.data().map(|d| d.close.clone()));
 
-        if close_prices.clone().count() != INDICATOR_BB_PERIOD as usize {
+        if close_prices.clone().count() != bb_period as usize {
             None
         } else {
-            let sma = close_prices.clone().sum::<U32F96>() / *Q_INDICATOR_BB_PERIOD;
+            let q_bb_period = U32F96::from_num(bb_period);
+            let sma = close_prices.clone().sum::<U32F96>() / q_bb_period;
             let variance = {
                 let variance_sma = sma.to_num::<I32F96>();
                 close_prices
@@ -53,15 +99,16 @@ impl Indicators {
                         (p * p).to_num::<U32F96>()
                     })
                     .sum::<U32F96>()
-                    / *Q_INDICATOR_BB_PERIOD
+                    / q_bb_period
             };
 
             let std_dev = variance.sqrt();
+            let bb_std_dev = config.bb_std_dev();
 
             Some((
                 sma.clone(),
-                sma.clone() + (std_dev * *Q_INDICATOR_BB_STD_DEV),
-                sma.checked_sub(std_dev * *Q_INDICATOR_BB_STD_DEV)
+                sma.clone() + (std_dev * bb_std_dev),
+                sma.checked_sub(std_dev * bb_std_dev)
                     .unwrap_or(U32F96::ZERO),
             ))
         }
@@ -71,6 +118,7 @@ impl Indicators {
         timestamp: &ResolutionTimestamp,
         resolution: &Resolution,
         data: &BTreeMap<ResolutionTimestamp, TimePriceBar>,
+        config: &IndicatorsConfig,
     ) -> (I32F96, I32F96) {
         match (
             data.get(timestamp),
@@ -82,7 +130,7 @@ impl Indicators {
                     .map(|i| i.ema.0)
                     .unwrap_or_else(|| prev_time_price_bar.close().to_num::<I32F96>());
                 let ema = (time_price_bar.close().to_num::<I32F96>() - prev_ema)
-                    * *Q_INDICATOR_EMA_SMOOTHING_FACTOR
+                    * config.ema_smoothing_factor()
                     + prev_ema;
                 (ema, ema - prev_ema)
             }
@@ -110,10 +158,36 @@ impl Indicators {
         timestamp: &ResolutionTimestamp,
         resolution: &Resolution,
         data: &BTreeMap<ResolutionTimestamp, TimePriceBar>,
-        _config: &IndicatorsConfig,
+        config: &IndicatorsConfig,
+    ) -> Indicators {
+        let bollinger_bands = Indicators::bollinger_band(timestamp, resolution, data, config);
+        let ema = Indicators::ema(timestamp, resolution, data, config);
+
+        Indicators::new(bollinger_bands, ema)
+    }
+
+    // Computes the indicators without relying on those already set on the
+    // preceding bars, replaying the ema from the earliest bar in the data.
+    // Used for configs other than the one the bars were indexed with.
+    pub fn replay(
+        timestamp: &ResolutionTimestamp,
+        resolution: &Resolution,
+        data: &BTreeMap<ResolutionTimestamp, TimePriceBar>,
+        config: &IndicatorsConfig,
     ) -> Indicators {
-        let bollinger_bands = Indicators::bollinger_band(timestamp, resolution, data);
-        let ema = Indicators::ema(timestamp, resolution, data);
+        let bollinger_bands = Indicators::bollinger_band(timestamp, resolution, data, config);
+        let ema = data
+            .range(..=*timestamp)
+            .filter_map(|(_, time_price_bar)| time_price_bar.data())
+            .map(|data| data.close.to_num::<I32F96>())
+            .fold(None, |acc, close| match acc {
+                None => Some((close, I32F96::ZERO)),
+                Some((prev_ema, _)) => {
+                    let ema = (close - prev_ema) * config.ema_smoothing_factor() + prev_ema;
+                    Some((ema, ema - prev_ema))
+                }
+            })
+            .unwrap_or((I32F96::ZERO, I32F96::ZERO));
 
         Indicators::new(bollinger_bands, ema)
     }
@@ -126,7 +200,7 @@ mod tests {
         TimePriceBar,
     };
 
-    use super::{Indicators, INDICATOR_BB_PERIOD};
+    use super::{Indicators, IndicatorsConfig, INDICATOR_BB_PERIOD};
 
     use alloy::primitives::{uint, U256};
 
@@ -166,9 +240,13 @@ mod tests {
                 })
         };
 
-        let (sma, upper_band, lower_band) =
-            Indicators::bollinger_band(&mock_timestamp, &Resolution::FiveMinutes, &data)
-                .expect("Bollinger band not found");
+        let (sma, upper_band, lower_band) = Indicators::bollinger_band(
+            &mock_timestamp,
+            &Resolution::FiveMinutes,
+            &data,
+            &IndicatorsConfig::All,
+        )
+        .expect("Bollinger band not found");
 
         assert_eq!(sma.to_string(), "10.5");
         assert_eq!(upper_band.to_string(), "22.0325625946707958893541832388");
@@ -208,11 +286,68 @@ mod tests {
                 })
         };
 
-        let result = Indicators::bollinger_band(&mock_timestamp, &Resolution::FiveMinutes, &data);
+        let result = Indicators::bollinger_band(
+            &mock_timestamp,
+            &Resolution::FiveMinutes,
+            &data,
+            &IndicatorsConfig::All,
+        );
 
         assert_eq!(result, None);
     }
 
+    #[test]
+    fn test_custom_bollinger_band() {
+        let mock_timestamp = ResolutionTimestamp::from_timestamp(10000, &Resolution::FiveMinutes);
+        let data = (0..5u64).fold(BTreeMap::new(), |mut acc, idx| {
+            acc.insert(
+                mock_timestamp.decrement(&Resolution::FiveMinutes, idx),
+                TimePriceBar::Finalized(FinalizedTimePriceBar::new(
+                    1,
+                    1,
+                    TickData::new(
+                        U32F96::ONE,
+                        U32F96::ONE,
+                        U32F96::ONE,
+                        U32F96::from_num(idx + 1),
+                        0_u128.into(),
+                    ),
+                    Some(Indicators::new(None, (I32F96::ZERO, I32F96::ZERO))),
+                )),
+            );
+
+            acc
+        });
+
+        // too few bars for the default period
+        assert_eq!(
+            Indicators::bollinger_band(
+                &mock_timestamp,
+                &Resolution::FiveMinutes,
+                &data,
+                &IndicatorsConfig::All,
+            ),
+            None
+        );
+
+        let (sma, upper_band, lower_band) = Indicators::bollinger_band(
+            &mock_timestamp,
+            &Resolution::FiveMinutes,
+            &data,
+            &IndicatorsConfig::Custom {
+                bb_period: 4,
+                bb_std_dev: U32F96::ONE,
+                ema_period: 9,
+            },
+        )
+        .expect("Bollinger band not found");
+
+        // closes of 1 through 4, variance of 1.25
+        assert_eq!(sma.to_string(), "2.5");
+        assert!(upper_band > U32F96::from_num(3.61) && upper_band < U32F96::from_num(3.62));
+        assert!(lower_band > U32F96::from_num(1.38) && lower_band < U32F96::from_num(1.39));
+    }
+
     #[test]
     fn test_ema() {
         let mock_timestamp = ResolutionTimestamp::from_timestamp(10000, &Resolution::FiveMinutes);
@@ -249,7 +384,12 @@ mod tests {
             ),
         ]);
 
-        let (ema, slope) = Indicators::ema(&mock_timestamp, &Resolution::FiveMinutes, &data);
+        let (ema, slope) = Indicators::ema(
+            &mock_timestamp,
+            &Resolution::FiveMinutes,
+            &data,
+            &IndicatorsConfig::All,
+        );
 
         assert_eq!(ema.to_string(), "5.09523809523809523809523809524");
         assert_eq!(slope.to_string(), "0.09523809523809523809523809524");
@@ -274,7 +414,12 @@ mod tests {
             )),
         )]);
 
-        let (ema, slope) = Indicators::ema(&mock_timestamp, &Resolution::FiveMinutes, &data);
+        let (ema, slope) = Indicators::ema(
+            &mock_timestamp,
+            &Resolution::FiveMinutes,
+            &data,
+            &IndicatorsConfig::All,
+        );
 
         assert_eq!(ema.to_string(), "6");
         assert_eq!(slope.to_string(), "0");
@@ -1,9 +1,13 @@
 pub use indicators::{Indicators, IndicatorsConfig, INDICATOR_BB_PERIOD};
+pub use price_bars::PriceBars;
 pub use resolution_timestamp::{Resolution, ResolutionTimestamp};
+pub use sampled_price_bars::{BarSampling, SampledPriceBar, SampledPriceBars};
 pub use time_price_bar::{FinalizedTimePriceBar, PendingTimePriceBar, TimePriceBar};
 pub use time_price_bars::TimePriceBars;
 
 mod indicators;
+mod price_bars;
 mod resolution_timestamp;
+mod sampled_price_bars;
 mod time_price_bar;
 mod time_price_bars;
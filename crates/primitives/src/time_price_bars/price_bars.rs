@@ -0,0 +1,72 @@
+use super::{ResolutionTimestamp, TimePriceBar, TimePriceBars};
+use crate::{PairBlockTick, TickData};
+
+use alloy::primitives::BlockNumber;
+
+use eyre::Result;
+
+// Common interface over the different bar sampling schemes, all of which are
+// fed from the same per block PairBlockTick stream.
+pub trait PriceBars {
+    fn insert_data(
+        &mut self,
+        block_number: BlockNumber,
+        data: TickData,
+        block_timestamp: u64,
+        finalized_timestamp: Option<ResolutionTimestamp>,
+    ) -> Result<()>;
+
+    fn prune_to_reorged_block_number(&mut self, reorged_block_number: BlockNumber) -> Result<()>;
+
+    // The most recent bar, which may still be pending
+    fn last_price_bar(&self) -> Option<&TimePriceBar>;
+
+    fn is_empty(&self) -> bool;
+
+    fn insert_pair_block_tick(
+        &mut self,
+        block_number: BlockNumber,
+        pair_block_tick: &PairBlockTick,
+        block_timestamp: u64,
+        finalized_timestamp: Option<ResolutionTimestamp>,
+    ) -> Result<()> {
+        self.insert_data(
+            block_number,
+            pair_block_tick.tick().clone(),
+            block_timestamp,
+            finalized_timestamp,
+        )
+    }
+}
+
+impl PriceBars for TimePriceBars {
+    fn insert_data(
+        &mut self,
+        block_number: BlockNumber,
+        data: TickData,
+        block_timestamp: u64,
+        finalized_timestamp: Option<ResolutionTimestamp>,
+    ) -> Result<()> {
+        TimePriceBars::insert_data(
+            self,
+            block_number,
+            data,
+            block_timestamp,
+            finalized_timestamp,
+        )
+    }
+
+    fn prune_to_reorged_block_number(&mut self, reorged_block_number: BlockNumber) -> Result<()> {
+        TimePriceBars::prune_to_reorged_block_number(self, reorged_block_number)
+    }
+
+    fn last_price_bar(&self) -> Option<&TimePriceBar> {
+        self.data()
+            .last_key_value()
+            .map(|(_, time_price_bar)| time_price_bar)
+    }
+
+    fn is_empty(&self) -> bool {
+        TimePriceBars::is_empty(self)
+    }
+}
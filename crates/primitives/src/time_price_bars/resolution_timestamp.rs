@@ -1,6 +1,8 @@
-use serde::Deserialize;
+use eyre::{eyre, Report};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
 
-#[derive(Debug, Clone, Copy, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Resolution {
     #[serde(rename = "5m")]
     FiveMinutes,
@@ -12,6 +14,29 @@ impl Resolution {
             Resolution::FiveMinutes => 300,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::FiveMinutes => "5m",
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "5m" => Ok(Resolution::FiveMinutes),
+            _ => Err(eyre!("Failed to parse resolution: {}", s)),
+        }
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 #[derive(PartialOrd, Ord, Eq, PartialEq, Clone, Copy, Debug)]
@@ -0,0 +1,395 @@
+use super::{PendingTimePriceBar, PriceBars, ResolutionTimestamp, TimePriceBar};
+use crate::TickData;
+
+use alloy::primitives::BlockNumber;
+
+use eyre::{eyre, Report, Result};
+use num_bigint::BigUint;
+use std::{collections::VecDeque, str::FromStr};
+
+// Activity based sampling thresholds. A bar closes at the end of the first
+// block in which its aggregated tick data reaches the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BarSampling {
+    // Close after N trades
+    Trades(u64),
+    // Close after N wei of WETH volume
+    WethVolume(BigUint),
+    // Close once the absolute difference between buy and sell WETH volume
+    // reaches N wei
+    Imbalance(BigUint),
+}
+
+impl BarSampling {
+    pub fn is_complete(&self, data: &TickData) -> bool {
+        match self {
+            BarSampling::Trades(threshold) => data.trade_count >= *threshold,
+            BarSampling::WethVolume(threshold) => data.weth_volume >= *threshold,
+            BarSampling::Imbalance(threshold) => {
+                let imbalance = if data.buy_weth_volume >= data.sell_weth_volume {
+                    &data.buy_weth_volume - &data.sell_weth_volume
+                } else {
+                    &data.sell_weth_volume - &data.buy_weth_volume
+                };
+                imbalance >= *threshold
+            }
+        }
+    }
+}
+
+// Parses "trades:<count>", "weth_volume:<wei>" or "imbalance:<wei>"
+impl FromStr for BarSampling {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(':') {
+            Some(("trades", threshold)) => threshold
+                .parse()
+                .map(BarSampling::Trades)
+                .map_err(|err| eyre!("Failed to parse bar sampling {}: {:?}", s, err)),
+            Some(("weth_volume", threshold)) => threshold
+                .parse()
+                .map(BarSampling::WethVolume)
+                .map_err(|err| eyre!("Failed to parse bar sampling {}: {:?}", s, err)),
+            Some(("imbalance", threshold)) => threshold
+                .parse()
+                .map(BarSampling::Imbalance)
+                .map_err(|err| eyre!("Failed to parse bar sampling {}: {:?}", s, err)),
+            _ => Err(eyre!("Failed to parse bar sampling: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SampledPriceBar {
+    pub price_bar: TimePriceBar,
+    // Timestamp of the last block inserted into the bar
+    pub end_timestamp: u64,
+    // Whether the sampling threshold has been reached. Closed bars remain
+    // Pending until their blocks have been finalized so they can still be
+    // reopened by a reorg.
+    pub is_closed: bool,
+}
+
+impl SampledPriceBar {
+    fn end_block_number(&self) -> Option<BlockNumber> {
+        match &self.price_bar {
+            TimePriceBar::Pending(price_bar) => price_bar.end_block_number().copied(),
+            TimePriceBar::Finalized(price_bar) => Some(price_bar.end_block_number),
+        }
+    }
+}
+
+enum PruneAction {
+    Remove,
+    Prune,
+    Done,
+}
+
+pub struct SampledPriceBars {
+    data: VecDeque<SampledPriceBar>,
+
+    sampling: BarSampling,
+
+    // How many historical bars to retain. Once exceeded, oldest are pruned
+    // first.
+    retention_count: Option<u64>,
+}
+
+impl SampledPriceBars {
+    pub fn new(retention_count: Option<u64>, sampling: BarSampling) -> Self {
+        Self {
+            data: VecDeque::new(),
+            sampling,
+            retention_count,
+        }
+    }
+
+    pub fn data(&self) -> &VecDeque<SampledPriceBar> {
+        &self.data
+    }
+
+    pub fn sampling(&self) -> &BarSampling {
+        &self.sampling
+    }
+
+    // Closed bars, oldest first
+    pub fn closed_price_bars(&self) -> impl Iterator<Item = &TimePriceBar> {
+        self.data
+            .iter()
+            .filter(|sampled_price_bar| sampled_price_bar.is_closed)
+            .map(|sampled_price_bar| &sampled_price_bar.price_bar)
+    }
+
+    fn prune_to_retention_count(&mut self) {
+        if let Some(retention_count) = self.retention_count {
+            while self.data.len() > retention_count as usize {
+                let _ = self.data.pop_front();
+            }
+        }
+    }
+
+    // Block timestamps are not retained per block, so a bar is only finalized
+    // once its last block is earlier than the start of the finalized
+    // resolution timestamp.
+    fn finalize(&mut self, finalized_timestamp: &ResolutionTimestamp) -> Result<()> {
+        for sampled_price_bar in self.data.iter_mut() {
+            if !sampled_price_bar.is_closed
+                || sampled_price_bar.end_timestamp >= finalized_timestamp.0
+            {
+                break;
+            }
+
+            if let TimePriceBar::Pending(pending_price_bar) = &sampled_price_bar.price_bar {
+                match pending_price_bar.as_finalized() {
+                    Some(finalized_price_bar) => {
+                        sampled_price_bar.price_bar = TimePriceBar::Finalized(finalized_price_bar);
+                    }
+                    None => return Err(eyre!("Failed to finalize Pending SampledPriceBar")),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PriceBars for SampledPriceBars {
+    fn insert_data(
+        &mut self,
+        block_number: BlockNumber,
+        data: TickData,
+        block_timestamp: u64,
+        finalized_timestamp: Option<ResolutionTimestamp>,
+    ) -> Result<()> {
+        if let Some(end_block_number) = self
+            .data
+            .back()
+            .and_then(|sampled_price_bar| sampled_price_bar.end_block_number())
+        {
+            if block_number <= end_block_number {
+                return Err(eyre!(
+                    "Attempted to insert block number {:?} at or before last inserted block number {:?}",
+                    block_number,
+                    end_block_number
+                ));
+            }
+        }
+
+        match self.data.back_mut() {
+            Some(SampledPriceBar {
+                price_bar: TimePriceBar::Pending(pending_price_bar),
+                end_timestamp,
+                is_closed: false,
+            }) => {
+                pending_price_bar.insert_block_price_bar(block_number, data);
+                *end_timestamp = block_timestamp;
+            }
+            _ => {
+                let mut pending_price_bar = PendingTimePriceBar::new();
+                pending_price_bar.insert_block_price_bar(block_number, data);
+                self.data.push_back(SampledPriceBar {
+                    price_bar: TimePriceBar::Pending(pending_price_bar),
+                    end_timestamp: block_timestamp,
+                    is_closed: false,
+                });
+            }
+        }
+
+        if let Some(sampled_price_bar) = self.data.back_mut() {
+            sampled_price_bar.is_closed = sampled_price_bar
+                .price_bar
+                .data()
+                .is_some_and(|data| self.sampling.is_complete(data));
+        }
+
+        if let Some(finalized_timestamp) = finalized_timestamp {
+            self.finalize(&finalized_timestamp)?;
+        }
+
+        self.prune_to_retention_count();
+
+        Ok(())
+    }
+
+    fn prune_to_reorged_block_number(&mut self, reorged_block_number: BlockNumber) -> Result<()> {
+        loop {
+            let action = match self
+                .data
+                .back()
+                .map(|sampled_price_bar| &sampled_price_bar.price_bar)
+            {
+                Some(TimePriceBar::Pending(price_bar)) => {
+                    match (price_bar.start_block_number(), price_bar.end_block_number()) {
+                        (Some(start_block_number), _)
+                            if *start_block_number >= reorged_block_number =>
+                        {
+                            PruneAction::Remove
+                        }
+                        (_, Some(end_block_number))
+                            if *end_block_number >= reorged_block_number =>
+                        {
+                            PruneAction::Prune
+                        }
+                        (Some(_), Some(_)) => PruneAction::Done,
+                        (None, None) => {
+                            return Err(eyre!(
+                                "Attempted to prune into empty Pending SampledPriceBar"
+                            ))
+                        }
+                        (None, Some(_)) | (Some(_), None) => unreachable!(),
+                    }
+                }
+                Some(TimePriceBar::Finalized(price_bar)) => {
+                    if price_bar.end_block_number >= reorged_block_number {
+                        return Err(eyre!("Attempted to prune into Finalized SampledPriceBar"));
+                    }
+                    PruneAction::Done
+                }
+                None => PruneAction::Done,
+            };
+
+            match action {
+                PruneAction::Remove => {
+                    self.data.pop_back();
+                }
+                PruneAction::Prune => {
+                    if let Some(sampled_price_bar) = self.data.back_mut() {
+                        if let TimePriceBar::Pending(price_bar) = &mut sampled_price_bar.price_bar {
+                            price_bar.prune_to_reorged_block_number(reorged_block_number);
+                        }
+                        // The bar may no longer meet its threshold, in which case it is
+                        // reopened. Its end timestamp is left as is, which only delays
+                        // finalization.
+                        sampled_price_bar.is_closed = sampled_price_bar
+                            .price_bar
+                            .data()
+                            .is_some_and(|data| self.sampling.is_complete(data));
+                    }
+                    break;
+                }
+                PruneAction::Done => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn last_price_bar(&self) -> Option<&TimePriceBar> {
+        self.data
+            .back()
+            .map(|sampled_price_bar| &sampled_price_bar.price_bar)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BarSampling, SampledPriceBars};
+    use crate::{PriceBars, ResolutionTimestamp, TickData, TimePriceBar};
+
+    use eyre::Result;
+    use fixed::types::U32F96;
+    use num_bigint::BigUint;
+
+    fn mock_data(trade_count: u64, buy_weth_volume: u64, sell_weth_volume: u64) -> TickData {
+        let mut data = TickData::new(
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            BigUint::from(buy_weth_volume + sell_weth_volume),
+        );
+        data.trade_count = trade_count;
+        data.buy_weth_volume = BigUint::from(buy_weth_volume);
+        data.sell_weth_volume = BigUint::from(sell_weth_volume);
+        data
+    }
+
+    #[test]
+    fn test_parse_bar_sampling() {
+        assert_eq!(
+            "trades:50".parse::<BarSampling>().unwrap(),
+            BarSampling::Trades(50)
+        );
+        assert_eq!(
+            "imbalance:1000".parse::<BarSampling>().unwrap(),
+            BarSampling::Imbalance(BigUint::from(1000_u64))
+        );
+        assert!("volume:1".parse::<BarSampling>().is_err());
+    }
+
+    #[test]
+    fn test_trade_bars() -> Result<()> {
+        let mut price_bars = SampledPriceBars::new(None, BarSampling::Trades(3));
+
+        price_bars.insert_data(1, mock_data(2, 1, 0), 100, None)?;
+        price_bars.insert_data(4, mock_data(1, 1, 0), 108, None)?;
+        price_bars.insert_data(5, mock_data(1, 1, 0), 110, None)?;
+
+        assert_eq!(price_bars.data().len(), 2);
+        assert!(price_bars.data()[0].is_closed);
+        assert!(!price_bars.data()[1].is_closed);
+        assert_eq!(
+            price_bars.data()[0].price_bar.data().unwrap().trade_count,
+            3
+        );
+        assert_eq!(price_bars.data()[0].end_timestamp, 108);
+
+        // blocks must be inserted in order
+        assert!(price_bars
+            .insert_data(5, mock_data(1, 1, 0), 110, None)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_imbalance_bars() -> Result<()> {
+        let mut price_bars =
+            SampledPriceBars::new(None, BarSampling::Imbalance(BigUint::from(10_u64)));
+
+        price_bars.insert_data(1, mock_data(1, 8, 0), 100, None)?;
+        price_bars.insert_data(2, mock_data(1, 0, 6), 102, None)?;
+        assert!(!price_bars.data()[0].is_closed);
+
+        price_bars.insert_data(3, mock_data(1, 0, 12), 104, None)?;
+        assert!(price_bars.data()[0].is_closed);
+        assert_eq!(price_bars.closed_price_bars().count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_and_finalize() -> Result<()> {
+        let mut price_bars =
+            SampledPriceBars::new(None, BarSampling::WethVolume(BigUint::from(10_u64)));
+
+        price_bars.insert_data(1, mock_data(1, 6, 0), 100, None)?;
+        price_bars.insert_data(2, mock_data(1, 6, 0), 102, None)?;
+        price_bars.insert_data(3, mock_data(1, 6, 0), 104, None)?;
+
+        // reorg of block 2 removes the second bar and reopens the first
+        price_bars.prune_to_reorged_block_number(2)?;
+        assert_eq!(price_bars.data().len(), 1);
+        assert!(!price_bars.data()[0].is_closed);
+
+        price_bars.insert_data(2, mock_data(1, 0, 6), 102, None)?;
+        price_bars.insert_data(3, mock_data(1, 6, 0), 104, Some(ResolutionTimestamp(103)))?;
+
+        assert!(matches!(
+            price_bars.data()[0].price_bar,
+            TimePriceBar::Finalized(_)
+        ));
+        assert!(matches!(
+            price_bars.data()[1].price_bar,
+            TimePriceBar::Pending(_)
+        ));
+        assert!(price_bars.prune_to_reorged_block_number(2).is_err());
+
+        Ok(())
+    }
+}
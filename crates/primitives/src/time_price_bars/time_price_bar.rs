@@ -1,8 +1,11 @@
-use super::Indicators;
+use super::{Indicators, Resolution, ResolutionTimestamp};
 use crate::TickData;
 
-use alloy::primitives::BlockNumber;
+use pochtecatl_db::TimePriceBarModel;
 
+use alloy::primitives::{Address, BlockNumber};
+
+use eyre::{Result, WrapErr};
 use fixed::types::U32F96;
 use std::collections::BTreeMap;
 
@@ -36,6 +39,46 @@ impl FinalizedTimePriceBar {
     pub fn indicators(&self) -> &Option<Indicators> {
         &self.indicators
     }
+
+    pub fn to_model(
+        &self,
+        pair_address: Address,
+        resolution: &Resolution,
+        timestamp: &ResolutionTimestamp,
+    ) -> TimePriceBarModel {
+        TimePriceBarModel::new(
+            pair_address,
+            resolution.to_string(),
+            timestamp.0,
+            self.start_block_number,
+            self.end_block_number,
+            serde_json::to_value(&self.data).unwrap(),
+            self.indicators
+                .as_ref()
+                .map(|indicators| serde_json::to_value(indicators).unwrap()),
+        )
+    }
+}
+
+impl TryFrom<TimePriceBarModel> for FinalizedTimePriceBar {
+    type Error = eyre::Report;
+
+    fn try_from(value: TimePriceBarModel) -> Result<Self> {
+        let data = serde_json::from_value(value.data)
+            .wrap_err("Failed to deserialize TimePriceBarModel data")?;
+        let indicators = value
+            .indicators
+            .map(serde_json::from_value)
+            .transpose()
+            .wrap_err("Failed to deserialize TimePriceBarModel indicators")?;
+
+        Ok(Self::new(
+            value.start_block_number.into(),
+            value.end_block_number.into(),
+            data,
+            indicators,
+        ))
+    }
 }
 
 // Holds individual BlockPriceBars until the underlying block range has been finalized
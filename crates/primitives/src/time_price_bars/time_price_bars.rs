@@ -1,9 +1,9 @@
 use super::{
-    Indicators, IndicatorsConfig, PendingTimePriceBar, Resolution, ResolutionTimestamp,
-    TimePriceBar,
+    FinalizedTimePriceBar, Indicators, IndicatorsConfig, PendingTimePriceBar, Resolution,
+    ResolutionTimestamp, TimePriceBar,
 };
 
-use crate::{constants, TickData};
+use crate::TickData;
 
 use alloy::primitives::BlockNumber;
 
@@ -56,6 +56,23 @@ impl TimePriceBars {
         self.data.get(timestamp)
     }
 
+    // The indicators of the time price bar at the timestamp under the given
+    // config, replayed from the retained bars if they maintain another config.
+    pub fn indicators(
+        &self,
+        timestamp: &ResolutionTimestamp,
+        indicators_config: &IndicatorsConfig,
+    ) -> Option<Indicators> {
+        let time_price_bar = self.data.get(timestamp)?;
+        if self.indicators_config.as_ref() == Some(indicators_config) {
+            time_price_bar.indicators().copied()
+        } else {
+            time_price_bar.data().map(|_| {
+                Indicators::replay(timestamp, &self.resolution, &self.data, indicators_config)
+            })
+        }
+    }
+
     pub fn time_price_bar_range(
         &self,
         start_resolution_timestamp: &ResolutionTimestamp,
@@ -69,6 +86,47 @@ impl TimePriceBars {
             .collect()
     }
 
+    pub fn finalized_range(
+        &self,
+        start_resolution_timestamp: &ResolutionTimestamp,
+        end_resolution_timestamp: &ResolutionTimestamp,
+    ) -> Vec<(&ResolutionTimestamp, &FinalizedTimePriceBar)> {
+        self.data
+            .range(start_resolution_timestamp..=end_resolution_timestamp)
+            .filter_map(|(timestamp, time_price_bar)| match time_price_bar {
+                TimePriceBar::Finalized(finalized_time_price_bar) => {
+                    Some((timestamp, finalized_time_price_bar))
+                }
+                TimePriceBar::Pending(_) => None,
+            })
+            .collect()
+    }
+
+    // Restores a previously persisted finalized bar, e.g. when rehydrating on
+    // restart. Bars must be inserted in timestamp order before any block data.
+    pub fn insert_finalized(
+        &mut self,
+        timestamp: ResolutionTimestamp,
+        finalized_time_price_bar: FinalizedTimePriceBar,
+    ) -> Result<()> {
+        if let Some((last_timestamp, _)) = self.data.last_key_value() {
+            if *last_timestamp >= timestamp {
+                return Err(eyre!(
+                    "Attempted to insert Finalized TimePriceBar at time {:?} at or before last time {:?}",
+                    timestamp,
+                    last_timestamp
+                ));
+            }
+        }
+
+        self.data
+            .insert(timestamp, TimePriceBar::Finalized(finalized_time_price_bar));
+        self.last_finalized_timestamp = Some(timestamp);
+        self.prune_to_retention_count();
+
+        Ok(())
+    }
+
     fn prune_to_retention_count(&mut self) {
         if let Some(retention_count) = self.retention_count {
             while self.data.len() > retention_count as usize {
@@ -168,6 +226,97 @@ impl TimePriceBars {
         Ok(())
     }
 
+    // The last block number inserted, which the next insert must follow
+    pub fn last_block_number(&self) -> Option<BlockNumber> {
+        self.data
+            .last_key_value()
+            .and_then(|(_, time_price_bar)| match time_price_bar {
+                TimePriceBar::Pending(price_bar) => price_bar
+                    .block_price_bars
+                    .last_key_value()
+                    .map(|(last_block_number, _)| *last_block_number),
+                TimePriceBar::Finalized(price_bar) => Some(price_bar.end_block_number),
+            })
+    }
+
+    // Pads the time price bars with the blocks between the last inserted and
+    // the given block, carrying the last inserted data forward. Each padded
+    // block goes in the time price bar of its timestamp, which must be given.
+    pub fn pad_to(
+        &mut self,
+        block_number: BlockNumber,
+        block_timestamps: &BTreeMap<BlockNumber, u64>,
+    ) -> Result<()> {
+        let pad_from =
+            self.data
+                .last_key_value()
+                .and_then(|(_, time_price_bar)| match time_price_bar {
+                    TimePriceBar::Pending(price_bar) => {
+                        price_bar.block_price_bars.last_key_value().map(
+                            |(last_block_number, data)| (*last_block_number, data.carry_forward()),
+                        )
+                    }
+                    TimePriceBar::Finalized(price_bar) => {
+                        Some((price_bar.end_block_number, price_bar.data.carry_forward()))
+                    }
+                });
+        let (last_inserted_block_number, last_inserted_data) = match pad_from {
+            Some(pad_from) if pad_from.0 + 1 < block_number => pad_from,
+            _ => return Ok(()),
+        };
+
+        let mut padded_block_numbers: BTreeMap<ResolutionTimestamp, Vec<BlockNumber>> =
+            BTreeMap::new();
+        for padded_block_number in (last_inserted_block_number + 1)..block_number {
+            let padded_block_timestamp =
+                block_timestamps.get(&padded_block_number).ok_or_else(|| {
+                    eyre!("Missing timestamp of padded block {}", padded_block_number)
+                })?;
+            padded_block_numbers
+                .entry(ResolutionTimestamp::from_timestamp(
+                    *padded_block_timestamp,
+                    &self.resolution,
+                ))
+                .or_default()
+                .push(padded_block_number);
+        }
+
+        let mut updated_block_resolution_timestamps = Vec::new();
+        for (padded_resolution_timestamp, block_numbers) in padded_block_numbers {
+            match self
+                .data
+                .entry(padded_resolution_timestamp)
+                .or_insert_with(|| TimePriceBar::Pending(PendingTimePriceBar::new()))
+            {
+                TimePriceBar::Pending(time_price_bar) => {
+                    time_price_bar.insert_block_price_bar_range(
+                        block_numbers.into_iter(),
+                        &last_inserted_data,
+                    );
+                    updated_block_resolution_timestamps.push(padded_resolution_timestamp);
+                }
+                TimePriceBar::Finalized(_) => {
+                    error!(
+                        "Expected Pending TimePriceBar at time {:?}, but found Finalized",
+                        padded_resolution_timestamp
+                    );
+                }
+            }
+        }
+
+        self.prune_to_retention_count();
+
+        if let Some(indicators_config) = self.indicators_config {
+            for block_resolution_timestamp in updated_block_resolution_timestamps {
+                self.update_indicators(&block_resolution_timestamp, &indicators_config)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Inserts the data of the block following the last inserted, so any
+    // blocks skipped since must be padded first
     pub fn insert_data(
         &mut self,
         block_number: BlockNumber,
@@ -179,90 +328,15 @@ impl TimePriceBars {
             ResolutionTimestamp::from_timestamp(block_timestamp, &self.resolution);
         let mut updated_block_resolution_timestamps = Vec::new();
 
-        // Pad time price bars with the missing intermediate blocks since the last insert if required
+        if let Some(last_block_number) = self
+            .last_block_number()
+            .filter(|last_block_number| last_block_number + 1 < block_number)
         {
-            let pad_from =
-                self.data
-                    .last_key_value()
-                    .and_then(|(_, time_price_bar)| match time_price_bar {
-                        TimePriceBar::Pending(price_bar) => price_bar
-                            .block_price_bars
-                            .last_key_value()
-                            .and_then(|(last_block_number, data)| {
-                                if last_block_number + 1 != block_number {
-                                    Some((*last_block_number, data.clone()))
-                                } else {
-                                    None
-                                }
-                            }),
-                        TimePriceBar::Finalized(price_bar) => {
-                            if price_bar.end_block_number + 1 != block_number {
-                                Some((price_bar.end_block_number, price_bar.data.clone()))
-                            } else {
-                                None
-                            }
-                        }
-                    });
-
-            if let Some((last_inserted_block_number, last_inserted_data)) = pad_from {
-                let mut block_numbers_to_pad = Vec::with_capacity(
-                    (self.resolution().offset() / constants::AVERAGE_BLOCK_TIME_SECONDS) as usize,
-                );
-                let mut resolution_timestamp_to_pad = None;
-                for padded_block_number in (last_inserted_block_number + 1)..block_number {
-                    let padded_resolution_timestamp = ResolutionTimestamp::from_timestamp(
-                        block_timestamp
-                            - ((block_number - last_inserted_block_number)
-                                * constants::AVERAGE_BLOCK_TIME_SECONDS),
-                        &self.resolution,
-                    );
-
-                    if resolution_timestamp_to_pad
-                        .is_some_and(|ts| ts != padded_resolution_timestamp)
-                    {
-                        let ts = resolution_timestamp_to_pad.unwrap();
-                        match self
-                            .data
-                            .entry(ts)
-                            .or_insert_with(|| TimePriceBar::Pending(PendingTimePriceBar::new()))
-                        {
-                            TimePriceBar::Pending(time_price_bar) => {
-                                time_price_bar.insert_block_price_bar_range(
-                                    block_numbers_to_pad.drain(..),
-                                    &last_inserted_data,
-                                );
-                                updated_block_resolution_timestamps
-                                    .push(padded_resolution_timestamp);
-                            }
-                            TimePriceBar::Finalized(_) => {
-                                error!("Expected Pending TimePriceBar at time {:?}, but found Finalized", ts);
-                            }
-                        }
-                        resolution_timestamp_to_pad = Some(padded_resolution_timestamp);
-                    }
-
-                    block_numbers_to_pad.push(padded_block_number);
-                    if padded_block_number == block_number - 1 {
-                        match self
-                            .data
-                            .entry(padded_resolution_timestamp)
-                            .or_insert_with(|| TimePriceBar::Pending(PendingTimePriceBar::new()))
-                        {
-                            TimePriceBar::Pending(time_price_bar) => {
-                                time_price_bar.insert_block_price_bar_range(
-                                    block_numbers_to_pad.drain(..),
-                                    &last_inserted_data,
-                                );
-                                updated_block_resolution_timestamps
-                                    .push(padded_resolution_timestamp);
-                            }
-                            TimePriceBar::Finalized(_) => {
-                                error!("Expected Pending TimePriceBar at time {:?}, but found Finalized", padded_resolution_timestamp);
-                            }
-                        }
-                    }
-                }
-            }
+            return Err(eyre!(
+                "Attempted to insert block number {:?} before padding from {:?}",
+                block_number,
+                last_block_number
+            ));
         }
 
         // Insert the new data into the time price bar
@@ -372,14 +446,15 @@ impl TimePriceBars {
 mod tests {
     use super::TimePriceBars;
     use crate::{
-        time_price_bars::INDICATOR_BB_PERIOD, u32f96_from_u256_frac, IndicatorsConfig, Resolution,
-        ResolutionTimestamp, TickData, TimePriceBar,
+        time_price_bars::INDICATOR_BB_PERIOD, u32f96_from_u256_frac, FinalizedTimePriceBar,
+        IndicatorsConfig, Resolution, ResolutionTimestamp, TickData, TimePriceBar,
     };
 
     use alloy::primitives::{uint, U256};
 
     use eyre::Result;
     use fixed::types::U32F96;
+    use std::collections::BTreeMap;
 
     #[test]
     pub fn test_insert_data() -> Result<()> {
@@ -445,6 +520,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_insert_data_padding() -> Result<()> {
+        let mut time_price_bars = TimePriceBars::new(
+            Some(5),
+            Resolution::FiveMinutes,
+            Some(IndicatorsConfig::All),
+        );
+        let mock_data = TickData::new(
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            0_u128.into(),
+        );
+
+        // Block times drift, block 150 is the first at or after 10200 and
+        // starts the next bar
+        let block_timestamps = (2..200)
+            .map(|block_number| {
+                if block_number < 150 {
+                    (block_number, 10000 + block_number - 1)
+                } else {
+                    (block_number, 10200 + (block_number - 150) * 3)
+                }
+            })
+            .collect::<BTreeMap<u64, u64>>();
+
+        time_price_bars.insert_data(1_u64, mock_data.clone(), 10000, None)?;
+        assert!(time_price_bars
+            .insert_data(200_u64, mock_data.clone(), 10398, None)
+            .is_err());
+        time_price_bars.pad_to(200, &block_timestamps)?;
+        time_price_bars.insert_data(200_u64, mock_data.clone(), 10398, None)?;
+
+        let block_numbers =
+            |timestamp: u64| match time_price_bars
+                .data
+                .get(&ResolutionTimestamp::from_timestamp(
+                    timestamp,
+                    &Resolution::FiveMinutes,
+                )) {
+                Some(TimePriceBar::Pending(time_price_bar)) => time_price_bar
+                    .block_price_bars
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>(),
+                _ => Vec::new(),
+            };
+        assert_eq!(block_numbers(10000), (1..=149).collect::<Vec<_>>());
+        assert_eq!(block_numbers(10200), (150..=200).collect::<Vec<_>>());
+
+        Ok(())
+    }
+
     #[test]
     pub fn test_prune_to_reorged_block_number() -> Result<()> {
         let mock_timestamp = 10000;
@@ -529,6 +658,55 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    pub fn test_insert_finalized() -> Result<()> {
+        let mock_resolution_timestamp =
+            ResolutionTimestamp::from_timestamp(10000, &Resolution::FiveMinutes);
+        let mock_data = TickData::new(
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            U32F96::ONE,
+            0_u128.into(),
+        );
+        let mut time_price_bars = TimePriceBars::new(
+            Some(5),
+            Resolution::FiveMinutes,
+            Some(IndicatorsConfig::All),
+        );
+
+        time_price_bars.insert_finalized(
+            mock_resolution_timestamp,
+            FinalizedTimePriceBar::new(1, 2, mock_data.clone(), None),
+        )?;
+        assert_eq!(
+            time_price_bars.last_finalized_timestamp(),
+            &Some(mock_resolution_timestamp)
+        );
+        assert!(time_price_bars
+            .insert_finalized(
+                mock_resolution_timestamp,
+                FinalizedTimePriceBar::new(1, 2, mock_data.clone(), None),
+            )
+            .is_err());
+
+        // block data continues from the restored bar
+        let next_ts = mock_resolution_timestamp.next(&Resolution::FiveMinutes);
+        time_price_bars.insert_data(3, mock_data.clone(), next_ts.0, None)?;
+        assert_eq!(
+            time_price_bars
+                .finalized_range(&mock_resolution_timestamp, &next_ts)
+                .len(),
+            1
+        );
+        assert!(matches!(
+            time_price_bars.time_price_bar(&next_ts),
+            Some(TimePriceBar::Pending(_))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     pub fn test_update_indicators() -> Result<()> {
         let mut time_price_bars = TimePriceBars::new(
@@ -567,4 +745,66 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    pub fn test_replayed_indicators() -> Result<()> {
+        let mut time_price_bars = TimePriceBars::new(
+            Some(100),
+            Resolution::FiveMinutes,
+            Some(IndicatorsConfig::All),
+        );
+
+        for i in 1..=INDICATOR_BB_PERIOD {
+            time_price_bars.insert_data(
+                i,
+                TickData::new(
+                    U32F96::ONE,
+                    U32F96::ONE,
+                    U32F96::ONE,
+                    u32f96_from_u256_frac(U256::from(i), uint!(1_U256)),
+                    0_u128.into(),
+                ),
+                i * Resolution::FiveMinutes.offset() + 10000,
+                None,
+            )?;
+        }
+
+        let last_inserted_timestamp = ResolutionTimestamp::from_timestamp(
+            INDICATOR_BB_PERIOD * Resolution::FiveMinutes.offset() + 10000,
+            &Resolution::FiveMinutes,
+        );
+        let indicators = time_price_bars
+            .indicators(&last_inserted_timestamp, &IndicatorsConfig::All)
+            .expect("Expected indicators");
+
+        // the same periods replayed from the bars match the maintained indicators
+        let replayed = time_price_bars
+            .indicators(
+                &last_inserted_timestamp,
+                &IndicatorsConfig::Custom {
+                    bb_period: IndicatorsConfig::All.bb_period(),
+                    bb_std_dev: IndicatorsConfig::All.bb_std_dev(),
+                    ema_period: IndicatorsConfig::All.ema_period(),
+                },
+            )
+            .expect("Expected replayed indicators");
+        assert_eq!(replayed.ema, indicators.ema);
+        assert_eq!(replayed.bollinger_bands, indicators.bollinger_bands);
+
+        // a shorter ema tracks the rising closes more closely
+        let shorter = time_price_bars
+            .indicators(
+                &last_inserted_timestamp,
+                &IndicatorsConfig::Custom {
+                    bb_period: 5,
+                    bb_std_dev: IndicatorsConfig::All.bb_std_dev(),
+                    ema_period: 3,
+                },
+            )
+            .expect("Expected replayed indicators");
+        assert!(shorter.ema.0 > indicators.ema.0);
+        assert!(shorter.bollinger_bands.is_some());
+
+        Ok(())
+    }
 }
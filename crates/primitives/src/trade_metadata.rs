@@ -1,6 +1,8 @@
-use super::{IndexedTrade, TradeRequestOp};
+use super::{constants, u32f96_from_u256_frac, IndexedTrade, TradeRequestOp};
 
-use alloy::primitives::{Address, BlockNumber, TxHash, U256};
+use alloy::primitives::{Address, BlockNumber, TxHash, I256, U256};
+
+use fixed::types::U32F96;
 
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +15,10 @@ pub struct TradeMetadata {
     token_address: Address,
     gas_fee: U256,
     indexed_trade: IndexedTrade,
+    // Output the trade was quoted at when submitted, None for simulated
+    // trades which fill at their quote
+    #[serde(default)]
+    quoted_amount_out: Option<U256>,
 }
 
 impl TradeMetadata {
@@ -33,9 +39,15 @@ impl TradeMetadata {
             token_address,
             gas_fee,
             indexed_trade,
+            quoted_amount_out: None,
         }
     }
 
+    pub fn with_quoted_amount_out(mut self, quoted_amount_out: U256) -> Self {
+        self.quoted_amount_out = Some(quoted_amount_out);
+        self
+    }
+
     pub fn tx_hash(&self) -> &TxHash {
         &self.tx_hash
     }
@@ -63,4 +75,50 @@ impl TradeMetadata {
     pub fn gas_fee(&self) -> &U256 {
         &self.gas_fee
     }
+
+    // weth swapped in (open) or out (close) by the trade
+    pub fn weth_amount(&self) -> U256 {
+        self.indexed_trade.weth_volume(&self.token_address)
+    }
+
+    // tokens swapped out (open) or in (close) by the trade
+    pub fn token_amount(&self) -> U256 {
+        self.indexed_trade.token_volume(&self.token_address)
+    }
+
+    pub fn quoted_amount_out(&self) -> Option<&U256> {
+        self.quoted_amount_out.as_ref()
+    }
+
+    // tokens (open) or weth (close) received by the trade
+    pub fn amount_out(&self) -> U256 {
+        match self.op {
+            TradeRequestOp::Open => self.token_amount(),
+            TradeRequestOp::Close { .. } => self.weth_amount(),
+        }
+    }
+
+    // Shortfall of the output from its quote in basis points, negative if the
+    // trade filled better than quoted
+    pub fn realized_slippage_bps(&self) -> Option<i64> {
+        self.quoted_amount_out
+            .filter(|quoted_amount_out| !quoted_amount_out.is_zero())
+            .and_then(|quoted_amount_out| {
+                ((I256::from_raw(quoted_amount_out) - I256::from_raw(self.amount_out()))
+                    * I256::from_raw(constants::BP_FACTOR)
+                    / I256::from_raw(quoted_amount_out))
+                .try_into()
+                .ok()
+            })
+    }
+
+    // Average price the trade filled at in weth, including price impact.
+    pub fn execution_price(&self) -> U32F96 {
+        let token_amount = self.token_amount();
+        if token_amount == U256::ZERO {
+            self.indexed_trade.token_price_after(&self.token_address)
+        } else {
+            u32f96_from_u256_frac(self.weth_amount(), token_amount)
+        }
+    }
 }